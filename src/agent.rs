@@ -0,0 +1,214 @@
+use std::sync::{Arc, Mutex};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use clap::Parser;
+use serde_json::json;
+use solana_sdk::{message::{Message, VersionedMessage}, signature::Keypair, signer::Signer};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::audit;
+use crate::control;
+use crate::metrics::{self, MetricsAddr, SocketMode};
+
+/// Shared secret between the agent and its rigs, required for an agent listening beyond
+/// loopback. Rigs send it with every request.
+pub const TOKEN_ENV: &str = "ORE_HQ_AGENT_TOKEN";
+/// Domain tag the session key certificate starts with, see session.rs.
+const SESSION_CERTIFICATE_TAG: &[u8] = b"ore-hq-session";
+
+#[derive(Debug, Parser)]
+pub struct AgentArgs {
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "Where to listen for rigs, unix:/path.sock or host:port. Rigs mine with --signer agent:<ADDR>. Listening beyond loopback needs a token in ORE_HQ_AGENT_TOKEN, on the agent and the rigs"
+    )]
+    pub socket: MetricsAddr,
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "600",
+        help = "Permissions of a unix socket, in octal"
+    )]
+    pub socket_mode: SocketMode,
+    #[arg(
+        long,
+        action,
+        help = "Ask on this terminal before every signature. Mining signs every round, so this suits claims and occasional logins"
+    )]
+    pub confirm: bool,
+}
+
+struct Agent {
+    key: Keypair,
+    confirm: bool,
+    token: Option<String>,
+    /// One question at a time on the terminal.
+    prompt: Mutex<()>,
+}
+
+impl Agent {
+    fn approve(&self, message: &[u8]) -> bool {
+        if !self.confirm {
+            return true;
+        }
+        let _prompt = self.prompt.lock().unwrap();
+        let preview: String = message.iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+        inquire::Confirm::new(&format!("Sign a {} byte message starting {} as {}?", message.len(), preview, self.key.pubkey()))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false)
+    }
+
+    /// What a mining session signs: the 8 byte timestamp of the handshake and Ready, the
+    /// 24 byte hash and nonce of a solution, a session key certificate, and attestation and
+    /// capacity reports for this key. Anything else, a transaction in particular, is refused.
+    fn is_mining_payload(&self, message: &[u8]) -> bool {
+        if bincode::deserialize::<Message>(message).is_ok() || bincode::deserialize::<VersionedMessage>(message).is_ok() {
+            return false;
+        }
+        let pubkey = self.key.pubkey().to_bytes();
+        let for_this_key = message.starts_with(&pubkey);
+        match message.len() {
+            8 | 24 => true,
+            len if len == SESSION_CERTIFICATE_TAG.len() + 32 + 8 => message.starts_with(SESSION_CERTIFICATE_TAG),
+            // Capacity report: pubkey, timestamp and hashrate
+            48 => for_this_key,
+            // Attestation: pubkey, interval, hashes, then the client version prefixed by its length
+            len if len > 56 => for_this_key && message[56] as usize == len - 57,
+            _ => false,
+        }
+    }
+
+    fn authorized(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (None, _) => true,
+            (Some(expected), Some(token)) => control::same_token(expected, token),
+            (Some(_), None) => false,
+        }
+    }
+
+    fn execute(&self, line: &str) -> serde_json::Value {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        let token = match words.as_slice() {
+            ["token", token, ..] => Some(token.to_string()),
+            _ => None,
+        };
+        if token.is_some() {
+            words.drain(..2);
+        }
+        if !self.authorized(token.as_deref()) {
+            return json!({ "ok": false, "error": format!("missing or wrong token, set {} on the rig", TOKEN_ENV) });
+        }
+        match words.as_slice() {
+            ["pubkey"] => json!({ "ok": true, "pubkey": self.key.pubkey().to_string() }),
+            ["sign", message] => {
+                let Ok(message) = BASE64_STANDARD.decode(message) else {
+                    return json!({ "ok": false, "error": "the message is not base64" });
+                };
+                if !self.is_mining_payload(&message) {
+                    println!("  Refused to sign a {} byte message that is not a mining frame.", message.len());
+                    return json!({ "ok": false, "error": "the agent only signs mining frames" });
+                }
+                if !self.approve(&message) {
+                    return json!({ "ok": false, "error": "declined on the agent" });
+                }
+                json!({ "ok": true, "signature": audit::sign(&self.key, "agent", &message).to_string() })
+            },
+            _ => json!({ "ok": false, "error": format!("unknown request: {}", words.join(" ")) }),
+        }
+    }
+}
+
+/// Answers request lines on one connection until the rig closes it. Confirmation prompts
+/// block, so requests run off the runtime.
+async fn handle<S: AsyncRead + AsyncWrite + Unpin>(stream: S, agent: Arc<Agent>) {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    while matches!(stream.read_line(&mut line).await, Ok(len) if len > 0) {
+        let request = std::mem::take(&mut line);
+        let agent = agent.clone();
+        let Ok(answer) = tokio::task::spawn_blocking(move || agent.execute(&request)).await else {
+            return;
+        };
+        if stream.write_all(format!("{}\n", answer).as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends one request to an agent and returns its answer once it is ok.
+pub async fn request(addr: &MetricsAddr, line: &str) -> Result<serde_json::Value, String> {
+    let line = match std::env::var(TOKEN_ENV) {
+        Ok(token) if !token.is_empty() => format!("token {} {}", token, line),
+        _ => line.to_string(),
+    };
+    let line = line.as_str();
+    let answer = match addr {
+        MetricsAddr::Tcp(addr) => match tokio::net::TcpStream::connect(addr).await {
+            Ok(stream) => control::send(stream, line).await,
+            Err(e) => Err(e),
+        },
+        #[cfg(unix)]
+        MetricsAddr::Unix(path) => match tokio::net::UnixStream::connect(path).await {
+            Ok(stream) => control::send(stream, line).await,
+            Err(e) => Err(e),
+        },
+    };
+    let answer = answer.map_err(|e| format!("could not reach the agent: {}", e))?;
+    let answer: serde_json::Value = serde_json::from_str(&answer).map_err(|_| format!("unexpected answer from the agent: {}", answer.trim()))?;
+    if answer["ok"] == true {
+        Ok(answer)
+    } else {
+        Err(answer["error"].as_str().unwrap_or("refused without a reason").to_string())
+    }
+}
+
+/// Holds the keypair and signs for rigs that only know its pubkey, until interrupted.
+pub async fn serve(args: &AgentArgs, key: Keypair) {
+    let pubkey = key.pubkey();
+    let token = std::env::var(TOKEN_ENV).ok().filter(|token| !token.is_empty());
+    let agent = Arc::new(Agent { key, confirm: args.confirm, token: token.clone(), prompt: Mutex::new(()) });
+    match &args.socket {
+        MetricsAddr::Tcp(addr) => {
+            if !addr.ip().is_loopback() && token.is_none() {
+                println!("  Refusing to listen on {} without a token, set {} here and on the rigs or listen on loopback.", addr, TOKEN_ENV);
+                return;
+            }
+            let listener = match tokio::net::TcpListener::bind(addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    println!("  Failed to start the agent on {}: {}", addr, e);
+                    return;
+                },
+            };
+            println!("  Signing as {} for rigs using --signer agent:{}", pubkey, addr);
+            loop {
+                if let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle(stream, agent.clone()));
+                }
+            }
+        },
+        #[cfg(unix)]
+        MetricsAddr::Unix(path) => {
+            use std::os::unix::fs::PermissionsExt;
+
+            let listener = match metrics::bind_unix(path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    println!("  Failed to start the agent on {}: {}", path.display(), e);
+                    return;
+                },
+            };
+            if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(args.socket_mode.0)) {
+                println!("  Failed to set permissions {:o} on {}: {}", args.socket_mode.0, path.display(), e);
+            }
+            println!("  Signing as {} for rigs using --signer agent:unix:{}", pubkey, path.display());
+            loop {
+                if let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle(stream, agent.clone()));
+                }
+            }
+        },
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
+use spl_token::amount_to_ui_amount;
+
+use crate::audit;
+use crate::claim;
+use crate::endpoint;
+use crate::history::{self, HistoryEntry};
+use crate::network;
+use crate::pool_cache;
+use crate::response_cache;
+use crate::schema::{self, OreAmount, SchemaError, ServerTime, StakeAmount};
+
+#[derive(Debug, Parser)]
+pub struct AccountArgs {
+    #[arg(
+        long,
+        global = true,
+        action,
+        help = "Print the result as JSON"
+    )]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: AccountCommands,
+}
+
+/// Non-interactive pool account commands, for scripts.
+#[derive(Debug, Subcommand)]
+pub enum AccountCommands {
+    #[command(about = "ORE in the wallet's token account.")]
+    Balance,
+    #[command(about = "Unclaimed rewards and delegated stake at the pool.")]
+    Rewards,
+    #[command(about = "Claim rewards without prompting.")]
+    Claim {
+        #[arg(value_name = "AMOUNT", help = "ORE to claim")]
+        amount: f64,
+    },
+    #[command(about = "Delegate stake without prompting. Pools only accept stake early in the hour.")]
+    Stake {
+        #[arg(value_name = "AMOUNT", help = "ORE to stake")]
+        amount: f64,
+    },
+}
+
+impl AccountCommands {
+    /// Commands that sign and send a transaction.
+    pub fn is_on_chain(&self) -> bool {
+        matches!(self, AccountCommands::Claim { .. } | AccountCommands::Stake { .. })
+    }
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    Schema(SchemaError),
+    /// The pool answered, but refused the request.
+    Rejected { endpoint: &'static str, reason: String },
+    /// The transaction could not be built.
+    Transaction(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Schema(e) => e.fmt(f),
+            ApiError::Rejected { endpoint, reason } => write!(f, "/{} rejected the request: {}", endpoint, reason),
+            ApiError::Transaction(detail) => write!(f, "could not build the transaction: {}", detail),
+        }
+    }
+}
+
+impl From<SchemaError> for ApiError {
+    fn from(e: SchemaError) -> Self {
+        ApiError::Schema(e)
+    }
+}
+
+fn to_grains(ore: f64) -> u64 {
+    (ore * 10f64.powf(ore_api::consts::TOKEN_DECIMALS as f64)) as u64
+}
+
+/// The pool's REST endpoints. Requests that change the account carry the same signed
+/// timestamp `Authorization` header as the websocket handshake.
+pub struct PoolApi {
+    client: reqwest::Client,
+    url_prefix: &'static str,
+    base_url: String,
+}
+
+impl PoolApi {
+    pub fn new(base_url: String, unsecure: bool) -> Self {
+        PoolApi {
+            client: network::client(),
+            url_prefix: if unsecure { "http" } else { "https" },
+            base_url,
+        }
+    }
+
+    async fn get<T: schema::PoolResponse>(&self, endpoint: &'static str, pubkey: &Pubkey) -> Result<T, SchemaError> {
+        schema::get(&self.client, self.url_prefix, &self.base_url, endpoint, &format!("?pubkey={}", pubkey)).await
+    }
+
+    pub async fn balance(&self, pubkey: &Pubkey) -> Result<f64, SchemaError> {
+        self.get::<OreAmount>("miner/balance", pubkey).await.map(|amount| amount.0)
+    }
+
+    pub async fn rewards(&self, pubkey: &Pubkey) -> Result<f64, SchemaError> {
+        self.get::<OreAmount>("miner/rewards", pubkey).await.map(|amount| amount.0)
+    }
+
+    /// Delegated stake, None if the wallet has no stake account at the pool.
+    pub async fn stake_balance(&self, pubkey: &Pubkey) -> Result<Option<f64>, SchemaError> {
+        self.get::<StakeAmount>("miner/stake", pubkey).await.map(|amount| amount.0)
+    }
+
+    async fn authorization(&self, key: &Keypair) -> Result<String, SchemaError> {
+        let ServerTime(timestamp) = schema::get(&self.client, self.url_prefix, &self.base_url, "timestamp", "").await?;
+        let sig = audit::sign(key, "timestamp", &timestamp.to_le_bytes());
+        Ok(format!("Basic {}", BASE64_STANDARD.encode(format!("{}:{}", key.pubkey(), sig))))
+    }
+
+    async fn post(&self, key: &Keypair, endpoint: &'static str, query: &str, body: String) -> Result<String, ApiError> {
+        let authorization = self.authorization(key).await?;
+        let request_error = |e: reqwest::Error| SchemaError::Request { endpoint, detail: e.to_string() };
+        let response = self
+            .client
+            .post(endpoint::http(self.url_prefix, &self.base_url, &format!("{}{}", endpoint, query)))
+            .header("Authorization", authorization)
+            .body(body)
+            .send()
+            .await
+            .map_err(request_error)?;
+        let text = response.text().await.map_err(request_error)?;
+        match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["SUCCESS", rest @ ..] => {
+                response_cache::forget(&self.base_url);
+                Ok(rest.join(" "))
+            },
+            _ => Err(ApiError::Rejected { endpoint, reason: text.trim().to_string() }),
+        }
+    }
+
+    /// Claims `amount` ORE, returning the claim transaction signature if the pool reports one.
+    pub async fn claim(&self, key: &Keypair, amount: f64) -> Result<Option<String>, ApiError> {
+        let query = format!("?pubkey={}&amount={}", key.pubkey(), to_grains(amount));
+        let signature = self.post(key, "claim", &query, String::new()).await?;
+        Ok(Some(signature).filter(|signature| !signature.is_empty()))
+    }
+
+    /// Signs a delegate stake transaction for `amount` ORE and has the pool submit it.
+    pub async fn stake(&self, key: &Keypair, amount: f64) -> Result<(), ApiError> {
+        let pool_pubkey = pool_cache::authority_pubkey(&self.client, self.url_prefix, &self.base_url).await.map_err(ApiError::Transaction)?;
+        let fee_pubkey = pool_cache::fee_payer_pubkey(&self.client, self.url_prefix, &self.base_url).await.map_err(ApiError::Transaction)?;
+        let blockhash = self
+            .client
+            .get(endpoint::http(self.url_prefix, &self.base_url, "latest-blockhash"))
+            .send()
+            .await
+            .map_err(|e| SchemaError::Request { endpoint: "latest-blockhash", detail: e.to_string() })?
+            .text()
+            .await
+            .map_err(|e| SchemaError::Request { endpoint: "latest-blockhash", detail: e.to_string() })?;
+        let blockhash = BASE64_STANDARD
+            .decode(blockhash.trim())
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .ok_or_else(|| ApiError::Transaction("unusable /latest-blockhash response".to_string()))?;
+
+        let grains = to_grains(amount);
+        let ix = ore_miner_delegation::instruction::delegate_stake(key.pubkey(), pool_pubkey, grains);
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&fee_pubkey));
+        tx.partial_sign(&[key], blockhash);
+        audit::record("delegate-stake-transaction", &key.pubkey(), &tx.message_data());
+        let encoded_tx = BASE64_STANDARD.encode(bincode::serialize(&tx).map_err(|e| ApiError::Transaction(e.to_string()))?);
+
+        let query = format!("?pubkey={}&amount={}", key.pubkey(), grains);
+        self.post(key, "stake", &query, encoded_tx).await.map(|_| ())
+    }
+}
+
+fn print_error(json: bool, e: &dyn fmt::Display) {
+    if json {
+        println!("{}", json!({ "error": e.to_string() }));
+    } else {
+        println!("  {}", e);
+    }
+}
+
+pub async fn account(args: AccountArgs, key: Keypair, url: String, unsecure: bool) {
+    let api = PoolApi::new(url.clone(), unsecure);
+    let pubkey = key.pubkey();
+    match args.command {
+        AccountCommands::Balance => match api.balance(&pubkey).await {
+            Ok(balance) if args.json => println!("{}", json!({ "pubkey": pubkey.to_string(), "balance": balance })),
+            Ok(balance) => println!("  Wallet Balance: {:.11} ORE", balance),
+            Err(e) => print_error(args.json, &e),
+        },
+        AccountCommands::Rewards => {
+            let rewards = match api.rewards(&pubkey).await {
+                Ok(rewards) => rewards,
+                Err(e) => return print_error(args.json, &e),
+            };
+            let staked = match api.stake_balance(&pubkey).await {
+                Ok(staked) => staked,
+                Err(e) => return print_error(args.json, &e),
+            };
+            if args.json {
+                println!("{}", json!({ "pubkey": pubkey.to_string(), "rewards": rewards, "staked": staked }));
+            } else {
+                println!("  Unclaimed Rewards: {:.11} ORE", rewards);
+                match staked {
+                    Some(staked) => println!("  Staked:            {:.11} ORE", staked),
+                    None => println!("  Staked:            no stake account"),
+                }
+            }
+        },
+        AccountCommands::Claim { amount } => {
+            let balance = match api.balance(&pubkey).await {
+                Ok(balance) => balance,
+                Err(e) => return print_error(args.json, &e),
+            };
+            let claim_info = claim::fetch_claim_info(&api.client, api.url_prefix, &url).await;
+            if amount < claim_info.min_claim {
+                return print_error(args.json, &format!("The pool's minimum claim is {} ORE.", claim_info.min_claim));
+            }
+            match api.claim(&key, amount).await {
+                Ok(signature) => {
+                    let fee = claim_info.fee + if balance == 0.0 { claim_info.token_account_fee } else { 0.0 };
+                    history::record(HistoryEntry::Claim {
+                        at: history::now(),
+                        pool: url,
+                        wallet: pubkey.to_string(),
+                        ore: amount_to_ui_amount(to_grains(amount), ore_api::consts::TOKEN_DECIMALS),
+                        fee_ore: fee,
+                        signature: signature.clone(),
+                    });
+                    if args.json {
+                        println!("{}", json!({ "pubkey": pubkey.to_string(), "claimed": amount, "fee": fee, "signature": signature }));
+                    } else {
+                        println!("  Claimed {} ORE (fee {} ORE).", amount, fee);
+                        if let Some(signature) = signature {
+                            println!("  Claim transaction: {}", signature);
+                        }
+                    }
+                },
+                Err(e) => print_error(args.json, &e),
+            }
+        },
+        AccountCommands::Stake { amount } => match api.stake(&key, amount).await {
+            Ok(()) if args.json => println!("{}", json!({ "pubkey": pubkey.to_string(), "staked": amount })),
+            Ok(()) => println!("  Staked {} ORE.", amount),
+            Err(e) => print_error(args.json, &e),
+        },
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+
+/// Solver tuning preset and advice for the CPU architecture this binary runs on.
+pub struct ArchProfile {
+    pub name: &'static str,
+    /// Instruction set extensions detected at runtime that matter for hashing.
+    pub features: Vec<&'static str>,
+    /// Combinations swept by `tune-solver`.
+    pub memory_counts: &'static [usize],
+    pub interleaves: &'static [u64],
+    pub guidance: Vec<&'static str>,
+}
+
+#[cfg(target_arch = "x86_64")]
+fn detect_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if std::arch::is_x86_feature_detected!("avx2") {
+        features.push("avx2");
+    }
+    if std::arch::is_x86_feature_detected!("avx512f") {
+        features.push("avx512f");
+    }
+    if std::arch::is_x86_feature_detected!("aes") {
+        features.push("aes");
+    }
+    features
+}
+
+#[cfg(target_arch = "aarch64")]
+fn detect_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        features.push("neon");
+    }
+    if std::arch::is_aarch64_feature_detected!("aes") {
+        features.push("aes");
+    }
+    if std::arch::is_aarch64_feature_detected!("sha3") {
+        features.push("sha3");
+    }
+    features
+}
+
+/// RISC-V extensions from the kernel's ISA string, e.g. `rv64imafdcv`.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn detect_features() -> Vec<&'static str> {
+    let isa = fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|cpuinfo| cpuinfo.lines().find_map(|line| line.strip_prefix("isa")?.split(':').nth(1).map(str::trim).map(str::to_string)))
+        .unwrap_or_default();
+    let base = isa.split('_').next().unwrap_or_default();
+    if base.starts_with("rv") && base.get(4..).is_some_and(|extensions| extensions.contains('v')) {
+        vec!["v"]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Whether the kernel reports a big.LITTLE style mix of core types.
+fn heterogeneous_cores() -> bool {
+    let parts: std::collections::BTreeSet<String> = fs::read_to_string("/proc/cpuinfo")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.strip_prefix("CPU part")?.split(':').nth(1).map(|part| part.trim().to_string()))
+        .collect();
+    parts.len() > 1
+}
+
+/// The CPU model as the kernel reports it, e.g. `AMD Ryzen 9 7950X 16-Core Processor`.
+pub fn cpu_model() -> Option<String> {
+    fs::read_to_string("/proc/cpuinfo").ok()?.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        matches!(key.trim(), "model name" | "Model" | "uarch").then(|| value.trim().to_string()).filter(|model| !model.is_empty())
+    })
+}
+
+/// Picks the preset for this CPU from runtime feature detection.
+pub fn detect() -> ArchProfile {
+    let features = detect_features();
+    let mut guidance = Vec::new();
+
+    let (name, memory_counts, interleaves): (_, &'static [usize], &'static [u64]) = if cfg!(target_arch = "aarch64") {
+        guidance.push("Wide ARM cores usually gain from 2-4 solver memories with interleaving, run tune-solver to confirm.");
+        if !features.contains(&"aes") {
+            guidance.push("No ARMv8 crypto extensions, expect lower hashrate per core than on recent SoCs.");
+        }
+        if heterogeneous_cores() {
+            guidance.push("Mixed big and little cores detected, use the threads command to check placement and try fewer threads.");
+        }
+        ("aarch64", &[1, 2, 4], &[1, 2, 4, 8])
+    } else if cfg!(target_arch = "riscv64") {
+        if !features.contains(&"v") {
+            guidance.push("No vector extension reported, hashing runs on scalar code.");
+        }
+        guidance.push("In-order RISC-V cores rarely benefit from extra solver memories.");
+        ("riscv64", &[1, 2], &[1, 2])
+    } else {
+        if !features.contains(&"avx2") {
+            guidance.push("AVX2 is not available, hashrate will be noticeably lower than on recent x86 CPUs.");
+        }
+        ("x86_64", &[1, 2, 4], &[1, 2, 4, 8])
+    };
+    guidance.push("Build on the mining machine itself so target-cpu=native can use every available extension.");
+
+    ArchProfile {
+        name,
+        features,
+        memory_counts,
+        interleaves,
+        guidance,
+    }
+}
+
+impl ArchProfile {
+    pub fn print(&self) {
+        let features = if self.features.is_empty() { "none detected".to_string() } else { self.features.join(", ") };
+        println!("  Architecture:    {} ({})", self.name, features);
+        for line in &self.guidance {
+            println!("  Note: {}", line);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    fs,
+    io::Write,
+    str::FromStr,
+    sync::Mutex,
+};
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    hash::{hash, hashv, Hash},
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+};
+
+use crate::history;
+
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommands {
+    #[command(about = "Check that the audit log is complete and has not been modified.")]
+    Verify,
+}
+
+/// One signature made by a local key. Each entry commits to the hash of the previous one,
+/// so editing, removing or reordering entries breaks the chain.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditEntry {
+    seq: u64,
+    at: u64,
+    kind: String,
+    signer: String,
+    /// SHA-256 of the signed payload.
+    digest: String,
+    prev: String,
+    hash: String,
+}
+
+impl AuditEntry {
+    fn chain_hash(&self) -> Hash {
+        hashv(&[
+            self.prev.as_bytes(),
+            &self.seq.to_le_bytes(),
+            &self.at.to_le_bytes(),
+            self.kind.as_bytes(),
+            self.signer.as_bytes(),
+            self.digest.as_bytes(),
+        ])
+    }
+}
+
+/// Sequence number and hash of the last entry, loaded from the file on first use.
+static HEAD: Mutex<Option<(u64, Hash)>> = Mutex::new(None);
+
+fn load() -> Vec<AuditEntry> {
+    fs::read_to_string(AUDIT_FILE)
+        .map(|contents| contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Signs `message` with `signer` and appends the signature to the audit log.
+pub fn sign(signer: &dyn Signer, kind: &str, message: &[u8]) -> Signature {
+    let signature = signer.sign_message(message);
+    record(kind, &signer.pubkey(), message);
+    signature
+}
+
+/// Appends a signed payload to the audit log, for signatures not made through `sign`
+/// such as transactions.
+pub fn record(kind: &str, signer: &Pubkey, message: &[u8]) {
+    // Unit tests sign with throwaway keys, keep them out of the real log
+    if cfg!(test) {
+        return;
+    }
+    let mut head = HEAD.lock().unwrap();
+    let (last_seq, last_hash) = *head.get_or_insert_with(|| {
+        load()
+            .last()
+            .and_then(|entry| Some((entry.seq, Hash::from_str(&entry.hash).ok()?)))
+            .unwrap_or((0, Hash::default()))
+    });
+
+    let mut entry = AuditEntry {
+        seq: last_seq + 1,
+        at: history::now(),
+        kind: kind.to_string(),
+        signer: signer.to_string(),
+        digest: hash(message).to_string(),
+        prev: last_hash.to_string(),
+        hash: String::new(),
+    };
+    let entry_hash = entry.chain_hash();
+    entry.hash = entry_hash.to_string();
+
+    let Ok(line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    let written = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(AUDIT_FILE)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    match written {
+        Ok(()) => *head = Some((entry.seq, entry_hash)),
+        Err(e) => println!("Failed to write audit log: {}", e),
+    }
+}
+
+/// Walks the hash chain and reports the first entry that does not link up.
+pub fn verify() {
+    let contents = match fs::read_to_string(AUDIT_FILE) {
+        Ok(contents) => contents,
+        Err(_) => {
+            println!("  No audit log found at {}.", AUDIT_FILE);
+            return;
+        }
+    };
+
+    let mut prev = Hash::default().to_string();
+    let mut count = 0;
+    for (line_number, line) in contents.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(line) else {
+            println!("  Line {}: not a valid audit entry.", line_number);
+            return;
+        };
+        if entry.seq != count + 1 {
+            println!("  Line {}: expected entry {}, found {}. Entries were removed or reordered.", line_number, count + 1, entry.seq);
+            return;
+        }
+        if entry.prev != prev {
+            println!("  Line {}: entry {} does not link to the previous entry.", line_number, entry.seq);
+            return;
+        }
+        if entry.chain_hash().to_string() != entry.hash {
+            println!("  Line {}: entry {} was modified.", line_number, entry.seq);
+            return;
+        }
+        prev = entry.hash;
+        count = entry.seq;
+    }
+
+    println!("  Audit log intact: {} signatures, chain head {}.", count, prev);
+}
@@ -1,75 +1,48 @@
-use solana_sdk::{signature::Keypair, signer::Signer};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
 
-pub async fn balance(key: &Keypair, url: String, unsecure: bool) {
+use crate::network;
+use crate::schema::{self, OreAmount, StakeAmount};
+
+pub async fn balance(wallet: &Pubkey, url: String, unsecure: bool) {
     let base_url = url;
-    let client = reqwest::Client::new();
+    let client = network::client();
 
     let url_prefix = if unsecure {
         "http".to_string()
     } else {
         "https".to_string()
     };
+    let query = format!("?pubkey={}", wallet);
 
     // Fetch Wallet (Stakable) Balance
-    let balance_response = client
-        .get(format!(
-            "{}://{}/miner/balance?pubkey={}",
-            url_prefix,
-            base_url,
-            key.pubkey().to_string()
-        ))
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    let balance = match balance_response.parse::<f64>() {
-        Ok(b) => b,
-        Err(_) => 0.0,
+    let balance = match schema::get::<OreAmount>(&client, &url_prefix, &base_url, "miner/balance", &query).await {
+        Ok(OreAmount(b)) => b,
+        Err(e) => {
+            println!("  Error fetching wallet balance: {}", e);
+            0.0
+        }
     };
 
     // Fetch Unclaimed Rewards
-    let rewards_response = client
-        .get(format!(
-            "{}://{}/miner/rewards?pubkey={}",
-            url_prefix,
-            base_url,
-            key.pubkey().to_string()
-        ))
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    let rewards = match rewards_response.parse::<f64>() {
-        Ok(r) => r,
-        Err(_) => 0.0,
+    let rewards = match schema::get::<OreAmount>(&client, &url_prefix, &base_url, "miner/rewards", &query).await {
+        Ok(OreAmount(r)) => r,
+        Err(e) => {
+            println!("  Error fetching unclaimed rewards: {}", e);
+            0.0
+        }
     };
 
     // Fetch Staked Balance
-    let stake_response = client
-        .get(format!(
-            "{}://{}/miner/stake?pubkey={}",
-            url_prefix,
-            base_url,
-            key.pubkey().to_string()
-        ))
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    let staked_balance = if stake_response.contains("Failed to g") {
-        println!("  Delegated stake balance: No staked account");
-        0.0
-    } else {
-        stake_response.parse::<f64>().unwrap_or(0.0)
+    let staked_balance = match schema::get::<StakeAmount>(&client, &url_prefix, &base_url, "miner/stake", &query).await {
+        Ok(StakeAmount(Some(staked))) => staked,
+        Ok(StakeAmount(None)) => {
+            println!("  Delegated stake balance: No staked account");
+            0.0
+        }
+        Err(e) => {
+            println!("  Error fetching stake balance: {}", e);
+            0.0
+        }
     };
     println!("  Unclaimed Rewards: {:.11} ORE", rewards);
     println!("  Wallet (Stakable): {:.11} ORE", balance);
@@ -77,20 +50,14 @@ pub async fn balance(key: &Keypair, url: String, unsecure: bool) {
 }
 
 pub async fn get_balance(key: &Keypair, url: String, unsecure: bool) -> f64 {
-    let client = reqwest::Client::new();
+    let client = network::client();
     let url_prefix = if unsecure { "http" } else { "https" };
 
-    let balance_response = client
-        .get(format!(
-            "{}://{}/miner/balance?pubkey={}",
-            url_prefix, url, key.pubkey().to_string()
-        ))
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    balance_response.parse::<f64>().unwrap_or(0.0)
+    match schema::get::<OreAmount>(&client, url_prefix, &url, "miner/balance", &format!("?pubkey={}", key.pubkey())).await {
+        Ok(OreAmount(balance)) => balance,
+        Err(e) => {
+            println!("  Error fetching wallet balance: {}", e);
+            0.0
+        }
+    }
 }
@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use crate::format;
+use crate::history::{self, HistoryEntry};
+
+/// |t| above this is reported as a significant difference (about 95% confidence).
+const SIGNIFICANT_T: f64 = 1.96;
+
+#[derive(Debug, Default)]
+struct Sample {
+    values: Vec<f64>,
+}
+
+impl Sample {
+    fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    fn variance(&self) -> f64 {
+        let mean = self.mean();
+        self.values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (self.values.len() as f64 - 1.0)
+    }
+
+    fn median(&self) -> f64 {
+        let mut sorted = self.values.clone();
+        sorted.sort_by(f64::total_cmp);
+        sorted[sorted.len() / 2]
+    }
+
+    /// Welch's t statistic of this sample against `other`, None with fewer than two values each.
+    fn welch_t(&self, other: &Sample) -> Option<f64> {
+        if self.values.len() < 2 || other.values.len() < 2 {
+            return None;
+        }
+        let se = (self.variance() / self.values.len() as f64 + other.variance() / other.values.len() as f64).sqrt();
+        (se > 0.0).then(|| (self.mean() - other.mean()) / se)
+    }
+}
+
+/// Compares this session's rounds against round stats recorded in a history file.
+pub struct BaselineComparison {
+    every: usize,
+    baseline_hashrates: Sample,
+    baseline_difficulties: Sample,
+    hashrates: Sample,
+    difficulties: Sample,
+}
+
+impl BaselineComparison {
+    /// Loads the Round entries of a history file. Returns None if it has fewer than two.
+    pub fn load(path: &Path, every: usize) -> Option<Self> {
+        let mut comparison = BaselineComparison {
+            every: every.max(2),
+            baseline_hashrates: Sample::default(),
+            baseline_difficulties: Sample::default(),
+            hashrates: Sample::default(),
+            difficulties: Sample::default(),
+        };
+        for entry in history::load_from(path) {
+            if let HistoryEntry::Round { difficulty, hashes, hash_secs, .. } = entry {
+                if hash_secs > 0.0 {
+                    comparison.baseline_hashrates.values.push(hashes as f64 / hash_secs);
+                    comparison.baseline_difficulties.values.push(difficulty as f64);
+                }
+            }
+        }
+        (comparison.baseline_hashrates.values.len() >= 2).then_some(comparison)
+    }
+
+    pub fn baseline_rounds(&self) -> usize {
+        self.baseline_hashrates.values.len()
+    }
+
+    /// Records a round and prints the comparison every N rounds.
+    pub fn record(&mut self, hashrate: f64, difficulty: u32) {
+        self.hashrates.values.push(hashrate);
+        self.difficulties.values.push(difficulty as f64);
+        if self.hashrates.values.len().is_multiple_of(self.every) {
+            self.print();
+        }
+    }
+
+    fn print(&self) {
+        println!("Baseline comparison after {} rounds (baseline {} rounds):", self.hashrates.values.len(), self.baseline_rounds());
+        print_metric("Hashpower", &self.hashrates, &self.baseline_hashrates, format::hashrate);
+        print_metric("Difficulty", &self.difficulties, &self.baseline_difficulties, format::decimal);
+        println!("  Difficulty median: {} vs {}", self.difficulties.median(), self.baseline_difficulties.median());
+    }
+}
+
+fn print_metric(label: &str, current: &Sample, baseline: &Sample, format_value: fn(f64) -> String) {
+    let verdict = match current.welch_t(baseline) {
+        Some(t) if t.abs() > SIGNIFICANT_T => format!("significantly {} (t = {:.2})", if t > 0.0 { "higher" } else { "lower" }, t),
+        Some(t) => format!("no significant difference (t = {:.2})", t),
+        None => "not enough data".to_string(),
+    };
+    println!("  {}: {} vs {}, {}", label, format_value(current.mean()), format_value(baseline.mean()), verdict);
+}
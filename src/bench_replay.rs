@@ -0,0 +1,217 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{atomic::{AtomicBool, AtomicU64}, Arc},
+    time::Instant,
+};
+
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use crate::format;
+use crate::mine::{self, RoundJob};
+use crate::settings;
+
+#[derive(Debug, Parser)]
+pub struct BenchReplayArgs {
+    #[arg(
+        value_name = "CORPUS",
+        default_value = "challenges.jsonl",
+        help = "Challenges recorded with mine --record-challenges"
+    )]
+    pub corpus: PathBuf,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "5",
+        help = "How long each challenge hashes"
+    )]
+    pub budget: u64,
+    #[arg(
+        long,
+        value_name = "THREADS",
+        help = "Threads to hash with. Defaults to every core"
+    )]
+    pub threads: Option<u32>,
+    #[arg(
+        long,
+        value_name = "COUNT",
+        help = "Replay only the first COUNT challenges of the corpus"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the result as JSON, to compare against later with --compare"
+    )]
+    pub output: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "A result written by an earlier --output, e.g. from another client version or machine"
+    )]
+    pub compare: Option<PathBuf>,
+}
+
+/// One recorded challenge. Only what the solver needs is kept: no pool, wallet, time or
+/// nonce offset, so corpora can be shared.
+#[derive(Debug, Serialize, Deserialize)]
+struct CorpusEntry {
+    challenge: String,
+    range: u64,
+}
+
+/// Appends a challenge to the corpus at `path`.
+pub fn record(path: &Path, challenge: &[u8; 32], range: u64) {
+    let entry = CorpusEntry { challenge: solana_sdk::bs58::encode(challenge).into_string(), range };
+    let appended = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(&entry).unwrap_or_default()));
+    if let Err(e) = appended {
+        println!("Failed to record the challenge to {}: {}", path.display(), e);
+    }
+}
+
+fn load(path: &Path) -> Vec<([u8; 32], u64)> {
+    let Ok(file) = fs::File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<CorpusEntry>(&line).ok())
+        .filter_map(|entry| {
+            let challenge = solana_sdk::bs58::decode(&entry.challenge).into_vec().ok()?.try_into().ok()?;
+            Some((challenge, entry.range.max(1)))
+        })
+        .collect()
+}
+
+/// Outcome of a replay. The score is the hashrate over the corpus, and since every run
+/// hashes the same nonces of the same challenges the difficulties line up across runs too.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReplayResult {
+    version: String,
+    threads: u32,
+    budget: u64,
+    challenges: usize,
+    hashes: u64,
+    secs: f64,
+    score: f64,
+    mean_difficulty: f64,
+}
+
+fn print_comparison(result: &ReplayResult, path: &Path) {
+    let previous = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| serde_json::from_str::<ReplayResult>(&contents).map_err(|e| e.to_string()));
+    let previous = match previous {
+        Ok(previous) => previous,
+        Err(e) => {
+            println!("  Can't compare with {}: {}", path.display(), e);
+            return;
+        },
+    };
+    if (previous.threads, previous.budget, previous.challenges) != (result.threads, result.budget, result.challenges) {
+        println!(
+            "  Note: {} ran {} challenges at {} threads for {}s each, the scores are not directly comparable.",
+            path.display(),
+            previous.challenges,
+            previous.threads,
+            previous.budget
+        );
+    }
+    let change = |now: f64, before: f64| if before > 0.0 { (now / before - 1.0) * 100.0 } else { 0.0 };
+    println!("  Compared with v{} ({}):", previous.version, path.display());
+    println!("    Score:           {:.0} -> {:.0} ({:+.1}%)", previous.score, result.score, change(result.score, previous.score));
+    println!(
+        "    Mean difficulty: {:.2} -> {:.2} ({:+.1}%)",
+        previous.mean_difficulty,
+        result.mean_difficulty,
+        change(result.mean_difficulty, previous.mean_difficulty)
+    );
+}
+
+/// Runs the solver over a recorded corpus of real challenges with a fixed budget each and
+/// prints a score that can be compared across client versions and machines.
+pub async fn bench_replay(args: &BenchReplayArgs) {
+    let mut corpus = load(&args.corpus);
+    if let Some(limit) = args.limit {
+        corpus.truncate(limit);
+    }
+    if corpus.is_empty() {
+        println!("  No challenges in {}, record some with mine --record-challenges {}.", args.corpus.display(), args.corpus.display());
+        return;
+    }
+    let running = Arc::new(AtomicBool::new(true));
+    let solver = settings::load().solver;
+    let threads = args.threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get() as u32)).max(1);
+
+    println!(
+        "  Replaying {} challenges for {}s each on {} threads (solver: memories {} interleave {})",
+        corpus.len(),
+        args.budget,
+        threads,
+        solver.memories,
+        solver.interleave
+    );
+    let mut hashes: u64 = 0;
+    let mut secs = 0.0;
+    let mut difficulties = Vec::new();
+    for (i, (challenge, range)) in corpus.iter().enumerate() {
+        let started = Instant::now();
+        let job = RoundJob {
+            challenge: *challenge,
+            nonce_range: 0..*range,
+            cutoff: args.budget,
+            hash_timer: started,
+            cursor: Arc::new(AtomicU64::new(0)),
+            paranoid: false,
+            paranoid_min_difficulty: 0,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            solver,
+            control: Default::default(),
+            best_difficulty: Default::default(),
+            exit_target: None,
+        };
+        let results: Vec<_> = mine::mine_round(job, threads, None, Default::default(), &running).await.into_iter().flatten().collect();
+        let round_secs = started.elapsed().as_secs_f64();
+        let round_hashes = results.iter().map(|result| result.total_hashes).sum::<u64>();
+        let difficulty = results.iter().map(|result| result.best_difficulty).max().unwrap_or(0);
+        println!(
+            "  {:>4}/{}: {}, difficulty {}",
+            i + 1,
+            corpus.len(),
+            format::hashrate(round_hashes as f64 / round_secs.max(f64::EPSILON)),
+            difficulty
+        );
+        hashes += round_hashes;
+        secs += round_secs;
+        difficulties.push(difficulty);
+    }
+
+    let result = ReplayResult {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        threads,
+        budget: args.budget,
+        challenges: corpus.len(),
+        hashes,
+        secs,
+        score: hashes as f64 / secs.max(f64::EPSILON),
+        mean_difficulty: difficulties.iter().sum::<u32>() as f64 / difficulties.len() as f64,
+    };
+    println!("  Score: {:.0} ({}), mean difficulty {:.2}", result.score, format::hashrate(result.score), result.mean_difficulty);
+
+    if let Some(path) = &args.compare {
+        print_comparison(&result, path);
+    }
+    if let Some(path) = &args.output {
+        match serde_json::to_string_pretty(&result).map_err(|e| e.to_string()).and_then(|json| fs::write(path, json).map_err(|e| e.to_string())) {
+            Ok(()) => println!("  Result written to {}", path.display()),
+            Err(e) => println!("  Failed to write {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    sync::{atomic::{AtomicBool, AtomicU64}, Arc},
+    time::Instant,
+};
+
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::arch;
+use crate::format;
+use crate::mine::{self, RoundJob};
+use crate::network;
+use crate::settings;
+
+#[derive(Debug, Parser)]
+pub struct BenchmarkArgs {
+    #[arg(
+        long,
+        value_name = "THREADS",
+        value_delimiter = ',',
+        help = "Thread counts to try, e.g. 4,8,12. Defaults to powers of two up to all cores, and all cores"
+    )]
+    pub threads: Vec<u32>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "15",
+        help = "How long each round hashes"
+    )]
+    pub duration: u64,
+    #[arg(
+        long,
+        value_name = "ROUNDS",
+        default_value = "3",
+        help = "Rounds per thread count, each with a fresh challenge"
+    )]
+    pub rounds: u32,
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Opt in to community stats: send the best result as CPU model, cores, threads, hashrate and client version, nothing else, to this service and show how it compares"
+    )]
+    pub share_with: Option<String>,
+}
+
+/// How a shared result compares to the others for the same CPU model.
+#[derive(Debug, Deserialize)]
+struct Comparison {
+    percentile: f64,
+    samples: u64,
+}
+
+fn ordinal(n: u64) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Posts the best result to `<url>/benchmarks` and prints the percentile it answers with.
+async fn share(url: &str, threads: u32, rate: f64) {
+    let cpu = arch::cpu_model().unwrap_or_else(|| "unknown".to_string());
+    let result = json!({
+        "cpu_model": cpu,
+        "cores": std::thread::available_parallelism().map_or(1, |n| n.get()),
+        "arch": std::env::consts::ARCH,
+        "threads": threads,
+        "hashrate": rate,
+        "client_version": env!("CARGO_PKG_VERSION"),
+    });
+    println!("  Sharing {}", result);
+    let response = network::client()
+        .post(format!("{}/benchmarks", url.trim_end_matches('/')))
+        .json(&result)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let comparison = match response {
+        Ok(response) => response.json::<Comparison>().await,
+        Err(e) => Err(e),
+    };
+    match comparison {
+        Ok(comparison) => {
+            let percentile = comparison.percentile.clamp(0.0, 100.0).round() as u64;
+            println!("  Your {} performs at the {} percentile of {} shared results.", cpu, ordinal(percentile), comparison.samples);
+            if percentile < 50 {
+                println!("  That is below the median for this CPU, check cooling, power limits and the solver settings.");
+            }
+        },
+        Err(e) => println!("  Could not share the result: {}", e),
+    }
+}
+
+/// Thread counts to sweep when none are given: 1, 2, 4, ... and every core.
+fn default_sweep() -> Vec<u32> {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+    let mut sweep: Vec<u32> = std::iter::successors(Some(1u32), |n| n.checked_mul(2)).take_while(|n| *n < cores).collect();
+    sweep.push(cores);
+    sweep
+}
+
+/// Hashes synthetic challenges with the mining solver at each thread count and prints the
+/// hashrate and best difficulties, to pick --threads without a pool or keypair.
+pub async fn benchmark(args: &BenchmarkArgs) {
+    let running = Arc::new(AtomicBool::new(true));
+    let solver = settings::load().solver;
+    let sweep = if args.threads.is_empty() { default_sweep() } else { args.threads.clone() };
+    let rounds = args.rounds.max(1);
+
+    println!(
+        "  Benchmarking {} thread counts, {} rounds of {}s each (solver: memories {} interleave {})",
+        sweep.len(),
+        rounds,
+        args.duration,
+        solver.memories,
+        solver.interleave
+    );
+    let mut best: Option<(u32, f64)> = None;
+    for &threads in sweep.iter().filter(|threads| **threads > 0) {
+        let mut hashes: u64 = 0;
+        let mut secs = 0.0;
+        let mut difficulties = Vec::new();
+        for _ in 0..rounds {
+            let started = Instant::now();
+            let job = RoundJob {
+                challenge: rand::random(),
+                nonce_range: 0..u64::MAX,
+                cutoff: args.duration,
+                hash_timer: started,
+                cursor: Arc::new(AtomicU64::new(0)),
+                paranoid: false,
+                paranoid_min_difficulty: 0,
+                cancelled: Arc::new(AtomicBool::new(false)),
+                solver,
+                control: Default::default(),
+                best_difficulty: Default::default(),
+                exit_target: None,
+            };
+            let results: Vec<_> = mine::mine_round(job, threads, None, Default::default(), &running).await.into_iter().flatten().collect();
+            secs += started.elapsed().as_secs_f64();
+            hashes += results.iter().map(|result| result.total_hashes).sum::<u64>();
+            difficulties.push(results.iter().map(|result| result.best_difficulty).max().unwrap_or(0));
+        }
+        difficulties.sort_unstable();
+
+        let rate = hashes as f64 / secs;
+        println!(
+            "  {:>3} threads: {} ({} per thread), best difficulty per round: min {} median {} max {}",
+            threads,
+            format::hashrate(rate),
+            format::hashrate(rate / threads as f64),
+            difficulties[0],
+            difficulties[difficulties.len() / 2],
+            difficulties[difficulties.len() - 1]
+        );
+        if best.is_none_or(|(_, best_rate)| rate > best_rate) {
+            best = Some((threads, rate));
+        }
+    }
+
+    if let Some((threads, rate)) = best {
+        println!("  Best: --threads {} at {}", threads, format::hashrate(rate));
+        if let Some(url) = &args.share_with {
+            share(url, threads, rate).await;
+        }
+    }
+}
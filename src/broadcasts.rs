@@ -0,0 +1,45 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// How many pool text messages to keep.
+const RECENT_BROADCASTS: usize = 50;
+
+/// The most recent text messages broadcast by the pool, oldest first, so announcements
+/// can still be read after they have scrolled past.
+#[derive(Debug, Default)]
+pub struct RecentBroadcasts {
+    entries: Mutex<VecDeque<(DateTime<Utc>, String)>>,
+}
+
+impl RecentBroadcasts {
+    pub fn push(&self, text: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == RECENT_BROADCASTS {
+            entries.pop_front();
+        }
+        entries.push_back((Utc::now(), text.to_string()));
+    }
+
+    /// One line per message, prefixed with when it arrived.
+    pub fn lines(&self) -> Vec<String> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(at, text)| format!("[{}] {}", at.to_rfc3339_opts(SecondsFormat::Secs, true), text.trim_end()))
+            .collect()
+    }
+
+    pub fn print_summary(&self) {
+        let lines = self.lines();
+        if lines.is_empty() {
+            return;
+        }
+        println!("Last {} pool messages:", lines.len());
+        for line in lines {
+            println!("  {}", line);
+        }
+    }
+}
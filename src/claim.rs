@@ -1,71 +1,148 @@
 use inquire::{Text, InquireError};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use clap::Parser;
-use solana_sdk::{signature::Keypair, signer::Signer};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::{Keypair, Signature}, signer::Signer};
 use colored::*;
+use spl_associated_token_account::get_associated_token_address;
 use spl_token::amount_to_ui_amount;
 
+use crate::audit;
+use crate::endpoint;
+use crate::history::{self, HistoryEntry};
+use crate::network;
+use crate::notify;
+use crate::response_cache;
+use crate::schema::{self, ClaimRules, OreAmount, ServerTime};
+use crate::settings;
+use crate::signer::{self, MinerSigner, SignerSource};
+
+/// ORE deducted from the first claim to create the token account.
+const TOKEN_ACCOUNT_FEE_ORE: f64 = 0.004;
+/// Smallest claim accepted by pools that don't publish their own minimum.
+const DEFAULT_MIN_CLAIM_ORE: f64 = 0.005;
+/// A blockhash is valid for 150 slots, about a minute. Allow some slack before giving up on a claim.
+const BLOCKHASH_EXPIRY_SECS: u64 = 90;
+const CONFIRMATION_POLL_SECS: u64 = 2;
+/// Times the pool is asked to re-sign and resubmit a claim whose blockhash expired.
+const MAX_CLAIM_RESUBMITS: u32 = 2;
+
+/// Claim rules published by the pool at `/claim-info`, all amounts in ORE.
+#[derive(Debug)]
+pub struct ClaimInfo {
+    pub min_claim: f64,
+    /// Flat fee charged on every claim.
+    pub fee: f64,
+    /// One time fee when the claim creates the token account.
+    pub token_account_fee: f64,
+}
+
+impl Default for ClaimInfo {
+    fn default() -> Self {
+        ClaimInfo {
+            min_claim: DEFAULT_MIN_CLAIM_ORE,
+            fee: 0.0,
+            token_account_fee: TOKEN_ACCOUNT_FEE_ORE,
+        }
+    }
+}
+
+/// Fetches the pool's claim rules, falling back to the defaults for pools without the endpoint.
+pub async fn fetch_claim_info(client: &reqwest::Client, url_prefix: &str, url: &str) -> ClaimInfo {
+    let defaults = ClaimInfo::default();
+    match schema::get::<ClaimRules>(client, url_prefix, url, "claim-info", "").await {
+        Ok(rules) => ClaimInfo {
+            min_claim: rules.min_claim.unwrap_or(defaults.min_claim),
+            fee: rules.fee.unwrap_or(defaults.fee),
+            token_account_fee: rules.token_account_fee.unwrap_or(defaults.token_account_fee),
+        },
+        Err(e) if e.is_not_found() => defaults,
+        Err(e) => {
+            println!("  Using default claim rules, {}", e);
+            defaults
+        }
+    }
+}
+
 #[derive(Debug, Parser)]
 pub struct ClaimArgs {
     #[arg(
         long,
         value_name = "AMOUNT",
-        help = "Amount of ore to claim. (Minimum of 0.005 ORE)"
+        help = "Amount of ore to claim. (Pool minimum, 0.005 ORE by default)"
     )]
     pub amount: Option<f64>,
+    #[arg(
+        long,
+        value_name = "SIGNER",
+        help = "Supervisor key that co-signs the claim, as ledger, remote:<url> or file:<path>. Overrides cosigner in [claim] of the settings"
+    )]
+    pub cosigner: Option<SignerSource>,
+}
+
+/// Connects to the claim co-signer from --cosigner or the settings, if there is one.
+async fn connect_cosigner(arg: Option<SignerSource>) -> Result<Option<MinerSigner>, String> {
+    let source = match arg {
+        Some(source) => source,
+        None => match settings::load().claim.cosigner {
+            Some(spec) => spec.parse().map_err(|e| format!("invalid cosigner in [claim]: {}", e))?,
+            None => return Ok(None),
+        },
+    };
+    signer::connect(&source).await.map(Some)
 }
 
-pub async fn claim(args: ClaimArgs, key: Keypair, url: String, unsecure: bool) {
-    let client = reqwest::Client::new();
+pub async fn claim(args: ClaimArgs, key: Keypair, url: String, unsecure: bool, rpc_url: String) {
+    let client = network::client();
     let url_prefix = if unsecure {
         "http".to_string()
     } else {
         "https".to_string()
     };
 
-    let balance_response = client
-    .get(format!(
-        "{}://{}/miner/balance?pubkey={}",
-        url_prefix,
-        url,
-        key.pubkey().to_string()
-    ))
-    .send()
-    .await
-    .unwrap()
-    .text()
-    .await
-    .unwrap();
-
-let balance = balance_response.parse::<f64>().unwrap_or(0.0);
-
-let rewards_response = client
-    .get(format!(
-        "{}://{}/miner/rewards?pubkey={}",
-        url_prefix,
-        url,
-        key.pubkey().to_string()
-    ))
-    .send()
-    .await
-    .unwrap()
-    .text()
-    .await
-    .unwrap();
-
-let rewards = rewards_response.parse::<f64>().unwrap_or(0.0);
+    // Reach the co-signer before anything is asked, a claim it can't sign would only fail later
+    let cosigner = match connect_cosigner(args.cosigner).await {
+        Ok(cosigner) => cosigner,
+        Err(e) => {
+            println!("  Error connecting to the claim co-signer: {}", e);
+            return;
+        }
+    };
+    if let Some(cosigner) = &cosigner {
+        println!("  Claims are co-signed by {}", cosigner.pubkey());
+    }
+
+    let query = format!("?pubkey={}", key.pubkey());
+    let balance = match schema::get::<OreAmount>(&client, &url_prefix, &url, "miner/balance", &query).await {
+        Ok(OreAmount(balance)) => balance,
+        Err(e) => {
+            println!("  Error fetching wallet balance: {}", e);
+            return;
+        }
+    };
+    let rewards = match schema::get::<OreAmount>(&client, &url_prefix, &url, "miner/rewards", &query).await {
+        Ok(OreAmount(rewards)) => rewards,
+        Err(e) => {
+            println!("  Error fetching unclaimed rewards: {}", e);
+            return;
+        }
+    };
+let claim_info = fetch_claim_info(&client, &url_prefix, &url).await;
+let min_claim = claim_info.min_claim;
+// The token account fee only applies when the claim has to create the account
+let claim_fee = claim_info.fee + if balance == 0.0 { claim_info.token_account_fee } else { 0.0 };
 
 println!("  Unclaimed Rewards: {:.11} ORE", rewards);
 println!("  Wallet Balance:    {:.11} ORE", balance);
 
 // Check if the wallet balance is zero and display the note
 if balance == 0.0 {
-    println!("\n  Note: A 0.004 ORE fee will be deducted from your claim amount to cover the cost\n  of Token Account Creation. This is a one time fee used to create the ORE Token Account.");
+    println!("\n  Note: A {} ORE fee will be deducted from your claim amount to cover the cost\n  of Token Account Creation. This is a one time fee used to create the ORE Token Account.", claim_info.token_account_fee);
 }
 
-if rewards < 0.005 {
+if rewards < min_claim {
     println!();
-    println!("  You have not reached the required claim limit of 0.005 ORE.");
+    println!("  You have not reached the pool's required claim limit of {} ORE.", min_claim);
     println!("  Keep mining to accumulate more rewards before you can withdraw.");
     return;
 }
@@ -81,14 +158,14 @@ if rewards < 0.005 {
 
     let mut claim_amount = args.amount.unwrap_or(0.0);
 
-    // Prompt the user for an amount if it's not provided or less than 0.005
+    // Prompt the user for an amount if it's not provided or less than the pool minimum
     loop {
-        if claim_amount < 0.005 {
+        if claim_amount < min_claim {
             if claim_amount != 0.0 { // Only show the message if they previously entered an invalid value
-                println!("  Please enter a number above 0.005.");
+                println!("  Please enter a number above {}.", min_claim);
             }
 
-            match Text::new("\n  Enter the amount to claim (minimum 0.005 ORE or 'esc' to cancel):")
+            match Text::new(&format!("\n  Enter the amount to claim (minimum {} ORE or 'esc' to cancel):", min_claim))
                 .prompt()
             {
                 Ok(input) => {
@@ -98,9 +175,9 @@ if rewards < 0.005 {
                     }
 
                     claim_amount = match input.trim().parse::<f64>() {
-                        Ok(val) if val >= 0.005 => val,
+                        Ok(val) if val >= min_claim => val,
                         _ => {
-                            println!("  Please enter a valid number above 0.005.");
+                            println!("  Please enter a valid number above {}.", min_claim);
                             continue;
                         }
                     };
@@ -135,6 +212,19 @@ if rewards < 0.005 {
         );
     }
 
+    let claim_ore = amount_to_ui_amount(claim_amount_grains, ore_api::consts::TOKEN_DECIMALS);
+    let net_ore = claim_ore - claim_fee;
+    println!();
+    println!("  Claim preview");
+    println!("    Amount:      {} ORE", claim_ore);
+    println!("    Fee:         {} ORE", claim_fee);
+    println!("    Net:         {:.11} ORE", net_ore);
+    println!("    Destination: {}", get_associated_token_address(&key.pubkey(), &ore_api::consts::MINT_ADDRESS));
+    if net_ore <= 0.0 {
+        println!("  The claim amount does not cover the fee, claim a larger amount.");
+        return;
+    }
+
     // RED TEXT
 match Text::new(
     &format!(
@@ -171,43 +261,134 @@ match Text::new(
         amount_to_ui_amount(claim_amount_grains, ore_api::consts::TOKEN_DECIMALS)
     );
 
-    let resp = client
-        .post(format!(
-            "{}://{}/claim?pubkey={}&amount={}",
-            url_prefix,
-            url,
-            key.pubkey().to_string(),
-            claim_amount_grains
-        ))
-        .send()
-        .await;
-
-    match resp {
-        Ok(res) => match res.text().await.unwrap().as_str() {
-            "SUCCESS" => {
-                println!("  Successfully claimed rewards!");
-            }
-            "QUEUED" => {
-                println!("  Claim is already queued for processing.");
+    let mut resubmits = 0;
+    loop {
+        let resp = send_claim(&client, &url_prefix, &url, &key, claim_amount_grains, cosigner.as_ref()).await;
+
+        match resp {
+            Ok(text) => match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["SUCCESS", rest @ ..] => {
+                    response_cache::forget(&url);
+                    // Pools that report the claim transaction let us follow it until it is finalized
+                    let signature = rest.first().and_then(|sig| sig.parse::<Signature>().ok());
+                    let signature = match signature {
+                        Some(signature) => match track_confirmation(&network::rpc(rpc_url.clone()), &signature).await {
+                            ClaimStatus::Finalized => Some(signature),
+                            ClaimStatus::Failed(err) => {
+                                println!("  Claim transaction {} failed: {}", signature, err);
+                                return;
+                            }
+                            ClaimStatus::Expired if resubmits < MAX_CLAIM_RESUBMITS => {
+                                resubmits += 1;
+                                println!("  Claim transaction {} expired before landing, asking the pool to resubmit ({}/{})...", signature, resubmits, MAX_CLAIM_RESUBMITS);
+                                continue;
+                            }
+                            ClaimStatus::Expired => {
+                                println!("  Claim transaction {} expired before landing. Check your balance before claiming again.", signature);
+                                return;
+                            }
+                        },
+                        None => {
+                            println!("  The pool did not report a claim transaction, confirmation is not tracked.");
+                            None
+                        }
+                    };
+                    println!("  Successfully claimed rewards!");
+                    notify::claim_confirmed(amount_to_ui_amount(claim_amount_grains, ore_api::consts::TOKEN_DECIMALS));
+                    history::record(HistoryEntry::Claim {
+                        at: history::now(),
+                        pool: url.clone(),
+                        wallet: key.pubkey().to_string(),
+                        ore: amount_to_ui_amount(claim_amount_grains, ore_api::consts::TOKEN_DECIMALS),
+                        fee_ore: claim_fee,
+                        signature: signature.map(|signature| signature.to_string()),
+                    });
+                }
+                ["QUEUED"] => {
+                    println!("  Claim is already queued for processing.");
+                }
+                _ => {
+                    if let Ok(time) = text.parse::<u64>() {
+                        let time_left = 1800 - time;
+                        let secs = time_left % 60;
+                        let mins = (time_left / 60) % 60;
+                        println!(
+                            "  You cannot claim until the time is up. Time left until next claim available: {}m {}s",
+                            mins, secs
+                        );
+                    } else {
+                        println!("  Unexpected response: {}", text);
+                    }
+                }
+            },
+            Err(e) => {
+                println!("  ERROR: {}", e);
+                println!("  Retrying in 5 seconds...");
+                tokio::time::sleep(Duration::from_secs(5)).await;
             }
-            other => {
-                if let Ok(time) = other.parse::<u64>() {
-                    let time_left = 1800 - time;
-                    let secs = time_left % 60;
-                    let mins = (time_left / 60) % 60;
-                    println!(
-                        "  You cannot claim until the time is up. Time left until next claim available: {}m {}s",
-                        mins, secs
-                    );
+        }
+        break;
+    }
+}
+
+/// Requests a claim. With a co-signer, the rig key and the co-signer both sign
+/// `claim:<pubkey>:<grains>:<timestamp>` for pools that require two-party authorization.
+async fn send_claim(client: &reqwest::Client, url_prefix: &str, url: &str, key: &Keypair, grains: u64, cosigner: Option<&MinerSigner>) -> Result<String, String> {
+    let pubkey = key.pubkey();
+    let mut request = client.post(endpoint::http(url_prefix, url, &format!("claim?pubkey={}&amount={}", pubkey, grains)));
+    if let Some(cosigner) = cosigner {
+        let ServerTime(timestamp) = schema::get::<ServerTime>(client, url_prefix, url, "timestamp", "").await.map_err(|e| e.to_string())?;
+        let message = format!("claim:{}:{}:{}", pubkey, grains, timestamp);
+        let cosignature = cosigner.try_sign_message(message.as_bytes()).map_err(|e| format!("the co-signer did not sign: {}", e))?;
+        audit::record("claim_cosign", &cosigner.pubkey(), message.as_bytes());
+        request = request
+            .header("X-Claim-Timestamp", timestamp.to_string())
+            .header("X-Claim-Signature", audit::sign(key, "claim", message.as_bytes()).to_string())
+            .header("X-Cosigner", cosigner.pubkey().to_string())
+            .header("X-Cosigner-Signature", cosignature.to_string());
+    }
+    request.send().await.map_err(|e| e.to_string())?.text().await.map_err(|e| e.to_string())
+}
+
+enum ClaimStatus {
+    Finalized,
+    Failed(String),
+    /// Never seen by the cluster within a blockhash lifetime, so it can no longer land.
+    Expired,
+}
+
+/// Polls the claim transaction, printing each commitment level it reaches, until it is
+/// finalized, fails, or its blockhash has certainly expired.
+async fn track_confirmation(rpc: &RpcClient, signature: &Signature) -> ClaimStatus {
+    println!("  Claim transaction: {}", signature);
+    let started = Instant::now();
+    let mut last_level = "";
+    loop {
+        if let Ok(response) = rpc.get_signature_statuses_with_history(&[*signature]).await {
+            if let Some(Some(status)) = response.value.first() {
+                if let Some(err) = &status.err {
+                    return ClaimStatus::Failed(err.to_string());
+                }
+                let level = if status.satisfies_commitment(CommitmentConfig::finalized()) {
+                    "finalized"
+                } else if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                    "confirmed"
                 } else {
-                    println!("  Unexpected response: {}", other);
+                    "processed"
+                };
+                if level != last_level {
+                    println!("  Claim transaction {} after {}s", level, started.elapsed().as_secs());
+                    last_level = level;
+                }
+                if level == "finalized" {
+                    return ClaimStatus::Finalized;
                 }
             }
-        },
-        Err(e) => {
-            println!("  ERROR: {}", e);
-            println!("  Retrying in 5 seconds...");
-            tokio::time::sleep(Duration::from_secs(5)).await;
         }
+        // Once a transaction has been processed it no longer depends on its blockhash
+        if last_level.is_empty() && started.elapsed() >= Duration::from_secs(BLOCKHASH_EXPIRY_SECS) {
+            return ClaimStatus::Expired;
+        }
+        tokio::time::sleep(Duration::from_secs(CONFIRMATION_POLL_SECS)).await;
     }
-}
\ No newline at end of file
+}
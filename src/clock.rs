@@ -0,0 +1,106 @@
+use std::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::schema::{self, ServerTime};
+
+/// Skew the pool tolerates comfortably. Both sources have whole second resolution, so
+/// anything below this is measurement noise.
+const SKEW_WARN_SECS: i64 = 3;
+/// Secondary time source when none is configured, any HTTPS server sends a `Date` header.
+pub const DEFAULT_TIME_SOURCE: &str = "https://www.cloudflare.com";
+
+/// Seconds added to the local clock for signed timestamps, set by --auto-clock-offset.
+static OFFSET: AtomicI64 = AtomicI64::new(0);
+
+/// Unix time for signed timestamps: the local clock plus the offset in effect.
+pub fn now() -> u64 {
+    let local = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs() as i64;
+    (local + OFFSET.load(Ordering::Relaxed)).max(0) as u64
+}
+
+/// How far the local clock is ahead (positive) or behind (negative) each source, in seconds.
+/// None where a source could not be reached.
+pub struct ClockSkew {
+    pub pool: Option<i64>,
+    pub http_date: Option<i64>,
+}
+
+/// Local unix time at the midpoint of a request, to cancel out most of the round trip.
+async fn measure<T, F: std::future::Future<Output = Option<T>>>(request: F) -> Option<(T, f64)> {
+    let sent = SystemTime::now();
+    let timer = Instant::now();
+    let response = request.await?;
+    let midpoint = sent + timer.elapsed() / 2;
+    Some((response, midpoint.duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs_f64()))
+}
+
+pub async fn check(client: &reqwest::Client, url_prefix: &str, base_url: &str, time_source: &str) -> ClockSkew {
+    let pool = measure(async { schema::get::<ServerTime>(client, url_prefix, base_url, "timestamp", "").await.ok() })
+        .await
+        .map(|(ServerTime(pool), local)| (local - pool as f64).round() as i64);
+    let http_date = measure(async {
+        let response = client.head(time_source).send().await.ok()?;
+        let date = response.headers().get(reqwest::header::DATE)?.to_str().ok()?;
+        chrono::DateTime::parse_from_rfc2822(date).ok()
+    })
+    .await
+    .map(|(date, local)| (local - date.timestamp() as f64).round() as i64);
+    ClockSkew { pool, http_date }
+}
+
+fn describe(skew: i64) -> String {
+    match skew {
+        0 => "in sync".to_string(),
+        s if s > 0 => format!("{}s ahead", s),
+        s => format!("{}s behind", -s),
+    }
+}
+
+fn remediation() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "w32tm /resync /force (as Administrator)"
+    } else if cfg!(target_os = "macos") {
+        "sudo sntp -sS time.apple.com"
+    } else {
+        "sudo timedatectl set-ntp true, or sudo chronyc makestep when chrony is installed"
+    }
+}
+
+/// Prints the measured skew and what to do about it. With `auto_offset`, signed timestamps
+/// follow the pool's clock from now on instead.
+pub fn report(skew: &ClockSkew, base_url: &str, time_source: &str, auto_offset: bool) {
+    let Some(pool) = skew.pool else {
+        if let Some(http_date) = skew.http_date.filter(|s| s.abs() >= SKEW_WARN_SECS) {
+            println!("WARNING: the local clock is {} compared to {}.", describe(http_date), time_source);
+            println!("  Sync it with: {}", remediation());
+        }
+        return;
+    };
+    if pool.abs() < SKEW_WARN_SECS {
+        return;
+    }
+
+    let local_is_wrong = skew.http_date.is_none_or(|http_date| (http_date - pool).abs() < SKEW_WARN_SECS);
+    match skew.http_date {
+        Some(http_date) => println!(
+            "WARNING: the local clock is {} compared to {} and {} compared to {}.",
+            describe(pool),
+            base_url,
+            describe(http_date),
+            time_source
+        ),
+        None => println!("WARNING: the local clock is {} compared to {} ({} unreachable).", describe(pool), base_url, time_source),
+    }
+    if auto_offset {
+        OFFSET.store(-pool, Ordering::Relaxed);
+        println!("  Signing timestamps with a {}s offset to match {} (--auto-clock-offset).", -pool, base_url);
+    } else if local_is_wrong {
+        println!("  The pool may reject authentication. Sync the clock with: {}", remediation());
+        println!("  Or pass --auto-clock-offset to sign with the pool's time instead.");
+    } else {
+        println!("  The local clock agrees with {}, so the pool's clock is likely off. Tell the operator,", time_source);
+        println!("  or pass --auto-clock-offset to sign with the pool's time.");
+    }
+}
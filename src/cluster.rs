@@ -0,0 +1,108 @@
+use std::{fs, io::Write};
+
+use clap::{Parser, ValueEnum};
+use solana_sdk::{commitment_config::CommitmentConfig, native_token::sol_to_lamports, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::network;
+
+/// Pubkeys that received devnet airdrops, so they can be kept away from mainnet pools.
+pub const DEVNET_KEYS_FILE: &str = "devnet_keys";
+const MAINNET_POOL_URL: &str = "ec1ipse.me";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    /// Requires --rpc.
+    Custom,
+}
+
+impl Cluster {
+    fn default_rpc(&self) -> Option<&'static str> {
+        match self {
+            Cluster::Mainnet => Some("https://api.mainnet-beta.solana.com"),
+            Cluster::Devnet => Some("https://api.devnet.solana.com"),
+            Cluster::Custom => None,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct AirdropArgs {
+    #[arg(
+        long,
+        value_name = "SOL",
+        default_value = "1.0",
+        help = "Amount of devnet SOL to request"
+    )]
+    pub amount: f64,
+}
+
+/// Picks the RPC URL: an explicit --rpc wins, otherwise the cluster default.
+pub fn resolve_rpc(cluster: Cluster, rpc: Option<String>) -> String {
+    match rpc.or(cluster.default_rpc().map(|s| s.to_string())) {
+        Some(rpc) => rpc,
+        None => {
+            println!("  --cluster custom requires --rpc.");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn is_devnet_key(pubkey: &Pubkey) -> bool {
+    fs::read_to_string(DEVNET_KEYS_FILE)
+        .map(|contents| contents.lines().any(|line| line.trim() == pubkey.to_string()))
+        .unwrap_or(false)
+}
+
+fn mark_devnet_key(pubkey: &Pubkey) {
+    if is_devnet_key(pubkey) {
+        return;
+    }
+    if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(DEVNET_KEYS_FILE) {
+        let _ = writeln!(file, "{}", pubkey);
+    }
+}
+
+/// Guard rails against mixing devnet keys with mainnet pools and vice versa.
+pub fn check_pool(cluster: Cluster, url: &str, pubkey: &Pubkey) -> bool {
+    if cluster == Cluster::Devnet && url == MAINNET_POOL_URL {
+        println!("  Refusing to connect to the mainnet pool {} with --cluster devnet. Pass --url for a devnet pool.", url);
+        return false;
+    }
+    if cluster == Cluster::Mainnet && is_devnet_key(pubkey) {
+        println!("  Keypair {} was funded by a devnet airdrop, refusing to use it against a mainnet pool.", pubkey);
+        println!("  Pass --cluster devnet to mine against a devnet pool.");
+        return false;
+    }
+    true
+}
+
+pub async fn airdrop(args: AirdropArgs, key: &Keypair, rpc_url: String, cluster: Cluster) {
+    if cluster != Cluster::Devnet {
+        println!("  Airdrops are only available with --cluster devnet.");
+        return;
+    }
+
+    let client = network::rpc_with_commitment(rpc_url, CommitmentConfig::confirmed());
+    let pubkey = key.pubkey();
+
+    println!("  Requesting airdrop of {} SOL to {}...", args.amount, pubkey);
+    let sig = match client.request_airdrop(&pubkey, sol_to_lamports(args.amount)).await {
+        Ok(sig) => sig,
+        Err(e) => {
+            println!("  Airdrop request failed: {}", e);
+            return;
+        }
+    };
+
+    match client.poll_for_signature(&sig).await {
+        Ok(()) => {
+            mark_devnet_key(&pubkey);
+            println!("  Airdrop confirmed: {}", sig);
+        },
+        Err(e) => {
+            println!("  Airdrop {} was not confirmed: {}", sig, e);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::ffi::OsString;
+
+use clap::Command;
+
+/// Options ore-cli accepts anywhere on the line, which are global options here and have to
+/// come before the subcommand.
+const GLOBAL_OPTIONS: &[&str] = &["--rpc", "--keypair"];
+/// ore-cli options with another name here.
+const RENAMED_OPTIONS: &[(&str, &str)] = &[("--cores", "--threads"), ("--buffer-time", "--buffer")];
+/// ore-cli fee options and whether they take a value. The pool sends and pays for the
+/// transactions, so they don't apply.
+const FEE_OPTIONS: &[(&str, bool)] = &[
+    ("--priority-fee", true),
+    ("--fee-payer", true),
+    ("--dynamic-fee-url", true),
+    ("--dynamic-fee", false),
+    ("--jito", false),
+];
+/// ore-cli subcommands with another name here.
+const RENAMED_COMMANDS: &[(&str, &str)] = &[("rewards", "balance")];
+
+/// Whether a global option of `command` takes a value, so the next argument isn't
+/// mistaken for the subcommand.
+fn takes_value(command: &Command, long: &str) -> bool {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == long.strip_prefix("--"))
+        .is_some_and(|arg| arg.get_action().takes_values())
+}
+
+/// Rewrites an ore-cli style command line into this client's, so scripts written for
+/// `ore mine --cores 8 --priority-fee 1000 --keypair id.json` keep working. Arguments that
+/// already mean something here are left alone, and each translation is printed once.
+pub fn translate(command: &Command, argv: Vec<OsString>) -> Vec<OsString> {
+    let mut args = argv.into_iter();
+    let mut globals: Vec<OsString> = args.next().into_iter().collect();
+    let mut rest: Vec<OsString> = Vec::new();
+    let mut subcommand = false;
+    let mut notes: Vec<String> = Vec::new();
+    let mut note = |text: String| {
+        if !notes.contains(&text) {
+            notes.push(text);
+        }
+    };
+
+    while let Some(arg) = args.next() {
+        let Some(text) = arg.to_str() else {
+            (if subcommand { &mut rest } else { &mut globals }).push(arg);
+            continue;
+        };
+        let (name, inline_value) = match text.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (text, None),
+        };
+
+        if let Some((_, has_value)) = FEE_OPTIONS.iter().find(|(fee, _)| *fee == name) {
+            if *has_value && inline_value.is_none() {
+                args.next();
+            }
+            note(format!("Ignoring ore-cli option {}, the pool pays transaction fees.", name));
+            continue;
+        }
+        let name = match RENAMED_OPTIONS.iter().find(|(old, _)| *old == name) {
+            Some((old, new)) => {
+                note(format!("ore-cli option {} is {} here.", old, new));
+                *new
+            },
+            None => name,
+        };
+        let arg = match inline_value {
+            Some(value) => OsString::from(format!("{}={}", name, value)),
+            None => OsString::from(name),
+        };
+
+        if !subcommand && !name.starts_with('-') {
+            subcommand = true;
+            match RENAMED_COMMANDS.iter().find(|(old, _)| *old == name) {
+                Some((old, new)) => {
+                    note(format!("ore-cli command {} is {} here.", old, new));
+                    rest.push(new.into());
+                },
+                None => rest.push(arg),
+            }
+            continue;
+        }
+
+        let global = !subcommand || GLOBAL_OPTIONS.contains(&name);
+        let target = if global { &mut globals } else { &mut rest };
+        target.push(arg);
+        if global && inline_value.is_none() && takes_value(command, name) {
+            target.extend(args.next());
+        }
+    }
+
+    for text in notes {
+        println!("{}", text);
+    }
+    globals.extend(rest);
+    globals
+}
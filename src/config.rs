@@ -0,0 +1,166 @@
+use std::{collections::HashSet, fs, io::Write, path::PathBuf};
+
+use clap::Subcommand;
+
+use crate::expand_tilde;
+
+pub const CONFIG_FILE: &str = "keypair_list";
+const CONFIG_BACKUP_FILE: &str = "keypair_list.bak";
+
+/// Current schema version of the configuration file.
+const CONFIG_VERSION: u32 = 1;
+const VERSION_HEADER: &str = "# ore-hq-client config version ";
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    #[command(about = "Check the configuration file for unknown entries and deprecated settings.")]
+    Validate,
+    #[command(about = "Print the settings file, or with --effective every option a mine run would use and its source.")]
+    Show {
+        #[arg(
+            long,
+            action,
+            help = "Print the merged options of the command line, settings file, environment and defaults"
+        )]
+        effective: bool,
+    },
+    #[command(about = "Print the options the command line or environment change relative to the settings file.")]
+    Diff,
+    #[command(about = "Encrypt secrets such as webhook URLs in the settings file with a passphrase.")]
+    Encrypt {
+        #[arg(
+            long = "field",
+            value_name = "PATH",
+            help = "Dotted path of a setting to encrypt, e.g. report.webhook or options.url. Repeatable, defaults to report.webhook"
+        )]
+        fields: Vec<String>,
+        #[arg(
+            long,
+            action,
+            help = "Store the passphrase in the OS keychain so it isn't asked for at startup"
+        )]
+        keychain: bool,
+    },
+}
+
+/// Version recorded in the file header. Files without a header predate versioning.
+fn file_version(contents: &str) -> u32 {
+    contents
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix(VERSION_HEADER))
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn entries(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Version 0 stored paths as typed, with `~` prefixes and duplicates.
+/// Version 1 stores each expanded path once, below a version header.
+fn migrate(contents: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    entries(contents)
+        .map(expand_tilde)
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Loads the keypair paths from the configuration file, creating it if missing and
+/// migrating older versions in place after writing a backup.
+pub fn load() -> Vec<String> {
+    let config_path = PathBuf::from(CONFIG_FILE);
+    let contents = match fs::read_to_string(&config_path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            save(&[]);
+            return Vec::new();
+        }
+    };
+
+    let version = file_version(&contents);
+    if version < CONFIG_VERSION {
+        if fs::copy(&config_path, CONFIG_BACKUP_FILE).is_err() {
+            println!("  Failed to back up configuration file, skipping migration.");
+            return entries(&contents).map(expand_tilde).collect();
+        }
+        let paths = migrate(&contents);
+        save(&paths);
+        println!(
+            "  Migrated configuration file to version {} (backup saved to {}).",
+            CONFIG_VERSION, CONFIG_BACKUP_FILE
+        );
+        return paths;
+    }
+
+    entries(&contents).map(|path| path.to_string()).collect()
+}
+
+/// Overwrites the configuration file with the given keypair paths.
+pub fn save(paths: &[String]) {
+    let mut file = fs::File::create(CONFIG_FILE).expect("Failed to open configuration file for writing.");
+    writeln!(file, "{}{}", VERSION_HEADER, CONFIG_VERSION).expect("Failed to write configuration file header.");
+    for path in paths {
+        writeln!(file, "{}", expand_tilde(path)).expect("Failed to write keypair path to configuration file.");
+    }
+}
+
+/// Appends keypair paths to the configuration file.
+pub fn append(paths: &[String]) {
+    let mut existing = load();
+    existing.extend(paths.iter().map(|path| expand_tilde(path)));
+    save(&existing);
+}
+
+/// Reports unknown entries, deprecated settings and missing paths without modifying the file.
+pub fn validate() {
+    let contents = match fs::read_to_string(CONFIG_FILE) {
+        Ok(contents) => contents,
+        Err(_) => {
+            println!("  No configuration file found at '{}'.", CONFIG_FILE);
+            return;
+        }
+    };
+
+    let mut issues = 0;
+    let version = file_version(&contents);
+    if version < CONFIG_VERSION {
+        println!("  Deprecated: configuration version {} will be migrated to version {} on next load.", version, CONFIG_VERSION);
+        issues += 1;
+    } else if version > CONFIG_VERSION {
+        println!("  Unknown: configuration version {} is newer than this client supports ({}).", version, CONFIG_VERSION);
+        issues += 1;
+    }
+
+    let mut seen = HashSet::new();
+    for entry in entries(&contents) {
+        if entry.contains('=') {
+            println!("  Unknown key: '{}'", entry);
+            issues += 1;
+            continue;
+        }
+        if entry.starts_with('~') {
+            println!("  Deprecated: '{}' uses a '~' prefix, paths are now stored expanded.", entry);
+            issues += 1;
+        }
+        let expanded = expand_tilde(entry);
+        if !seen.insert(expanded.clone()) {
+            println!("  Duplicate entry: '{}'", entry);
+            issues += 1;
+        }
+        if !PathBuf::from(&expanded).exists() {
+            println!("  Missing path: '{}'", entry);
+            issues += 1;
+        }
+    }
+
+    if issues == 0 {
+        println!("  Configuration is valid.");
+    } else {
+        println!("  Found {} issue(s) in '{}'.", issues, CONFIG_FILE);
+    }
+}
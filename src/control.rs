@@ -0,0 +1,361 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+use crate::metrics::{self, MetricsAddr, SocketMode, StatsBoard};
+use crate::network;
+
+/// Shared secret the control socket requires, needed to listen beyond loopback.
+pub const TOKEN_ENV: &str = "ORE_HQ_CONTROL_TOKEN";
+/// How often a paused miner checks whether it was resumed.
+const PAUSE_POLL: Duration = Duration::from_millis(200);
+
+/// State a running miner takes orders from, changed over the control socket. Shared by
+/// every wallet of a multi-wallet session.
+#[derive(Debug, Default)]
+pub struct MinerControl {
+    paused: AtomicBool,
+    /// Threads per round set with `set-threads`, 0 when --threads applies.
+    threads: AtomicU32,
+    /// Share of time workers idle between chunks in permille, set by thermal feedback.
+    idle: AtomicU32,
+    /// Set while a thermal feedback loop adjusts `idle`.
+    regulated: AtomicBool,
+    /// Worker threads currently hashing.
+    working: AtomicU32,
+}
+
+impl MinerControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Threads to use instead of --threads, if set over the control socket.
+    pub fn threads(&self) -> Option<u32> {
+        Some(self.threads.load(Ordering::Relaxed)).filter(|threads| *threads > 0)
+    }
+
+    pub fn idle_permille(&self) -> u32 {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    pub fn set_idle_permille(&self, idle: u32) {
+        self.idle.store(idle.min(999), Ordering::Relaxed);
+    }
+
+    /// How long a worker rests after hashing for `worked`, to keep the idle share.
+    pub fn rest_after(&self, worked: Duration) -> Duration {
+        let idle = self.idle_permille() as f64 / 1000.0;
+        worked.mul_f64(idle / (1.0 - idle))
+    }
+
+    /// Counts the calling worker as hashing until the guard is dropped.
+    pub fn working(&self) -> Working<'_> {
+        self.working.fetch_add(1, Ordering::Relaxed);
+        Working(self)
+    }
+
+    pub fn is_working(&self) -> bool {
+        self.working.load(Ordering::Relaxed) > 0
+    }
+
+    /// Claims the thermal feedback loop for one of the miners sharing this control.
+    pub fn start_regulating(&self) -> bool {
+        !self.regulated.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn stop_regulating(&self) {
+        self.idle.store(0, Ordering::Relaxed);
+        self.regulated.store(false, Ordering::SeqCst);
+    }
+
+    /// Blocks a worker thread while paused. Returns false if it should stop instead.
+    pub fn hold(&self, keep_waiting: impl Fn() -> bool) -> bool {
+        while self.is_paused() {
+            if !keep_waiting() {
+                return false;
+            }
+            std::thread::sleep(PAUSE_POLL);
+        }
+        true
+    }
+
+    /// Waits until resumed. Returns false if the miner is shut down first.
+    pub async fn wait_resumed(&self, shutdown: &mut tokio::sync::watch::Receiver<bool>) -> bool {
+        while self.is_paused() {
+            tokio::select! {
+                _ = tokio::time::sleep(PAUSE_POLL) => {},
+                _ = shutdown.changed() => return false,
+            }
+        }
+        true
+    }
+
+    fn status(&self, stats: &StatsBoard) -> serde_json::Value {
+        json!({
+            "paused": self.is_paused(),
+            "threads": self.threads(),
+            "miners": stats.miners(),
+        })
+    }
+
+    /// Runs one command line and returns the answer, always a single JSON line.
+    fn execute(&self, line: &str, stats: &StatsBoard) -> serde_json::Value {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["pause"] => {
+                self.paused.store(true, Ordering::Relaxed);
+                println!("Paused from the control socket.");
+                json!({ "ok": true, "paused": true })
+            },
+            ["resume"] => {
+                self.paused.store(false, Ordering::Relaxed);
+                println!("Resumed from the control socket.");
+                json!({ "ok": true, "paused": false })
+            },
+            ["set-threads", threads] => match threads.parse::<u32>() {
+                Ok(threads) if threads > max_threads() => {
+                    json!({ "ok": false, "error": format!("{} threads is more than this machine's core count of {}", threads, max_threads()) })
+                },
+                Ok(threads) => {
+                    self.threads.store(threads, Ordering::Relaxed);
+                    match threads {
+                        0 => println!("Using --threads again from the next round, as set from the control socket."),
+                        threads => println!("Using {} threads from the next round, as set from the control socket.", threads),
+                    }
+                    json!({ "ok": true, "threads": self.threads() })
+                },
+                Err(_) => json!({ "ok": false, "error": format!("not a thread count: {}", threads) }),
+            },
+            ["status"] => json!({ "ok": true, "status": self.status(stats) }),
+            ["version"] => json!({ "ok": true, "version": env!("CARGO_PKG_VERSION") }),
+            _ => json!({ "ok": false, "error": format!("unknown command: {}", line.trim()) }),
+        }
+    }
+}
+
+/// The most threads `set-threads` accepts, one per core.
+fn max_threads() -> u32 {
+    std::thread::available_parallelism().map_or(1, |n| n.get() as u32)
+}
+
+pub struct Working<'a>(&'a MinerControl);
+
+impl Drop for Working<'_> {
+    fn drop(&mut self) {
+        self.0.working.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Compares tokens in constant time, so the answer time doesn't tell how much of one matched.
+pub fn same_token(expected: &str, token: &str) -> bool {
+    expected.len() == token.len() && expected.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The command of a request line after its `token <TOKEN>` prefix, if it carries the token the
+/// server expects.
+fn authorized<'a>(line: &'a str, expected: Option<&str>) -> Option<&'a str> {
+    let (token, command) = match line.trim_start().strip_prefix("token ") {
+        Some(rest) => rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest.trim(), "")),
+        None => ("", line),
+    };
+    match expected {
+        None => Some(command),
+        Some(expected) => same_token(expected, token).then_some(command),
+    }
+}
+
+/// Answers command lines on one connection until the client closes it.
+async fn handle<S: AsyncRead + AsyncWrite + Unpin>(stream: S, control: &MinerControl, stats: &StatsBoard, token: Option<&str>) {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    while matches!(stream.read_line(&mut line).await, Ok(len) if len > 0) {
+        let answer = match authorized(&line, token) {
+            Some(command) => control.execute(command, stats),
+            None => json!({ "ok": false, "error": format!("missing or wrong token, set {} for ctl and fleet", TOKEN_ENV) }),
+        };
+        if stream.write_all(format!("{}\n", answer).as_bytes()).await.is_err() {
+            return;
+        }
+        line.clear();
+    }
+}
+
+/// Starts the control server for every session sharing `control`, whose stats are on `stats`.
+/// Commands need the token in ORE_HQ_CONTROL_TOKEN when it is set. Returns None if the address
+/// can't be bound, or is beyond loopback without a token.
+pub async fn serve(addr: MetricsAddr, socket_mode: SocketMode, control: Arc<MinerControl>, stats: Arc<StatsBoard>) -> Option<JoinHandle<()>> {
+    let token: Option<Arc<str>> = std::env::var(TOKEN_ENV).ok().filter(|token| !token.is_empty()).map(Into::into);
+    match addr {
+        MetricsAddr::Tcp(addr) => {
+            if !addr.ip().is_loopback() && token.is_none() {
+                println!("Refusing to accept control commands on {} without a token, set {} here and for ctl and fleet or listen on loopback.", addr, TOKEN_ENV);
+                return None;
+            }
+            let listener = match TcpListener::bind(addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    println!("Failed to start the control server on {}: {}", addr, e);
+                    return None;
+                },
+            };
+            println!("Accepting control commands on {}", addr);
+            Some(tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let (control, stats, token) = (control.clone(), stats.clone(), token.clone());
+                            tokio::spawn(async move { handle(stream, &control, &stats, token.as_deref()).await });
+                        },
+                        Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                    }
+                }
+            }))
+        },
+        #[cfg(unix)]
+        MetricsAddr::Unix(path) => {
+            use std::os::unix::fs::PermissionsExt;
+
+            let listener = match metrics::bind_unix(&path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    println!("Failed to start the control server on {}: {}", path.display(), e);
+                    return None;
+                },
+            };
+            if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(socket_mode.0)) {
+                println!("Failed to set permissions {:o} on {}: {}", socket_mode.0, path.display(), e);
+            }
+            println!("Accepting control commands on unix:{}", path.display());
+            Some(tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let (control, stats, token) = (control.clone(), stats.clone(), token.clone());
+                            tokio::spawn(async move { handle(stream, &control, &stats, token.as_deref()).await });
+                        },
+                        Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                    }
+                }
+            }))
+        },
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CtlArgs {
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "The miner's --control-socket, host:port or unix:/path.sock. Sends the token in ORE_HQ_CONTROL_TOKEN if set"
+    )]
+    pub socket: MetricsAddr,
+    #[command(subcommand)]
+    pub command: CtlCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CtlCommands {
+    #[command(about = "Stop hashing until resumed. A round in progress holds and continues after resume.")]
+    Pause,
+    #[command(about = "Continue mining after a pause.")]
+    Resume,
+    #[command(about = "Change the threads used per round from the next round, up to the core count, 0 to go back to --threads.")]
+    SetThreads {
+        #[arg(value_name = "THREADS")]
+        threads: u32,
+    },
+    #[command(about = "Print whether mining is paused, the thread setting and each wallet's stats.")]
+    Status,
+    #[command(about = "Print the client version the miner runs.")]
+    Version,
+}
+
+impl CtlCommands {
+    pub fn line(&self) -> String {
+        match self {
+            CtlCommands::Pause => "pause".to_string(),
+            CtlCommands::Resume => "resume".to_string(),
+            CtlCommands::SetThreads { threads } => format!("set-threads {}", threads),
+            CtlCommands::Status => "status".to_string(),
+            CtlCommands::Version => "version".to_string(),
+        }
+    }
+}
+
+/// Writes one request line and reads the answer line, for the control socket and the signer agent.
+pub async fn send<S: AsyncRead + AsyncWrite + Unpin>(stream: S, line: &str) -> std::io::Result<String> {
+    let mut stream = BufReader::new(stream);
+    stream.write_all(format!("{}\n", line).as_bytes()).await?;
+    let mut answer = String::new();
+    stream.read_line(&mut answer).await?;
+    Ok(answer)
+}
+
+/// Sends one command line to a running miner, with the token in ORE_HQ_CONTROL_TOKEN if set,
+/// and returns its answer, or why it failed.
+pub async fn request(addr: &MetricsAddr, line: &str) -> Result<serde_json::Value, String> {
+    let line = match std::env::var(TOKEN_ENV) {
+        Ok(token) if !token.is_empty() => format!("token {} {}", token, line),
+        _ => line.to_string(),
+    };
+    let line = line.as_str();
+    let answer = network::within(network::read_timeout(), "the control request", async {
+        match addr {
+            MetricsAddr::Tcp(addr) => send(tokio::net::TcpStream::connect(addr).await?, line).await,
+            #[cfg(unix)]
+            MetricsAddr::Unix(path) => send(tokio::net::UnixStream::connect(path).await?, line).await,
+        }
+    })
+    .await
+    .map_err(|e| format!("Could not reach the miner: {}", e))?;
+    match serde_json::from_str::<serde_json::Value>(&answer) {
+        Ok(answer) if answer["ok"] == true => Ok(answer),
+        Ok(answer) => Err(format!("The miner refused: {}", answer["error"].as_str().unwrap_or("no reason given"))),
+        Err(_) => Err(format!("Unexpected answer from the miner: {}", answer.trim())),
+    }
+}
+
+/// Sends one command to a running miner and prints its answer.
+pub async fn ctl(args: &CtlArgs) {
+    match request(&args.socket, &args.command.line()).await {
+        Ok(answer) => match (answer.get("status"), answer.get("version")) {
+            (Some(status), _) => println!("{}", serde_json::to_string_pretty(status).unwrap_or_default()),
+            (None, Some(version)) => println!("  {}", version.as_str().unwrap_or_default()),
+            (None, None) => println!("  Done."),
+        },
+        Err(e) => println!("  {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_the_token_before_the_command() {
+        assert_eq!(authorized("token s3cret set-threads 4\n", Some("s3cret")), Some("set-threads 4\n"));
+        assert_eq!(authorized("status\n", None), Some("status\n"));
+        assert_eq!(authorized("token s3cret pause\n", None), Some("pause\n"));
+    }
+
+    #[test]
+    fn refuses_a_missing_or_wrong_token() {
+        assert_eq!(authorized("pause\n", Some("s3cret")), None);
+        assert_eq!(authorized("token s3cre pause\n", Some("s3cret")), None);
+        assert_eq!(authorized("token pause\n", Some("s3cret")), None);
+        assert!(!same_token("s3cret", "s3cret "));
+    }
+}
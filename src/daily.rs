@@ -0,0 +1,92 @@
+use std::{fs, time::Duration};
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Hashing time spent on the current local calendar day, for --max-hours-per-day.
+pub const DAILY_USAGE_FILE: &str = "daily_usage.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DailyUsage {
+    date: Option<NaiveDate>,
+    secs: f64,
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+fn load() -> DailyUsage {
+    let usage: DailyUsage = fs::read_to_string(DAILY_USAGE_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    usage.on(today())
+}
+
+impl DailyUsage {
+    /// The usage counted against the budget of `date`.
+    fn on(self, date: NaiveDate) -> DailyUsage {
+        // Yesterday's time doesn't count against today's budget
+        if self.date == Some(date) {
+            self
+        } else {
+            DailyUsage { date: Some(date), secs: 0.0 }
+        }
+    }
+}
+
+/// Time spent mining today.
+pub fn used_today() -> Duration {
+    Duration::from_secs_f64(load().secs)
+}
+
+/// Adds the hashing time of a round to today's total.
+pub fn record(spent: Duration) {
+    let mut usage = load();
+    usage.secs += spent.as_secs_f64();
+    if let Ok(contents) = serde_json::to_string(&usage) {
+        let _ = fs::write(DAILY_USAGE_FILE, contents);
+    }
+}
+
+/// True once today's mining time has reached `hours`.
+pub fn budget_spent(hours: Option<f64>) -> bool {
+    over_budget(used_today(), hours)
+}
+
+fn over_budget(used: Duration, hours: Option<f64>) -> bool {
+    hours.is_some_and(|hours| used.as_secs_f64() >= hours * 3600.0)
+}
+
+/// Time until the local calendar day ends and a new budget starts.
+pub fn until_tomorrow() -> Duration {
+    let now = Local::now();
+    let midnight = (now.date_naive() + chrono::Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest());
+    midnight.map_or(Duration::from_secs(60 * 60), |midnight| (midnight - now).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_resets_on_a_new_day() {
+        let day = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let usage = DailyUsage { date: Some(day), secs: 1_800.0 };
+        assert_eq!(usage.on(day).secs, 1_800.0);
+        let next = DailyUsage { date: Some(day), secs: 1_800.0 }.on(day.succ_opt().unwrap());
+        assert_eq!((next.date, next.secs), (day.succ_opt(), 0.0));
+        assert_eq!(DailyUsage::default().on(day).date, Some(day));
+    }
+
+    #[test]
+    fn budget_is_spent_once_the_hours_are_reached() {
+        assert!(!over_budget(Duration::from_secs(10 * 3600), None));
+        assert!(!over_budget(Duration::from_secs(5_399), Some(1.5)));
+        assert!(over_budget(Duration::from_secs(5_400), Some(1.5)));
+        assert!(over_budget(Duration::ZERO, Some(0.0)));
+    }
+}
@@ -1,12 +1,17 @@
-use std::{str::FromStr, time::Duration};
+use std::time::Duration;
 use base64::{prelude::BASE64_STANDARD, Engine};
 use clap::Parser;
 use colored::*;
 use inquire::{Text, InquireError};
-use reqwest::StatusCode;
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
+use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
 
+use crate::audit;
 use crate::balance::get_balance;
+use crate::endpoint;
+use crate::network;
+use crate::pool_cache;
+use crate::response_cache;
+use crate::schema::{self, SchemaError, ServerTime};
 
 #[derive(Debug, Parser)]
 pub struct StakeArgs {
@@ -28,7 +33,7 @@ pub struct StakeArgs {
 
 pub async fn delegate_stake(args: StakeArgs, key: Keypair, url: String, unsecure: bool) {
     let base_url = url;
-    let client = reqwest::Client::new();
+    let client = network::client();
     let url_prefix = if unsecure { "http".to_string() } else { "https".to_string() };
     let balance = get_balance(&key, base_url.clone(), unsecure).await;
 
@@ -102,13 +107,11 @@ pub async fn delegate_stake(args: StakeArgs, key: Keypair, url: String, unsecure
 
                     // Attempt staking transaction
                     loop {
-                        let resp = client.get(format!("{}://{}/pool/authority/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-                        let pool_pubkey = Pubkey::from_str(&resp).unwrap();
+                        let pool_pubkey = pool_cache::authority_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-                        let resp = client.get(format!("{}://{}/pool/fee_payer/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-                        let fee_pubkey = Pubkey::from_str(&resp).unwrap();
+                        let fee_pubkey = pool_cache::fee_payer_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-                        let resp = client.get(format!("{}://{}/latest-blockhash", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
+                        let resp = client.get(endpoint::http(&url_prefix, &base_url, "latest-blockhash")).send().await.unwrap().text().await.unwrap();
                         let decoded_blockhash = BASE64_STANDARD.decode(resp).unwrap();
                         let deserialized_blockhash = bincode::deserialize(&decoded_blockhash).unwrap();
 
@@ -117,16 +120,18 @@ pub async fn delegate_stake(args: StakeArgs, key: Keypair, url: String, unsecure
 
                         let mut tx = Transaction::new_with_payer(&[ix], Some(&fee_pubkey));
                         tx.partial_sign(&[&key], deserialized_blockhash);
+                        audit::record("delegate-stake-transaction", &key.pubkey(), &tx.message_data());
                         let serialized_tx = bincode::serialize(&tx).unwrap();
                         let encoded_tx = BASE64_STANDARD.encode(&serialized_tx);
 
-                        let resp = client.post(format!("{}://{}/stake?pubkey={}&amount={}", url_prefix, base_url, key.pubkey().to_string(), stake_amount_u64)).body(encoded_tx).send().await;
+                        let resp = client.post(endpoint::http(&url_prefix, &base_url, &format!("stake?pubkey={}&amount={}", key.pubkey().to_string(), stake_amount_u64))).body(encoded_tx).send().await;
 
                         if let Ok(res) = resp {
                             if let Ok(txt) = res.text().await {
                                 match txt.as_str() {
                                     "SUCCESS" => {
                                         println!("  Successfully staked!");
+                                        response_cache::forget(&base_url);
                                         return; // Exit the loop and function when successful
                                     },
                                     other => {
@@ -155,13 +160,11 @@ pub async fn delegate_stake(args: StakeArgs, key: Keypair, url: String, unsecure
     }
 
     // Non-auto and auto logic converge for transaction execution
-    let resp = client.get(format!("{}://{}/pool/authority/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-    let pool_pubkey = Pubkey::from_str(&resp).unwrap();
+    let pool_pubkey = pool_cache::authority_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-    let resp = client.get(format!("{}://{}/pool/fee_payer/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-    let fee_pubkey = Pubkey::from_str(&resp).unwrap();
+    let fee_pubkey = pool_cache::fee_payer_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-    let resp = client.get(format!("{}://{}/latest-blockhash", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
+    let resp = client.get(endpoint::http(&url_prefix, &base_url, "latest-blockhash")).send().await.unwrap().text().await.unwrap();
     let decoded_blockhash = BASE64_STANDARD.decode(resp).unwrap();
     let deserialized_blockhash = bincode::deserialize(&decoded_blockhash).unwrap();
 
@@ -170,15 +173,17 @@ pub async fn delegate_stake(args: StakeArgs, key: Keypair, url: String, unsecure
 
     let mut tx = Transaction::new_with_payer(&[ix], Some(&fee_pubkey));
     tx.partial_sign(&[&key], deserialized_blockhash);
+    audit::record("delegate-stake-transaction", &key.pubkey(), &tx.message_data());
     let serialized_tx = bincode::serialize(&tx).unwrap();
     let encoded_tx = BASE64_STANDARD.encode(&serialized_tx);
 
-    let resp = client.post(format!("{}://{}/stake?pubkey={}&amount={}", url_prefix, base_url, key.pubkey().to_string(), stake_amount_u64)).body(encoded_tx).send().await;
+    let resp = client.post(endpoint::http(&url_prefix, &base_url, &format!("stake?pubkey={}&amount={}", key.pubkey().to_string(), stake_amount_u64))).body(encoded_tx).send().await;
     if let Ok(res) = resp {
         if let Ok(txt) = res.text().await {
             match txt.as_str() {
                 "SUCCESS" => {
                     println!("  Successfully staked!");
+                    response_cache::forget(&base_url);
                 },
                 other => {
                     println!("  Transaction failed: {}", other);
@@ -194,17 +199,9 @@ pub async fn delegate_stake(args: StakeArgs, key: Keypair, url: String, unsecure
 
 // Helper function to fetch server timestamp
 async fn get_timestamp(client: &reqwest::Client, url_prefix: &str, base_url: &str) -> u64 {
-    if let Ok(response) = client.get(format!("{}://{}/timestamp", url_prefix, base_url)).send().await {
-        match response.status() {
-            StatusCode::OK => {
-                if let Ok(ts) = response.text().await {
-                    if let Ok(ts) = ts.parse::<u64>() {
-                        return ts;
-                    }
-                }
-            },
-            _ => panic!("  Server restarting, trying again in 3 seconds..."),
-        }
+    match schema::get::<ServerTime>(client, url_prefix, base_url, "timestamp", "").await {
+        Ok(ServerTime(ts)) => ts,
+        Err(e @ SchemaError::Status { .. }) => panic!("  Server restarting, trying again in 3 seconds... ({})", e),
+        Err(e) => panic!("  Unable to retrieve timestamp, retrying... ({})", e),
     }
-    panic!("  Unable to retrieve timestamp, retrying...");
 }
\ No newline at end of file
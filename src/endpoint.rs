@@ -0,0 +1,94 @@
+/// Schemes a --url may start with, and whether each is unencrypted.
+const SCHEMES: &[(&str, bool)] = &[("https://", false), ("wss://", false), ("http://", true), ("ws://", true)];
+
+/// Splits a --url into the pool address used everywhere else (host, port and base path
+/// without trailing slashes, e.g. `example.com/ore`) and whether its scheme asked for an
+/// unencrypted connection. None when no scheme was given.
+pub fn normalize(url: &str) -> (String, Option<bool>) {
+    let url = url.trim();
+    let (rest, unsecure) = SCHEMES
+        .iter()
+        .find_map(|(scheme, unsecure)| {
+            url.get(..scheme.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(scheme))
+                .map(|_| (&url[scheme.len()..], Some(*unsecure)))
+        })
+        .unwrap_or((url, None));
+    let (path, query) = match rest.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (rest, None),
+    };
+    let path = path.trim_end_matches('/');
+    match query {
+        Some(query) => (format!("{}?{}", path, query), unsecure),
+        None => (path.to_string(), unsecure),
+    }
+}
+
+/// Joins `endpoint`, which may carry its own query string, onto the pool address below
+/// `scheme`. The base path is kept, exactly one slash separates it from the endpoint and a
+/// query string on the pool address is merged with the endpoint's.
+fn join(scheme: &str, base_url: &str, endpoint: &str) -> String {
+    let (base, base_query) = base_url.split_once('?').unwrap_or((base_url, ""));
+    let (path, query) = endpoint.split_once('?').unwrap_or((endpoint, ""));
+    let mut url = format!("{}://{}/{}", scheme, base.trim_end_matches('/'), path.trim_start_matches('/'));
+    let query: Vec<&str> = [base_query, query].into_iter().filter(|query| !query.is_empty()).collect();
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(&query.join("&"));
+    }
+    url
+}
+
+/// URL of a REST endpoint such as `timestamp` or `submit?pubkey=...`, `url_prefix` being
+/// http or https.
+pub fn http(url_prefix: &str, base_url: &str, endpoint: &str) -> String {
+    join(url_prefix, base_url, endpoint)
+}
+
+/// URL of the websocket upgrade, the pool address itself with `query` appended.
+pub fn ws(unsecure: bool, base_url: &str, query: &str) -> String {
+    join(if unsecure { "ws" } else { "wss" }, base_url, &format!("?{}", query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_strips_the_scheme_and_trailing_slashes() {
+        assert_eq!(normalize("https://example.com/"), ("example.com".to_string(), Some(false)));
+        assert_eq!(normalize("WSS://example.com"), ("example.com".to_string(), Some(false)));
+        assert_eq!(normalize("http://127.0.0.1:8080//"), ("127.0.0.1:8080".to_string(), Some(true)));
+        assert_eq!(normalize("ws://example.com:80"), ("example.com:80".to_string(), Some(true)));
+        assert_eq!(normalize("  example.com  "), ("example.com".to_string(), None));
+    }
+
+    #[test]
+    fn normalize_keeps_the_base_path_and_query() {
+        assert_eq!(normalize("https://example.com/ore/"), ("example.com/ore".to_string(), Some(false)));
+        assert_eq!(normalize("example.com:8443/pools/ore/?region=eu"), ("example.com:8443/pools/ore?region=eu".to_string(), None));
+        assert_eq!(normalize("http://example.com?key=a/b"), ("example.com?key=a/b".to_string(), Some(true)));
+    }
+
+    #[test]
+    fn join_puts_one_slash_between_base_and_endpoint() {
+        assert_eq!(join("https", "example.com", "timestamp"), "https://example.com/timestamp");
+        assert_eq!(join("https", "example.com/", "/timestamp"), "https://example.com/timestamp");
+        assert_eq!(join("http", "example.com:8080/ore", "timestamp"), "http://example.com:8080/ore/timestamp");
+    }
+
+    #[test]
+    fn join_merges_query_strings() {
+        assert_eq!(join("https", "example.com/ore?region=eu", "submit?pubkey=abc"), "https://example.com/ore/submit?region=eu&pubkey=abc");
+        assert_eq!(join("https", "example.com?region=eu", "timestamp"), "https://example.com/timestamp?region=eu");
+        assert_eq!(join("https", "example.com", "submit?pubkey=abc"), "https://example.com/submit?pubkey=abc");
+    }
+
+    #[test]
+    fn http_and_ws_urls() {
+        assert_eq!(http("https", "example.com:8443/ore", "miner/balance?pubkey=abc"), "https://example.com:8443/ore/miner/balance?pubkey=abc");
+        assert_eq!(ws(false, "example.com/ore", "timestamp=1"), "wss://example.com/ore/?timestamp=1");
+        assert_eq!(ws(true, "127.0.0.1:8080?region=eu", "timestamp=1"), "ws://127.0.0.1:8080/?region=eu&timestamp=1");
+    }
+}
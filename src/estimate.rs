@@ -0,0 +1,53 @@
+use clap::ValueEnum;
+
+/// How the pool weights a submitted difficulty when splitting round rewards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PayoutCurve {
+    /// Each extra difficulty bit doubles the share (hashpower weighted).
+    Exponential,
+    /// Share grows linearly with difficulty.
+    Linear,
+}
+
+/// Estimates ORE earned per submission from the pool's payout curve, calibrated
+/// against the official figures the pool broadcasts after each round.
+pub struct RewardEstimator {
+    curve: PayoutCurve,
+    reward_per_weight: Option<f64>,
+    pending: Option<(u32, Option<f64>)>,
+}
+
+impl RewardEstimator {
+    pub fn new(curve: PayoutCurve) -> Self {
+        RewardEstimator {
+            curve,
+            reward_per_weight: None,
+            pending: None,
+        }
+    }
+
+    fn weight(&self, difficulty: u32) -> f64 {
+        match self.curve {
+            PayoutCurve::Exponential => 2f64.powi(difficulty as i32),
+            PayoutCurve::Linear => difficulty as f64,
+        }
+    }
+
+    /// Records a submitted difficulty and returns the estimated reward, if calibrated.
+    pub fn submit(&mut self, difficulty: u32) -> Option<f64> {
+        let estimate = self.reward_per_weight.map(|r| r * self.weight(difficulty));
+        self.pending = Some((difficulty, estimate));
+        estimate
+    }
+
+    /// Reconciles the pending submission with the actual reward reported by the pool.
+    /// Returns the estimate that was made for it, if any.
+    pub fn reconcile(&mut self, actual: f64) -> Option<Option<f64>> {
+        let (difficulty, estimate) = self.pending.take()?;
+        let weight = self.weight(difficulty);
+        if weight > 0.0 {
+            self.reward_per_weight = Some(actual / weight);
+        }
+        Some(estimate)
+    }
+}
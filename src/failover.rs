@@ -0,0 +1,175 @@
+use std::time::{Duration, Instant};
+
+use crate::schema::{self, ServerTime};
+
+/// Failed attempts on a pool before moving on to the next one in the list.
+const FAILURES_BEFORE_SWITCH: u32 = 2;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(3);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// How often a fallback connection checks whether the primary pool is back.
+pub const PRIMARY_PROBE_INTERVAL: Duration = Duration::from_secs(300);
+/// Time allowed for a `/timestamp` health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// A connection that gets no challenge this long after Ready is treated as dead when there
+/// are other pools to try. Rounds are a minute, so a healthy pool answers well within this.
+pub const READY_ACK_TIMEOUT: Duration = Duration::from_secs(150);
+/// How often `--auto-select-pool` scores the pools again.
+pub const AUTO_SELECT_INTERVAL: Duration = Duration::from_secs(600);
+/// `/timestamp` requests per pool when scoring, each unanswered one counts against the pool.
+const PROBES_PER_POOL: u32 = 3;
+/// Another pool has to score this many times better before we leave a working one.
+const SWITCH_MARGIN: f64 = 1.5;
+
+/// Which pool the miner connects to, from `--url` values in priority order, and how long to
+/// back off between failed attempts.
+pub struct PoolFailover {
+    urls: Vec<String>,
+    current: usize,
+    failures: u32,
+    backoff: Duration,
+}
+
+impl PoolFailover {
+    pub fn new(primary: String, fallbacks: &[String]) -> Self {
+        let mut urls = vec![primary];
+        for url in fallbacks {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+        PoolFailover { urls, current: 0, failures: 0, backoff: INITIAL_BACKOFF }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.urls[self.current]
+    }
+
+    pub fn has_fallbacks(&self) -> bool {
+        self.urls.len() > 1
+    }
+
+    pub fn on_fallback(&self) -> bool {
+        self.current != 0
+    }
+
+    /// The pool accepted us and sent work, start counting failures from scratch.
+    pub fn healthy(&mut self) {
+        self.failures = 0;
+        self.backoff = INITIAL_BACKOFF;
+    }
+
+    /// Records a failed connection attempt or dropped connection and returns how long to
+    /// wait before the next attempt. Moves on to the next pool after repeated failures.
+    pub fn failed(&mut self) -> Duration {
+        self.failures += 1;
+        if self.has_fallbacks() && self.failures >= FAILURES_BEFORE_SWITCH {
+            self.switch_to((self.current + 1) % self.urls.len());
+            // The next pool gets a fresh attempt right away, backoff grows once all have failed
+            if self.current != 0 {
+                return Duration::ZERO;
+            }
+        }
+        let wait = self.backoff;
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        wait
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        if index != self.current {
+            println!("Failing over from {} to {}.", self.urls[self.current], self.urls[index]);
+        }
+        self.current = index;
+        self.failures = 0;
+    }
+
+    pub fn return_to_primary(&mut self) {
+        self.switch_to(0);
+        self.healthy();
+    }
+
+    /// Health-checks the pools in priority order and switches to the first one that answers.
+    /// Stays on the current pool if none do.
+    pub async fn select_healthy(&mut self, client: &reqwest::Client, url_prefix: &str) {
+        for index in 0..self.urls.len() {
+            if is_healthy(client, url_prefix, &self.urls[index]).await {
+                self.switch_to(index);
+                return;
+            }
+            println!("Pool {} failed its health check.", self.urls[index]);
+        }
+    }
+
+    /// Scores every pool by its median `/timestamp` round trip, divided by the share of
+    /// requests it answered. None for pools that answered none.
+    async fn scores(&self, client: &reqwest::Client, url_prefix: &str) -> Vec<Option<f64>> {
+        let mut scores = Vec::new();
+        for url in &self.urls {
+            let mut times = Vec::new();
+            for _ in 0..PROBES_PER_POOL {
+                let started = Instant::now();
+                if is_healthy(client, url_prefix, url).await {
+                    times.push(started.elapsed());
+                }
+            }
+            times.sort();
+            let score = times
+                .get(times.len() / 2)
+                .map(|median| median.as_secs_f64() * 1000.0 * PROBES_PER_POOL as f64 / times.len() as f64);
+            match score {
+                Some(score) => println!("Pool {} scored {:.1} ms ({}/{} answered).", url, score, times.len(), PROBES_PER_POOL),
+                None => println!("Pool {} did not answer.", url),
+            }
+            scores.push(score);
+        }
+        scores
+    }
+
+    /// Switches to the best scoring pool, for `--auto-select-pool` at startup. Stays on the
+    /// current pool if none answer.
+    pub async fn select_fastest(&mut self, client: &reqwest::Client, url_prefix: &str) {
+        let scores = self.scores(client, url_prefix).await;
+        if let Some(best) = best(&scores) {
+            self.switch_to(best);
+        }
+    }
+
+    /// Scores the pools again and switches when the current one stopped answering or another
+    /// scores SWITCH_MARGIN times better. Returns true if it switched.
+    pub async fn reselect(&mut self, client: &reqwest::Client, url_prefix: &str) -> bool {
+        let scores = self.scores(client, url_prefix).await;
+        let Some(best) = best(&scores) else {
+            return false;
+        };
+        let degraded = match (scores[self.current], scores[best]) {
+            (None, _) => true,
+            (Some(current), Some(best)) => current > best * SWITCH_MARGIN,
+            _ => false,
+        };
+        if best == self.current || !degraded {
+            return false;
+        }
+        self.switch_to(best);
+        self.healthy();
+        true
+    }
+
+    pub async fn primary_healthy(&self, client: &reqwest::Client, url_prefix: &str) -> bool {
+        is_healthy(client, url_prefix, &self.urls[0]).await
+    }
+}
+
+/// Index of the lowest score, ignoring pools that didn't answer.
+fn best(scores: &[Option<f64>]) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .filter_map(|(index, score)| Some((index, (*score)?)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// A pool is healthy when `/timestamp` answers with a usable time.
+async fn is_healthy(client: &reqwest::Client, url_prefix: &str, url: &str) -> bool {
+    let check = schema::get::<ServerTime>(client, url_prefix, url, "timestamp", "");
+    matches!(tokio::time::timeout(HEALTH_CHECK_TIMEOUT, check).await, Ok(Ok(_)))
+}
@@ -0,0 +1,227 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{atomic::{AtomicBool, AtomicU64}, Arc},
+    time::{Duration, Instant},
+};
+
+use base64::prelude::*;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use crate::mine::{self, RoundJob, WorkerResult};
+use crate::format;
+use crate::settings;
+
+/// How often a regular file is re-read while waiting for new lines.
+const POLL_INTERVAL_MILLIS: u64 = 200;
+/// Extra time the relay waits for a solution past the round's cutoff.
+const RELAY_GRACE_SECS: u64 = 10;
+
+/// A round handed from the network-facing client to the compute side, one JSON object per line.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedChallenge {
+    /// Base64 encoded 32 byte challenge.
+    pub challenge: String,
+    pub nonce_start: u64,
+    pub nonce_end: u64,
+    pub cutoff: u64,
+}
+
+/// Best hash found by the compute side for a challenge, one JSON object per line.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedSolution {
+    pub challenge: String,
+    pub nonce: u64,
+    /// Base64 encoded 16 byte equix digest.
+    pub digest: String,
+    /// Base64 encoded 32 byte hash.
+    pub hash: String,
+    pub difficulty: u32,
+    pub hashes: u64,
+    /// Missing from solutions written by older versions.
+    #[serde(default)]
+    pub nonces: u64,
+}
+
+#[derive(Debug, Parser)]
+pub struct SolveFeedArgs {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "File or FIFO to read challenges from"
+    )]
+    pub input: PathBuf,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "File or FIFO to write solutions to"
+    )]
+    pub output: PathBuf,
+    #[arg(
+        long,
+        value_name = "threads",
+        default_value = "4",
+        help = "Number of threads to use while mining"
+    )]
+    pub threads: u32,
+}
+
+fn append_line(path: &Path, value: &impl Serialize) -> std::io::Result<()> {
+    let line = serde_json::to_string(value).map_err(std::io::Error::other)?;
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+fn decode_array<const N: usize>(encoded: &str) -> Option<[u8; N]> {
+    BASE64_STANDARD.decode(encoded).ok()?.try_into().ok()
+}
+
+/// Compute side: solves every challenge read from the input and writes the best hash to the output.
+/// Runs without a keypair or network access.
+pub async fn solve_feed(args: &SolveFeedArgs) {
+    let running = Arc::new(AtomicBool::new(true));
+    let input = match fs::File::open(&args.input) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("  Failed to open {}: {}", args.input.display(), e);
+            return;
+        }
+    };
+    let mut reader = BufReader::new(input);
+
+    println!("  Waiting for challenges on {}", args.input.display());
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            // No writer or nothing appended yet, keep following the file
+            Ok(0) => {
+                tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MILLIS)).await;
+                continue;
+            },
+            Ok(_) => {},
+            Err(e) => {
+                println!("  Failed to read challenge feed: {}", e);
+                return;
+            },
+        }
+
+        let Ok(feed_challenge) = serde_json::from_str::<FeedChallenge>(line.trim()) else {
+            println!("  Skipping malformed challenge line");
+            continue;
+        };
+        let Some(challenge) = decode_array::<32>(&feed_challenge.challenge) else {
+            println!("  Skipping challenge with invalid encoding");
+            continue;
+        };
+
+        let nonce_range = feed_challenge.nonce_start..feed_challenge.nonce_end;
+        let job = RoundJob {
+            challenge,
+            nonce_range: nonce_range.clone(),
+            cutoff: feed_challenge.cutoff,
+            hash_timer: Instant::now(),
+            cursor: Arc::new(AtomicU64::new(nonce_range.start)),
+            paranoid: false,
+            paranoid_min_difficulty: 0,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            solver: settings::load().solver,
+            control: Default::default(),
+            best_difficulty: Default::default(),
+            exit_target: None,
+        };
+        let results = mine::mine_round(job, args.threads, None, Default::default(), &running).await;
+
+        let mut hashes = 0;
+        let mut nonces = 0;
+        let mut best: Option<WorkerResult> = None;
+        for result in results.into_iter().flatten() {
+            hashes += result.total_hashes;
+            nonces += result.total_nonces;
+            if best.as_ref().is_none_or(|b| result.best_difficulty > b.best_difficulty) {
+                best = Some(result);
+            }
+        }
+        let Some(best) = best else {
+            continue;
+        };
+
+        println!("  Solved challenge with difficulty {} ({} hashes)", best.best_difficulty, format::count(hashes));
+        let solution = FeedSolution {
+            challenge: feed_challenge.challenge,
+            nonce: best.best_nonce,
+            digest: BASE64_STANDARD.encode(best.best_hash.d),
+            hash: BASE64_STANDARD.encode(best.best_hash.h),
+            difficulty: best.best_difficulty,
+            hashes,
+            nonces,
+        };
+        if let Err(e) = append_line(&args.output, &solution) {
+            println!("  Failed to write solution to {}: {}", args.output.display(), e);
+        }
+    }
+}
+
+/// Network side: hands the round to the compute side through `challenges` and waits for its
+/// answer in `solutions` until shortly after the cutoff. Returns the solution as a worker result.
+pub async fn relay_round(challenges: &Path, solutions: &Path, job: &RoundJob) -> Vec<Option<WorkerResult>> {
+    let encoded_challenge = BASE64_STANDARD.encode(job.challenge);
+    let feed_challenge = FeedChallenge {
+        challenge: encoded_challenge.clone(),
+        nonce_start: job.nonce_range.start,
+        nonce_end: job.nonce_range.end,
+        cutoff: job.cutoff,
+    };
+
+    // Opening a FIFO blocks until the other side opens it too
+    let challenges = challenges.to_path_buf();
+    let written = tokio::task::spawn_blocking(move || append_line(&challenges, &feed_challenge)).await;
+    if !matches!(written, Ok(Ok(()))) {
+        println!("Failed to write challenge to the relay feed");
+        return Vec::new();
+    }
+
+    let solutions = solutions.to_path_buf();
+    let deadline = Duration::from_secs(job.cutoff + RELAY_GRACE_SECS);
+    let started = job.hash_timer;
+    let solution = tokio::task::spawn_blocking(move || {
+        while started.elapsed() < deadline {
+            if let Ok(file) = fs::File::open(&solutions) {
+                let found = BufReader::new(file)
+                    .lines()
+                    .map_while(Result::ok)
+                    .filter_map(|line| serde_json::from_str::<FeedSolution>(&line).ok())
+                    .find(|solution| solution.challenge == encoded_challenge);
+                if found.is_some() {
+                    return found;
+                }
+            }
+            std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MILLIS));
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten();
+
+    let Some(solution) = solution else {
+        println!("No solution arrived from the relay before the cutoff");
+        return Vec::new();
+    };
+    let (Some(d), Some(h)) = (decode_array::<16>(&solution.digest), decode_array::<32>(&solution.hash)) else {
+        println!("Relay solution has an invalid encoding");
+        return Vec::new();
+    };
+
+    vec![Some(WorkerResult {
+        best_nonce: solution.nonce,
+        best_difficulty: solution.difficulty,
+        best_hash: drillx_2::Hash { d, h },
+        total_hashes: solution.hashes,
+        total_nonces: solution.nonces,
+        exhausted: false,
+        faults: 0,
+        hash_secs: 0.0,
+    })]
+}
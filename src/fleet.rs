@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use futures_util::future::join_all;
+use serde::Deserialize;
+
+use crate::control::{self, CtlCommands};
+use crate::format;
+use crate::metrics::MetricsAddr;
+
+pub const FLEET_FILE: &str = "fleet.toml";
+
+#[derive(Debug, Parser)]
+pub struct FleetArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        default_value = FLEET_FILE,
+        help = "Inventory of rigs: [[rig]] entries with a name and the --control-socket of its miner. Every rig is sent the token in ORE_HQ_CONTROL_TOKEN if set"
+    )]
+    pub inventory: PathBuf,
+    #[command(subcommand)]
+    pub command: CtlCommands,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rig {
+    name: String,
+    control: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Inventory {
+    #[serde(default, rename = "rig")]
+    rigs: Vec<Rig>,
+}
+
+fn load(path: &Path) -> Result<Vec<(String, MetricsAddr)>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let inventory: Inventory = toml::from_str(&contents).map_err(|e| format!("Invalid inventory {}: {}", path.display(), e))?;
+    inventory
+        .rigs
+        .into_iter()
+        .map(|rig| match rig.control.parse() {
+            Ok(addr) => Ok((rig.name, addr)),
+            Err(e) => Err(format!("Rig {} in {}: {}", rig.name, path.display(), e)),
+        })
+        .collect()
+}
+
+/// One table row for a rig's `status` answer: state, threads, wallets, hashrate, rounds and submissions.
+fn status_row(status: &serde_json::Value) -> (Vec<String>, f64) {
+    let miners = status["miners"].as_array().cloned().unwrap_or_default();
+    let sum = |key: &str| miners.iter().filter_map(|miner| miner[key].as_f64()).sum::<f64>();
+    let hashrate = sum("hashrate");
+    let row = vec![
+        if status["paused"] == true { "paused" } else { "mining" }.to_string(),
+        status["threads"].as_u64().map_or("--threads".to_string(), |threads| threads.to_string()),
+        miners.len().to_string(),
+        format::hashrate(hashrate),
+        sum("rounds").to_string(),
+        sum("submissions").to_string(),
+    ];
+    (row, hashrate)
+}
+
+fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|title| title.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|title| title.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        println!("  {}", cells.join("  ").trim_end());
+    }
+}
+
+/// `fleet`: sends one control command to the miner of every rig in the inventory at once
+/// and prints a line per rig, so operators don't need ssh loops.
+pub async fn fleet(args: &FleetArgs) {
+    let rigs = match load(&args.inventory) {
+        Ok(rigs) if rigs.is_empty() => {
+            println!("  No rigs in {}, add [[rig]] entries with a name and control address.", args.inventory.display());
+            return;
+        },
+        Ok(rigs) => rigs,
+        Err(e) => {
+            println!("  {}", e);
+            return;
+        },
+    };
+    let line = args.command.line();
+    let answers = join_all(rigs.iter().map(|(_, addr)| control::request(addr, &line))).await;
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut total_hashrate = 0.0;
+    for ((name, _), answer) in rigs.iter().zip(answers) {
+        let mut row = vec![name.clone()];
+        match (&args.command, answer) {
+            (_, Err(e)) => {
+                errors.push(format!("{}: {}", name, e));
+                row.push("failed".to_string());
+            },
+            (CtlCommands::Status, Ok(answer)) => {
+                let (cells, hashrate) = status_row(&answer["status"]);
+                total_hashrate += hashrate;
+                row.extend(cells);
+            },
+            (CtlCommands::Version, Ok(answer)) => {
+                let version = answer["version"].as_str().unwrap_or("unknown");
+                row.push(if version == env!("CARGO_PKG_VERSION") { version.to_string() } else { format!("{} (this client is {})", version, env!("CARGO_PKG_VERSION")) });
+            },
+            (_, Ok(_)) => row.push("done".to_string()),
+        }
+        rows.push(row);
+    }
+
+    match args.command {
+        CtlCommands::Status => print_table(&["Rig", "State", "Threads", "Wallets", "Hashrate", "Rounds", "Submissions"], &rows),
+        CtlCommands::Version => print_table(&["Rig", "Version"], &rows),
+        _ => print_table(&["Rig", "Result"], &rows),
+    }
+    if matches!(args.command, CtlCommands::Status) {
+        println!("  Fleet hashrate: {}", format::hashrate(total_hashrate));
+    }
+    if !errors.is_empty() {
+        println!();
+        for error in errors {
+            println!("  {}", error);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::{sync::OnceLock, time::Duration};
+
+const SI_PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+
+/// Digit grouping and decimal separators for the user's locale.
+struct Separators {
+    group: char,
+    decimal: char,
+}
+
+/// Picks separators from LC_ALL, LC_NUMERIC or LANG, falling back to `1,234.5`.
+fn separators() -> &'static Separators {
+    static SEPARATORS: OnceLock<Separators> = OnceLock::new();
+    SEPARATORS.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+        let language = locale.split(['_', '.', '-']).next().unwrap_or_default();
+        match language {
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" => Separators { group: '.', decimal: ',' },
+            "fr" | "ru" | "pl" | "cs" | "sv" | "nb" | "fi" | "uk" => Separators { group: ' ', decimal: ',' },
+            _ => Separators { group: ',', decimal: '.' },
+        }
+    })
+}
+
+/// Formats a count with thousands separators, e.g. `123,456,789`.
+pub fn count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separators().group);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Formats a value with one decimal using the locale's decimal separator.
+pub fn decimal(value: f64) -> String {
+    format!("{:.1}", value).replace('.', &separators().decimal.to_string())
+}
+
+/// Formats a hashrate with an SI prefix, e.g. `12.4 MH/s`.
+pub fn hashrate(hashes_per_sec: f64) -> String {
+    let mut value = hashes_per_sec;
+    let mut prefix = 0;
+    while value >= 1000.0 && prefix < SI_PREFIXES.len() - 1 {
+        value /= 1000.0;
+        prefix += 1;
+    }
+    if prefix == 0 {
+        format!("{:.0} H/s", value)
+    } else {
+        format!("{} {}H/s", decimal(value), SI_PREFIXES[prefix])
+    }
+}
+
+/// Formats a duration for people, e.g. `4.2s`, `3m 05s` or `2h 03m 05s`.
+pub fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", decimal(duration.as_secs_f64())),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m {:02}s", secs / 3600, (secs / 60) % 60, secs % 60),
+    }
+}
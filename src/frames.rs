@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::prelude::*;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::broadcasts::RecentBroadcasts;
+use crate::mine::{self, ServerMessage, UnexpectedFrames};
+use crate::network;
+use crate::protocol::{Quirk, Quirks, ServerFlavor};
+use crate::tasks::TaskContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// One websocket frame as written by `--dump-frames`, one JSON object per line.
+/// Text frames are stored as is, everything else base64 encoded.
+#[derive(Debug, Serialize, Deserialize)]
+struct DumpedFrame {
+    at_ms: u128,
+    direction: Direction,
+    kind: String,
+    data: String,
+}
+
+impl DumpedFrame {
+    fn new(direction: Direction, message: &Message) -> Self {
+        let (kind, data) = match message {
+            Message::Text(text) => ("text", text.clone()),
+            Message::Binary(bytes) => ("binary", BASE64_STANDARD.encode(bytes)),
+            Message::Ping(bytes) => ("ping", BASE64_STANDARD.encode(bytes)),
+            Message::Pong(bytes) => ("pong", BASE64_STANDARD.encode(bytes)),
+            Message::Close(frame) => ("close", frame.as_ref().map(|f| f.reason.to_string()).unwrap_or_default()),
+            Message::Frame(frame) => ("frame", BASE64_STANDARD.encode(frame.payload())),
+        };
+        DumpedFrame {
+            at_ms: SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_millis(),
+            direction,
+            kind: kind.to_string(),
+            data,
+        }
+    }
+
+    /// Rebuilds the frame as the parser would have received it. Raw frames are not replayed.
+    fn message(&self) -> Option<Message> {
+        match self.kind.as_str() {
+            "text" => Some(Message::Text(self.data.clone())),
+            "binary" => Some(Message::Binary(BASE64_STANDARD.decode(&self.data).ok()?)),
+            "ping" => Some(Message::Ping(BASE64_STANDARD.decode(&self.data).ok()?)),
+            "pong" => Some(Message::Pong(BASE64_STANDARD.decode(&self.data).ok()?)),
+            "close" => Some(Message::Close(None)),
+            _ => None,
+        }
+    }
+}
+
+/// Appends every websocket frame to a capture file when enabled.
+pub struct FrameDump {
+    file: Option<Mutex<fs::File>>,
+}
+
+impl FrameDump {
+    pub fn open(path: Option<&PathBuf>) -> Self {
+        let file = path.and_then(|path| match fs::OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => {
+                println!("Capturing websocket frames to {}", path.display());
+                Some(Mutex::new(file))
+            }
+            Err(e) => {
+                println!("Failed to open frame dump {}: {}", path.display(), e);
+                None
+            }
+        });
+        FrameDump { file }
+    }
+
+    pub fn record(&self, direction: Direction, message: &Message) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        if let Ok(line) = serde_json::to_string(&DumpedFrame::new(direction, message)) {
+            let _ = writeln!(file.lock().unwrap(), "{}", line);
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct ReplayFramesArgs {
+    #[arg(value_name = "FILE", help = "Frame capture written by mine --dump-frames")]
+    pub file: PathBuf,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FLAVOR",
+        default_value = "official",
+        help = "Server fork whose protocol quirks should be used"
+    )]
+    pub server_flavor: ServerFlavor,
+    #[arg(
+        long = "quirk",
+        value_enum,
+        value_name = "QUIRK",
+        help = "Protocol quirk to enable, may be repeated (only with --server-flavor custom)"
+    )]
+    pub quirks: Vec<Quirk>,
+    #[arg(
+        long,
+        action,
+        help = "Hex-dump binary frames with unknown message types"
+    )]
+    pub dump_unknown_frames: bool,
+}
+
+/// Feeds the inbound frames of a capture through the client's message parser and prints
+/// what it made of each, outbound frames are listed for context.
+pub fn replay_frames(args: &ReplayFramesArgs) {
+    let file = match fs::File::open(&args.file) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("  Failed to open {}: {}", args.file.display(), e);
+            return;
+        }
+    };
+
+    let quirks = Quirks::new(args.server_flavor, &args.quirks);
+    let task_ctx = TaskContext::new(network::client(), String::new(), false, Pubkey::default());
+    let unexpected = UnexpectedFrames::default();
+    let broadcasts = RecentBroadcasts::default();
+    let (sender, mut parsed) = tokio::sync::mpsc::unbounded_channel::<ServerMessage>();
+
+    for (line_number, line) in BufReader::new(file).lines().map_while(Result::ok).enumerate() {
+        let Ok(frame) = serde_json::from_str::<DumpedFrame>(&line) else {
+            println!("  Line {}: not a frame record", line_number + 1);
+            continue;
+        };
+        println!("[{}] {:?} {} ({} bytes)", frame.at_ms, frame.direction, frame.kind, frame.data.len());
+        if frame.direction == Direction::Out {
+            continue;
+        }
+        let Some(message) = frame.message() else {
+            println!("  not replayable");
+            continue;
+        };
+
+        let flow = mine::process_message(message, sender.clone(), &quirks, &task_ctx, &unexpected, &broadcasts, args.dump_unknown_frames);
+        while let Ok(server_message) = parsed.try_recv() {
+            println!("  parsed: {:?}", server_message);
+        }
+        if flow.is_break() {
+            println!("  connection would close here");
+        }
+    }
+
+    unexpected.print_summary();
+}
@@ -0,0 +1,79 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// How long after a submission the pool's result may take before the round counts as
+/// dropped. Results normally arrive while the next round is being mined.
+const RESULT_GRACE: Duration = Duration::from_secs(180);
+/// Consecutive dropped submissions before warning about them.
+pub const ALERT_AFTER: u32 = 3;
+
+/// Pairs submissions with the pool's result messages, in order, to find rounds the pool
+/// accepted a solution for but never reported on.
+#[derive(Default)]
+pub struct GhostRounds {
+    pending: VecDeque<(String, DateTime<Utc>, Instant)>,
+    dropped: Vec<(String, DateTime<Utc>)>,
+    consecutive: u32,
+    submitted: u64,
+    results: u64,
+}
+
+impl GhostRounds {
+    pub fn submitted(&mut self, challenge: &[u8; 32]) {
+        self.submitted += 1;
+        self.pending.push_back((solana_sdk::bs58::encode(challenge).into_string(), Utc::now(), Instant::now()));
+    }
+
+    /// A result arrived, it belongs to the oldest submission still waiting.
+    pub fn result(&mut self) {
+        self.results += 1;
+        self.consecutive = 0;
+        self.pending.pop_front();
+    }
+
+    /// Results for a dropped connection are lost with it, which says nothing about the pool.
+    pub fn reset_connection(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Moves submissions past the grace period to the dropped list and returns their
+    /// challenges. Pools that never send results can't be checked, so nothing counts as
+    /// dropped until the first result arrived.
+    pub fn expire(&mut self) -> Vec<String> {
+        if self.results == 0 {
+            return Vec::new();
+        }
+        let mut expired = Vec::new();
+        while let Some((challenge, at, _)) = self.pending.front().filter(|(_, _, sent)| sent.elapsed() >= RESULT_GRACE).cloned() {
+            self.pending.pop_front();
+            self.consecutive += 1;
+            self.dropped.push((challenge.clone(), at));
+            expired.push(challenge);
+        }
+        expired
+    }
+
+    /// Dropped submissions since the last result.
+    pub fn consecutive(&self) -> u32 {
+        self.consecutive
+    }
+
+    pub fn print_summary(&self) {
+        if self.dropped.is_empty() {
+            return;
+        }
+        println!(
+            "{} of {} submissions never got a result from the pool ({} results received):",
+            self.dropped.len(),
+            self.submitted,
+            self.results
+        );
+        for (challenge, at) in &self.dropped {
+            println!("  [{}] challenge {}", at.to_rfc3339_opts(SecondsFormat::Secs, true), challenge);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Mutex;
+
+use clap::ValueEnum;
+
+/// Which earnings count towards --stop-after-earned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GoalBasis {
+    /// Only rewards the pool reported after a round.
+    Confirmed,
+    /// Confirmed rewards plus the estimate for the submission still awaiting its result.
+    Estimated,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    confirmed: f64,
+    pending: Option<f64>,
+}
+
+/// ORE earned this session, shared by the pools of a rotation and the sessions of `--wallets`
+/// so the goal covers all of them.
+#[derive(Debug, Default)]
+pub struct EarningsTally {
+    tally: Mutex<Tally>,
+}
+
+impl EarningsTally {
+    /// A solution was submitted, with the estimated reward for it once the estimator is calibrated.
+    pub fn submitted(&self, estimate: Option<f64>) {
+        self.tally.lock().unwrap().pending = estimate;
+    }
+
+    /// The pool reported the reward for the last submission.
+    pub fn earned(&self, ore: f64) {
+        let mut tally = self.tally.lock().unwrap();
+        tally.confirmed += ore;
+        tally.pending = None;
+    }
+
+    pub fn total(&self, basis: GoalBasis) -> f64 {
+        let tally = self.tally.lock().unwrap();
+        match basis {
+            GoalBasis::Confirmed => tally.confirmed,
+            GoalBasis::Estimated => tally.confirmed + tally.pending.unwrap_or(0.0),
+        }
+    }
+
+    /// The total once it has reached `goal`.
+    pub fn reached(&self, goal: Option<f64>, basis: GoalBasis) -> Option<f64> {
+        let total = self.total(basis);
+        goal.filter(|goal| total >= *goal).map(|_| total)
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Renders values as a single line sparkline, scaled between their min and max.
+pub fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|v| {
+            if range <= 0.0 {
+                BARS[BARS.len() / 2]
+            } else {
+                let idx = ((v - min) / range * (BARS.len() - 1) as f64).round() as usize;
+                BARS[idx.min(BARS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// Hashrate, best difficulty and assigned range size of the last N rounds, for terminal charts.
+pub struct RoundGraph {
+    capacity: usize,
+    hashrates: VecDeque<f64>,
+    difficulties: VecDeque<f64>,
+    ranges: VecDeque<f64>,
+}
+
+impl RoundGraph {
+    pub fn new(capacity: usize) -> Self {
+        RoundGraph {
+            capacity,
+            hashrates: VecDeque::with_capacity(capacity),
+            difficulties: VecDeque::with_capacity(capacity),
+            ranges: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, hashrate: u64, difficulty: u32, range_size: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.hashrates.len() == self.capacity {
+            self.hashrates.pop_front();
+            self.difficulties.pop_front();
+            self.ranges.pop_front();
+        }
+        self.hashrates.push_back(hashrate as f64);
+        self.difficulties.push_back(difficulty as f64);
+        self.ranges.push_back(range_size as f64);
+    }
+
+    pub fn print(&self) {
+        if self.hashrates.len() < 2 {
+            return;
+        }
+        let hashrates: Vec<f64> = self.hashrates.iter().cloned().collect();
+        let difficulties: Vec<f64> = self.difficulties.iter().cloned().collect();
+        println!(
+            "Hashrate   {} ({:.0}-{:.0} H/s, last {} rounds)",
+            sparkline(&hashrates),
+            hashrates.iter().cloned().fold(f64::INFINITY, f64::min),
+            hashrates.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            hashrates.len()
+        );
+        println!(
+            "Difficulty {} ({:.0}-{:.0})",
+            sparkline(&difficulties),
+            difficulties.iter().cloned().fold(f64::INFINITY, f64::min),
+            difficulties.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        );
+        let ranges: Vec<f64> = self.ranges.iter().cloned().collect();
+        println!(
+            "Range      {} ({:.0}-{:.0} nonces)",
+            sparkline(&ranges),
+            ranges.iter().cloned().fold(f64::INFINITY, f64::min),
+            ranges.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        );
+    }
+}
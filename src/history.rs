@@ -0,0 +1,446 @@
+use std::{collections::BTreeMap, fs, path::Path, time::{SystemTime, UNIX_EPOCH}};
+
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::format;
+use crate::network;
+use crate::store;
+
+/// Store collection holding the history, one JSON entry per record.
+pub const HISTORY_COLLECTION: &str = "history";
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+/// Gaps between rounds longer than this count as downtime.
+const MAX_ROUND_GAP_SECS: u64 = 180;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryEntry {
+    /// A solution submitted to the pool. `hashes` counts hashes computed and `nonces` the
+    /// nonces scanned, each nonce yielding a few hashes. `submit_ms` is how long the submission
+    /// took to send, `range_size` how many nonces the pool assigned for the round.
+    Round {
+        at: u64,
+        pool: String,
+        wallet: String,
+        difficulty: u32,
+        hashes: u64,
+        hash_secs: f64,
+        #[serde(default)]
+        rig: String,
+        #[serde(default)]
+        submit_ms: Option<u64>,
+        #[serde(default)]
+        range_size: Option<u64>,
+        #[serde(default)]
+        nonces: Option<u64>,
+    },
+    /// Rewards credited by the pool for a round.
+    Earned {
+        at: u64,
+        pool: String,
+        wallet: String,
+        ore: f64,
+        #[serde(default)]
+        rig: String,
+    },
+    /// A claim request accepted by the pool. `fee_ore` is deducted from the claimed amount,
+    /// `signature` is the finalized claim transaction when the pool reported one.
+    Claim {
+        at: u64,
+        pool: String,
+        wallet: String,
+        ore: f64,
+        fee_ore: f64,
+        #[serde(default)]
+        signature: Option<String>,
+    },
+    /// SOL spent signing up with a pool, including the transaction fee.
+    Signup { at: u64, pool: String, wallet: String, sol: f64 },
+}
+
+impl HistoryEntry {
+    pub fn at(&self) -> u64 {
+        match self {
+            HistoryEntry::Round { at, .. }
+            | HistoryEntry::Earned { at, .. }
+            | HistoryEntry::Claim { at, .. }
+            | HistoryEntry::Signup { at, .. } => *at,
+        }
+    }
+
+    pub fn wallet(&self) -> &str {
+        match self {
+            HistoryEntry::Round { wallet, .. }
+            | HistoryEntry::Earned { wallet, .. }
+            | HistoryEntry::Claim { wallet, .. }
+            | HistoryEntry::Signup { wallet, .. } => wallet,
+        }
+    }
+
+    pub fn pool(&self) -> &str {
+        match self {
+            HistoryEntry::Round { pool, .. }
+            | HistoryEntry::Earned { pool, .. }
+            | HistoryEntry::Claim { pool, .. }
+            | HistoryEntry::Signup { pool, .. } => pool,
+        }
+    }
+}
+
+/// Highest difficulty the wallet has submitted, 0 without any rounds.
+pub fn best_difficulty(wallet: &str) -> u32 {
+    load()
+        .iter()
+        .filter_map(|entry| match entry {
+            HistoryEntry::Round { wallet: w, difficulty, .. } if w == wallet => Some(*difficulty),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Difficulties of the wallet's last `count` rounds, oldest first.
+pub fn recent_difficulties(wallet: &str, count: usize) -> Vec<u32> {
+    let mut difficulties: Vec<u32> = load()
+        .iter()
+        .filter_map(|entry| match entry {
+            HistoryEntry::Round { wallet: w, difficulty, .. } if w == wallet => Some(*difficulty),
+            _ => None,
+        })
+        .collect();
+    difficulties.drain(..difficulties.len().saturating_sub(count));
+    difficulties
+}
+
+/// Name of this machine, used to tell rigs apart in shared history.
+pub fn rig_name() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|name| name.trim().to_string())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs()
+}
+
+/// Appends an entry to the history store. Failures are ignored, history is best effort.
+pub fn record(entry: HistoryEntry) {
+    let Ok(line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    let _ = store::with(|store| store.append(HISTORY_COLLECTION, &line));
+}
+
+/// Reads all entries, skipping lines that fail to parse.
+pub fn load() -> Vec<HistoryEntry> {
+    store::with(|store| store.load(HISTORY_COLLECTION))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Reads all entries of a history file at any path, e.g. a saved baseline.
+pub fn load_from(path: &Path) -> Vec<HistoryEntry> {
+    fs::read_to_string(path)
+        .map(|contents| contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Parser)]
+pub struct PnlArgs {
+    #[arg(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "Only include history from this date on"
+    )]
+    pub from: Option<NaiveDate>,
+    #[arg(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "Only include history up to and including this date"
+    )]
+    pub to: Option<NaiveDate>,
+    #[arg(
+        long,
+        action,
+        help = "Include every wallet in the history instead of only the selected keypair"
+    )]
+    pub all_wallets: bool,
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "https://api.jup.ag/price/v2",
+        help = "Price API queried with ?ids=<mint>,<mint> for USD prices"
+    )]
+    pub price_api: String,
+}
+
+#[derive(Debug, Default)]
+struct WalletPnl {
+    earned_ore: f64,
+    claimed_ore: f64,
+    claim_fees_ore: f64,
+    signup_sol: f64,
+}
+
+fn date_bounds(from: Option<NaiveDate>, to: Option<NaiveDate>) -> (u64, u64) {
+    let start = from
+        .map(|d| d.and_time(NaiveTime::MIN).and_utc().timestamp().max(0) as u64)
+        .unwrap_or(0);
+    let end = to
+        .and_then(|d| d.succ_opt())
+        .map(|d| d.and_time(NaiveTime::MIN).and_utc().timestamp().max(0) as u64)
+        .unwrap_or(u64::MAX);
+    (start, end)
+}
+
+/// Fetches USD prices for the given mints, missing prices are simply absent.
+async fn fetch_prices(price_api: &str, mints: &[&str]) -> BTreeMap<String, f64> {
+    let mut prices = BTreeMap::new();
+    let Ok(response) = network::client().get(format!("{}?ids={}", price_api, mints.join(","))).send().await else {
+        return prices;
+    };
+    let Ok(body) = response.json::<serde_json::Value>().await else {
+        return prices;
+    };
+    for mint in mints {
+        let price = body["data"][*mint]["price"]
+            .as_str()
+            .and_then(|p| p.parse::<f64>().ok())
+            .or_else(|| body["data"][*mint]["price"].as_f64());
+        if let Some(price) = price {
+            prices.insert(mint.to_string(), price);
+        }
+    }
+    prices
+}
+
+/// Summarizes ORE earned against SOL and ORE spent per wallet.
+pub async fn pnl(args: PnlArgs, wallet: &Pubkey) {
+    let (start, end) = date_bounds(args.from, args.to);
+    let wallet = wallet.to_string();
+
+    let mut wallets: BTreeMap<String, WalletPnl> = BTreeMap::new();
+    for entry in load() {
+        if entry.at() < start || entry.at() >= end || (!args.all_wallets && entry.wallet() != wallet) {
+            continue;
+        }
+        let totals = wallets.entry(entry.wallet().to_string()).or_default();
+        match entry {
+            HistoryEntry::Earned { ore, .. } => totals.earned_ore += ore,
+            HistoryEntry::Claim { ore, fee_ore, .. } => {
+                totals.claimed_ore += ore;
+                totals.claim_fees_ore += fee_ore;
+            },
+            HistoryEntry::Signup { sol, .. } => totals.signup_sol += sol,
+            HistoryEntry::Round { .. } => {},
+        }
+    }
+
+    if wallets.is_empty() {
+        println!("  No history recorded for this period.");
+        return;
+    }
+
+    let ore_mint = ore_api::consts::MINT_ADDRESS.to_string();
+    let prices = fetch_prices(&args.price_api, &[&ore_mint, SOL_MINT]).await;
+    let ore_price = prices.get(&ore_mint).copied();
+    let sol_price = prices.get(SOL_MINT).copied();
+
+    for (wallet, totals) in wallets {
+        println!("  {}", wallet);
+        println!("    Earned:       {:.11} ORE", totals.earned_ore);
+        println!("    Claimed:      {:.11} ORE", totals.claimed_ore);
+        println!("    Claim Fees:   {:.11} ORE", totals.claim_fees_ore);
+        println!("    Signup Cost:  {:.9} SOL", totals.signup_sol);
+        match (ore_price, sol_price) {
+            (Some(ore_price), Some(sol_price)) => {
+                let net = (totals.earned_ore - totals.claim_fees_ore) * ore_price - totals.signup_sol * sol_price;
+                println!("    Net Position: ${:.2} (ORE ${:.2}, SOL ${:.2})", net, ore_price, sol_price);
+            },
+            _ => println!("    Net Position: unavailable, failed to fetch prices from {}", args.price_api),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Pool,
+    Day,
+    Week,
+    Rig,
+}
+
+#[derive(Debug, Parser)]
+pub struct HistoryArgs {
+    #[arg(
+        long,
+        value_enum,
+        value_name = "GROUP",
+        default_value = "day",
+        help = "How to group the recorded rounds"
+    )]
+    pub group_by: GroupBy,
+    #[arg(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "Only include history from this date on"
+    )]
+    pub from: Option<NaiveDate>,
+    #[arg(
+        long,
+        value_name = "YYYY-MM-DD",
+        help = "Only include history up to and including this date"
+    )]
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Default)]
+struct GroupStats {
+    rounds: u64,
+    hashes: u64,
+    hash_secs: f64,
+    earned_ore: f64,
+    best_difficulty: u32,
+    difficulty_total: u64,
+    submit_ms_total: u64,
+    submit_samples: u64,
+    range_total: u64,
+    range_samples: u64,
+    round_times: Vec<u64>,
+}
+
+impl GroupStats {
+    fn add(&mut self, entry: &HistoryEntry) {
+        match entry {
+            HistoryEntry::Round { at, difficulty, hashes, hash_secs, submit_ms, range_size, .. } => {
+                self.rounds += 1;
+                self.hashes += hashes;
+                self.hash_secs += hash_secs;
+                self.best_difficulty = self.best_difficulty.max(*difficulty);
+                self.difficulty_total += *difficulty as u64;
+                self.round_times.push(*at);
+                if let Some(ms) = submit_ms {
+                    self.submit_ms_total += ms;
+                    self.submit_samples += 1;
+                }
+                if let Some(size) = range_size {
+                    self.range_total += size;
+                    self.range_samples += 1;
+                }
+            },
+            HistoryEntry::Earned { ore, .. } => self.earned_ore += ore,
+            _ => {},
+        }
+    }
+
+    /// Share of the group's time span covered by back-to-back rounds.
+    fn uptime_percent(&self) -> Option<f64> {
+        let first = *self.round_times.first()?;
+        let last = *self.round_times.last()?;
+        if last == first {
+            return None;
+        }
+        let up: u64 = self.round_times
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .filter(|gap| *gap <= MAX_ROUND_GAP_SECS)
+            .sum();
+        Some(up as f64 * 100.0 / (last - first) as f64)
+    }
+}
+
+fn group_key(group_by: GroupBy, at: u64, pool: &str, rig: &str) -> String {
+    let date = DateTime::from_timestamp(at as i64, 0).map(|dt| dt.date_naive()).unwrap_or_default();
+    match group_by {
+        GroupBy::Pool => pool.to_string(),
+        GroupBy::Day => date.format("%Y-%m-%d").to_string(),
+        GroupBy::Week => date.format("%G-W%V").to_string(),
+        GroupBy::Rig if rig.is_empty() => "unknown".to_string(),
+        GroupBy::Rig => rig.to_string(),
+    }
+}
+
+/// Aggregates recorded rounds across sessions, grouped by pool, day, week or rig.
+pub fn history(args: &HistoryArgs) {
+    let (start, end) = date_bounds(args.from, args.to);
+
+    let mut entries: Vec<HistoryEntry> = load().into_iter().filter(|e| e.at() >= start && e.at() < end).collect();
+    entries.sort_by_key(|e| e.at());
+
+    let mut groups: BTreeMap<String, GroupStats> = BTreeMap::new();
+    let mut overall = GroupStats::default();
+    for entry in &entries {
+        let (HistoryEntry::Round { at, pool, rig, .. } | HistoryEntry::Earned { at, pool, rig, .. }) = entry else {
+            continue;
+        };
+        groups.entry(group_key(args.group_by, *at, pool, rig)).or_default().add(entry);
+        overall.add(entry);
+    }
+
+    if groups.is_empty() {
+        println!("  No rounds recorded for this period.");
+        return;
+    }
+
+    for (key, stats) in groups.iter().chain([(&"Total".to_string(), &overall)]) {
+        println!("  {}", key);
+        print_group(stats);
+    }
+}
+
+fn group_lines(stats: &GroupStats) -> Vec<String> {
+    let mut lines = vec![
+        format!("Rounds:          {}", stats.rounds),
+        format!("Hashes:          {}", format::count(stats.hashes)),
+    ];
+    if stats.hash_secs > 0.0 {
+        lines.push(format!("Avg Hashpower:   {}", format::hashrate(stats.hashes as f64 / stats.hash_secs)));
+    }
+    if let Some(avg_difficulty) = stats.difficulty_total.checked_div(stats.rounds) {
+        lines.push(format!("Avg Difficulty:  {}", avg_difficulty));
+    }
+    lines.push(format!("Best Difficulty: {}", stats.best_difficulty));
+    lines.push(format!("Earned:          {:.11} ORE", stats.earned_ore));
+    match stats.uptime_percent() {
+        Some(uptime) => lines.push(format!("Uptime:          {:.1}%", uptime)),
+        None => lines.push("Uptime:          n/a".to_string()),
+    }
+    if let Some(avg_ms) = stats.submit_ms_total.checked_div(stats.submit_samples) {
+        lines.push(format!("Avg Submit:      {} ms", avg_ms));
+    }
+    if let Some(avg_range) = stats.range_total.checked_div(stats.range_samples) {
+        lines.push(format!("Avg Range:       {} nonces", format::count(avg_range)));
+    }
+    lines
+}
+
+fn print_group(stats: &GroupStats) {
+    for line in group_lines(stats) {
+        println!("    {}", line);
+    }
+}
+
+/// Summary of one wallet's rounds and confirmed earnings between two unix timestamps,
+/// optionally limited to a single pool.
+pub fn summary_lines(start: u64, end: u64, wallet: &str, pool: Option<&str>) -> Vec<String> {
+    let mut entries: Vec<HistoryEntry> = load()
+        .into_iter()
+        .filter(|e| e.at() >= start && e.at() < end && e.wallet() == wallet && pool.is_none_or(|pool| e.pool() == pool))
+        .collect();
+    entries.sort_by_key(|e| e.at());
+
+    let mut stats = GroupStats::default();
+    for entry in &entries {
+        stats.add(entry);
+    }
+    group_lines(&stats)
+}
@@ -0,0 +1,243 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use solana_sdk::signature::{read_keypair_file, Signer};
+
+use crate::clock;
+use crate::network;
+use crate::schema::{self, ServerTime};
+use crate::settings::{self, Settings};
+
+/// Name of the service, launch agent or scheduled task.
+const SERVICE_NAME: &str = "ore-hq-client";
+
+#[derive(Debug, Parser)]
+pub struct InstallArgs {
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Working directory for settings, history and logs. Defaults to ore-hq-client in the user data directory"
+    )]
+    pub dir: Option<PathBuf>,
+    #[arg(
+        long,
+        action,
+        help = "Also write a service for this platform that mines at login and restarts on failure: a systemd user unit, a launchd agent or a Task Scheduler script"
+    )]
+    pub service: bool,
+    #[arg(
+        long,
+        value_name = "THREADS",
+        help = "Threads to mine with, written to the settings skeleton. Defaults to every core"
+    )]
+    pub threads: Option<u32>,
+    #[arg(
+        long,
+        action,
+        help = "Overwrite an existing settings file and service"
+    )]
+    pub force: bool,
+}
+
+fn default_dir() -> PathBuf {
+    dirs::data_local_dir().map_or_else(|| PathBuf::from("."), |dir| dir.join(SERVICE_NAME))
+}
+
+fn settings_skeleton(url: &str, unsecure: bool, keypair: &str, threads: u32) -> String {
+    format!(
+        "# Settings for ore-hq-client, read from the working directory.\n\
+         # `ore-hq-client config show --effective` lists every option and where it comes from.\n\
+         \n\
+         [options]\n\
+         url = {:?}\n\
+         use-http = {}\n\
+         keypair = {:?}\n\
+         threads = {}\n\
+         \n\
+         # Daily earnings summary to a Slack or Discord webhook, encrypt it with `config encrypt`.\n\
+         # [report]\n\
+         # webhook = \"https://discord.com/api/webhooks/...\"\n\
+         # at = \"08:00\"\n",
+        url, unsecure, keypair, threads
+    )
+}
+
+/// Writes `contents` to `path` unless it exists and --force wasn't given.
+fn write_file(path: &Path, contents: &str, force: bool) -> bool {
+    if path.exists() && !force {
+        println!("  Keeping existing {} (--force overwrites it)", path.display());
+        return true;
+    }
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    match fs::write(path, contents) {
+        Ok(()) => {
+            println!("  Wrote {}", path.display());
+            true
+        },
+        Err(e) => {
+            println!("  Failed to write {}: {}", path.display(), e);
+            false
+        },
+    }
+}
+
+/// Writes the service definition for this platform and prints how to enable it.
+fn install_service(dir: &Path, force: bool) -> bool {
+    let Ok(exe) = std::env::current_exe() else {
+        println!("  Can't locate this executable, skipping the service.");
+        return false;
+    };
+    let log = dir.join("logs").join("miner.log");
+
+    if cfg!(target_os = "windows") {
+        let script = dir.join(format!("{}.cmd", SERVICE_NAME));
+        let contents = format!("@echo off\r\ncd /d \"{}\"\r\n\"{}\" --log-file \"{}\" mine\r\n", dir.display(), exe.display(), log.display());
+        if !write_file(&script, &contents, force) {
+            return false;
+        }
+        println!("  Mine at every login with:");
+        println!("    schtasks /Create /TN {} /TR \"{}\" /SC ONLOGON", SERVICE_NAME, script.display());
+    } else if cfg!(target_os = "macos") {
+        let Some(home) = dirs::home_dir() else {
+            println!("  No home directory, skipping the launch agent.");
+            return false;
+        };
+        let plist = home.join("Library/LaunchAgents").join(format!("me.ec1ipse.{}.plist", SERVICE_NAME));
+        let contents = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n\
+             <dict>\n\
+             \t<key>Label</key><string>me.ec1ipse.{name}</string>\n\
+             \t<key>ProgramArguments</key>\n\
+             \t<array><string>{exe}</string><string>--log-file</string><string>{log}</string><string>mine</string></array>\n\
+             \t<key>WorkingDirectory</key><string>{dir}</string>\n\
+             \t<key>RunAtLoad</key><true/>\n\
+             \t<key>KeepAlive</key><dict><key>SuccessfulExit</key><false/></dict>\n\
+             </dict>\n\
+             </plist>\n",
+            name = SERVICE_NAME,
+            exe = exe.display(),
+            log = log.display(),
+            dir = dir.display()
+        );
+        if !write_file(&plist, &contents, force) {
+            return false;
+        }
+        println!("  Start it now and at every login with:");
+        println!("    launchctl load -w {}", plist.display());
+    } else {
+        let Some(config) = dirs::config_dir() else {
+            println!("  No config directory, skipping the systemd unit.");
+            return false;
+        };
+        let unit = config.join("systemd/user").join(format!("{}.service", SERVICE_NAME));
+        let contents = format!(
+            "[Unit]\n\
+             Description=ORE pool miner\n\
+             After=network-online.target\n\
+             Wants=network-online.target\n\
+             \n\
+             [Service]\n\
+             WorkingDirectory={dir}\n\
+             ExecStart={exe} --log-file {log} mine\n\
+             Restart=on-failure\n\
+             RestartSec=10\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n",
+            dir = dir.display(),
+            exe = exe.display(),
+            log = log.display()
+        );
+        if !write_file(&unit, &contents, force) {
+            return false;
+        }
+        println!("  Start it now and at every login with:");
+        println!("    systemctl --user daemon-reload && systemctl --user enable --now {}", SERVICE_NAME);
+        println!("  To keep mining while logged out: loginctl enable-linger $USER");
+    }
+    true
+}
+
+fn check(ok: bool, what: &str, detail: &str) -> bool {
+    println!("  [{}] {}: {}", if ok { "ok" } else { "!!" }, what, detail);
+    ok
+}
+
+/// First-run checks of what a mining session needs. Returns the number of problems found.
+async fn sanity_check(dir: &Path, url: &str, unsecure: bool, keypair: &str) -> usize {
+    let mut failed = 0;
+
+    let settings_file = dir.join(settings::SETTINGS_FILE);
+    let parsed = fs::read_to_string(&settings_file).map_err(|e| e.to_string()).and_then(|contents| toml::from_str::<Settings>(&contents).map_err(|e| e.to_string()));
+    let detail = match &parsed {
+        Ok(_) => format!("{} is valid", settings_file.display()),
+        Err(e) => format!("{}: {}", settings_file.display(), e),
+    };
+    failed += !check(parsed.is_ok(), "settings", &detail) as usize;
+
+    let keypair_path = crate::expand_tilde(keypair);
+    let detail = match read_keypair_file(&keypair_path) {
+        Ok(key) => Ok(format!("{} ({})", keypair_path, key.pubkey())),
+        Err(_) => Err(format!("can't read {}, create one with `solana-keygen new -o {}` or pass --keypair", keypair_path, keypair_path)),
+    };
+    failed += !check(detail.is_ok(), "keypair", detail.as_ref().unwrap_or_else(|e| e)) as usize;
+
+    let client = network::client();
+    let url_prefix = if unsecure { "http" } else { "https" };
+    let reachable = schema::get::<ServerTime>(&client, url_prefix, url, "timestamp", "").await;
+    let detail = match &reachable {
+        Ok(_) => format!("{} answers", url),
+        Err(e) => format!("{}: {}", url, e),
+    };
+    failed += !check(reachable.is_ok(), "pool", &detail) as usize;
+    if reachable.is_ok() {
+        let skew = clock::check(&client, url_prefix, url, clock::DEFAULT_TIME_SOURCE).await;
+        let in_sync = skew.pool.is_none_or(|skew| skew.abs() < 3);
+        let detail = skew.pool.map_or("not measured".to_string(), |skew| format!("{}s off the pool's clock", skew));
+        failed += !check(in_sync, "clock", &detail) as usize;
+        clock::report(&skew, url, clock::DEFAULT_TIME_SOURCE, false);
+    }
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    check(true, "cpu", &format!("{} cores available", cores));
+    failed
+}
+
+/// Sets up a working directory with a settings skeleton and optionally a service, then checks
+/// that mining can start from it.
+pub async fn install(args: &InstallArgs, url: &str, unsecure: bool, keypair: &str) {
+    let dir = args.dir.clone().unwrap_or_else(default_dir);
+    let dir = fs::create_dir_all(dir.join("logs")).and_then(|_| dir.canonicalize());
+    let dir = match dir {
+        Ok(dir) => dir,
+        Err(e) => {
+            println!("  Failed to create the working directory: {}", e);
+            return;
+        },
+    };
+    println!("  Working directory: {}", dir.display());
+
+    let threads = args.threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get() as u32));
+    let keypair = crate::expand_tilde(keypair);
+    let mut ok = write_file(&dir.join(settings::SETTINGS_FILE), &settings_skeleton(url, unsecure, &keypair, threads), args.force);
+    if args.service {
+        ok &= install_service(&dir, args.force);
+    }
+
+    println!();
+    println!("  Checking the setup:");
+    let failed = sanity_check(&dir, url, unsecure, &keypair).await;
+    println!();
+    if ok && failed == 0 {
+        println!("  Ready to mine: cd {} && ore-hq-client mine", dir.display());
+    } else {
+        println!("  {} problem(s) to fix before mining, run install again afterwards to recheck.", failed.max(!ok as usize));
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant};
+
+/// Summary statistics of a series of intervals, in milliseconds.
+#[derive(Debug, Default)]
+struct IntervalStats {
+    samples: Vec<f64>,
+}
+
+impl IntervalStats {
+    fn push(&mut self, interval: Duration) {
+        self.samples.push(interval.as_secs_f64() * 1000.0);
+    }
+
+    fn print(&self, label: &str) {
+        if self.samples.is_empty() {
+            println!("  {}: no samples", label);
+            return;
+        }
+        let n = self.samples.len() as f64;
+        let mean = self.samples.iter().sum::<f64>() / n;
+        let stddev = (self.samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(f64::total_cmp);
+        let p95 = sorted[((sorted.len() - 1) as f64 * 0.95).round() as usize];
+        println!(
+            "  {}: mean {:.0} ms, jitter (stddev) {:.0} ms, min {:.0} ms, p95 {:.0} ms, max {:.0} ms ({} samples)",
+            label,
+            mean,
+            stddev,
+            sorted[0],
+            p95,
+            sorted[sorted.len() - 1],
+            sorted.len()
+        );
+    }
+}
+
+/// Timing of challenges relative to each other and to our Ready messages, to tell
+/// client-side latency apart from the pool's round scheduling.
+#[derive(Default)]
+pub struct ChallengeJitter {
+    last_challenge: Option<Instant>,
+    last_ready: Option<Instant>,
+    between_challenges: IntervalStats,
+    ready_to_challenge: IntervalStats,
+}
+
+impl ChallengeJitter {
+    /// Forgets timestamps from a previous connection so reconnect gaps aren't counted.
+    pub fn reset_connection(&mut self) {
+        self.last_challenge = None;
+        self.last_ready = None;
+    }
+
+    pub fn ready_sent(&mut self) {
+        self.last_ready = Some(Instant::now());
+    }
+
+    /// Time since the last Ready, while no challenge has answered it.
+    pub fn awaiting_challenge(&self) -> Option<Duration> {
+        self.last_ready.map(|ready| ready.elapsed())
+    }
+
+    /// Records a StartMining arrival and returns how long after our Ready it came.
+    pub fn challenge_received(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        if let Some(last) = self.last_challenge.replace(now) {
+            self.between_challenges.push(now - last);
+        }
+        let since_ready = self.last_ready.take().map(|ready| now - ready);
+        if let Some(since_ready) = since_ready {
+            self.ready_to_challenge.push(since_ready);
+        }
+        since_ready
+    }
+
+    pub fn print_summary(&self) {
+        println!("Challenge timing:");
+        self.between_challenges.print("Between challenges");
+        self.ready_to_challenge.print("Ready to challenge");
+    }
+}
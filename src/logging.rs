@@ -0,0 +1,147 @@
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use clap::ValueEnum;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{fmt, fmt::MakeWriter, prelude::*};
+
+use crate::redact;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines.
+    Pretty,
+    /// One JSON object per line, including a `round` event for every mining round.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Makes writers that pass every log record through redact::filter, so events logged with
+/// tracing directly are redacted like console output.
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = Redacted<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacted(self.0.make_writer())
+    }
+}
+
+struct Redacted<W>(W);
+
+impl<W: io::Write> io::Write for Redacted<W> {
+    // The formatter writes each record in one call, so secrets aren't split between writes
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(record) => self.0.write_all(redact::filter(record).as_bytes()).map(|()| buf.len()),
+            Err(_) => self.0.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Set once a log subscriber is installed, so console output is also recorded as events.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Set when JSON events go to stdout, which then carries nothing else.
+static JSON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// Installs the log subscriber. Logs go to `file` when given, rotated and pruned to `keep`
+/// files, otherwise JSON logs replace the console output and pretty logs are the console
+/// output itself. The returned guard flushes the log when dropped.
+pub fn init(file: Option<&Path>, format: LogFormat, rotation: LogRotation, keep: usize) -> Option<WorkerGuard> {
+    let (writer, guard) = match file {
+        Some(path) => {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let prefix = path.file_name().map_or_else(|| "ore-hq-client.log".into(), |name| name.to_string_lossy());
+            let appender = std::fs::create_dir_all(dir).map_err(|e| e.to_string()).and_then(|()| {
+                RollingFileAppender::builder()
+                    .rotation(rotation.into())
+                    .filename_prefix(prefix.as_ref())
+                    .max_log_files(keep.max(1))
+                    .build(dir)
+                    .map_err(|e| e.to_string())
+            });
+            match appender {
+                Ok(appender) => tracing_appender::non_blocking(appender),
+                Err(e) => {
+                    println!("Failed to open log file {}: {}", path.display(), e);
+                    return None;
+                },
+            }
+        },
+        None if format == LogFormat::Json => {
+            JSON_STDOUT.store(true, Ordering::Relaxed);
+            tracing_appender::non_blocking(std::io::stdout())
+        },
+        None => return None,
+    };
+
+    let layer = fmt::layer().with_writer(Redacting(writer)).with_ansi(false);
+    match format {
+        LogFormat::Json => tracing_subscriber::registry().with(layer.json().flatten_event(true)).init(),
+        LogFormat::Pretty => tracing_subscriber::registry().with(layer).init(),
+    }
+    ACTIVE.store(true, Ordering::Relaxed);
+    Some(guard)
+}
+
+/// Whether to draw spinners and other terminal-only output.
+pub fn interactive() -> bool {
+    std::io::stdout().is_terminal() && !JSON_STDOUT.load(Ordering::Relaxed)
+}
+
+/// Writes a line of console output, see the println! macro at the top of main.rs. The line is
+/// also logged when logging is set up, instead of printed when stdout carries JSON logs.
+pub fn line(line: &str) {
+    if !JSON_STDOUT.load(Ordering::Relaxed) {
+        ::std::println!("{}", line);
+    }
+    if ACTIVE.load(Ordering::Relaxed) && !line.trim().is_empty() {
+        tracing::info!(target: "output", "{}", strip_ansi(line.trim()));
+    }
+}
+
+/// Removes color escape sequences, which only mean something to a terminal.
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip `[` and parameters up to the final letter of the sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
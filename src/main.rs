@@ -1,16 +1,32 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use inquire::{Text, Confirm, Select};
 use dirs::home_dir;
 use std::path::PathBuf;
-use std::io::{self, Write, BufRead};
-use solana_sdk::signature::read_keypair_file;
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file, signer::Signer};
 use signup::signup;
 use claim::ClaimArgs;
 use mine::{MineArgs, mine};
 use protomine::{MineArgs as ProtoMineArgs, protomine};
 use balance::balance;
-use std::fs;
+use std::{ffi::OsString, fs, str::FromStr, sync::{Arc, OnceLock}};
 
+// All console output goes through the redaction filter and into the log. Defined before the
+// modules so it replaces std's println! everywhere in the crate.
+macro_rules! println {
+    () => {
+        crate::logging::line("")
+    };
+    ($($arg:tt)*) => {
+        crate::logging::line(&crate::redact::filter(&::std::format!($($arg)*)))
+    };
+}
+
+mod redact;
+mod logging;
+mod profiles;
+mod compat;
+mod api;
 mod signup;
 mod protomine;
 mod mine;
@@ -19,19 +35,101 @@ mod balance;
 mod delegate_stake;
 mod stake_balance;
 mod undelegate_stake;
-
-const CONFIG_FILE: &str = "keypair_list";
+mod wallet_guard;
+mod protocol;
+mod estimate;
+mod resource_monitor;
+mod tasks;
+mod theme;
+mod steal;
+mod session;
+mod signer;
+mod agent;
+mod state;
+mod graph;
+mod ghosts;
+mod goal;
+mod daily;
+mod quota;
+mod config;
+mod cluster;
+mod proof;
+mod pool_cache;
+mod response_cache;
+mod ping;
+mod sleep_detect;
+mod history;
+mod sprint;
+mod feed;
+mod sla;
+mod settings;
+mod network;
+mod install;
+mod secrets;
+mod tune_solver;
+mod benchmark;
+mod bench_replay;
+mod baseline;
+mod power;
+mod clock;
+mod control;
+mod thermal;
+mod audit;
+mod format;
+mod threads;
+mod frames;
+mod report;
+mod notify;
+mod sound;
+mod store;
+mod jitter;
+mod solo;
+mod arch;
+mod ranges;
+mod broadcasts;
+mod schedule;
+mod schema;
+mod endpoint;
+mod profiler;
+mod wallets;
+mod metrics;
+mod failover;
+mod migrate;
+mod fleet;
+
+const EXAMPLES: &str = "\
+Examples:
+  Mine with 8 threads on the default pool:
+    ore-hq-client mine --threads 8
+
+  Mine against a pool on a custom host with a specific keypair:
+    ore-hq-client --url pool.example.com --keypair ~/keys/miner.json mine --threads 16 --buffer 2
+
+  Check balances and claim rewards:
+    ore-hq-client balance
+    ore-hq-client claim
+
+  Coming from ore-cli: --cores, --buffer-time and rewards are understood, fee options are ignored:
+    ore-hq-client mine --cores 8 --keypair ~/keys/miner.json
+
+  Install bash completions:
+    ore-hq-client completions bash > /etc/bash_completion.d/ore-hq-client";
 
 /// A command line interface tool for pooling power to submit hashes for proportional ORE rewards
 #[derive(Parser, Debug)]
-#[command(version, author, about, long_about = None)]
+#[command(version, author, about, long_about = None, after_long_help = EXAMPLES)]
 struct Args {
     #[arg(
-        long,
+        long = "url",
         value_name = "SERVER_URL",
-        help = "URL of the server to connect to",
+        value_delimiter = ',',
+        help = "URL of the server to connect to. Repeat or comma-separate to fail over between pools in priority order",
         default_value = "ec1ipse.me",
     )]
+    urls: Vec<String>,
+
+    /// The first of --url, which all commands but mine use.
+    #[arg(skip)]
     url: String,
 
     #[arg(
@@ -42,6 +140,30 @@ struct Args {
     )]
     keypair: String,
 
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        value_parser = parse_pubkey,
+        help = "Wallet address, or a file holding it, for read-only commands (balance, stake-balance, proof, pnl), so no keypair is needed. With --signer, the wallet the signer has to sign as",
+    )]
+    pubkey: Option<Pubkey>,
+
+    #[arg(
+        long,
+        value_name = "RPC_URL",
+        help = "Solana RPC URL used for on-chain lookups (defaults to the cluster's public RPC)",
+    )]
+    rpc: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CLUSTER",
+        help = "Solana cluster the pool runs on",
+        default_value = "mainnet",
+    )]
+    cluster: cluster::Cluster,
+
     #[arg(
         long,
         short,
@@ -50,6 +172,108 @@ struct Args {
     )]
     use_http: bool,
 
+    #[arg(
+        long,
+        action,
+        help = "Run with an ephemeral throwaway keypair instead of a real one. On-chain commands are refused.",
+    )]
+    mock_signer: bool,
+
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        help = "Sign for mining with remote:<url> (a signing service), agent:<addr> (a signer-agent) or file:<path> instead of --keypair. Only mine supports remote and agent",
+    )]
+    signer: Option<signer::SignerSource>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        help = "Colorize output (NO_COLOR is respected in auto mode)",
+        default_value = "auto",
+    )]
+    color: theme::ColorChoice,
+
+    #[arg(
+        long,
+        action,
+        help = "Disable colored output, same as --color never.",
+    )]
+    no_color: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "THEME",
+        help = "Color theme for status output",
+        default_value = "default",
+    )]
+    theme: theme::Theme,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Settings file with [options] and [profile.<name>] tables of command line options (default config.toml)",
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Apply the options in [profile.<NAME>] of the settings file",
+    )]
+    profile: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        help = "What to hide in output: signatures and tokens, also pubkeys, or nothing",
+        default_value = "secrets",
+    )]
+    log_redaction: redact::LogRedaction,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Also write output and round events to this log file, rotated by --log-rotation into PATH.<date>"
+    )]
+    log_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        help = "Log format. json without --log-file replaces console output with JSON events",
+        default_value = "pretty",
+    )]
+    log_format: logging::LogFormat,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "PERIOD",
+        help = "How often to start a new log file",
+        default_value = "daily",
+    )]
+    log_rotation: logging::LogRotation,
+
+    #[arg(
+        long,
+        value_name = "FILES",
+        help = "Rotated log files to keep",
+        default_value = "7",
+    )]
+    log_keep: usize,
+
+    #[arg(
+        long,
+        action,
+        help = "Ask the pool again instead of reusing cached balance, stake and claim-info responses",
+    )]
+    refresh: bool,
+
     #[command(subcommand)]
     command: Option<Commands>
 }
@@ -57,7 +281,7 @@ struct Args {
 #[derive(Debug, Subcommand)]
 enum Commands {
     #[command(about = "Connect to pool and start mining. (Default)")]
-    Mine(MineArgs),
+    Mine(Box<MineArgs>),
     #[command(about = "Connect to pool and start mining using Prototype Software.")]
     Protomine(ProtoMineArgs),
     #[command(about = "Transfer SOL to the pool authority to sign up.")]
@@ -72,6 +296,74 @@ enum Commands {
     Unstake(undelegate_stake::UnstakeArgs),
     #[command(about = "Delegated stake balance.")]
     StakeBalance,
+    #[command(about = "Query and manage the pool account without prompts, for scripts.")]
+    Account(api::AccountArgs),
+    #[command(about = "Move to another pool: claim and unstake at the old one, sign up at the new one.")]
+    Migrate(migrate::MigrateArgs),
+    #[command(about = "Pause, resume, resize or query a miner running with --control-socket.")]
+    Ctl(control::CtlArgs),
+    #[command(about = "Send a ctl command to the miner of every rig in an inventory file at once.")]
+    Fleet(fleet::FleetArgs),
+    #[command(about = "Hold the keypair and sign for rigs that run with --signer agent:<addr> and no key on disk.")]
+    SignerAgent(agent::AgentArgs),
+    #[command(about = "Print shell completions for bash, zsh, fish, elvish or powershell.")]
+    Completions {
+        #[arg(value_enum, value_name = "SHELL")]
+        shell: Shell,
+    },
+    #[command(about = "Solve challenges read from a file or FIFO, for a mine --relay-challenges process.")]
+    SolveFeed(feed::SolveFeedArgs),
+    #[command(about = "Sweep solver memory and interleave settings and save the fastest.")]
+    TuneSolver(tune_solver::TuneSolverArgs),
+    #[command(about = "Measure hashrate at several thread counts, offline and without a keypair.")]
+    Benchmark(benchmark::BenchmarkArgs),
+    #[command(about = "Replay recorded challenges with a fixed time budget and score the solver, to compare client versions.")]
+    BenchReplay(bench_replay::BenchReplayArgs),
+    #[command(about = "Feed a websocket frame capture through the message parser (developer tool).")]
+    ReplayFrames(frames::ReplayFramesArgs),
+    #[command(about = "Show CPU topology and where mining threads would be pinned.")]
+    Threads(threads::ThreadsArgs),
+    #[command(about = "Show mining statistics aggregated across sessions.")]
+    History(history::HistoryArgs),
+    #[command(about = "Summarize ORE earned against SOL and ORE spent per wallet.")]
+    Pnl(history::PnlArgs),
+    #[command(about = "List cached pool capabilities without connecting.")]
+    Pools,
+    #[command(about = "Check pools before mining with them, no keypair needed.")]
+    Pool {
+        #[command(subcommand)]
+        command: ping::PoolCommands,
+    },
+    #[command(about = "Inspect on-chain proof and delegated stake accounts.")]
+    Proof,
+    #[command(about = "Decode the pool's on-chain accounts to check its balances (no keypair needed).")]
+    Info,
+    #[command(about = "Request devnet SOL for the keypair (devnet only).")]
+    Airdrop(cluster::AirdropArgs),
+    #[command(about = "Manage the configuration file.")]
+    Config {
+        #[command(subcommand)]
+        command: config::ConfigCommands,
+    },
+    #[command(about = "Inspect the audit log of signatures made by local keys.")]
+    Audit {
+        #[command(subcommand)]
+        command: audit::AuditCommands,
+    },
+    #[command(about = "Set up a working directory, settings and optionally a service, then check the setup.")]
+    Install(install::InstallArgs),
+    #[command(about = "Export or import settings, history and caches to move them to another rig.")]
+    State {
+        #[command(subcommand)]
+        command: state::StateCommands,
+    },
+}
+
+impl Commands {
+    /// Commands that only look up a wallet's state and never sign anything.
+    fn is_read_only(&self) -> bool {
+        matches!(self, Commands::Balance | Commands::StakeBalance | Commands::Proof | Commands::Pnl(_))
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -84,19 +376,141 @@ enum StakeCommands {
 
 #[tokio::main]
 async fn main() {
-    let mut args = Args::parse();
+    let mut args = parse_args();
+
+    let color = if args.no_color { theme::ColorChoice::Never } else { args.color };
+    theme::init(color, args.theme);
+    redact::init(args.log_redaction);
+    response_cache::init(args.refresh);
+    let _log_guard = logging::init(args.log_file.as_deref(), args.log_format, args.log_rotation, args.log_keep);
+
+    // Pools may be given with a scheme, a base path or a trailing slash, e.g. https://example.com/ore/
+    let mut schemes = Vec::new();
+    for url in args.urls.iter_mut() {
+        let (normalized, unsecure) = endpoint::normalize(url);
+        schemes.extend(unsecure);
+        *url = normalized;
+    }
+    if schemes.contains(&true) && schemes.contains(&false) {
+        println!("  Mixing http and https pool URLs is not supported, give them all the same scheme.");
+        return;
+    }
+    if schemes.contains(&false) && args.use_http {
+        println!("  --use-http contradicts the https pool URL, drop one of them.");
+        return;
+    }
+    if schemes.contains(&true) {
+        args.use_http = true;
+    }
 
     // Ensure the URL is set to the default if not provided
+    args.url = args.urls.first().cloned().unwrap_or_default();
     if args.url.is_empty() {
         args.url = "ec1ipse.me".to_string();
     }
+    if let Some(Commands::Mine(mine_args)) = &mut args.command {
+        mine_args.failover_urls = args.urls.iter().skip(1).cloned().collect();
+        if mine_args.auto_select_pool {
+            mine_args.failover_urls.extend(profiles::pool_urls().iter().map(|url| endpoint::normalize(url).0));
+        }
+    }
 
-    // Does the config file exist? If not, create one
-    let config_path = PathBuf::from(CONFIG_FILE);
-    if !config_path.exists() {
-        fs::File::create(&config_path).expect("Failed to create configuration file.");
+    // Commands that only touch local configuration don't need a keypair
+    if let Some(Commands::Config { command }) = &args.command {
+        run_config_command(command);
+        return;
+    }
+    if let Some(Commands::Audit { command }) = &args.command {
+        run_audit_command(command);
+        return;
+    }
+    if let Some(Commands::State { command }) = &args.command {
+        state::run(command);
+        return;
+    }
+    if let Some(Commands::Install(install_args)) = &args.command {
+        install::install(install_args, &args.url, args.use_http, &args.keypair).await;
+        return;
+    }
+    if let Some(Commands::Pools) = &args.command {
+        pool_cache::print_pools();
+        return;
+    }
+    if let Some(Commands::Pool { command: ping::PoolCommands::Ping(ping_args) }) = &args.command {
+        ping::ping(ping_args, &args.urls, args.use_http).await;
+        return;
+    }
+    if let Some(Commands::Ctl(ctl_args)) = &args.command {
+        control::ctl(ctl_args).await;
+        return;
+    }
+    if let Some(Commands::Fleet(fleet_args)) = &args.command {
+        fleet::fleet(fleet_args).await;
+        return;
+    }
+    if let Some(Commands::SolveFeed(feed_args)) = &args.command {
+        feed::solve_feed(feed_args).await;
+        return;
+    }
+    if let Some(Commands::TuneSolver(tune_args)) = &args.command {
+        tune_solver::tune_solver(tune_args).await;
+        return;
+    }
+    if let Some(Commands::Benchmark(benchmark_args)) = &args.command {
+        benchmark::benchmark(benchmark_args).await;
+        return;
+    }
+    if let Some(Commands::BenchReplay(replay_args)) = &args.command {
+        bench_replay::bench_replay(replay_args).await;
+        return;
+    }
+    if let Some(Commands::ReplayFrames(replay_args)) = &args.command {
+        frames::replay_frames(replay_args);
+        return;
+    }
+    if let Some(Commands::Threads(threads_args)) = &args.command {
+        threads::threads(threads_args);
+        return;
+    }
+    if let Some(Commands::History(history_args)) = &args.command {
+        history::history(history_args);
+        return;
+    }
+    if let Some(Commands::Completions { shell }) = &args.command {
+        print_completions(*shell);
+        return;
+    }
+    if let Some(Commands::Info) = &args.command {
+        proof::pool_info(args.url, args.use_http, cluster::resolve_rpc(args.cluster, args.rpc)).await;
+        return;
     }
 
+    if args.mock_signer {
+        run_mock_signer(args).await;
+        return;
+    }
+    match args.signer.take() {
+        Some(signer::SignerSource::File(path)) => args.keypair = path.to_string_lossy().into_owned(),
+        Some(source) => {
+            run_with_signer(args, source).await;
+            return;
+        },
+        None => {},
+    }
+
+    // Read-only commands only need an address, which lets monitoring hosts run them without a keypair
+    if let Some(command) = args.command.take_if(|command| command.is_read_only()) {
+        let Some(wallet) = args.pubkey.or_else(|| read_keypair_file(expand_tilde(&args.keypair)).ok().map(|key| key.pubkey())) else {
+            println!("  No wallet to look up. Pass --pubkey <ADDRESS> or --keypair <KEYPAIR_PATH>.");
+            return;
+        };
+        run_read_only_command(command, &wallet, args.url, cluster::resolve_rpc(args.cluster, args.rpc), args.use_http).await;
+        return;
+    }
+
+    // Load the config file, creating or migrating it if needed
+    config::load();
+
     // Check if keypair path is provided or fallback to the default
     let keypair_path = expand_tilde(&args.keypair);
     let keypair_exists = PathBuf::from(&keypair_path).exists();
@@ -108,7 +522,7 @@ async fn main() {
 
         if let Some(command) = args.command {
             // A valid command is provided, execute it directly
-            if let Err(_) = run_command(Some(command), key, args.url, args.use_http, None).await {
+            if let Err(_) = run_command(Some(command), key, args.url, cluster::resolve_rpc(args.cluster, args.rpc), args.cluster, args.use_http, None).await {
                 println!("  An error occurred while executing the command.");
             }
         } else {
@@ -126,58 +540,40 @@ async fn main() {
 }
 
 fn get_keypair_path(default_keypair: &str) -> Option<String> {
-    let config_path = PathBuf::from(CONFIG_FILE);
     let mut keypair_paths = Vec::new();
     let mut seen_paths = std::collections::HashSet::new();
+    let mut valid_keypair_paths = Vec::new();
 
-    if config_path.exists() {
-        let file = match fs::File::open(&config_path) {
-            Ok(f) => f,
-            Err(_) => {
-                println!("  Failed to open configuration file.");
-                return ask_for_custom_keypair();
-            }
-        };
-        let reader = io::BufReader::new(file);
-
-        let mut valid_keypair_paths = Vec::new();
-
-        for line in reader.lines() {
-            if let Ok(path) = line {
-                let expanded_path = expand_tilde(&path);
-                let path_buf = PathBuf::from(&expanded_path);
-                
-                if path_buf.exists() && !seen_paths.contains(&expanded_path) {
-                    seen_paths.insert(expanded_path.clone());
-
-                    if path_buf.is_dir() {
-                        // Add all keypair files in the directory
-                        for entry in fs::read_dir(path_buf).expect("Failed to read directory") {
-                            let entry = entry.expect("Failed to get directory entry");
-                            let file_path = entry.path();
-                            if file_path.is_file() {
-                                let file_path_str = file_path.to_string_lossy().to_string();
-                                if !seen_paths.contains(&file_path_str) {
-                                    valid_keypair_paths.push(replace_home_with_tilde(&file_path_str));
-                                    seen_paths.insert(file_path_str);
-                                }
-                            }
+    for path in config::load() {
+        let expanded_path = expand_tilde(&path);
+        let path_buf = PathBuf::from(&expanded_path);
+
+        if path_buf.exists() && !seen_paths.contains(&expanded_path) {
+            seen_paths.insert(expanded_path.clone());
+
+            if path_buf.is_dir() {
+                // Add all keypair files in the directory
+                for entry in fs::read_dir(path_buf).expect("Failed to read directory") {
+                    let entry = entry.expect("Failed to get directory entry");
+                    let file_path = entry.path();
+                    if file_path.is_file() {
+                        let file_path_str = file_path.to_string_lossy().to_string();
+                        if !seen_paths.contains(&file_path_str) {
+                            valid_keypair_paths.push(replace_home_with_tilde(&file_path_str));
+                            seen_paths.insert(file_path_str);
                         }
-                    } else {
-                        valid_keypair_paths.push(replace_home_with_tilde(&expanded_path));
                     }
                 }
+            } else {
+                valid_keypair_paths.push(replace_home_with_tilde(&expanded_path));
             }
         }
+    }
 
-        if !valid_keypair_paths.is_empty() {
-            keypair_paths = valid_keypair_paths.clone();
-            // Update config file with only valid paths
-            let mut file = fs::File::create(&config_path).expect("Failed to open configuration file for writing.");
-            for path in valid_keypair_paths {
-                writeln!(file, "{}", expand_tilde(&path)).expect("Failed to write keypair path to configuration file.");
-            }
-        }
+    if !valid_keypair_paths.is_empty() {
+        keypair_paths = valid_keypair_paths.clone();
+        // Update config file with only valid paths
+        config::save(&valid_keypair_paths);
     }
 
     // Hardcode check for the default Solana keypair
@@ -232,23 +628,12 @@ fn get_keypair_path(default_keypair: &str) -> Option<String> {
 }
 
 fn remove_keypair() {
-    let config_path = PathBuf::from(CONFIG_FILE);
-    let mut keypair_paths = Vec::new();
-
     let solana_default_keypair = expand_tilde("~/.config/solana/id.json");
 
-    if config_path.exists() {
-        let file = fs::File::open(&config_path).expect("  Failed to open configuration file.");
-        let reader = io::BufReader::new(file);
-
-        for line in reader.lines() {
-            if let Ok(path) = line {
-                let expanded_path = expand_tilde(&path);
-                let display_path = replace_home_with_tilde(&expanded_path);
-                keypair_paths.push(display_path);
-            }
-        }
-    }
+    let mut keypair_paths: Vec<String> = config::load()
+        .iter()
+        .map(|path| replace_home_with_tilde(&expand_tilde(path)))
+        .collect();
 
     if keypair_paths.is_empty() {
         println!("  No keypairs available to remove.");
@@ -278,12 +663,7 @@ fn remove_keypair() {
     keypair_paths.remove(remove_index);
 
     // Write the updated list back to the config file
-    let mut file = fs::File::create(&config_path).expect("Failed to open configuration file for writing.");
-
-    for path in keypair_paths {
-        let expanded_path = expand_tilde(&path);
-        writeln!(file, "{}", expanded_path).expect("Failed to write keypair path to configuration file.");
-    }
+    config::save(&keypair_paths);
 
     println!("  Keypair path '{}' has been removed.", selection);
 }
@@ -308,6 +688,16 @@ fn expand_tilde(path: &str) -> String {
     path.to_string()
 }
 
+/// A base58 pubkey, or the path of a file holding one, so rigs can keep a pubkey file
+/// instead of a keypair.
+fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    if let Ok(pubkey) = Pubkey::from_str(s) {
+        return Ok(pubkey);
+    }
+    let contents = fs::read_to_string(expand_tilde(s)).map_err(|_| format!("{} is neither a pubkey nor a readable file", s))?;
+    Pubkey::from_str(contents.trim()).map_err(|_| format!("{} does not hold a base58 pubkey", s))
+}
+
 fn ask_for_custom_keypair() -> Option<String> {
     loop {
         let custom_path = Text::new("  Enter the path to your keypair or keypair directory:")
@@ -342,17 +732,7 @@ fn ask_for_custom_keypair() -> Option<String> {
             }
 
             // Read and normalize existing paths from the configuration file
-            let config_path = PathBuf::from(CONFIG_FILE);
-            let mut existing_paths = Vec::new();
-            if config_path.exists() {
-                let file = fs::File::open(&config_path).expect("Failed to open configuration file.");
-                let reader = io::BufReader::new(file);
-                for line in reader.lines() {
-                    if let Ok(path) = line {
-                        existing_paths.push(expand_tilde(&path));
-                    }
-                }
-            }
+            let existing_paths: Vec<String> = config::load().iter().map(|path| expand_tilde(path)).collect();
 
             // Normalize paths for comparison
             let original_count = keypair_files.len();
@@ -370,14 +750,7 @@ fn ask_for_custom_keypair() -> Option<String> {
             }
 
             // Update the configuration file with unique paths
-            let mut file = fs::OpenOptions::new()
-                .append(true)
-                .open(&config_path)
-                .expect("Failed to open configuration file for appending.");
-            for path in &keypair_files {
-                writeln!(file, "{}", expand_tilde(path))
-                    .expect("Failed to write keypair path to configuration file.");
-            }
+            config::append(&keypair_files);
 
             // Prompt the user to select a keypair from the directory
             let selection = match Select::new("  Select a keypair to use from the directory:", keypair_files.clone())
@@ -417,14 +790,7 @@ fn ask_for_custom_keypair() -> Option<String> {
                 .unwrap_or(true);
 
             if add_to_list {
-                let config_path = PathBuf::from(CONFIG_FILE);
-                let mut file = fs::OpenOptions::new()
-                    .append(true)
-                    .open(&config_path)
-                    .expect("Failed to open configuration file for appending.");
-
-                writeln!(file, "{}", expanded_path)
-                    .expect("Failed to write keypair path to configuration file.");
+                config::append(std::slice::from_ref(&expanded_path));
             }
 
             return Some(expanded_path);
@@ -433,22 +799,7 @@ fn ask_for_custom_keypair() -> Option<String> {
 }
 
 fn check_keypair_exists(path: &str) -> bool {
-    let config_path = PathBuf::from(CONFIG_FILE);
-
-    if config_path.exists() {
-        let file = fs::File::open(&config_path).expect("Failed to open configuration file.");
-        let reader = io::BufReader::new(file);
-
-        for line in reader.lines() {
-            if let Ok(existing_path) = line {
-                if expand_tilde(&existing_path) == path {
-                    return true;
-                }
-            }
-        }
-    }
-
-    false
+    config::load().iter().any(|existing_path| expand_tilde(existing_path) == path)
 }
 
 fn load_keypair(keypair_path: &str) -> Option<solana_sdk::signature::Keypair> {
@@ -468,7 +819,7 @@ fn load_keypair(keypair_path: &str) -> Option<solana_sdk::signature::Keypair> {
 }
 
 async fn run_menu() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+    let args = parse_args();
     let version = env!("CARGO_PKG_VERSION");
 
     let options = vec![
@@ -532,21 +883,157 @@ async fn run_menu() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     });
 
-    run_command(args.command, key, base_url, unsecure_conn, selection.as_deref()).await?;
+    run_command(args.command, key, base_url, cluster::resolve_rpc(args.cluster, args.rpc), args.cluster, unsecure_conn, selection.as_deref()).await?;
     Ok(())
 }
 
+/// Runs a command with a freshly generated keypair that is never written to disk.
+async fn run_mock_signer(args: Args) {
+    let key = solana_sdk::signature::Keypair::new();
+    println!("  MOCK SIGNER: using ephemeral keypair {}. It is not saved and holds no funds.", key.pubkey());
+
+    let command = args.command.unwrap_or_else(|| Commands::Mine(Box::new(MineArgs::parse_from(["mine"]))));
+    if matches!(
+        command,
+        Commands::Signup | Commands::Claim(_) | Commands::Stake(_) | Commands::Unstake(_) | Commands::Migrate(_) | Commands::Airdrop(_)
+    ) || matches!(&command, Commands::Account(account) if account.command.is_on_chain())
+    {
+        println!("  On-chain commands are disabled with --mock-signer.");
+        return;
+    }
+
+    if run_command(Some(command), key, args.url, cluster::resolve_rpc(args.cluster, args.rpc), args.cluster, args.use_http, None).await.is_err() {
+        println!("  An error occurred while executing the command.");
+    }
+}
+
+/// Mines with a signer that holds the key elsewhere. Other commands build transactions or
+/// use the API with a keypair file, so they are refused.
+async fn run_with_signer(args: Args, source: signer::SignerSource) {
+    let command = args.command.unwrap_or_else(|| Commands::Mine(Box::new(MineArgs::parse_from(["mine"]))));
+    let Commands::Mine(mine_args) = command else {
+        println!("  Only mine works with --signer remote or agent, other commands need --keypair.");
+        return;
+    };
+    if matches!(source, signer::SignerSource::Ledger) {
+        println!("  The Ledger only signs transactions and refuses the frames mining signs. Use --signer remote or agent, or --keypair.");
+        return;
+    }
+    if !mine_args.wallets.is_empty() {
+        println!("  --wallets loads its own keypairs and can't be combined with --signer.");
+        return;
+    }
+
+    let key = match signer::connect(&source).await {
+        Ok(key) => key,
+        Err(e) => {
+            println!("  Could not connect to the signer: {}", e);
+            return;
+        },
+    };
+    if let Some(pubkey) = args.pubkey.filter(|pubkey| *pubkey != key.pubkey()) {
+        println!("  The signer signs as {}, not as --pubkey {}. Refusing to mine for the wrong wallet.", key.pubkey(), pubkey);
+        return;
+    }
+    println!("  Signing as {}", key.pubkey());
+
+    let rpc_url = cluster::resolve_rpc(args.cluster, args.rpc);
+    if !std::iter::once(&args.url).chain(&mine_args.failover_urls).all(|url| cluster::check_pool(args.cluster, url, &key.pubkey())) {
+        return;
+    }
+    if !wallet_guard::check_wallet_activity(&mine_args, &key.pubkey(), &rpc_url, false).await {
+        return;
+    }
+    if mine_args.rotate {
+        schedule::rotate(*mine_args, key, args.use_http, rpc_url, args.cluster).await;
+    } else {
+        let servers = mine::serve_endpoints(&mine_args).await;
+        mine(*mine_args, key, args.url, args.use_http, rpc_url).await;
+        for server in servers {
+            server.abort();
+        }
+    }
+}
+
+/// Parses the command line, with ore-cli options translated, merged with the options and
+/// profile from the settings file.
+fn parse_args() -> Args {
+    static ARGV: OnceLock<Vec<OsString>> = OnceLock::new();
+    let argv = ARGV.get_or_init(|| profiles::apply(Args::command(), compat::translate(&Args::command(), std::env::args_os().collect())));
+    Args::parse_from(argv)
+}
+
+fn print_completions(shell: Shell) {
+    let mut cmd = Args::command();
+    let bin_name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, bin_name, &mut std::io::stdout());
+}
+
+async fn run_read_only_command(command: Commands, wallet: &Pubkey, base_url: String, rpc_url: String, unsecure_conn: bool) {
+    match command {
+        Commands::Balance => balance(wallet, base_url, unsecure_conn).await,
+        Commands::StakeBalance => stake_balance::stake_balance(wallet, base_url, unsecure_conn).await,
+        Commands::Proof => proof::proof(wallet, base_url, unsecure_conn, rpc_url).await,
+        Commands::Pnl(args) => history::pnl(args, wallet).await,
+        _ => unreachable!("not a read-only command"),
+    }
+}
+
+fn run_config_command(command: &config::ConfigCommands) {
+    match command {
+        config::ConfigCommands::Validate => config::validate(),
+        config::ConfigCommands::Show { effective: true } => profiles::show_effective(Args::command(), std::env::args_os().collect()),
+        config::ConfigCommands::Show { effective: false } => match fs::read_to_string(settings::path()) {
+            Ok(contents) => {
+                for line in contents.lines() {
+                    println!("{}", line);
+                }
+            },
+            Err(_) => println!("  No settings file at {}.", settings::path().display()),
+        },
+        config::ConfigCommands::Diff => profiles::diff(Args::command(), std::env::args_os().collect()),
+        config::ConfigCommands::Encrypt { fields, keychain } => secrets::encrypt_settings_file(fields, *keychain),
+    }
+}
+
+fn run_audit_command(command: &audit::AuditCommands) {
+    match command {
+        audit::AuditCommands::Verify => audit::verify(),
+    }
+}
+
 async fn run_command(
     command: Option<Commands>,
     key: solana_sdk::signature::Keypair,
     base_url: String,
+    rpc_url: String,
+    cluster: cluster::Cluster,
     unsecure_conn: bool,
     selection: Option<&str>,
     
 ) -> Result<(), Box<dyn std::error::Error>> {
     match command {
+        Some(Commands::Mine(args)) if !args.wallets.is_empty() => {
+            wallets::mine_wallets(*args, base_url, unsecure_conn, rpc_url, cluster).await;
+        },
+        Some(Commands::Mine(args)) if args.rotate => {
+            if !wallet_guard::check_wallet_activity(&args, &key.pubkey(), &rpc_url, false).await {
+                return Ok(());
+            }
+            schedule::rotate(*args, Arc::new(key), unsecure_conn, rpc_url, cluster).await;
+        },
         Some(Commands::Mine(args)) => {
-            mine(args, key, base_url, unsecure_conn).await;
+            if !std::iter::once(&base_url).chain(&args.failover_urls).all(|url| cluster::check_pool(cluster, url, &key.pubkey())) {
+                return Ok(());
+            }
+            if !wallet_guard::check_wallet_activity(&args, &key.pubkey(), &rpc_url, false).await {
+                return Ok(());
+            }
+            let servers = mine::serve_endpoints(&args).await;
+            mine(*args, Arc::new(key), base_url, unsecure_conn, rpc_url).await;
+            for server in servers {
+                server.abort();
+            }
         },
         Some(Commands::Protomine(args)) => {
             protomine(args, key, base_url, unsecure_conn).await;
@@ -555,10 +1042,10 @@ async fn run_command(
             signup(base_url, key, unsecure_conn).await;
         },
         Some(Commands::Claim(args)) => {
-            claim::claim(args, key, base_url, unsecure_conn).await;
+            claim::claim(args, key, base_url, unsecure_conn, rpc_url).await;
         },
-        Some(Commands::Balance) => {
-            balance(&key, base_url, unsecure_conn).await;
+        Some(command @ (Commands::Balance | Commands::StakeBalance | Commands::Proof | Commands::Pnl(_))) => {
+            run_read_only_command(command, &key.pubkey(), base_url, rpc_url, unsecure_conn).await;
         },
         Some(Commands::Stake(args)) => {
             delegate_stake::delegate_stake(args, key, base_url, unsecure_conn).await;
@@ -566,8 +1053,64 @@ async fn run_command(
         Some(Commands::Unstake(args)) => {
             undelegate_stake::undelegate_stake(args, &key, base_url, unsecure_conn).await;
         },
-        Some(Commands::StakeBalance) => {
-            stake_balance::stake_balance(&key, base_url, unsecure_conn).await;
+        Some(Commands::Account(args)) => {
+            api::account(args, key, base_url, unsecure_conn).await;
+        },
+        Some(Commands::Migrate(args)) => {
+            migrate::migrate(args, key, unsecure_conn, rpc_url).await;
+        },
+        Some(Commands::Config { command }) => {
+            run_config_command(&command);
+        },
+        Some(Commands::Audit { command }) => {
+            run_audit_command(&command);
+        },
+        Some(Commands::State { command }) => {
+            state::run(&command);
+        },
+        Some(Commands::Install(_)) => unreachable!("install runs before a keypair is loaded"),
+        Some(Commands::Pools) => {
+            pool_cache::print_pools();
+        },
+        Some(Commands::Pool { .. }) => unreachable!("pool commands run before a keypair is loaded"),
+        Some(Commands::Ctl(args)) => {
+            control::ctl(&args).await;
+        },
+        Some(Commands::Fleet(args)) => {
+            fleet::fleet(&args).await;
+        },
+        Some(Commands::SignerAgent(args)) => {
+            agent::serve(&args, key).await;
+        },
+        Some(Commands::Completions { shell }) => {
+            print_completions(shell);
+        },
+        Some(Commands::SolveFeed(args)) => {
+            feed::solve_feed(&args).await;
+        },
+        Some(Commands::TuneSolver(args)) => {
+            tune_solver::tune_solver(&args).await;
+        },
+        Some(Commands::Benchmark(args)) => {
+            benchmark::benchmark(&args).await;
+        },
+        Some(Commands::BenchReplay(args)) => {
+            bench_replay::bench_replay(&args).await;
+        },
+        Some(Commands::ReplayFrames(args)) => {
+            frames::replay_frames(&args);
+        },
+        Some(Commands::Threads(args)) => {
+            threads::threads(&args);
+        },
+        Some(Commands::History(args)) => {
+            history::history(&args);
+        },
+        Some(Commands::Info) => {
+            proof::pool_info(base_url, unsecure_conn, rpc_url).await;
+        },
+        Some(Commands::Airdrop(args)) => {
+            cluster::airdrop(args, &key, rpc_url, cluster).await;
         },
         None => {
             if let Some(choice) = selection {
@@ -590,8 +1133,16 @@ async fn run_command(
                             }
                         };
             
-                        let args = MineArgs { threads, buffer };
-                        mine(args, key, base_url, unsecure_conn).await;
+                        let mut args = MineArgs::parse_from(["mine"]);
+                        args.threads = threads;
+                        args.buffer = buffer;
+                        if !cluster::check_pool(cluster, &base_url, &key.pubkey()) {
+                            return Ok(());
+                        }
+                        if !wallet_guard::check_wallet_activity(&args, &key.pubkey(), &rpc_url, true).await {
+                            return Ok(());
+                        }
+                        mine(args, Arc::new(key), base_url, unsecure_conn, rpc_url).await;
                     },
                     "  ProtoMine" => {
                         let threads: u32 = loop {
@@ -614,14 +1165,14 @@ async fn run_command(
                         signup(base_url, key, unsecure_conn).await;
                     },
                     "  Claim Rewards" => {
-                        let args = ClaimArgs { amount: None };
-                        claim::claim(args, key, base_url, unsecure_conn).await;
+                        let args = ClaimArgs { amount: None, cosigner: None };
+                        claim::claim(args, key, base_url, unsecure_conn, rpc_url).await;
                     },
                     "  View Balances" => {
-                        balance(&key, base_url, unsecure_conn).await;
+                        balance(&key.pubkey(), base_url, unsecure_conn).await;
                     },
                     "  Stake" => {
-    balance(&key, base_url.clone(), unsecure_conn).await;
+    balance(&key.pubkey(), base_url.clone(), unsecure_conn).await;
 
     loop {
         let stake_input = Text::new("  Enter the amount of ore to stake (or 'esc' to cancel):")
@@ -664,7 +1215,7 @@ async fn run_command(
 },
 
                     "  Unstake" => {
-                        stake_balance::stake_balance(&key, base_url.clone(), unsecure_conn).await;
+                        stake_balance::stake_balance(&key.pubkey(), base_url.clone(), unsecure_conn).await;
 
                         loop {
                             let unstake_input = Text::new("  Enter the amount of ore to unstake (or 'esc' to cancel):")
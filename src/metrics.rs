@@ -0,0 +1,358 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+use crate::broadcasts::RecentBroadcasts;
+use crate::resource_monitor;
+use crate::sla::ConnectionSla;
+
+/// Number of per-round best difficulties kept for /stats.json.
+const RECENT_ROUNDS: usize = 20;
+
+/// Where the metrics server listens: a TCP address or `unix:/path.sock`.
+#[derive(Debug, Clone)]
+pub enum MetricsAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for MetricsAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return Ok(MetricsAddr::Unix(PathBuf::from(path)));
+            #[cfg(not(unix))]
+            return Err(format!("unix sockets are not supported on this platform: {}", path));
+        }
+        s.parse()
+            .map(MetricsAddr::Tcp)
+            .map_err(|_| format!("expected host:port or unix:/path.sock, got {}", s))
+    }
+}
+
+/// Unix socket permissions, given in octal like chmod.
+#[derive(Debug, Clone, Copy)]
+pub struct SocketMode(pub u32);
+
+impl FromStr for SocketMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u32::from_str_radix(s, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o777)
+            .map(SocketMode)
+            .ok_or_else(|| format!("expected octal permissions such as 600, got {}", s))
+    }
+}
+
+/// Binds a unix socket owner-only, so no other user can connect before the caller applies its
+/// --socket-mode. A socket file left behind by an earlier run is replaced.
+#[cfg(unix)]
+pub fn bind_unix(path: &Path) -> std::io::Result<tokio::net::UnixListener> {
+    let _ = std::fs::remove_file(path);
+    // SAFETY: umask only swaps the process file mode mask
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = tokio::net::UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    listener
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    rounds: u64,
+    total_nonces_checked: u64,
+    total_hashes: u64,
+    hashrate: f64,
+    best_difficulty: u32,
+    recent_difficulties: VecDeque<u32>,
+    submissions: u64,
+    dropped_submissions: u64,
+    last_challenge_at: Option<u64>,
+    connection: Option<ConnectionSla>,
+}
+
+/// Session stats the mining loop pushes after every round, read by the metrics server.
+#[derive(Debug)]
+pub struct MinerStats {
+    wallet: String,
+    pool: String,
+    counters: Mutex<Counters>,
+    broadcasts: Arc<RecentBroadcasts>,
+}
+
+impl MinerStats {
+    pub fn new(wallet: String, pool: String, broadcasts: Arc<RecentBroadcasts>) -> Self {
+        MinerStats { wallet, pool, counters: Mutex::new(Counters::default()), broadcasts }
+    }
+
+    pub fn record_challenge(&self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        self.counters.lock().unwrap().last_challenge_at = Some(now);
+    }
+
+    pub fn record_round(&self, nonces_checked: u64, hashes: u64, hash_time: Duration, best_difficulty: u32) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.rounds += 1;
+        counters.total_nonces_checked += nonces_checked;
+        counters.total_hashes += hashes;
+        if hash_time.as_secs_f64() > 0.0 {
+            counters.hashrate = hashes as f64 / hash_time.as_secs_f64();
+        }
+        counters.best_difficulty = counters.best_difficulty.max(best_difficulty);
+        if counters.recent_difficulties.len() == RECENT_ROUNDS {
+            counters.recent_difficulties.pop_front();
+        }
+        counters.recent_difficulties.push_back(best_difficulty);
+    }
+
+    pub fn record_submission(&self) {
+        self.counters.lock().unwrap().submissions += 1;
+    }
+
+    pub fn record_dropped_submissions(&self, count: u64) {
+        self.counters.lock().unwrap().dropped_submissions += count;
+    }
+
+    /// Keeps a copy of the connection tracker so scrapes see time spent in the current state.
+    pub fn connection(&self, sla: &ConnectionSla) {
+        self.counters.lock().unwrap().connection = Some(sla.clone());
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        let counters = self.counters.lock().unwrap();
+        let (mining, idle, reconnecting) = counters.connection.as_ref().map_or((Duration::ZERO, Duration::ZERO, Duration::ZERO), ConnectionSla::totals);
+        let resources = resource_monitor::sample();
+        json!({
+            "wallet": self.wallet,
+            "pool": self.pool,
+            "rounds": counters.rounds,
+            "hashrate": counters.hashrate,
+            "total_nonces_checked": counters.total_nonces_checked,
+            "total_hashes": counters.total_hashes,
+            "best_difficulty": counters.best_difficulty,
+            "last_round_difficulty": counters.recent_difficulties.back(),
+            "recent_round_difficulties": counters.recent_difficulties,
+            "submissions": counters.submissions,
+            "dropped_submissions": counters.dropped_submissions,
+            "reconnects": counters.connection.as_ref().map_or(0, ConnectionSla::reconnects),
+            "last_challenge_at": counters.last_challenge_at,
+            "connection_secs": {
+                "mining": mining.as_secs_f64(),
+                "idle": idle.as_secs_f64(),
+                "reconnecting": reconnecting.as_secs_f64(),
+            },
+            "rss_kb": resources.map(|sample| sample.rss_kb),
+            "open_fds": resources.map(|sample| sample.open_fds),
+            "recent_broadcasts": self.broadcasts.lines(),
+        })
+    }
+
+    /// The labelled samples of this miner, in exposition order.
+    fn samples(&self) -> Vec<Sample> {
+        let counters = self.counters.lock().unwrap();
+        let mut samples = vec![
+            Sample::new("hashrate", "gauge", "Hashes per second in the last round.", counters.hashrate),
+            Sample::new("nonces_checked_total", "counter", "Nonces scanned this session.", counters.total_nonces_checked as f64),
+            Sample::new("hashes_total", "counter", "Hashes computed this session, a few per nonce.", counters.total_hashes as f64),
+            Sample::new("rounds_total", "counter", "Rounds mined this session.", counters.rounds as f64),
+            Sample::new(
+                "round_best_difficulty",
+                "gauge",
+                "Best difficulty found in the last round.",
+                counters.recent_difficulties.back().copied().unwrap_or(0) as f64,
+            ),
+            Sample::new("best_difficulty", "gauge", "Best difficulty found this session.", counters.best_difficulty as f64),
+            Sample::new("submissions_total", "counter", "Solutions submitted to the pool.", counters.submissions as f64),
+            Sample::new(
+                "dropped_submissions_total",
+                "counter",
+                "Submissions the pool never reported a result for.",
+                counters.dropped_submissions as f64,
+            ),
+        ];
+        if let Some(at) = counters.last_challenge_at {
+            samples.push(Sample::new("last_challenge_timestamp_seconds", "gauge", "Unix time the last challenge was received.", at as f64));
+        }
+        if let Some(sla) = counters.connection.as_ref() {
+            let (mining, idle, reconnecting) = sla.totals();
+            samples.push(Sample::new("reconnects_total", "counter", "Reconnects to the pool this session.", sla.reconnects() as f64));
+            samples.push(Sample::new("mining_seconds_total", "counter", "Time spent hashing and submitting.", mining.as_secs_f64()));
+            samples.push(Sample::new("idle_seconds_total", "counter", "Time spent connected and waiting for work.", idle.as_secs_f64()));
+            samples.push(Sample::new("reconnecting_seconds_total", "counter", "Time spent connecting or backing off.", reconnecting.as_secs_f64()));
+        }
+        samples
+    }
+}
+
+struct Sample {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: f64,
+}
+
+impl Sample {
+    fn new(name: &'static str, kind: &'static str, help: &'static str, value: f64) -> Self {
+        Sample { name, kind, help, value }
+    }
+}
+
+/// The stats of every miner in this process, one per wallet with --wallets. Sessions attach
+/// their stats while they run, the metrics server is started once and serves them all.
+#[derive(Debug, Default)]
+pub struct StatsBoard {
+    miners: Mutex<Vec<Arc<MinerStats>>>,
+}
+
+impl StatsBoard {
+    pub fn attach(&self, stats: Arc<MinerStats>) {
+        self.miners.lock().unwrap().push(stats);
+    }
+
+    /// Removes a finished session's stats again.
+    pub fn detach(&self, stats: &Arc<MinerStats>) {
+        self.miners.lock().unwrap().retain(|attached| !Arc::ptr_eq(attached, stats));
+    }
+
+    /// Each session's stats, in the order they started.
+    pub fn miners(&self) -> Vec<serde_json::Value> {
+        self.miners.lock().unwrap().iter().map(|stats| stats.json()).collect()
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        json!({ "miners": self.miners() })
+    }
+
+    /// Renders the stats in the Prometheus text exposition format, one series per wallet.
+    fn prometheus(&self) -> String {
+        let series: Vec<(String, Vec<Sample>)> = self
+            .miners
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|stats| (format!("wallet=\"{}\",pool=\"{}\"", stats.wallet, stats.pool), stats.samples()))
+            .collect();
+        let mut names: Vec<&Sample> = Vec::new();
+        for sample in series.iter().flat_map(|(_, samples)| samples) {
+            if !names.iter().any(|named| named.name == sample.name) {
+                names.push(sample);
+            }
+        }
+        let mut out = String::new();
+        for metric in names {
+            let _ = writeln!(out, "# HELP ore_miner_{} {}", metric.name, metric.help);
+            let _ = writeln!(out, "# TYPE ore_miner_{} {}", metric.name, metric.kind);
+            for (labels, samples) in &series {
+                for sample in samples.iter().filter(|sample| sample.name == metric.name) {
+                    let _ = writeln!(out, "ore_miner_{}{{{}}} {}", sample.name, labels, sample.value);
+                }
+            }
+        }
+        // Process wide, so not labelled with a wallet
+        if let Some(sample) = resource_monitor::sample() {
+            let _ = writeln!(out, "# HELP ore_miner_resident_memory_bytes Resident set size of the client.");
+            let _ = writeln!(out, "# TYPE ore_miner_resident_memory_bytes gauge");
+            let _ = writeln!(out, "ore_miner_resident_memory_bytes {}", sample.rss_kb * 1024);
+            let _ = writeln!(out, "# HELP ore_miner_open_fds Open file descriptors of the client.");
+            let _ = writeln!(out, "# TYPE ore_miner_open_fds gauge");
+            let _ = writeln!(out, "ore_miner_open_fds {}", sample.open_fds);
+        }
+        out
+    }
+}
+
+/// Answers a single request for /metrics or /stats.json and closes the connection.
+async fn handle<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, stats: &StatsBoard) {
+    let mut buf = [0u8; 1024];
+    let Ok(len) = stream.read(&mut buf).await else {
+        return;
+    };
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = match path.split('?').next() {
+        Some("/metrics") => ("200 OK", "text/plain; version=0.0.4", stats.prometheus()),
+        Some("/stats.json") => ("200 OK", "application/json", stats.json().to_string()),
+        _ => ("404 Not Found", "text/plain", "Not found, try /metrics or /stats.json\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Starts the metrics server, returns None if the address can't be bound.
+/// Unix sockets get `socket_mode` permissions so only the intended users can scrape them.
+pub async fn serve(addr: MetricsAddr, socket_mode: SocketMode, stats: Arc<StatsBoard>) -> Option<JoinHandle<()>> {
+    match addr {
+        MetricsAddr::Tcp(addr) => {
+            let listener = match TcpListener::bind(addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    println!("Failed to start the metrics server on {}: {}", addr, e);
+                    return None;
+                },
+            };
+            println!("Serving metrics on http://{}/metrics and /stats.json", addr);
+            Some(tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let stats = stats.clone();
+                            tokio::spawn(async move { handle(stream, &stats).await });
+                        },
+                        Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                    }
+                }
+            }))
+        },
+        #[cfg(unix)]
+        MetricsAddr::Unix(path) => {
+            use std::os::unix::fs::PermissionsExt;
+
+            let listener = match bind_unix(&path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    println!("Failed to start the metrics server on {}: {}", path.display(), e);
+                    return None;
+                },
+            };
+            if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(socket_mode.0)) {
+                println!("Failed to set permissions {:o} on {}: {}", socket_mode.0, path.display(), e);
+            }
+            println!("Serving metrics on unix:{} at /metrics and /stats.json", path.display());
+            Some(tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let stats = stats.clone();
+                            tokio::spawn(async move { handle(stream, &stats).await });
+                        },
+                        Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                    }
+                }
+            }))
+        },
+    }
+}
@@ -0,0 +1,142 @@
+use clap::Parser;
+use colored::*;
+use inquire::{InquireError, Text};
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+use crate::claim::{self, ClaimArgs};
+use crate::delegate_stake::{self, StakeArgs};
+use crate::network;
+use crate::schema::{self, OreAmount};
+use crate::settings;
+use crate::signup::signup;
+use crate::stake_balance;
+use crate::undelegate_stake::{self, UnstakeArgs};
+
+#[derive(Debug, Parser)]
+pub struct MigrateArgs {
+    #[arg(
+        long,
+        value_name = "POOL_URL",
+        help = "Pool you are leaving"
+    )]
+    pub from: String,
+    #[arg(
+        long,
+        value_name = "POOL_URL",
+        help = "Pool you are moving to"
+    )]
+    pub to: String,
+}
+
+/// Asks whether to run a step. Returns None if the user cancels the whole migration.
+fn confirm_step(prompt: &str) -> Option<bool> {
+    match Text::new(&format!("  {} (Y/n or 'esc' to cancel)", prompt)).prompt() {
+        Ok(answer) if answer.trim().eq_ignore_ascii_case("esc") => None,
+        Ok(answer) => Some(answer.trim().is_empty() || answer.trim().eq_ignore_ascii_case("y")),
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => None,
+        Err(e) => {
+            println!("  Error reading input: {}", e);
+            None
+        },
+    }
+}
+
+/// Walks through leaving one pool for another: claim rewards and unstake at the old pool,
+/// sign up and restake at the new one, then point the settings file at it.
+/// Every step asks first and can be skipped.
+pub async fn migrate(args: MigrateArgs, key: Keypair, unsecure: bool, rpc_url: String) {
+    if args.from == args.to {
+        println!("  --from and --to are the same pool, nothing to migrate.");
+        return;
+    }
+    let client = network::client();
+    let url_prefix = if unsecure { "http" } else { "https" };
+
+    println!("  Migrating {} from {} to {}.", key.pubkey(), args.from, args.to);
+    let query = format!("?pubkey={}", key.pubkey());
+    let rewards = match schema::get::<OreAmount>(&client, url_prefix, &args.from, "miner/rewards", &query).await {
+        Ok(OreAmount(rewards)) => rewards,
+        Err(e) => {
+            println!("  Could not fetch unclaimed rewards from {}: {}", args.from, e);
+            0.0
+        },
+    };
+    let staked = stake_balance::get_staked_balance(&key, args.from.clone(), unsecure).await;
+    println!("  Unclaimed rewards at {}: {:.11} ORE", args.from, rewards);
+    println!("  Staked at {}:           {:.11} ORE", args.from, staked);
+
+    println!("\n  Step 1/4: claim rewards from {}", args.from);
+    if rewards > 0.0 {
+        match confirm_step(&format!("Claim {:.11} ORE?", rewards)) {
+            Some(true) => claim::claim(ClaimArgs { amount: Some(rewards), cosigner: None }, key.insecure_clone(), args.from.clone(), unsecure, rpc_url).await,
+            Some(false) => println!("  Skipped, the rewards stay claimable at {}.", args.from),
+            None => return cancelled(),
+        }
+    } else {
+        println!("  Nothing to claim.");
+    }
+
+    println!("\n  Step 2/4: unstake from {}", args.from);
+    let mut unstaked = 0.0;
+    if staked > 0.0 {
+        match confirm_step(&format!("Unstake {:.11} ORE?", staked)) {
+            Some(true) => {
+                undelegate_stake::undelegate_stake(UnstakeArgs { amount: staked }, &key, args.from.clone(), unsecure).await;
+                unstaked = staked - stake_balance::get_staked_balance(&key, args.from.clone(), unsecure).await;
+            },
+            Some(false) => println!("  Skipped, the stake keeps earning at {}.", args.from),
+            None => return cancelled(),
+        }
+    } else {
+        println!("  Nothing staked.");
+    }
+
+    println!("\n  Step 3/4: sign up at {}", args.to);
+    match confirm_step("Sign up? Pools that already know this wallet don't charge again.") {
+        Some(true) => signup(args.to.clone(), key.insecure_clone(), unsecure).await,
+        Some(false) => println!("  Skipped."),
+        None => return cancelled(),
+    }
+    if unstaked > 0.0 {
+        match confirm_step(&format!("Stake the {:.11} ORE you unstaked at {}?", unstaked, args.to)) {
+            Some(true) => {
+                let stake = StakeArgs { amount: unstaked, auto: false };
+                delegate_stake::delegate_stake(stake, key.insecure_clone(), args.to.clone(), unsecure).await;
+            },
+            Some(false) => println!("  Skipped, stake later with `stake --amount {:.11}`.", unstaked),
+            None => return cancelled(),
+        }
+    }
+
+    println!("\n  Step 4/4: update configuration");
+    let mut settings = settings::load();
+    let from = toml::Value::String(args.from.clone());
+    let scheduled = settings.schedule.iter().filter(|slice| slice.url == args.from).count();
+    let option_sets = std::iter::once(&settings.options).chain(settings.profile.values()).filter(|options| options.get("url") == Some(&from)).count();
+    if scheduled + option_sets > 0 {
+        let prompt = format!("Point {} [[schedule]] entries and {} option sets in {} at {}?", scheduled, option_sets, settings::path().display(), args.to);
+        match confirm_step(&prompt) {
+            Some(true) => {
+                for slice in settings.schedule.iter_mut().filter(|slice| slice.url == args.from) {
+                    slice.url = args.to.clone();
+                }
+                for options in std::iter::once(&mut settings.options).chain(settings.profile.values_mut()) {
+                    if options.get("url") == Some(&from) {
+                        options.insert("url".to_string(), toml::Value::String(args.to.clone()));
+                    }
+                }
+                match settings::save(&settings) {
+                    Ok(()) => println!("  Updated {}.", settings::path().display()),
+                    Err(e) => println!("  Failed to save {}: {}", settings::path().display(), e),
+                }
+            },
+            Some(false) => println!("  Skipped."),
+            None => return cancelled(),
+        }
+    }
+    println!("  Mine at the new pool with: {}", format!("ore-hq-client --url {} mine", args.to).green());
+}
+
+fn cancelled() {
+    println!("  Migration canceled, steps already done are kept.");
+}
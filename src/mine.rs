@@ -1,21 +1,121 @@
-use std::{ops::{ControlFlow, Range}, sync::Arc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use std::{collections::{BTreeMap, VecDeque}, future::Future, ops::{ControlFlow, Range}, path::PathBuf, sync::Arc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 use clap::{arg, Parser};
 use drillx_2::equix;
 use futures_util::{SinkExt, StreamExt};
-use solana_sdk::{signature::Keypair, signer::Signer};
+use solana_sdk::signer::Signer;
 use tokio::sync::{mpsc::UnboundedSender, Mutex};
-use tokio_tungstenite::{connect_async, tungstenite::{handshake::client::{generate_key, Request}, Message}};
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{client_async_tls, tungstenite::{handshake::client::{generate_key, Request}, Message}};
 use base64::prelude::*;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::env;
+use colored::*;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use crate::failover::{self, PoolFailover};
+use crate::clock;
+use crate::endpoint;
+use crate::network;
+use crate::thermal;
+use crate::control::{self, MinerControl};
+use crate::daily;
+use crate::feed;
+use crate::frames::{Direction, FrameDump};
+use crate::baseline::BaselineComparison;
+use crate::bench_replay;
+use crate::broadcasts::RecentBroadcasts;
+use crate::ghosts::{self, GhostRounds};
+use crate::power::{self, PowerEvent, PowerHooks};
+use crate::profiler::{self, Phase};
+use crate::audit;
+use crate::format;
+use crate::estimate::{PayoutCurve, RewardEstimator};
+use crate::goal::{EarningsTally, GoalBasis};
+use crate::graph::RoundGraph;
+use crate::ranges::{self, CapacityMeter, RangeCollision, RangeTracker};
+use crate::jitter::ChallengeJitter;
+use crate::metrics::{self, MetricsAddr, MinerStats, SocketMode, StatsBoard};
+use crate::history::{self, HistoryEntry};
+use crate::pool_cache;
+use crate::notify;
+use crate::quota;
+use crate::redact;
+use crate::report;
+use crate::resource_monitor;
+use crate::tasks::{self, MinerEvent, TaskContext};
+use crate::session::{BestSolution, Session, SessionKey, Submission};
+use crate::sound;
+use crate::signer::MinerSigner;
+use crate::sla::{ConnectionSla, ConnectionState};
+use crate::schema::{self, MaintenanceWindow, PolicyAction, PolicyNotice, PoolResponse, SchemaError, ServerTime};
+use crate::settings::{self, SolverSettings};
+use crate::sleep_detect;
+use crate::solo;
+use crate::wallets::SharedThreads;
+use crate::sprint::SprintPool;
+use crate::steal;
+use crate::theme;
+use crate::protocol::{self, Quirk, Quirks, ServerFlavor, ThreadHintPolicy};
+
+// Adaptive chunk sizing for the nonce cursor
+const INITIAL_CHUNK_SIZE: u64 = 100;
+const MIN_CHUNK_SIZE: u64 = 10;
+const MAX_CHUNK_SIZE: u64 = 10_000;
+const CHUNK_TARGET_SECS: f64 = 0.5;
+/// Seconds before the cutoff when --boost-finish decides whether to enlist reserved cores.
+const BOOST_SECS: u64 = 10;
+/// Rounds the --boost-finish average covers, and how many it needs before boosting.
+const BOOST_WINDOW: usize = 20;
+const BOOST_MIN_ROUNDS: usize = 5;
+/// Least difficulty a worker needs before it stops at the cutoff.
+const MIN_EXIT_DIFFICULTY: u32 = 8;
+/// Rounds the --exit-percentile threshold is taken from, and how many it needs before applying.
+const EXIT_WINDOW: usize = 100;
+const EXIT_MIN_ROUNDS: usize = 10;
+
+/// How long a submitted (challenge, nonce) is remembered for duplicate suppression.
+const SUBMISSION_CACHE_TTL_SECS: u64 = 600;
+
+/// Extra wait after an announced maintenance window before reconnecting.
+const MAINTENANCE_GRACE_SECS: u64 = 15;
+
+/// Consecutive failed submissions before an error report is raised.
+const SUBMISSION_FAILURE_REPORT_THRESHOLD: u32 = 3;
+
+/// StartMining frames repeating the last challenge and nonce range within this long of it
+/// are duplicates.
+const DUPLICATE_START_WINDOW: Duration = Duration::from_secs(10);
+
+tokio::task_local! {
+    /// Short wallet name prefixed to output when mining with several wallets at once.
+    pub static WALLET_TAG: String;
+}
+
+/// println! that prefixes the line with the current WALLET_TAG, if any.
+macro_rules! say {
+    () => {
+        say!("")
+    };
+    ($($arg:tt)*) => {
+        match $crate::mine::WALLET_TAG.try_with(Clone::clone) {
+            Ok(tag) => println!("[{}] {}", tag, format!($($arg)*)),
+            Err(_) => println!($($arg)*),
+        }
+    };
+}
+pub(crate) use say;
 
 #[derive(Debug)]
 pub enum ServerMessage {
-    StartMining([u8; 32], Range<u64>, u64)
+    /// Challenge, nonce range, cutoff, thread hint and when the frame arrived.
+    StartMining([u8; 32], Range<u64>, u64, Option<u32>, Instant),
+    /// The pool aborted a round, e.g. after a fork or an RPC failure. None means the current one.
+    RoundCancelled(Option<[u8; 32]>),
+    MinerEarned(f64),
+    Maintenance(Range<u64>),
+    Policy(PolicyNotice),
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Parser)]
 pub struct MineArgs {
     #[arg(
         long,
@@ -28,31 +128,612 @@ pub struct MineArgs {
         long,
         value_name = "BUFFER",
         default_value = "0",
-        help = "Buffer time in seconds, to send the submission to the server earlier"
+        help = "Buffer time in seconds, to send the submission to the server earlier. [cutoff] margin in the settings file overrides it per pool"
     )]
     pub buffer: u32,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "5",
+        help = "How long to wait for the pool's result after submitting before asking for work anyway, on top of --buffer"
+    )]
+    pub result_timeout: u64,
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value = "0",
+        help = "Wait a random 0 to MS milliseconds before submitting, to spread submissions at the cutoff. Never waits more than half the time left before the pool's cutoff"
+    )]
+    pub submit_jitter: u64,
+    #[arg(
+        long,
+        value_name = "SOL",
+        default_value = "1.0",
+        help = "Refuse to mine if the keypair holds more than this amount of SOL"
+    )]
+    pub max_wallet_sol: f64,
+    #[arg(
+        long,
+        value_name = "ORE",
+        default_value = "1.0",
+        help = "Refuse to mine if the keypair holds more than this amount of ORE"
+    )]
+    pub max_wallet_ore: f64,
+    #[arg(
+        long = "i-know-what-im-doing",
+        action,
+        help = "Mine even if the keypair holds balances above the wallet guard thresholds"
+    )]
+    pub i_know_what_im_doing: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FLAVOR",
+        default_value = "official",
+        help = "Server fork whose protocol quirks should be used"
+    )]
+    pub server_flavor: ServerFlavor,
+    #[arg(
+        long = "quirk",
+        value_enum,
+        value_name = "QUIRK",
+        help = "Protocol quirk to enable, may be repeated (only with --server-flavor custom)"
+    )]
+    pub quirks: Vec<Quirk>,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CURVE",
+        default_value = "exponential",
+        help = "Payout curve used by the pool, for estimating round earnings"
+    )]
+    pub payout_curve: PayoutCurve,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "600",
+        help = "Interval for sampling memory and open file descriptors, 0 to disable"
+    )]
+    pub resource_monitor_interval: u64,
+    #[arg(
+        long,
+        action,
+        help = "Poll unclaimed rewards in the background after each round"
+    )]
+    pub poll_rewards: bool,
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value = "10",
+        help = "Warn when CPU steal time during a round exceeds this percentage"
+    )]
+    pub steal_warn_percent: f64,
+    #[arg(
+        long,
+        action,
+        help = "Idle workers slightly when cores throttle, to hold a steady clock instead of boost and throttle cycles (Linux)"
+    )]
+    pub thermal_feedback: bool,
+    #[arg(
+        long,
+        action,
+        help = "Sign Ready and submission frames with an ephemeral session key, if the server supports it"
+    )]
+    pub session_key: bool,
+    #[arg(
+        long,
+        value_name = "ROUNDS",
+        default_value = "20",
+        help = "Number of recent rounds shown in the hashrate chart, 0 to disable"
+    )]
+    pub graph_rounds: usize,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "30",
+        help = "Reconnect when the system resumes after sleeping at least this long, 0 to disable"
+    )]
+    pub sleep_threshold: u64,
+    #[arg(
+        long,
+        action,
+        help = "Re-verify candidate best hashes with an independent recomputation to catch hardware faults"
+    )]
+    pub paranoid: bool,
+    #[arg(
+        long,
+        value_name = "DIFFICULTY",
+        default_value = "8",
+        help = "Only re-verify hashes at or above this difficulty in --paranoid mode"
+    )]
+    pub paranoid_min_difficulty: u32,
+    #[arg(
+        long,
+        action,
+        help = "Send compact reports of protocol errors and repeated submission failures to the pool"
+    )]
+    pub report_errors: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "ignore",
+        help = "Whether to follow per-round thread count hints sent by the server"
+    )]
+    pub thread_hints: ThreadHintPolicy,
+    #[arg(
+        long,
+        action,
+        help = "Keep worker threads and solver memory warm between rounds so hashing starts immediately"
+    )]
+    pub sprint: bool,
+    #[arg(
+        long,
+        value_name = "CORES",
+        default_value = "0",
+        help = "Keep workers off the last CORES cores, leaving them to the rest of the system"
+    )]
+    pub reserve_cores: u32,
+    #[arg(
+        long,
+        action,
+        help = "In the last seconds of a round whose best difficulty trails the recent average, also hash on the --reserve-cores cores"
+    )]
+    pub boost_finish: bool,
+    #[arg(
+        long,
+        value_name = "PERCENTILE",
+        value_parser = clap::value_parser!(u8).range(1..=99),
+        help = "Stop at the cutoff only once the round's best difficulty reaches this percentile of the last 100 rounds in the history, instead of difficulty 8"
+    )]
+    pub exit_percentile: Option<u8>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "5",
+        help = "How long past the cutoff --exit-percentile keeps hashing for its target before settling for difficulty 8"
+    )]
+    pub exit_grace: u64,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "20",
+        help = "Interval between websocket pings to keep NAT mappings alive, 0 to disable"
+    )]
+    pub ping_interval: u64,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "15",
+        help = "Idle time before TCP keepalive probes are sent on the websocket connection, 0 to disable"
+    )]
+    pub tcp_keepalive: u64,
+    #[arg(
+        long,
+        action,
+        help = "Offer signed hashrate attestations to pools that run loyalty or uptime programs"
+    )]
+    pub attestations: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "relay_solutions",
+        help = "Hand rounds to a separate solve-feed process through this file or FIFO instead of hashing locally"
+    )]
+    pub relay_challenges: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "relay_challenges",
+        help = "File or FIFO the solve-feed process writes solutions to"
+    )]
+    pub relay_solutions: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "History file with baseline rounds to compare this session against"
+    )]
+    pub baseline: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "ROUNDS",
+        default_value = "20",
+        help = "Print the baseline comparison every this many rounds"
+    )]
+    pub baseline_rounds: usize,
+    #[arg(
+        long,
+        action,
+        help = "Keep mining at full speed while the machine is running on battery power"
+    )]
+    pub allow_on_battery: bool,
+    #[arg(
+        long,
+        value_name = "THREADS",
+        default_value = "0",
+        help = "Number of threads to use on battery power, 0 to pause mining until AC power returns"
+    )]
+    pub battery_threads: u32,
+    #[arg(
+        long,
+        value_name = "WATTS",
+        help = "Limit the CPU package power through RAPL while mining, restored on pause and exit (Linux, needs root)"
+    )]
+    pub power_limit: Option<u64>,
+    #[arg(
+        long,
+        value_name = "SCRIPT",
+        help = "Run this script with start, pause, resume or stop as mining changes state, e.g. to apply undervolt settings"
+    )]
+    pub power_hook: Option<PathBuf>,
+    #[arg(
+        long,
+        action,
+        help = "Sign timestamps with the pool's time when the local clock is off, instead of only warning"
+    )]
+    pub auto_clock_offset: bool,
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Second time source for the clock check, any HTTPS server with a Date header",
+        default_value = clock::DEFAULT_TIME_SOURCE
+    )]
+    pub time_source: String,
+    #[arg(
+        long,
+        action,
+        help = "Hex-dump binary frames with unknown message types, for diagnosing pool protocol issues"
+    )]
+    pub dump_unknown_frames: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Append all websocket frames with timestamps and direction to this file, for replay-frames"
+    )]
+    pub dump_frames: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Append each challenge to this corpus for bench-replay, without pool, wallet or timing details"
+    )]
+    pub record_challenges: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        help = "Mine solo against the ORE program over RPC while the pool is unreachable for this long"
+    )]
+    pub solo_fallback: Option<u64>,
+    #[arg(
+        long,
+        action,
+        help = "Report measured sustained hashrate to pools that size nonce ranges by capacity"
+    )]
+    pub report_capacity: bool,
+    #[arg(
+        long,
+        value_name = "HASHES_PER_SEC",
+        help = "Never report more capacity than this, e.g. to leave headroom for other work"
+    )]
+    pub capacity_cap: Option<u64>,
+    #[arg(
+        long,
+        value_name = "ROUNDS",
+        help = "Disconnect and exit after mining this many rounds"
+    )]
+    pub max_rounds: Option<u64>,
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        help = "Disconnect and exit after mining for this many minutes, checked between rounds"
+    )]
+    pub max_minutes: Option<u64>,
+    #[arg(
+        long,
+        value_name = "ORE",
+        help = "Disconnect and exit once this much ORE has been earned this session, announcing it to the [report] webhook if one is set"
+    )]
+    pub stop_after_earned: Option<f64>,
+    #[arg(
+        long,
+        value_name = "HOURS",
+        help = "Mine at most this many hours per local calendar day, across runs. Checked between rounds, mining resumes the next day"
+    )]
+    pub max_hours_per_day: Option<f64>,
+    #[arg(
+        long,
+        value_name = "ROUNDS",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Mine at most this many rounds in any hour, across runs, for pools that ask miners to limit participation. Ready is held back once the quota is used up"
+    )]
+    pub max_rounds_per_hour: Option<u32>,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ACTION",
+        help = "Check each assigned range against the ranges other rigs sharing this working directory's store got for the same challenge. `log` reports an overlap, `offset` also mines only the part no other rig has"
+    )]
+    pub on_range_collision: Option<RangeCollision>,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "BASIS",
+        default_value = "confirmed",
+        help = "Whether --stop-after-earned counts only rewards the pool confirmed, or also the estimate for the latest submission"
+    )]
+    pub goal_basis: GoalBasis,
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Sample where solver threads spend their time for this many seconds and write folded stacks for flamegraph tools"
+    )]
+    pub profile_solver: Option<u64>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        default_value = "solver.folded",
+        help = "Where --profile-solver writes its output"
+    )]
+    pub profile_output: PathBuf,
+    #[arg(
+        long,
+        action,
+        help = "Alternate between the pools listed under [[schedule]] in config.toml"
+    )]
+    pub rotate: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 1..,
+        help = "Mine for several keypairs at once, one pool connection each. Accepts keypair files and directories of them"
+    )]
+    pub wallets: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PORT",
+        help = "Serve Prometheus metrics on /metrics and JSON stats per wallet on /stats.json at 127.0.0.1:PORT"
+    )]
+    pub metrics_port: Option<u16>,
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "Like --metrics-port with a full address, e.g. 0.0.0.0:9090 or unix:/run/ore-miner.sock"
+    )]
+    pub metrics_addr: Option<MetricsAddr>,
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "600",
+        help = "Octal permissions of a unix: metrics or control socket"
+    )]
+    pub metrics_socket_mode: SocketMode,
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "Accept pause, resume, set-threads, status and version commands on host:port or unix:/path.sock, see `ctl`. Listening beyond loopback needs a token in ORE_HQ_CONTROL_TOKEN, on the miner and for ctl and fleet"
+    )]
+    pub control_socket: Option<MetricsAddr>,
+    #[arg(
+        long,
+        action,
+        help = "Score the --url pools and the pools of every settings profile by latency and availability, mine with the best and switch between rounds when it degrades"
+    )]
+    pub auto_select_pool: bool,
+    /// Further `--url` values, failed over to in order when the pool is down.
+    #[arg(skip)]
+    pub failover_urls: Vec<String>,
+    /// Set by `--wallets` so the sessions split --threads between them.
+    #[arg(skip)]
+    pub thread_share: Option<Arc<SharedThreads>>,
+    /// Pause and thread settings from the control socket, shared by the sessions of `--wallets`.
+    #[arg(skip)]
+    pub control: Arc<MinerControl>,
+    /// Stats of the running sessions, served by the metrics and control servers.
+    #[arg(skip)]
+    pub stats: Arc<StatsBoard>,
+    /// Earnings towards --stop-after-earned, shared by the slices of a rotation and the sessions of `--wallets`.
+    #[arg(skip)]
+    pub earnings: Arc<EarningsTally>,
 }
 
-pub async fn mine(args: MineArgs, key: Keypair, url: String, unsecure: bool) {
+impl MineArgs {
+    fn limit_reached(&self, rounds: u64, started: Instant) -> bool {
+        self.max_rounds.is_some_and(|max| rounds >= max)
+            || self.max_minutes.is_some_and(|minutes| started.elapsed() >= Duration::from_secs(minutes * 60))
+    }
+
+    /// ORE earned this session once --stop-after-earned is reached.
+    fn goal_reached(&self) -> Option<f64> {
+        self.earnings.reached(self.stop_after_earned, self.goal_basis)
+    }
+}
+
+/// Starts the metrics and control servers for a whole run, which may mine several sessions at once with
+/// --wallets or one after another in a rotation. Abort the handles when the run ends.
+pub async fn serve_endpoints(args: &MineArgs) -> Vec<tokio::task::JoinHandle<()>> {
+    let metrics_addr = args.metrics_addr.clone().or(args.metrics_port.map(|port| MetricsAddr::Tcp(([127, 0, 0, 1], port).into())));
+    let mut servers = Vec::new();
+    if let Some(addr) = metrics_addr {
+        servers.extend(metrics::serve(addr, args.metrics_socket_mode, args.stats.clone()).await);
+    }
+    if let Some(addr) = args.control_socket.clone() {
+        servers.extend(control::serve(addr, args.metrics_socket_mode, args.control.clone(), args.stats.clone()).await);
+    }
+    servers
+}
+
+/// Mines until stopped with Ctrl+C, or until --max-rounds, --max-minutes or --stop-after-earned
+/// is reached. Returns false if the user stopped mining or the earnings goal was reached.
+pub async fn mine(args: MineArgs, key: MinerSigner, url: String, unsecure: bool, rpc_url: String) -> bool {
     let running = Arc::new(AtomicBool::new(true));
+    let started = Instant::now();
+    let mut rounds_mined: u64 = 0;
+    let mut limit_reached = false;
+    let mut goal_reached = None;
+    let quirks = Quirks::new(args.server_flavor, &args.quirks);
+    let mut estimator = RewardEstimator::new(args.payout_curve);
+    let mut round_graph = RoundGraph::new(args.graph_rounds);
+    let mut range_tracker = RangeTracker::default();
+    let mut capacity = CapacityMeter::default();
+    let mut baseline = args.baseline.as_ref().and_then(|path| {
+        let baseline = BaselineComparison::load(path, args.baseline_rounds);
+        match &baseline {
+            Some(baseline) => say!("Loaded {} baseline rounds from {}", baseline.baseline_rounds(), path.display()),
+            None => say!("No usable baseline rounds in {}, comparison disabled.", path.display()),
+        }
+        baseline
+    });
+    let mut idle_secs_saved: u64 = 0;
+    if let Some(secs) = args.profile_solver {
+        profiler::start(secs, args.profile_output.clone());
+    }
+    // Tasks that only serve this session, aborted when it ends so a rotation doesn't stack them
+    let mut background: Vec<tokio::task::JoinHandle<()>> = resource_monitor::spawn(args.resource_monitor_interval).into_iter().collect();
+
+    let task_ctx = TaskContext::new(network::client(), url.clone(), unsecure, key.pubkey());
+    if args.poll_rewards {
+        background.push(tasks::spawn_rewards_poller(task_ctx.clone()));
+    }
+    if args.report_errors {
+        background.push(tasks::spawn_error_reporter(task_ctx.clone()));
+    }
+    let mut events = task_ctx.subscribe();
+    let mut submission_failures: u32 = 0;
+    let mut submitted_solutions = SubmissionCache::default();
+    let rig = history::rig_name();
+    let unexpected_frames = Arc::new(UnexpectedFrames::default());
+    let recent_broadcasts = Arc::new(RecentBroadcasts::default());
+    let frame_dump = Arc::new(FrameDump::open(args.dump_frames.as_ref()));
+    let settings = settings::load();
+    let solver = settings.solver;
+    background.extend(report::spawn(task_ctx.clone(), settings.report.clone()));
+    background.extend(notify::spawn(task_ctx.clone(), settings.notifications));
+    background.extend(sound::spawn(task_ctx.clone(), settings.sounds.clone()));
+    let sprint_pool = args.sprint.then(|| SprintPool::new(args.threads, args.reserve_cores, running.clone(), solver));
+    background.extend(sleep_detect::spawn(task_ctx.clone(), args.sleep_threshold));
+    let mut sla = ConnectionSla::new();
+    let stats = Arc::new(MinerStats::new(key.pubkey().to_string(), url.clone(), recent_broadcasts.clone()));
+    stats.connection(&sla);
+    args.stats.attach(stats.clone());
+    let thermal = if args.thermal_feedback { thermal::spawn(args.control.clone()) } else { None };
+    let mut jitter = ChallengeJitter::default();
+    let mut ghost_rounds = GhostRounds::default();
+    let mut recent_best: VecDeque<u32> = VecDeque::new();
+    let mut exit_history: VecDeque<u32> = match args.exit_percentile {
+        Some(_) => history::recent_difficulties(&key.pubkey().to_string(), EXIT_WINDOW).into(),
+        None => VecDeque::new(),
+    };
+    if args.exit_percentile.is_some() && exit_history.len() < EXIT_MIN_ROUNDS {
+        say!("--exit-percentile needs {} rounds of history, stopping at difficulty {} until then.", EXIT_MIN_ROUNDS, MIN_EXIT_DIFFICULTY);
+    }
+    if args.boost_finish && split_cores(args.reserve_cores).1.is_empty() {
+        say!("--boost-finish has no cores to enlist, reserve some with --reserve-cores.");
+    }
+    let mut maintenance: Option<Range<u64>> = None;
+    // Token issued by servers that support resuming a session without a fresh signed timestamp.
+    // Not used with --session-key, since the delegation only covers a single connection.
+    let mut resume_token: Option<String> = None;
+    let mut pool_down_since: Option<Instant> = None;
+    let mut pools = PoolFailover::new(url.clone(), &args.failover_urls);
+    if pools.has_fallbacks() && args.auto_select_pool {
+        pools.select_fastest(&network::client(), if unsecure { "http" } else { "https" }).await;
+    } else if pools.has_fallbacks() {
+        pools.select_healthy(&network::client(), if unsecure { "http" } else { "https" }).await;
+    }
+    let skew = clock::check(&network::client(), if unsecure { "http" } else { "https" }, pools.current(), &args.time_source).await;
+    clock::report(&skew, pools.current(), &args.time_source, args.auto_clock_offset);
+    let mut last_pool = pools.current().to_string();
+    let mut power_hooks = PowerHooks::new(args.power_hook.clone(), args.power_limit);
+    power_hooks.run(PowerEvent::Start).await;
+
+    // First Ctrl+C stops after the current step and prints the session summary, a second one exits
+    let (shutdown_sender, mut shutdown) = tokio::sync::watch::channel(false);
+    let ctrl_c = tokio::spawn({
+        let running = running.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                say!("Stopping, press Ctrl+C again to exit immediately...");
+                running.store(false, Ordering::SeqCst);
+                let _ = shutdown_sender.send(true);
+            }
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
 
     loop {
         if !running.load(Ordering::SeqCst) {
             break;
         }
+        if args.limit_reached(rounds_mined, started) {
+            limit_reached = true;
+            break;
+        }
+        // Another wallet or an earlier slice of the rotation may have earned the rest
+        if let Some(earned) = args.goal_reached() {
+            goal_reached = Some(earned);
+            break;
+        }
+        // Resume tokens and maintenance windows belong to the pool that issued them
+        if pools.current() != last_pool {
+            resume_token = None;
+            maintenance = None;
+            last_pool = pools.current().to_string();
+        }
 
-        let base_url = url.clone();
-        let mut ws_url_str = if unsecure {
-            format!("ws://{}", url)
-        } else {
-            format!("wss://{}", url)
-        };
+        // Stay away from the pool for the rest of the day once the day's hours are used up
+        if daily::budget_spent(args.max_hours_per_day) {
+            let wait = daily::until_tomorrow();
+            say!(
+                "Mined {} today, the daily limit of {}h. Pausing for {} until tomorrow...",
+                format::duration(daily::used_today()),
+                args.max_hours_per_day.unwrap_or_default(),
+                format::duration(wait)
+            );
+            sla.enter(ConnectionState::Idle);
+            stats.connection(&sla);
+            power_hooks.run(PowerEvent::Pause).await;
+            tokio::select! {
+                _ = tokio::time::sleep(wait + Duration::from_secs(1)) => {},
+                _ = shutdown.changed() => {},
+            }
+            power_hooks.run(PowerEvent::Resume).await;
+            continue;
+        }
 
-        if ws_url_str.chars().last().unwrap() != '/' {
-            ws_url_str.push('/');
+        // Sit out an announced maintenance window instead of hammering reconnects during the outage
+        if let Some(window) = maintenance.clone() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+            if window.contains(&now) {
+                say!("Pool maintenance in progress, pausing for {}s until it ends...", window.end - now);
+                power_hooks.run(PowerEvent::Pause).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(window.end - now + MAINTENANCE_GRACE_SECS)) => {},
+                    _ = shutdown.changed() => {},
+                }
+                power_hooks.run(PowerEvent::Resume).await;
+                continue;
+            }
+            if now >= window.end {
+                maintenance = None;
+            }
         }
 
-        let client = reqwest::Client::new();
+        if let (Some(minutes), Some(since)) = (args.solo_fallback, pool_down_since) {
+            if since.elapsed() >= Duration::from_secs(minutes * 60) {
+                say!("Pool unreachable for {} minutes, switching to solo mining.", since.elapsed().as_secs() / 60);
+                sla.enter(ConnectionState::Mining);
+                stats.connection(&sla);
+                solo::mine_until_pool_recovers(&task_ctx, key.as_ref(), &rpc_url, args.threads, solver, &running).await;
+                sla.enter(ConnectionState::Reconnecting);
+                stats.connection(&sla);
+                pool_down_since = None;
+                continue;
+            }
+        }
+
+        let base_url = pools.current().to_string();
+        let client = network::client();
 
         let http_prefix = if unsecure {
             "http".to_string()
@@ -60,331 +741,1397 @@ pub async fn mine(args: MineArgs, key: Keypair, url: String, unsecure: bool) {
             "https".to_string()
         };
 
-        let timestamp = if let Ok(response) = client.get(format!("{}://{}/timestamp", http_prefix, base_url)).send().await {
-            if let Ok(ts) = response.text().await {
-                if let Ok(ts) = ts.parse::<u64>() {
-                    ts
-                } else {
-                    println!("Server response body for /timestamp failed to parse, contact admin.");
-                    tokio::time::sleep(Duration::from_secs(3)).await;
+        let resuming = resume_token.take();
+        let timestamp = if resuming.is_some() {
+            clock::now()
+        } else {
+            match schema::get::<ServerTime>(&client, &http_prefix, &base_url, "timestamp", "").await {
+                Ok(ServerTime(ts)) => ts,
+                Err(e @ (SchemaError::Request { .. } | SchemaError::Status { .. })) => {
+                    let wait = pools.failed();
+                    say!("Server restarting, trying again in {}s... ({})", wait.as_secs(), e);
+                    pool_down_since.get_or_insert_with(Instant::now);
+                    tokio::time::sleep(wait).await;
                     continue;
-                }
-            } else {
-                println!("Server response body for /timestamp is empty, contact admin.");
-                tokio::time::sleep(Duration::from_secs(3)).await;
-                continue;
+                },
+                Err(e) => {
+                    say!("Server response for /timestamp is unusable, contact admin: {}", e);
+                    pool_down_since.get_or_insert_with(Instant::now);
+                    tokio::time::sleep(pools.failed()).await;
+                    continue;
+                },
             }
-        } else {
-            println!("Server restarting, trying again in 3 seconds...");
-            tokio::time::sleep(Duration::from_secs(3)).await;
-            continue;
         };
-        println!("Server Timestamp: {}", timestamp);
+        if resuming.is_none() {
+            say!("Server Timestamp: {}", timestamp);
+        }
 
-        let ts_msg = timestamp.to_le_bytes();
-        let sig = key.sign_message(&ts_msg);
+        if let Some(window) = fetch_maintenance(&client, &http_prefix, &base_url).await {
+            if maintenance.as_ref() != Some(&window) {
+                say!("Pool maintenance scheduled from {} to {}.", window.start, window.end);
+            }
+            maintenance = Some(window);
+        }
 
-        ws_url_str.push_str(&format!("?timestamp={}", timestamp));
-        let url = url::Url::parse(&ws_url_str).expect("Failed to parse server url");
+        let url = url::Url::parse(&endpoint::ws(unsecure, &base_url, &format!("timestamp={}", timestamp))).expect("Failed to parse server url");
         let host = url.host_str().expect("Invalid host in server url");
+        let port = url.port_or_known_default().expect("Invalid port in server url");
         let threads = args.threads;
+        let paranoid = args.paranoid;
+        let paranoid_min_difficulty = args.paranoid_min_difficulty;
 
-        let auth = BASE64_STANDARD.encode(format!("{}:{}", key.pubkey(), sig));
-
-        println!("Connecting to server...");
-        let request = Request::builder()
+        say!("Connecting to server...");
+        let mut request = Request::builder()
             .method("GET")
             .uri(url.to_string())
             .header("Sec-Websocket-Key", generate_key())
             .header("Host", host)
             .header("Upgrade", "websocket")
             .header("Connection", "upgrade")
-            .header("Sec-Websocket-Version", "13")
-            .header("Authorization", format!("Basic {}", auth))
-            .body(())
-            .unwrap();
+            .header("Sec-Websocket-Version", "13");
+        request = match &resuming {
+            Some(token) => request.header("X-Resume-Token", token.as_str()),
+            None => {
+                let ts_msg = timestamp.to_le_bytes();
+                let sig = audit::sign(key.as_ref(), "timestamp", &ts_msg);
+                let auth = BASE64_STANDARD.encode(format!("{}:{}", key.pubkey(), sig));
+                request.header("Authorization", format!("Basic {}", auth))
+            }
+        };
+        if resuming.is_none() && !args.session_key {
+            request = request.header("X-Resume", "1");
+        }
+
+        // Offer a session key delegation, servers that support it acknowledge in the upgrade response
+        let session_key = if args.session_key {
+            let session = SessionKey::generate(key.as_ref(), timestamp);
+            request = request
+                .header("X-Session-Pubkey", session.keypair.pubkey().to_string())
+                .header("X-Session-Cert", session.certificate.to_string())
+                .header("X-Session-Expiry", session.expires_at.to_string());
+            Some(session)
+        } else {
+            None
+        };
+        if args.attestations {
+            request = request.header("X-Attestation", "1");
+        }
+        if args.report_capacity {
+            request = request.header("X-Capacity", "1");
+        }
+        if settings.submission.timing {
+            request = request.header("X-Timing-Metadata", "1");
+        }
+        let request = request.body(()).unwrap();
+
+        let connection = match connect_tcp(host, port, args.tcp_keepalive).await {
+            // Covers the TLS handshake and the upgrade response
+            Ok(stream) => network::within(network::read_timeout(), "websocket handshake", client_async_tls(request, stream)).await,
+            Err(e) => Err(tokio_tungstenite::tungstenite::Error::Io(e)),
+        };
+
+        match connection {
+            Ok((ws_stream, response)) => {
+                say!("Connected to network!");
+                task_ctx.publish(MinerEvent::Connected);
+                let _connected = args.thread_share.as_ref().map(|share| share.connect());
+                pool_down_since = None;
+                sla.enter(ConnectionState::Idle);
+                stats.connection(&sla);
+
+                let issued_token = response
+                    .headers()
+                    .get("X-Resume-Token")
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                if resuming.is_some() {
+                    say!("Resumed previous session.");
+                } else if !args.session_key {
+                    pool_cache::update(&base_url, |caps| caps.resumption = Some(issued_token.is_some()));
+                }
+                // Keep using the presented token unless the server rotated it
+                resume_token = issued_token.or(resuming.clone());
+                if let Some(token) = &resume_token {
+                    redact::register_secret(token);
+                }
 
-        match connect_async(request).await {
-            Ok((ws_stream, _)) => {
-                println!("Connected to network!");
+                // Resumes from before this connection are irrelevant
+                let _ = sleep_detect::resumed_since(&mut events);
 
-                let (mut sender, mut receiver) = ws_stream.split();
+                let (sender, mut receiver) = ws_stream.split();
                 let (message_sender, mut message_receiver) = tokio::sync::mpsc::unbounded_channel::<ServerMessage>();
 
-                let receiver_thread = tokio::spawn(async move {
+                let receiver_ctx = task_ctx.clone();
+                let receiver_frames = unexpected_frames.clone();
+                let receiver_broadcasts = recent_broadcasts.clone();
+                let dump_unknown_frames = args.dump_unknown_frames;
+                let receiver_dump = frame_dump.clone();
+                let receiver_tag = WALLET_TAG.try_with(Clone::clone).ok();
+                let receive = async move {
                     while let Some(Ok(message)) = receiver.next().await {
-                        if process_message(message, message_sender.clone()).is_break() {
+                        receiver_dump.record(Direction::In, &message);
+                        if process_message(message, message_sender.clone(), &quirks, &receiver_ctx, &receiver_frames, &receiver_broadcasts, dump_unknown_frames).is_break() {
                             break;
                         }
                     }
+                };
+                let receiver_thread = tokio::spawn(async move {
+                    match receiver_tag {
+                        Some(tag) => WALLET_TAG.scope(tag, receive).await,
+                        None => receive.await,
+                    }
                 });
 
-                // send Ready message
-                let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
-
-                let msg = now.to_le_bytes();
-                let sig = key.sign_message(&msg).to_string().as_bytes().to_vec();
-                let mut bin_data: Vec<u8> = Vec::new();
-                bin_data.push(0u8);
-                bin_data.extend_from_slice(&key.pubkey().to_bytes());
-                bin_data.extend_from_slice(&msg);
-                bin_data.extend(sig);
+                let sender = Arc::new(Mutex::new(sender));
+                let mut session = Session::new(key.clone(), quirks, base_url.clone(), sender.clone(), receiver_thread, frame_dump.clone(), task_ctx.clone());
+                session.negotiate(&response, session_key, &args, settings.submission.timing);
 
-                let _ = sender.send(Message::Binary(bin_data)).await;
+                // send Ready message
+                jitter.reset_connection();
+                ghost_rounds.reset_connection();
+                if let Err(e) = session.ready().await {
+                    // Without a receiver the loop below ends right away and we reconnect
+                    say!("Failed to send Ready: {}, reconnecting...", e);
+                    session.disconnect();
+                }
+                jitter.ready_sent();
 
-                let sender = Arc::new(Mutex::new(sender));
+                // Keep the connection busy between rounds so NATs don't drop the mapping
+                let pinger = (args.ping_interval > 0).then(|| {
+                    let sender = sender.clone();
+                    let frame_dump = frame_dump.clone();
+                    let period = Duration::from_secs(args.ping_interval);
+                    tokio::spawn(async move {
+                        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                        loop {
+                            interval.tick().await;
+                            let message = Message::Ping(Vec::new());
+                            frame_dump.record(Direction::Out, &message);
+                            if sender.lock().await.send(message).await.is_err() {
+                                break;
+                            }
+                        }
+                    })
+                });
 
-                // receive messages
-                let message_sender = sender.clone();
-                while let Some(msg) = message_receiver.recv().await {
+                let mut primary_probe = tokio::time::interval_at(tokio::time::Instant::now() + failover::PRIMARY_PROBE_INTERVAL, failover::PRIMARY_PROBE_INTERVAL);
+                let mut auto_select = tokio::time::interval_at(tokio::time::Instant::now() + failover::AUTO_SELECT_INTERVAL, failover::AUTO_SELECT_INTERVAL);
+                // Leaving a working pool on purpose doesn't count as a failure
+                let mut switching_pools = false;
+                let mut daily_budget_spent = false;
+                let mut last_start: Option<([u8; 32], Range<u64>, Instant)> = None;
+                // Messages that arrived while a round was mining, handled once it is over
+                let mut deferred: VecDeque<ServerMessage> = VecDeque::new();
+                loop {
+                    // Only give up on a silent pool when there is another one to go to
+                    let ready_ack_wait = jitter
+                        .awaiting_challenge()
+                        .filter(|_| pools.has_fallbacks())
+                        .map(|waited| failover::READY_ACK_TIMEOUT.saturating_sub(waited));
+                    // After a suspend the socket is most likely dead without us being told, reconnect right away
+                    let deferred_msg = deferred.pop_front();
+                    let msg = tokio::select! {
+                        msg = async { deferred_msg }, if deferred_msg.is_some() => msg,
+                        msg = message_receiver.recv(), if deferred_msg.is_none() => msg,
+                        slept = sleep_detect::wait_for_resume(&mut events) => {
+                            say!("System resumed after sleeping {}s, reconnecting...", slept);
+                            session.disconnect();
+                            break;
+                        }
+                        _ = shutdown.changed() => {
+                            session.disconnect();
+                            break;
+                        }
+                        _ = tokio::time::sleep(ready_ack_wait.unwrap_or_default()), if ready_ack_wait.is_some() => {
+                            say!("No challenge from {} within {}s of Ready, reconnecting...", base_url, failover::READY_ACK_TIMEOUT.as_secs());
+                            session.disconnect();
+                            break;
+                        }
+                        _ = primary_probe.tick(), if pools.on_fallback() && !args.auto_select_pool => {
+                            if !pools.primary_healthy(&client, &http_prefix).await {
+                                continue;
+                            }
+                            say!("Primary pool is healthy again, switching back.");
+                            pools.return_to_primary();
+                            switching_pools = true;
+                            session.disconnect();
+                            break;
+                        }
+                        _ = auto_select.tick(), if args.auto_select_pool && pools.has_fallbacks() => {
+                            if !pools.reselect(&client, &http_prefix).await {
+                                continue;
+                            }
+                            say!("Switching to {}, which scores better than {}.", pools.current(), base_url);
+                            switching_pools = true;
+                            session.disconnect();
+                            break;
+                        }
+                    };
+                    let Some(msg) = msg else {
+                        break;
+                    };
                     if !running.load(Ordering::SeqCst) {
                         break;
                     }
-                
+
                     match msg {
-                        ServerMessage::StartMining(challenge, nonce_range, cutoff) => {
-                            // Adjust the cutoff with the buffer
-                            let mut cutoff = cutoff.saturating_sub(args.buffer as u64);
-                            if cutoff > 60 {
-                                cutoff = 55;
-                            }
-
-                            // Detect if running on Windows and set symbols accordingly
-                            let pb = if env::consts::OS == "windows" {
-                                ProgressBar::new_spinner().with_style(
-                                    ProgressStyle::default_spinner()
-                                        .tick_strings(&["-", "\\", "|", "/"]) // Use simple ASCII symbols
-                                        .template("{spinner:.green} {msg}")
-                                        .expect("Failed to set progress bar template"),
-                                )
-                            } else {
-                                ProgressBar::new_spinner().with_style(
-                                    ProgressStyle::default_spinner()
-                                        .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"])
-                                        .template("{spinner:.red} {msg}")
-                                        .expect("Failed to set progress bar template"),
-                                )
+                        ServerMessage::StartMining(challenge, nonce_range, cutoff, thread_hint, arrived) => {
+                            // A server or proxy repeating the frame would have us mine the round twice and miss the next one
+                            let duplicate = last_start
+                                .as_ref()
+                                .is_some_and(|(last, range, at)| *last == challenge && *range == nonce_range && arrived.duration_since(*at) < DUPLICATE_START_WINDOW);
+                            if duplicate {
+                                let count = unexpected_frames.count("duplicate StartMining".to_string());
+                                say!("Ignoring a duplicate StartMining for the current challenge ({} this session).", count);
+                                continue;
+                            }
+                            last_start = Some((challenge, nonce_range.clone(), arrived));
+                            // Two of our rigs mining the same nonces means the pool misassigned one of them
+                            let nonce_range = match args.on_range_collision {
+                                Some(policy) => {
+                                    let (mined, collision) = ranges::check_collision(&base_url, &key.pubkey().to_string(), &challenge, nonce_range, policy);
+                                    if let Some(collision) = collision {
+                                        say!("WARNING: {}", collision);
+                                        tracing::warn!(target: "range_collision", challenge = %solana_sdk::bs58::encode(challenge).into_string(), start = mined.start, end = mined.end, "{}", collision);
+                                    }
+                                    mined
+                                },
+                                None => nonce_range,
                             };
+                            let challenge_latency = jitter.challenge_received();
+                            pools.healthy();
+                            stats.record_challenge();
+                            let dropped = ghost_rounds.expire();
+                            if !dropped.is_empty() {
+                                stats.record_dropped_submissions(dropped.len() as u64);
+                                for challenge in &dropped {
+                                    say!("No result from the pool for the submission to challenge {}.", challenge);
+                                }
+                                if ghost_rounds.consecutive() >= ghosts::ALERT_AFTER {
+                                    say!(
+                                        "WARNING: the pool has not reported results for the last {} submissions. It may be dropping them, the session summary lists the challenges for the operator.",
+                                        ghost_rounds.consecutive()
+                                    );
+                                    task_ctx.publish(MinerEvent::ClientError {
+                                        kind: "dropped_submissions",
+                                        detail: format!("no results for {} consecutive submissions, last challenge {}", ghost_rounds.consecutive(), dropped[dropped.len() - 1]),
+                                    });
+                                }
+                            }
+                            if let Some(corpus) = &args.record_challenges {
+                                bench_replay::record(corpus, &challenge, nonce_range.end.saturating_sub(nonce_range.start));
+                            }
+                            if let Some(since_ready) = challenge_latency {
+                                say!("Challenge arrived {} ms after Ready.", since_ready.as_millis());
+                            }
+                            let threads = args.control.threads().unwrap_or(threads);
+                            let threads = args.thread_share.as_ref().map_or(threads, |share| share.share(threads));
+                            let hinted = args.thread_hints.apply(threads, thread_hint);
+                            if hinted != threads {
+                                say!("Using {} threads this round, as hinted by the server.", hinted);
+                            }
+                            let mut threads = hinted;
+
+                            if let Some(max) = args.max_rounds_per_hour {
+                                if quota::until_free(max).is_some() {
+                                    skip_for_quota(max, &challenge);
+                                    if !wait_for_quota(max, &mut message_receiver, &mut deferred, &mut shutdown).await {
+                                        session.disconnect();
+                                        break;
+                                    }
+                                    if let Err(e) = session.ready().await {
+                                        say!("Failed to send Ready: {}, reconnecting...", e);
+                                        session.disconnect();
+                                        break;
+                                    }
+                                    jitter.ready_sent();
+                                    continue;
+                                }
+                            }
+
+                            if args.control.is_paused() {
+                                say!("Paused from the control socket, skipping rounds until resumed.");
+                                sla.enter(ConnectionState::Idle);
+                                stats.connection(&sla);
+                                power_hooks.run(PowerEvent::Pause).await;
+                                if !args.control.wait_resumed(&mut shutdown).await {
+                                    break;
+                                }
+                                power_hooks.run(PowerEvent::Resume).await;
+
+                                // Skip the stale challenge and ask for fresh work
+                                if let Err(e) = session.ready().await {
+                                    say!("Failed to send Ready: {}, reconnecting...", e);
+                                    session.disconnect();
+                                    break;
+                                }
+                                jitter.ready_sent();
+                                continue;
+                            }
+
+                            if !args.allow_on_battery && power::on_battery() {
+                                if args.battery_threads == 0 {
+                                    say!("Running on battery power, pausing mining until AC power returns.");
+                                    sla.enter(ConnectionState::Idle);
+                                    stats.connection(&sla);
+                                    power_hooks.run(PowerEvent::Pause).await;
+                                    if !power::wait_for_ac(&mut shutdown).await {
+                                        break;
+                                    }
+                                    power_hooks.run(PowerEvent::Resume).await;
+                                    say!("AC power restored, resuming mining.");
+
+                                    // Skip the stale challenge and ask for fresh work
+                                    if let Err(e) = session.ready().await {
+                                        say!("Failed to send Ready: {}, reconnecting...", e);
+                                        session.disconnect();
+                                        break;
+                                    }
+                                    jitter.ready_sent();
+                                    continue;
+                                }
+                                threads = threads.min(args.battery_threads);
+                                say!("Running on battery power, using {} threads this round.", threads);
+                            }
+
+                            // Adjust the cutoff with the buffer and the pool's clamp, see [cutoff]
+                            let deadline = settings.cutoff.deadline(&base_url, cutoff, quirks.clamp_cutoff);
+                            let cutoff = settings.cutoff.apply(&base_url, cutoff, args.buffer as u64, quirks.clamp_cutoff);
 
-                            println!();
-                            pb.set_message("Mining...");
-                            pb.enable_steady_tick(Duration::from_millis(120));
+                            if args.max_rounds_per_hour.is_some() {
+                                quota::record_round();
+                            }
+                            sla.enter(ConnectionState::Mining);
+                            stats.connection(&sla);
 
-                            // Original mining code
+                            // In sprint mode the workers are already waiting, start them before any UI setup
                             let hash_timer = Instant::now();
-                            let core_ids = core_affinity::get_core_ids().unwrap();
-                            let nonces_per_thread = 10_000;
-                            let handles = core_ids
-                                .into_iter()
-                                .map(|i| {
-                                    let running = running.clone(); // Capture running in thread
-                                    std::thread::spawn({
-                                        let mut memory = equix::SolverMemory::new();
-                                        move || {
-                                            if (i.id as u32).ge(&threads) {
-                                                return None;
-                                            }
-
-                                            let _ = core_affinity::set_for_current(i);
-
-                                            let first_nonce = nonce_range.start + (nonces_per_thread * (i.id as u64));
-                                            let mut nonce = first_nonce;
-                                            let mut best_nonce = nonce;
-                                            let mut best_difficulty = 0;
-                                            let mut best_hash = drillx_2::Hash::default();
-                                            let mut total_hashes: u64 = 0;
-
-                                            loop {
-                                                // Check if Ctrl+C was pressed
-                                                if !running.load(Ordering::SeqCst) {
-                                                    return None;
-                                                }
-
-                                                // Create hash
-                                                for hx in drillx_2::get_hashes_with_memory(&mut memory, &challenge, &nonce.to_le_bytes()) {
-                                                    total_hashes += 1;
-                                                    let difficulty = hx.difficulty();
-                                                    if difficulty.gt(&best_difficulty) {
-                                                        best_nonce = nonce;
-                                                        best_difficulty = difficulty;
-                                                        best_hash = hx;
-                                                    }
-                                                }
-
-                                                // Exit if processed nonce range
-                                                if nonce >= nonce_range.end {
-                                                    break;
-                                                }
-
-                                                if nonce % 100 == 0 {
-                                                    if hash_timer.elapsed().as_secs().ge(&cutoff) {
-                                                        if best_difficulty.ge(&8) {
-                                                            break;
-                                                        }
-                                                    }
-                                                }
-
-                                                // Increment nonce
-                                                nonce += 1;
-                                            }
-
-                                            // Return the best nonce
-                                            Some((best_nonce, best_difficulty, best_hash, total_hashes))
-                                        }
-                                    })
-                                })
-                                .collect::<Vec<_>>();
+                            let hash_started = SystemTime::now();
+                            let cpu_start = steal::sample();
+                            let job = RoundJob {
+                                challenge,
+                                nonce_range: nonce_range.clone(),
+                                cutoff,
+                                hash_timer,
+                                // Workers pull chunks from a shared cursor, sized from their own measured throughput
+                                cursor: Arc::new(AtomicU64::new(nonce_range.start)),
+                                paranoid,
+                                paranoid_min_difficulty,
+                                cancelled: Arc::new(AtomicBool::new(false)),
+                                solver,
+                                control: args.control.clone(),
+                                best_difficulty: Default::default(),
+                                exit_target: args.exit_percentile.and_then(|percentile| percentile_target(&exit_history, percentile, args.exit_grace)),
+                            };
+                            let reserve = Reserve {
+                                cores: args.reserve_cores,
+                                boost_below: (args.boost_finish && recent_best.len() >= BOOST_MIN_ROUNDS)
+                                    .then(|| recent_best.iter().sum::<u32>() as f64 / recent_best.len() as f64),
+                            };
+                            let round = args.relay_challenges.is_none().then(|| mine_round(job.clone(), threads, sprint_pool.as_ref(), reserve, &running));
+
+                            say!();
+                            // Spinners of concurrent wallets would overwrite each other
+                            let progress = WALLET_TAG.try_with(|_| ()).is_err().then(|| theme::RoundProgress::start("Mining..."));
 
                             // Join handles and return best nonce
                             let mut best_nonce: u64 = 0;
                             let mut best_difficulty = 0;
                             let mut best_hash = drillx_2::Hash::default();
-                            let mut total_nonces_checked = 0;
-                            for h in handles {
-                                if let Ok(Some((nonce, difficulty, hash, nonces_checked))) = h.join() {
-                                    total_nonces_checked += nonces_checked;
-                                    if difficulty > best_difficulty {
-                                        best_difficulty = difficulty;
-                                        best_nonce = nonce;
-                                        best_hash = hash;
-                                    }
+                            let mut total_hashes = 0;
+                            let mut total_nonces = 0;
+                            let mut range_exhausted = true;
+                            let mut workers = 0;
+                            let mut hash_faults = 0;
+                            let mut thread_rates: Vec<f64> = Vec::new();
+                            let results = async {
+                                match (round, &args.relay_challenges, &args.relay_solutions) {
+                                    (Some(round), _, _) => round.await,
+                                    (None, Some(challenges), Some(solutions)) => feed::relay_round(challenges, solutions, &job).await,
+                                    (None, _, _) => Vec::new(),
+                                }
+                            };
+                            tokio::pin!(results);
+                            // Keep reading messages while mining, so a RoundCancel stops the workers right away
+                            let mut receiving = true;
+                            let mut round_cancelled = false;
+                            let results = loop {
+                                tokio::select! {
+                                    results = &mut results => break results,
+                                    msg = message_receiver.recv(), if receiving => match msg {
+                                        Some(ServerMessage::RoundCancelled(cancelled)) if cancelled.is_none_or(|cancelled| cancelled == challenge) => {
+                                            job.cancelled.store(true, Ordering::Relaxed);
+                                            round_cancelled = true;
+                                        },
+                                        Some(msg) => deferred.push_back(msg),
+                                        None => receiving = false,
+                                    },
+                                }
+                            };
+                            for result in results.into_iter().flatten() {
+                                total_hashes += result.total_hashes;
+                                total_nonces += result.total_nonces;
+                                hash_faults += result.faults;
+                                range_exhausted &= result.exhausted;
+                                workers += 1;
+                                if result.hash_secs > 0.0 {
+                                    thread_rates.push(result.total_hashes as f64 / result.hash_secs);
+                                }
+                                if result.best_difficulty > best_difficulty {
+                                    best_difficulty = result.best_difficulty;
+                                    best_nonce = result.best_nonce;
+                                    best_hash = result.best_hash;
                                 }
                             }
 
                             let hash_time = hash_timer.elapsed();
+                            stats.record_round(total_nonces, total_hashes, hash_time, best_difficulty);
+                            if args.max_hours_per_day.is_some() {
+                                daily::record(hash_time);
+                            }
+                            if best_difficulty > 0 && !round_cancelled {
+                                if recent_best.len() == BOOST_WINDOW {
+                                    recent_best.pop_front();
+                                }
+                                recent_best.push_back(best_difficulty);
+                                if args.exit_percentile.is_some() {
+                                    if exit_history.len() == EXIT_WINDOW {
+                                        exit_history.pop_front();
+                                    }
+                                    exit_history.push_back(best_difficulty);
+                                }
+                            }
+                            let range_exhausted = range_exhausted && workers > 0 && hash_time.as_secs() < cutoff;
+                            let steal_pct = cpu_start.zip(steal::sample()).map(|(start, end)| steal::steal_percent(start, end));
 
                             // Stop the spinner after mining is done
-                            pb.finish_and_clear();
-                            println!("✔ Mining complete!");
-                            println!("Processed: {}", total_nonces_checked);
-                            println!("Hash time: {:?}", hash_time);
+                            if let Some(progress) = progress {
+                                progress.finish();
+                            }
+
+                            if !running.load(Ordering::SeqCst) {
+                                session.disconnect();
+                                break;
+                            }
+
+                            // Nothing found for an aborted round can be submitted, ask for the next one instead
+                            if round_cancelled {
+                                say!("Round cancelled by the pool after {}, discarding it.", format::duration(hash_time));
+                                sla.enter(ConnectionState::Idle);
+                                stats.connection(&sla);
+                                if let Err(e) = session.ready().await {
+                                    say!("Failed to send Ready: {}, reconnecting...", e);
+                                    session.disconnect();
+                                    break;
+                                }
+                                jitter.ready_sent();
+                                continue;
+                            }
+
+                            // The round's challenge and cutoff are stale if we slept through it
+                            if let Some(slept) = sleep_detect::resumed_since(&mut events) {
+                                say!("System resumed after sleeping {}s during the round, discarding it and reconnecting...", slept);
+                                session.disconnect();
+                                break;
+                            }
+                            say!("✔ Mining complete!");
+                            say!("Nonces scanned: {}", format::count(total_nonces));
+                            say!("Hashes computed: {}", format::count(total_hashes));
+                            say!("Hash time: {}", format::duration(hash_time));
                             let hash_time_secs = hash_time.as_secs();
                             if hash_time_secs > 0 {
-                                println!("Hashpower: {:?} H/s", total_nonces_checked.saturating_div(hash_time_secs));
+                                say!("Hashpower: {}", format::hashrate(total_hashes as f64 / hash_time.as_secs_f64()));
+                                print_thread_balance(&thread_rates);
+                                let range_size = nonce_range.end.saturating_sub(nonce_range.start);
+                                round_graph.record(total_hashes.saturating_div(hash_time_secs), best_difficulty, range_size);
+                                round_graph.print();
+                                // Ranges are sized in nonces, so capacity and range checks go by the nonce rate
+                                capacity.record(total_nonces.saturating_div(hash_time_secs));
+                                for warning in range_tracker.record(range_size, total_nonces as f64 / hash_time.as_secs_f64(), cutoff) {
+                                    say!("WARNING: {}", warning);
+                                }
+                            }
+                            if let Some(baseline) = baseline.as_mut().filter(|_| hash_time.as_secs_f64() > 0.0) {
+                                baseline.record(total_hashes as f64 / hash_time.as_secs_f64(), best_difficulty);
+                            }
+                            if let Some(steal_pct) = steal_pct {
+                                say!("CPU steal: {:.1}%", steal_pct);
+                                if steal_pct > args.steal_warn_percent {
+                                    say!("WARNING: high CPU steal time, your host is oversubscribed and hashrate is reduced.");
+                                }
+                            }
+
+                            if hash_faults > 0 {
+                                say!("WARNING: {} hashes failed re-verification and were discarded, check RAM and overclock stability.", format::count(hash_faults));
                             }
 
-                            // Send results to the server
-                            let message_type = 2u8; // 1 u8 - BestSolution Message
-                            let best_hash_bin = best_hash.d; // 16 u8
-                            let best_nonce_bin = best_nonce.to_le_bytes(); // 8 u8
+                            if range_exhausted {
+                                let saved = cutoff - hash_time.as_secs();
+                                idle_secs_saved += saved;
+                                say!("Nonce range exhausted {}s before cutoff, submitting early (idle time saved: {}s total).", saved, idle_secs_saved);
+                            }
 
-                            let mut hash_nonce_message = [0; 24];
-                            hash_nonce_message[0..16].copy_from_slice(&best_hash_bin);
-                            hash_nonce_message[16..24].copy_from_slice(&best_nonce_bin);
-                            let signature = key.sign_message(&hash_nonce_message).to_string().as_bytes().to_vec();
+                            let estimate = estimator.submit(best_difficulty);
+                            if let Some(estimate) = estimate {
+                                say!("Estimated earnings: ~{:.11} ORE", estimate);
+                            }
+                            args.earnings.submitted(estimate);
 
-                            let mut bin_data = [0; 57];
-                            bin_data[00..1].copy_from_slice(&message_type.to_le_bytes());
-                            bin_data[01..17].copy_from_slice(&best_hash_bin);
-                            bin_data[17..25].copy_from_slice(&best_nonce_bin);
-                            bin_data[25..57].copy_from_slice(&key.pubkey().to_bytes());
+                            // Every client submitting at the same moment makes a spike at the pool
+                            if args.submit_jitter > 0 {
+                                let left = Duration::from_secs(deadline).saturating_sub(hash_timer.elapsed());
+                                let jitter = Duration::from_millis(args.submit_jitter).min(left / 2);
+                                tokio::time::sleep(jitter.mul_f64(rand::random::<f64>())).await;
+                            }
 
-                            let mut bin_vec = bin_data.to_vec();
-                            bin_vec.extend(signature);
+                            // Servers may penalize a (challenge, nonce) seen twice, e.g. re-sent after a reconnect
+                            let submit_timer = Instant::now();
+                            let submission = if submitted_solutions.insert(challenge, best_nonce) {
+                                let best = BestSolution { hash: best_hash, nonce: best_nonce, hash_started, hash_time, challenge_latency };
+                                session.submit(&best).await
+                            } else {
+                                say!("Nonce {} was already submitted for this challenge, suppressing duplicate submission.", best_nonce);
+                                Submission::Duplicate
+                            };
+                            let submit_ms = (submission != Submission::Duplicate).then(|| submit_timer.elapsed().as_millis() as u64);
+                            match &submission {
+                                Submission::Websocket | Submission::Http => {
+                                    stats.record_submission();
+                                    submission_failures = 0;
+                                    ghost_rounds.submitted(&challenge);
+                                },
+                                Submission::Failed(e) => {
+                                    submission_failures += 1;
+                                    if submission_failures >= SUBMISSION_FAILURE_REPORT_THRESHOLD {
+                                        task_ctx.publish(MinerEvent::ClientError {
+                                            kind: "submission_failed",
+                                            detail: format!("{} consecutive submission failures, last: {}", submission_failures, e),
+                                        });
+                                    }
+                                },
+                                Submission::Duplicate => {},
+                            }
+                            tracing::info!(
+                                target: "round",
+                                wallet = WALLET_TAG.try_with(Clone::clone).ok(),
+                                pool = %base_url,
+                                challenge = %solana_sdk::bs58::encode(challenge).into_string(),
+                                difficulty = best_difficulty,
+                                nonces = total_nonces,
+                                hashes = total_hashes,
+                                hash_secs = hash_time.as_secs_f64(),
+                                hashrate = total_hashes as f64 / hash_time.as_secs_f64().max(f64::EPSILON),
+                                submission = submission.label(),
+                                submit_ms,
+                            );
+                            history::record(HistoryEntry::Round {
+                                at: history::now(),
+                                pool: base_url.clone(),
+                                wallet: key.pubkey().to_string(),
+                                difficulty: best_difficulty,
+                                hashes: total_hashes,
+                                nonces: Some(total_nonces),
+                                hash_secs: hash_time.as_secs_f64(),
+                                rig: rig.clone(),
+                                submit_ms,
+                                range_size: Some(nonce_range.end.saturating_sub(nonce_range.start)),
+                            });
+                            task_ctx.publish(MinerEvent::RoundFinished { difficulty: best_difficulty });
 
-                            {
-                                let mut message_sender = message_sender.lock().await;
-                                let _ = message_sender.send(Message::Binary(bin_vec)).await;
+                            if let Err(e) = session.attest(total_hashes).await {
+                                say!("Failed to send the attestation: {}, reconnecting...", e);
+                                session.disconnect();
+                                break;
+                            }
+                            if let Err(e) = session.report_capacity(capacity.sustained(args.capacity_cap)).await {
+                                say!("Failed to send the capacity report: {}, reconnecting...", e);
+                                session.disconnect();
+                                break;
                             }
 
-                            tokio::time::sleep(Duration::from_secs(5 + args.buffer as u64)).await;
+                            rounds_mined += 1;
+                            if args.limit_reached(rounds_mined, started) {
+                                say!("Mined {} rounds in {}, disconnecting.", rounds_mined, format::duration(started.elapsed()));
+                                limit_reached = true;
+                                running.store(false, Ordering::SeqCst);
+                                session.disconnect();
+                                break;
+                            }
+                            if let Some(earned) = args.goal_reached() {
+                                goal_reached = Some(earned);
+                                running.store(false, Ordering::SeqCst);
+                                session.disconnect();
+                                break;
+                            }
+                            if daily::budget_spent(args.max_hours_per_day) {
+                                daily_budget_spent = true;
+                                session.disconnect();
+                                break;
+                            }
 
-                            let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+                            sla.enter(ConnectionState::Idle);
+                            stats.connection(&sla);
 
-                            let msg = now.to_le_bytes();
-                            let sig = key.sign_message(&msg).to_string().as_bytes().to_vec();
-                            let mut bin_data: Vec<u8> = Vec::new();
-                            bin_data.push(0u8);
-                            bin_data.extend_from_slice(&key.pubkey().to_bytes());
-                            bin_data.extend_from_slice(&msg);
-                            bin_data.extend(sig);
-                            {
-                                let mut message_sender = message_sender.lock().await;
-                                let _ = message_sender.send(Message::Binary(bin_data)).await;
+                            // When the range ran out early, signal readiness right away to get more work
+                            if !range_exhausted {
+                                let timeout = Duration::from_secs(args.result_timeout + args.buffer as u64);
+                                let waited = Instant::now();
+                                let outcome = await_round_result(timeout, &mut message_receiver, &mut deferred, &mut shutdown).await;
+                                tracing::info!(target: "round_result", outcome = ?outcome, waited_ms = waited.elapsed().as_millis() as u64);
+                                match outcome {
+                                    ResultWait::Result => {},
+                                    ResultWait::TimedOut => say!("No result from the pool within {}s, asking for work anyway.", timeout.as_secs()),
+                                    // The pool already sent the next round, there is nothing to ask for
+                                    ResultWait::NextChallenge => continue,
+                                    ResultWait::Closed => {
+                                        session.disconnect();
+                                        break;
+                                    },
+                                }
+                            }
+                            if let Some(max) = args.max_rounds_per_hour {
+                                if quota::until_free(max).is_some() && !wait_for_quota(max, &mut message_receiver, &mut deferred, &mut shutdown).await {
+                                    session.disconnect();
+                                    break;
+                                }
+                            }
+
+                            if let Err(e) = session.ready().await {
+                                say!("Failed to send Ready: {}, reconnecting...", e);
+                                session.disconnect();
+                                break;
+                            }
+                            jitter.ready_sent();
+                        },
+                        ServerMessage::RoundCancelled(_) => {
+                            say!("Pool cancelled a round that had already finished, ignoring.");
+                        },
+                        ServerMessage::Maintenance(window) => {
+                            if maintenance.as_ref() != Some(&window) {
+                                say!("Pool maintenance scheduled from {} to {}, will pause and reconnect afterwards.", window.start, window.end);
+                            }
+                            maintenance = Some(window);
+                        },
+                        ServerMessage::Policy(notice) => {
+                            print_policy_notice(&notice);
+                            running.store(false, Ordering::SeqCst);
+                            break;
+                        },
+                        ServerMessage::MinerEarned(actual) => {
+                            ghost_rounds.result();
+                            history::record(HistoryEntry::Earned {
+                                at: history::now(),
+                                pool: base_url.clone(),
+                                wallet: key.pubkey().to_string(),
+                                ore: actual,
+                                rig: rig.clone(),
+                            });
+                            if let Some(Some(estimate)) = estimator.reconcile(actual) {
+                                say!("Round earnings: {:.11} ORE (estimated {:.11} ORE)", actual, estimate);
+                            }
+                            args.earnings.earned(actual);
+                            if let Some(earned) = args.goal_reached() {
+                                goal_reached = Some(earned);
+                                running.store(false, Ordering::SeqCst);
+                                session.disconnect();
+                                break;
                             }
                         }
                     }
                 }                    
 
-                let _ = receiver_thread.await;
+                if let Some(pinger) = pinger {
+                    pinger.abort();
+                }
+                session.close().await;
+                task_ctx.publish(MinerEvent::Disconnected);
+                if running.load(Ordering::SeqCst) && !daily_budget_spent {
+                    sla.enter(ConnectionState::Reconnecting);
+                    stats.connection(&sla);
+                    // Drops count against the pool, so one that keeps dropping us is eventually abandoned
+                    if !switching_pools {
+                        pools.failed();
+                    }
+                }
             }, 
             Err(e) => {
+                pool_down_since.get_or_insert_with(Instant::now);
+                if resuming.is_some() {
+                    // The token was taken for this attempt, the next one does a full handshake
+                    say!("Session resumption failed, falling back to full authentication.");
+                }
                 match e {
                     tokio_tungstenite::tungstenite::Error::Http(e) => {
-                        if let Some(body) = e.body() {
-                            println!("Error: {:?}", String::from_utf8(body.to_vec()));
+                        let notice = e.body().as_ref().and_then(|body| PolicyNotice::parse("websocket", &String::from_utf8_lossy(body)).ok());
+                        if let Some(notice) = notice {
+                            print_policy_notice(&notice);
+                            break;
+                        } else if let Some(body) = e.body() {
+                            say!("Error: {:?}", String::from_utf8(body.to_vec()));
                         } else {
-                            println!("Http Error: {:?}", e);
+                            say!("Http Error: {:?}", e);
+                        }
+                        // Rejected authentication is most often a skewed clock, measure it again
+                        if e.status().as_u16() == 401 {
+                            let skew = clock::check(&client, &http_prefix, &base_url, &args.time_source).await;
+                            clock::report(&skew, &base_url, &args.time_source, args.auto_clock_offset);
                         }
                     }, 
                     _ => {
-                        println!("Error: {:?}", e);
+                        say!("Error: {:?}", e);
+                    }
+                }
+                tokio::time::sleep(pools.failed()).await;
+            }
+        }
+    }
+
+    if let Ok(tag) = WALLET_TAG.try_with(Clone::clone) {
+        println!("Session summary for [{}]:", tag);
+    }
+    if let (Some(goal), Some(earned)) = (args.stop_after_earned, goal_reached) {
+        let basis = match args.goal_basis {
+            GoalBasis::Confirmed => "confirmed",
+            GoalBasis::Estimated => "confirmed and estimated",
+        };
+        let text = format!(
+            "Earnings goal of {:.11} ORE reached for {} on {}: {:.11} ORE {} in {}.",
+            goal,
+            key.pubkey(),
+            history::rig_name(),
+            earned,
+            basis,
+            format::duration(started.elapsed())
+        );
+        say!("{}", text);
+        if let Some(webhook) = &settings.report.webhook {
+            if let Err(e) = report::send(&task_ctx, webhook, &text).await {
+                say!("Failed to announce the earnings goal: {}", e);
+            }
+        }
+    }
+    sla.print_summary();
+    jitter.print_summary();
+    ghost_rounds.print_summary();
+    unexpected_frames.print_summary();
+    recent_broadcasts.print_summary();
+
+    power_hooks.run(PowerEvent::Stop).await;
+
+    // Leave Ctrl+C to whoever runs next, e.g. the next pool in a rotation
+    ctrl_c.abort();
+    for task in background {
+        task.abort();
+    }
+    if let Some(thermal) = thermal {
+        thermal.abort();
+        args.control.stop_regulating();
+    }
+    args.stats.detach(&stats);
+    limit_reached
+}
+
+/// Frames the client did not know how to handle, counted by kind across reconnects.
+#[derive(Default)]
+pub struct UnexpectedFrames {
+    counts: std::sync::Mutex<BTreeMap<String, u64>>,
+}
+
+impl UnexpectedFrames {
+    /// Counts a frame and returns how many of its kind have been seen this session.
+    fn count(&self, kind: String) -> u64 {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(kind).or_default();
+        *count += 1;
+        *count
+    }
+
+    pub fn print_summary(&self) {
+        let counts = self.counts.lock().unwrap();
+        if counts.is_empty() {
+            return;
+        }
+        println!("Unexpected frames:");
+        for (kind, count) in counts.iter() {
+            println!("  {}: {}", kind, count);
+        }
+    }
+}
+
+/// Recently submitted solutions, kept across reconnects.
+#[derive(Default)]
+struct SubmissionCache {
+    entries: Vec<([u8; 32], u64, Instant)>,
+}
+
+impl SubmissionCache {
+    /// Remembers a submission, returns false if it was already submitted recently.
+    fn insert(&mut self, challenge: [u8; 32], nonce: u64) -> bool {
+        self.entries.retain(|(_, _, at)| at.elapsed() < Duration::from_secs(SUBMISSION_CACHE_TTL_SECS));
+        if self.entries.iter().any(|(c, n, _)| *c == challenge && *n == nonce) {
+            return false;
+        }
+        self.entries.push((challenge, nonce, Instant::now()));
+        true
+    }
+}
+
+/// Parameters of one round, shared by all worker threads.
+#[derive(Clone)]
+pub struct RoundJob {
+    pub challenge: [u8; 32],
+    pub nonce_range: Range<u64>,
+    pub cutoff: u64,
+    pub hash_timer: Instant,
+    pub cursor: Arc<AtomicU64>,
+    pub paranoid: bool,
+    pub paranoid_min_difficulty: u32,
+    /// Set when the round is abandoned, workers stop at their next chunk.
+    pub cancelled: Arc<AtomicBool>,
+    pub solver: SolverSettings,
+    /// Workers hold at their next chunk while this is paused.
+    pub control: Arc<MinerControl>,
+    /// Best difficulty any worker has found so far, for decisions made while the round runs.
+    pub best_difficulty: Arc<AtomicU32>,
+    /// Replaces the difficulty 8 workers need before stopping at the cutoff, see --exit-percentile.
+    pub exit_target: Option<ExitTarget>,
+}
+
+/// Round-wide difficulty to reach before stopping at the cutoff, tried for at most `grace`
+/// seconds past it.
+#[derive(Debug, Clone, Copy)]
+pub struct ExitTarget {
+    pub difficulty: u32,
+    pub grace: u64,
+}
+
+/// The `percentile`th of the recent round difficulties, by nearest rank, once there are enough.
+fn percentile_target(recent: &VecDeque<u32>, percentile: u8, grace: u64) -> Option<ExitTarget> {
+    if recent.len() < EXIT_MIN_ROUNDS {
+        return None;
+    }
+    let mut sorted: Vec<u32> = recent.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = (percentile as usize * sorted.len()).div_ceil(100).max(1);
+    Some(ExitTarget { difficulty: sorted[rank - 1].max(MIN_EXIT_DIFFICULTY), grace })
+}
+
+/// Best hash found by a single worker thread in a round.
+pub struct WorkerResult {
+    pub best_nonce: u64,
+    pub best_difficulty: u32,
+    pub best_hash: drillx_2::Hash,
+    /// Hashes computed, a nonce yields as many as its equix solutions.
+    pub total_hashes: u64,
+    /// Nonces scanned, up to the one the worker stopped at.
+    pub total_nonces: u64,
+    pub exhausted: bool,
+    pub faults: u64,
+    /// How long this worker hashed, for its share of the round's hashpower.
+    pub hash_secs: f64,
+}
+
+/// Hashes chunks of the round's nonce range until it is exhausted or the cutoff passes.
+/// Returns None if mining was interrupted.
+pub fn solve(job: &RoundJob, memories: &mut [equix::SolverMemory], running: &AtomicBool) -> Option<WorkerResult> {
+    let mut best_nonce = job.nonce_range.start;
+    let mut best_difficulty = 0;
+    let mut best_hash = drillx_2::Hash::default();
+    let mut total_hashes: u64 = 0;
+    let mut total_nonces: u64 = 0;
+    let mut exhausted = false;
+    let mut faults: u64 = 0;
+    let mut chunk_size = INITIAL_CHUNK_SIZE;
+    let worker_timer = Instant::now();
+    let _profile = profiler::enter(Phase::Sync);
+    let _working = job.control.working();
+    // Whether the cutoff passed with a good enough best, `best_difficulty` being this thread's
+    let past_cutoff = |best_difficulty: u32| {
+        let elapsed = job.hash_timer.elapsed().as_secs();
+        let done = match job.exit_target {
+            Some(target) if elapsed < job.cutoff + target.grace => job.best_difficulty.load(Ordering::Relaxed).max(best_difficulty) >= target.difficulty,
+            _ => best_difficulty >= MIN_EXIT_DIFFICULTY,
+        };
+        elapsed >= job.cutoff && done
+    };
+
+    'mining: loop {
+        // Check if Ctrl+C was pressed or the round was cancelled
+        if !running.load(Ordering::SeqCst) || job.cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        // Paused from the control socket: hold until resumed, submitting what we have if the cutoff passes
+        if !job.control.hold(|| running.load(Ordering::SeqCst) && !job.cancelled.load(Ordering::Relaxed) && job.hash_timer.elapsed().as_secs() < job.cutoff) {
+            if !running.load(Ordering::SeqCst) || job.cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            break;
+        }
+        profiler::set(Phase::Sync);
+
+        // Chunks can be smaller than the nonce check interval below, so check before each one too
+        if past_cutoff(best_difficulty) {
+            break;
+        }
+        let chunk_start = job.cursor.fetch_add(chunk_size, Ordering::Relaxed);
+        // Exit if processed nonce range
+        if chunk_start >= job.nonce_range.end {
+            exhausted = true;
+            break;
+        }
+        let chunk_end = chunk_start.saturating_add(chunk_size).min(job.nonce_range.end);
+        let chunk_timer = Instant::now();
+        profiler::set(Phase::Hash);
+
+        for nonce in chunk_start..chunk_end {
+            total_nonces += 1;
+            // Rotate through the solver memories every `interleave` nonces
+            let memory = &mut memories[((nonce / job.solver.interleave.max(1)) % memories.len() as u64) as usize];
+            // Create hash
+            for hx in drillx_2::get_hashes_with_memory(memory, &job.challenge, &nonce.to_le_bytes()) {
+                total_hashes += 1;
+                let difficulty = hx.difficulty();
+                if difficulty.gt(&best_difficulty) {
+                    if job.paranoid && difficulty >= job.paranoid_min_difficulty {
+                        profiler::set(Phase::Verify);
+                        let valid = verify_hash(&job.challenge, nonce, &hx);
+                        profiler::set(Phase::Hash);
+                        if !valid {
+                            faults += 1;
+                            continue;
+                        }
                     }
+                    best_nonce = nonce;
+                    best_difficulty = difficulty;
+                    best_hash = hx;
+                    job.best_difficulty.fetch_max(difficulty, Ordering::Relaxed);
+                }
+            }
+
+            if nonce % 100 == 0 && past_cutoff(best_difficulty) {
+                break 'mining;
+            }
+        }
+
+        // Size the next chunk to take about CHUNK_TARGET_SECS at this thread's
+        // speed, shrinking towards the cutoff so threads finish together
+        let chunk_secs = chunk_timer.elapsed().as_secs_f64();
+        if chunk_secs > 0.0 {
+            let rate = (chunk_end - chunk_start) as f64 / chunk_secs;
+            let remaining = (job.cutoff as f64 - job.hash_timer.elapsed().as_secs_f64()).max(0.0);
+            let target = CHUNK_TARGET_SECS.min(remaining);
+            chunk_size = ((rate * target) as u64).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+        }
+
+        // Rest between chunks while thermal feedback holds the clock steady
+        if job.control.idle_permille() > 0 {
+            std::thread::sleep(job.control.rest_after(chunk_timer.elapsed()));
+        }
+    }
+
+    Some(WorkerResult {
+        best_nonce,
+        best_difficulty,
+        best_hash,
+        total_hashes,
+        total_nonces,
+        exhausted,
+        faults,
+        hash_secs: worker_timer.elapsed().as_secs_f64(),
+    })
+}
+
+/// Prints the spread of per-thread hashpower, to spot threads starved by other load or
+/// sharing a core.
+fn print_thread_balance(rates: &[f64]) {
+    if rates.len() < 2 {
+        return;
+    }
+    let min = rates.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = rates.iter().copied().fold(0.0, f64::max);
+    let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+    println!(
+        "Per-thread hashpower: min {}, mean {}, max {} across {} threads (slowest at {:.0}% of fastest)",
+        format::hashrate(min),
+        format::hashrate(mean),
+        format::hashrate(max),
+        rates.len(),
+        if max > 0.0 { min / max * 100.0 } else { 100.0 }
+    );
+}
+
+/// Allocates a worker's solver memories, at least one.
+pub fn solver_memories(solver: &SolverSettings) -> Vec<equix::SolverMemory> {
+    let _profile = profiler::enter(Phase::Setup);
+    (0..solver.memories.max(1)).map(|_| equix::SolverMemory::new()).collect()
+}
+
+/// Cores kept out of regular mining with --reserve-cores, and the best difficulty below which
+/// the last seconds of a round enlist them anyway (--boost-finish).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reserve {
+    pub cores: u32,
+    pub boost_below: Option<f64>,
+}
+
+/// Splits the cores into those workers are pinned to and the last `reserved` ones. At least
+/// one core is always left for mining.
+pub fn split_cores(reserved: u32) -> (Vec<core_affinity::CoreId>, Vec<core_affinity::CoreId>) {
+    let mut cores = core_affinity::get_core_ids().unwrap();
+    let mining = cores.len().saturating_sub(reserved as usize).max(1);
+    let reserved = cores.split_off(mining.min(cores.len()));
+    (cores, reserved)
+}
+
+/// Spawns `threads` pinned worker threads for a single round. With more threads than cores,
+/// cores are reused in order; all workers pull from the same nonce cursor either way.
+fn spawn_workers(job: RoundJob, threads: u32, reserved: u32, running: Arc<AtomicBool>) -> Vec<std::thread::JoinHandle<Option<WorkerResult>>> {
+    let (core_ids, _) = split_cores(reserved);
+    core_ids
+        .into_iter()
+        .cycle()
+        .take(threads.max(1) as usize)
+        .map(|i| {
+            let running = running.clone(); // Capture running in thread
+            let job = job.clone();
+            std::thread::spawn({
+                let mut memories = solver_memories(&job.solver);
+                move || {
+                    let _ = core_affinity::set_for_current(i);
+
+                    solve(&job, &mut memories, &running)
                 }
-                tokio::time::sleep(Duration::from_secs(3)).await;
+            })
+        })
+        .collect()
+}
+
+/// Waits until shortly before the cutoff and, if the round's best difficulty is still below
+/// `boost_below`, hashes the rest of the round on the reserved cores too. The workers end with
+/// the round, so the cores are free again for the next one.
+fn spawn_booster(
+    job: RoundJob,
+    reserved: Vec<core_affinity::CoreId>,
+    boost_below: f64,
+    finished: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+) -> std::thread::JoinHandle<Vec<Option<WorkerResult>>> {
+    let tag = WALLET_TAG.try_with(|tag| format!("[{}] ", tag)).unwrap_or_default();
+    std::thread::spawn(move || {
+        let boost_at = Duration::from_secs(job.cutoff.saturating_sub(BOOST_SECS));
+        let stopped = || finished.load(Ordering::SeqCst) || job.cancelled.load(Ordering::Relaxed) || !running.load(Ordering::SeqCst);
+        while job.hash_timer.elapsed() < boost_at {
+            if stopped() {
+                return Vec::new();
             }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let best = job.best_difficulty.load(Ordering::Relaxed);
+        if stopped() || best as f64 >= boost_below || job.cursor.load(Ordering::Relaxed) >= job.nonce_range.end {
+            return Vec::new();
         }
+        println!(
+            "{}Best difficulty {} trails the recent average of {:.1}, hashing on {} reserved cores until the cutoff.",
+            tag,
+            best,
+            boost_below,
+            reserved.len()
+        );
+        let handles: Vec<_> = reserved
+            .into_iter()
+            .map(|core| {
+                let job = job.clone();
+                let running = running.clone();
+                std::thread::spawn(move || {
+                    let _ = core_affinity::set_for_current(core);
+                    let mut memories = solver_memories(&job.solver);
+                    solve(&job, &mut memories, &running)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().ok().flatten()).collect()
+    })
+}
+
+/// Cancels a round when dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
     }
 }
 
-fn process_message(msg: Message, message_channel: UnboundedSender<ServerMessage>) -> ControlFlow<(), ()> {
+/// Starts a round right away and returns a future resolving to each worker's result.
+/// The future is cancellation safe: dropping it stops the workers at their next chunk
+/// boundary instead of leaving them hashing in the background.
+pub fn mine_round(
+    job: RoundJob,
+    threads: u32,
+    sprint_pool: Option<&SprintPool>,
+    reserve: Reserve,
+    running: &Arc<AtomicBool>,
+) -> impl Future<Output = Vec<Option<WorkerResult>>> {
+    let cancel = CancelOnDrop(job.cancelled.clone());
+    let finished = Arc::new(AtomicBool::new(false));
+    let booster = reserve.boost_below.and_then(|boost_below| {
+        let (_, reserved) = split_cores(reserve.cores);
+        (!reserved.is_empty()).then(|| spawn_booster(job.clone(), reserved, boost_below, finished.clone(), running.clone()))
+    });
+    let pending = match sprint_pool {
+        Some(pool) => PendingRound::Sprint(pool.dispatch(&job, threads)),
+        None => PendingRound::Spawned(spawn_workers(job, threads, reserve.cores, running.clone())),
+    };
+
+    async move {
+        let _cancel = cancel;
+        // Join on a blocking thread so background tasks keep running meanwhile
+        tokio::task::spawn_blocking(move || {
+            let mut results = pending.wait();
+            finished.store(true, Ordering::SeqCst);
+            if let Some(booster) = booster {
+                results.extend(booster.join().unwrap_or_default());
+            }
+            results
+        })
+        .await
+        .unwrap_or_default()
+    }
+}
+
+/// Workers of a round in flight, either spawned for it or running on the sprint pool.
+enum PendingRound {
+    Spawned(Vec<std::thread::JoinHandle<Option<WorkerResult>>>),
+    Sprint(std::sync::mpsc::Receiver<Option<WorkerResult>>),
+}
+
+impl PendingRound {
+    /// Blocks until every worker of the round has finished.
+    fn wait(self) -> Vec<Option<WorkerResult>> {
+        match self {
+            PendingRound::Spawned(handles) => handles.into_iter().map(|h| h.join().ok().flatten()).collect(),
+            PendingRound::Sprint(results) => results.iter().collect(),
+        }
+    }
+}
+
+/// Asks the pool for a scheduled maintenance window. Servers without the endpoint return None.
+async fn fetch_maintenance(client: &reqwest::Client, http_prefix: &str, base_url: &str) -> Option<Range<u64>> {
+    match schema::get::<MaintenanceWindow>(client, http_prefix, base_url, "maintenance", "").await {
+        Ok(MaintenanceWindow(window)) => window,
+        Err(e) if e.is_not_found() => None,
+        Err(e) => {
+            println!("Ignoring maintenance schedule: {}", e);
+            None
+        },
+    }
+}
+
+/// How waiting for the pool's result of a submitted round ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResultWait {
+    /// The pool reported what the round earned.
+    Result,
+    /// The next challenge came first.
+    NextChallenge,
+    /// Neither arrived within the timeout.
+    TimedOut,
+    /// The connection closed or a shutdown was requested.
+    Closed,
+}
+
+/// Waits after a submission until the pool reports the round's result or moves on to the
+/// next challenge. Messages are deferred so the loop handles them as usual afterwards.
+async fn await_round_result(
+    timeout: Duration,
+    message_receiver: &mut tokio::sync::mpsc::UnboundedReceiver<ServerMessage>,
+    deferred: &mut VecDeque<ServerMessage>,
+    shutdown: &mut tokio::sync::watch::Receiver<bool>,
+) -> ResultWait {
+    // A challenge that arrived while mining is already the next round
+    if deferred.iter().any(|msg| matches!(msg, ServerMessage::StartMining(..))) {
+        return ResultWait::NextChallenge;
+    }
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => return ResultWait::TimedOut,
+            _ = shutdown.changed() => return ResultWait::Closed,
+            msg = message_receiver.recv() => match msg {
+                Some(msg @ ServerMessage::MinerEarned(_)) => {
+                    deferred.push_back(msg);
+                    return ResultWait::Result;
+                },
+                Some(msg @ ServerMessage::StartMining(..)) => {
+                    deferred.push_back(msg);
+                    return ResultWait::NextChallenge;
+                },
+                Some(msg) => deferred.push_back(msg),
+                None => return ResultWait::Closed,
+            },
+        }
+    }
+}
+
+/// Logs a challenge passed up because the hourly round quota is used up.
+fn skip_for_quota(max: u32, challenge: &[u8; 32]) {
+    let skipped = quota::record_skipped();
+    let challenge = solana_sdk::bs58::encode(challenge).into_string();
+    say!("Skipped round {}: {} of {} rounds this hour ({} skipped so far).", challenge, quota::used(), max, skipped);
+    tracing::info!(target: "round_skipped", reason = "max_rounds_per_hour", challenge = %challenge, skipped);
+}
+
+/// Holds back Ready until another round fits in the hourly quota. Challenges the pool sends
+/// meanwhile are skipped, other messages are deferred. Returns false on shutdown or when the
+/// connection closed.
+async fn wait_for_quota(
+    max: u32,
+    message_receiver: &mut tokio::sync::mpsc::UnboundedReceiver<ServerMessage>,
+    deferred: &mut VecDeque<ServerMessage>,
+    shutdown: &mut tokio::sync::watch::Receiver<bool>,
+) -> bool {
+    while let Some(wait) = quota::until_free(max) {
+        say!("Used {} of {} rounds this hour, asking for work again in {}.", quota::used(), max, format::duration(wait));
+        let resume_at = tokio::time::Instant::now() + wait;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(resume_at) => break,
+                _ = shutdown.changed() => return false,
+                msg = message_receiver.recv() => match msg {
+                    Some(ServerMessage::StartMining(challenge, ..)) => skip_for_quota(max, &challenge),
+                    Some(msg) => deferred.push_back(msg),
+                    None => return false,
+                },
+            }
+        }
+    }
+    true
+}
+
+/// Opens the websocket's TCP connection with keepalive probes enabled.
+async fn connect_tcp(host: &str, port: u16, keepalive_secs: u64) -> std::io::Result<TcpStream> {
+    let stream = network::within(network::connect_timeout(), "connecting", TcpStream::connect((host, port))).await?;
+    if keepalive_secs > 0 {
+        let keepalive = TcpKeepalive::new()
+            .with_time(Duration::from_secs(keepalive_secs))
+            .with_interval(Duration::from_secs(keepalive_secs));
+        SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
+    }
+    Ok(stream)
+}
+
+/// Recomputes a hash through the verifier path (equix verify + keccak) instead of the solver,
+/// so a faulty core or bad memory can't produce the same wrong answer twice.
+fn verify_hash(challenge: &[u8; 32], nonce: u64, hx: &drillx_2::Hash) -> bool {
+    let solution = drillx_2::Solution::new(hx.d, nonce.to_le_bytes());
+    solution.is_valid(challenge) && solution.to_hash().h == hx.h
+}
+
+/// Shows why the pool disconnected us, in a way that stands out from the usual reconnect noise.
+fn print_policy_notice(notice: &PolicyNotice) {
+    let title = match notice.action {
+        PolicyAction::Kick => "DISCONNECTED BY THE POOL",
+        PolicyAction::Ban => "BANNED BY THE POOL",
+    };
+    say!("{}", "=".repeat(60).red());
+    say!("{}", title.red().bold());
+    say!("  Reason:  {}", notice.reason);
+    if let Some(until) = notice.until.and_then(|until| chrono::DateTime::from_timestamp(until as i64, 0)) {
+        say!("  Until:   {}", until.format("%Y-%m-%d %H:%M UTC"));
+    }
+    say!("  Contact: {}", notice.contact.as_deref().unwrap_or("the pool operator"));
+    say!("{}", "=".repeat(60).red());
+    say!("Not reconnecting automatically. Resolve this with the operator before mining at this pool again.");
+}
+
+pub fn process_message(
+    msg: Message,
+    message_channel: UnboundedSender<ServerMessage>,
+    quirks: &Quirks,
+    task_ctx: &TaskContext,
+    unexpected: &UnexpectedFrames,
+    broadcasts: &RecentBroadcasts,
+    dump_unknown: bool,
+) -> ControlFlow<(), ()> {
     match msg {
         Message::Text(t)=>{
-            println!("{}",t);
+            say!("{}",t);
+            broadcasts.push(&t);
+            if let Some(earned) = protocol::parse_miner_earned(&t) {
+                let _ = message_channel.send(ServerMessage::MinerEarned(earned));
+            }
+            if let Some(window) = protocol::parse_maintenance(&t) {
+                let _ = message_channel.send(ServerMessage::Maintenance(window));
+            }
+            if let Ok(notice) = PolicyNotice::parse("websocket", &t) {
+                let _ = message_channel.send(ServerMessage::Policy(notice));
+                return ControlFlow::Break(());
+            }
         },
         Message::Binary(b) => {
-            let message_type = b[0];
+            let Some(&message_type) = b.first() else {
+                let count = unexpected.count("empty binary frame".to_string());
+                println!("Received an empty binary frame ({} this session)", count);
+                return ControlFlow::Continue(());
+            };
             match message_type {
                 0 => {
-                    if b.len() < 49 {
-                        println!("Invalid data for Message StartMining");
-                    } else {
-                        let mut hash_bytes = [0u8; 32];
-                        // extract 256 bytes (32 u8's) from data for hash
-                        let mut b_index = 1;
-                        for i in 0..32 {
-                            hash_bytes[i] = b[i + b_index];
-                        }
-                        b_index += 32;
-
-                        // extract 64 bytes (8 u8's)
-                        let mut cutoff_bytes = [0u8; 8];
-                        for i in 0..8 {
-                            cutoff_bytes[i] = b[i + b_index];
-                        }
-                        b_index += 8;
-                        let cutoff = u64::from_le_bytes(cutoff_bytes);
-
-                        let mut nonce_start_bytes = [0u8; 8];
-                        for i in 0..8 {
-                            nonce_start_bytes[i] = b[i + b_index];
-                        }
-                        b_index += 8;
-                        let nonce_start = u64::from_le_bytes(nonce_start_bytes);
-
-                        let mut nonce_end_bytes = [0u8; 8];
-                        for i in 0..8 {
-                            nonce_end_bytes[i] = b[i + b_index];
-                        }
-                        let nonce_end = u64::from_le_bytes(nonce_end_bytes);
-
-                        let msg = ServerMessage::StartMining(hash_bytes, nonce_start..nonce_end, cutoff);
+                    if let Some((challenge, nonce_range, cutoff)) = protocol::parse_start_mining(&b, quirks) {
+                        let thread_hint = protocol::parse_thread_hint(&b, quirks);
+                        let msg = ServerMessage::StartMining(challenge, nonce_range, cutoff, thread_hint, Instant::now());
 
                         let _ = message_channel.send(msg);
+                    } else {
+                        println!("Invalid data for Message StartMining");
+                        task_ctx.publish(MinerEvent::ClientError {
+                            kind: "invalid_start_mining",
+                            detail: format!("{} byte StartMining frame", b.len()),
+                        });
                     }
                 },
-                _ => {
-                    println!("Failed to parse server message type");
+                1 => {
+                    if let Some(challenge) = protocol::parse_round_cancel(&b) {
+                        let _ = message_channel.send(ServerMessage::RoundCancelled(challenge));
+                    } else {
+                        println!("Invalid data for Message RoundCancel");
+                        task_ctx.publish(MinerEvent::ClientError {
+                            kind: "invalid_round_cancel",
+                            detail: format!("{} byte RoundCancel frame", b.len()),
+                        });
+                    }
+                },
+                other => {
+                    let count = unexpected.count(format!("binary message type {}", other));
+                    println!("Unknown server message type {} in a {} byte frame ({} this session)", other, b.len(), count);
+                    if dump_unknown {
+                        println!("{}", protocol::hex_dump(&b));
+                    }
+                    task_ctx.publish(MinerEvent::ClientError {
+                        kind: "unknown_message_type",
+                        detail: format!("message type {}", other),
+                    });
                 }
             }
         },
         Message::Ping(_) => {}, 
         Message::Pong(_) => {}, 
         Message::Close(v) => {
-            println!("Got Close: {:?}", v);
+            match v.as_ref().and_then(|frame| PolicyNotice::parse("websocket", &frame.reason).ok()) {
+                Some(notice) => {
+                    let _ = message_channel.send(ServerMessage::Policy(notice));
+                },
+                None => println!("Got Close: {:?}", v),
+            }
             return ControlFlow::Break(());
         }, 
-        _ => {}
+        Message::Frame(frame) => {
+            let opcode = frame.header().opcode;
+            let count = unexpected.count(format!("raw frame {:?}", opcode));
+            println!("Received an unexpected raw {:?} frame of {} bytes ({} this session)", opcode, frame.payload().len(), count);
+            if dump_unknown {
+                println!("{}", protocol::hex_dump(frame.payload()));
+            }
+        },
     }
 
     ControlFlow::Continue(())
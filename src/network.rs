@@ -0,0 +1,51 @@
+use std::{future::Future, io, sync::OnceLock, time::Duration};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::settings::{self, NetworkSettings};
+
+static TIMEOUTS: OnceLock<NetworkSettings> = OnceLock::new();
+
+/// The `[network]` timeouts, read from the settings file on first use.
+fn timeouts() -> NetworkSettings {
+    *TIMEOUTS.get_or_init(|| settings::load().network)
+}
+
+pub fn connect_timeout() -> Duration {
+    Duration::from_secs(timeouts().connect_timeout.max(1))
+}
+
+pub fn read_timeout() -> Duration {
+    Duration::from_secs(timeouts().read_timeout.max(1))
+}
+
+pub fn write_timeout() -> Duration {
+    Duration::from_secs(timeouts().write_timeout.max(1))
+}
+
+/// HTTP client for pool, webhook and signer requests with the configured timeouts.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout())
+        .timeout(read_timeout())
+        .build()
+        .unwrap_or_default()
+}
+
+/// RPC client whose requests give up after the read timeout.
+pub fn rpc(url: String) -> RpcClient {
+    RpcClient::new_with_timeout(url, read_timeout())
+}
+
+pub fn rpc_with_commitment(url: String, commitment: CommitmentConfig) -> RpcClient {
+    RpcClient::new_with_timeout_and_commitment(url, read_timeout(), commitment)
+}
+
+/// Runs `operation`, failing with a timed out I/O error once `limit` has passed.
+pub async fn within<T, E: From<io::Error>>(limit: Duration, what: &str, operation: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+    match tokio::time::timeout(limit, operation).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, format!("{} timed out after {}s", what, limit.as_secs())).into()),
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::history;
+use crate::settings::{self, NotificationSettings};
+use crate::tasks::{MinerEvent, TaskContext};
+
+/// Minimum time between two disconnect notifications, a flapping pool would bury the desktop.
+const DISCONNECT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Shows a native desktop notification. Failures, e.g. no notification daemon on a headless
+/// rig, are ignored. Runs on its own thread since the platform APIs block.
+pub fn show(summary: &str, body: &str) {
+    let (summary, body) = (summary.to_string(), body.to_string());
+    std::thread::spawn(move || {
+        let _ = notify_rust::Notification::new()
+            .appname("ore-hq-client")
+            .summary(&summary)
+            .body(&body)
+            .show();
+    });
+}
+
+/// Notifies of a confirmed claim, when enabled in the settings.
+pub fn claim_confirmed(ore: f64) {
+    let settings = settings::load().notifications;
+    if settings.enabled && settings.claims {
+        show("Claim confirmed", &format!("{:.11} ORE claimed", ore));
+    }
+}
+
+/// Turns mining events into desktop notifications.
+pub fn spawn(ctx: TaskContext, settings: NotificationSettings) -> Option<JoinHandle<()>> {
+    if !settings.enabled {
+        return None;
+    }
+    let mut events = ctx.subscribe();
+    let mut best = history::best_difficulty(&ctx.pubkey.to_string());
+    Some(tokio::spawn(async move {
+        let mut last_disconnect: Option<Instant> = None;
+        loop {
+            match events.recv().await {
+                Ok(MinerEvent::RoundFinished { difficulty }) if difficulty > best => {
+                    // The first round of a wallet without history is no achievement
+                    if settings.personal_best && best > 0 {
+                        show("New personal best", &format!("Difficulty {}, up from {}", difficulty, best));
+                    }
+                    best = difficulty;
+                },
+                Ok(MinerEvent::Disconnected) if settings.disconnects => {
+                    if last_disconnect.is_none_or(|at| at.elapsed() >= DISCONNECT_INTERVAL) {
+                        last_disconnect = Some(Instant::now());
+                        show("Disconnected from the pool", &format!("Lost the connection to {}, reconnecting", ctx.base_url));
+                    }
+                },
+                Ok(_) => {},
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }))
+}
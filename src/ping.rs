@@ -0,0 +1,239 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use base64::prelude::*;
+use clap::{Parser, Subcommand};
+use futures_util::{SinkExt, StreamExt};
+use solana_sdk::signature::{Keypair, Signer};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_tungstenite::{
+    client_async,
+    tungstenite::{client::IntoClientRequest, Error as WsError, Message},
+};
+
+use crate::endpoint;
+use crate::network;
+use crate::schema::{self, ServerTime};
+
+#[derive(Debug, Subcommand)]
+pub enum PoolCommands {
+    #[command(about = "Measure DNS, TCP, TLS, HTTP and websocket round trips to one or more pools.")]
+    Ping(PingArgs),
+}
+
+#[derive(Debug, Parser)]
+pub struct PingArgs {
+    #[arg(
+        value_name = "URL",
+        help = "Pools to measure. Defaults to the --url values"
+    )]
+    pub urls: Vec<String>,
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value = "3",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Measurements per pool, the table shows the median"
+    )]
+    pub count: u32,
+}
+
+/// How long a step took, or why it failed. None when it was skipped.
+type Measured = Option<Result<Duration, String>>;
+
+/// One measurement of every step of connecting to a pool. Steps after a failed one are skipped.
+#[derive(Debug, Default)]
+struct Sample {
+    dns: Measured,
+    tcp: Measured,
+    tls: Measured,
+    http: Measured,
+    upgrade: Measured,
+    ws_ping: Measured,
+}
+
+async fn timed<T, E: ToString>(fut: impl std::future::Future<Output = Result<T, E>>) -> (Result<Duration, String>, Option<T>) {
+    let started = Instant::now();
+    match fut.await {
+        Ok(value) => (Ok(started.elapsed()), Some(value)),
+        Err(e) => (Err(e.to_string()), None),
+    }
+}
+
+/// Times the websocket upgrade, then a ping frame until its pong. The handshake is signed with
+/// a throwaway key, so pools that only admit signed-up miners refuse it after answering.
+async fn measure_websocket<S>(stream: S, ws_url: &str, timestamp: u64, sample: &mut Sample)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = match ws_url.into_client_request() {
+        Ok(request) => request,
+        Err(e) => {
+            sample.upgrade = Some(Err(e.to_string()));
+            return;
+        },
+    };
+    let key = Keypair::new();
+    let sig = key.sign_message(&timestamp.to_le_bytes());
+    let auth = BASE64_STANDARD.encode(format!("{}:{}", key.pubkey(), sig));
+    if let Ok(value) = format!("Basic {}", auth).parse() {
+        request.headers_mut().insert("Authorization", value);
+    }
+
+    let started = Instant::now();
+    let upgraded = network::within(network::read_timeout(), "websocket handshake", client_async(request, stream)).await;
+    let mut ws_stream = match upgraded {
+        Ok((ws_stream, _)) => {
+            sample.upgrade = Some(Ok(started.elapsed()));
+            ws_stream
+        },
+        Err(WsError::Http(response)) => {
+            sample.upgrade = Some(Err(format!("refused, HTTP {} after {} ms", response.status().as_u16(), started.elapsed().as_millis())));
+            return;
+        },
+        Err(e) => {
+            sample.upgrade = Some(Err(e.to_string()));
+            return;
+        },
+    };
+
+    let started = Instant::now();
+    let pong = network::within(network::read_timeout(), "websocket ping", async {
+        ws_stream.send(Message::Ping(b"ore-hq-client".to_vec())).await.map_err(std::io::Error::other)?;
+        while let Some(message) = ws_stream.next().await {
+            if let Message::Pong(_) = message.map_err(std::io::Error::other)? {
+                return Ok(started.elapsed());
+            }
+        }
+        Err(std::io::Error::other("connection closed before the pong"))
+    })
+    .await;
+    sample.ws_ping = Some(pong.map_err(|e| e.to_string()));
+    let _ = ws_stream.close(None).await;
+}
+
+async fn measure(client: &reqwest::Client, base_url: &str, unsecure: bool) -> Sample {
+    let mut sample = Sample::default();
+    let ws_url = endpoint::ws(unsecure, base_url, "timestamp=0");
+    let Some((host, port)) = url::Url::parse(&ws_url).ok().and_then(|url| Some((url.host_str()?.to_string(), url.port_or_known_default()?))) else {
+        sample.dns = Some(Err("invalid pool URL".to_string()));
+        return sample;
+    };
+
+    let (dns, addrs) = timed(network::within(network::connect_timeout(), "resolving", tokio::net::lookup_host((host.as_str(), port)))).await;
+    sample.dns = Some(dns);
+    let Some(addr) = addrs.and_then(|mut addrs| addrs.next()) else {
+        return sample;
+    };
+
+    let url_prefix = if unsecure { "http" } else { "https" };
+    let started = Instant::now();
+    let timestamp = schema::get::<ServerTime>(client, url_prefix, base_url, "timestamp", "").await;
+    sample.http = Some(timestamp.as_ref().map(|_| started.elapsed()).map_err(|e| e.to_string()));
+    let timestamp = timestamp.map_or(0, |ServerTime(timestamp)| timestamp);
+    let ws_url = endpoint::ws(unsecure, base_url, &format!("timestamp={}", timestamp));
+
+    let (tcp, stream) = timed(connect(addr)).await;
+    sample.tcp = Some(tcp);
+    let Some(stream) = stream else {
+        return sample;
+    };
+    if unsecure {
+        measure_websocket(stream, &ws_url, timestamp, &mut sample).await;
+        return sample;
+    }
+    let connector = match tokio_native_tls::native_tls::TlsConnector::new() {
+        Ok(connector) => tokio_native_tls::TlsConnector::from(connector),
+        Err(e) => {
+            sample.tls = Some(Err(e.to_string()));
+            return sample;
+        },
+    };
+    let (tls, stream) = timed(network::within(network::read_timeout(), "TLS handshake", async {
+        connector.connect(&host, stream).await.map_err(std::io::Error::other)
+    }))
+    .await;
+    sample.tls = Some(tls);
+    if let Some(stream) = stream {
+        measure_websocket(stream, &ws_url, timestamp, &mut sample).await;
+    }
+    sample
+}
+
+async fn connect(addr: SocketAddr) -> std::io::Result<TcpStream> {
+    network::within(network::connect_timeout(), "connecting", TcpStream::connect(addr)).await
+}
+
+/// The median of the successful measurements, or `failed` and the last error if none succeeded.
+fn median(results: Vec<&Result<Duration, String>>) -> (String, Option<String>) {
+    let mut times: Vec<Duration> = results.iter().filter_map(|result| result.as_ref().ok().copied()).collect();
+    if times.is_empty() {
+        return match results.last() {
+            Some(Err(e)) => ("failed".to_string(), Some(e.clone())),
+            _ => ("-".to_string(), None),
+        };
+    }
+    times.sort();
+    (format!("{:.1} ms", times[times.len() / 2].as_secs_f64() * 1000.0), None)
+}
+
+/// `pool ping`: measures each pool `count` times and prints the medians side by side.
+pub async fn ping(args: &PingArgs, default_urls: &[String], use_http: bool) {
+    let urls = if args.urls.is_empty() { default_urls.to_vec() } else { args.urls.clone() };
+    let header = ["Pool", "DNS", "TCP", "TLS", "HTTP /timestamp", "WS upgrade", "WS ping"].map(str::to_string);
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for url in &urls {
+        let (base_url, scheme) = endpoint::normalize(url);
+        let unsecure = scheme.unwrap_or(use_http);
+        println!("  Pinging {}...", base_url);
+        let mut samples = Vec::new();
+        for _ in 0..args.count {
+            // A new client per sample so every HTTP request opens its own connection
+            samples.push(measure(&network::client(), &base_url, unsecure).await);
+        }
+        let steps: [fn(&Sample) -> &Measured; 6] = [
+            |sample| &sample.dns,
+            |sample| &sample.tcp,
+            |sample| &sample.tls,
+            |sample| &sample.http,
+            |sample| &sample.upgrade,
+            |sample| &sample.ws_ping,
+        ];
+        let mut row = vec![base_url.clone()];
+        for (step, title) in steps.iter().zip(&header[1..]) {
+            let (cell, error) = median(samples.iter().filter_map(|sample| step(sample).as_ref()).collect());
+            if let Some(error) = error {
+                errors.push(format!("{} {}: {}", base_url, title, error));
+            }
+            row.push(cell);
+        }
+        if unsecure {
+            row[3] = "n/a".to_string();
+        }
+        rows.push(row);
+    }
+
+    let mut widths = header.clone().map(|title| title.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    println!();
+    for row in std::iter::once(&header.to_vec()).chain(&rows) {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        println!("  {}", cells.join("  ").trim_end());
+    }
+    if !errors.is_empty() {
+        println!();
+        for error in errors {
+            println!("  {}", error);
+        }
+    }
+}
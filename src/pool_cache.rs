@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, fs, str::FromStr, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::schema::{self, PoolPubkey};
+
+pub const POOL_CACHE_FILE: &str = "pool_cache.json";
+/// Cached entries older than this are refetched.
+const POOL_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Facts learned about a pool, persisted per server URL.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PoolCapabilities {
+    pub fetched_at: u64,
+    pub authority_pubkey: Option<String>,
+    pub fee_payer_pubkey: Option<String>,
+    pub session_keys: Option<bool>,
+    pub http_submission: Option<bool>,
+    pub resumption: Option<bool>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs()
+}
+
+fn load_all() -> BTreeMap<String, PoolCapabilities> {
+    fs::read_to_string(POOL_CACHE_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_all(cache: &BTreeMap<String, PoolCapabilities>) {
+    if let Ok(contents) = serde_json::to_string_pretty(cache) {
+        let _ = fs::write(POOL_CACHE_FILE, contents);
+    }
+}
+
+/// Returns the cached capabilities for a pool, if present and not expired.
+pub fn get(url: &str) -> Option<PoolCapabilities> {
+    load_all()
+        .remove(url)
+        .filter(|caps| now().saturating_sub(caps.fetched_at) < POOL_CACHE_TTL_SECS)
+}
+
+/// Applies an update to a pool's cached capabilities, resetting expired entries first.
+pub fn update(url: &str, f: impl FnOnce(&mut PoolCapabilities)) {
+    let mut cache = load_all();
+    let caps = cache.entry(url.to_string()).or_default();
+    if now().saturating_sub(caps.fetched_at) >= POOL_CACHE_TTL_SECS {
+        *caps = PoolCapabilities::default();
+    }
+    caps.fetched_at = now();
+    f(caps);
+    save_all(&cache);
+}
+
+async fn cached_pubkey(
+    client: &reqwest::Client,
+    url_prefix: &str,
+    url: &str,
+    endpoint: &'static str,
+    cached: impl Fn(&PoolCapabilities) -> Option<String>,
+    store: impl FnOnce(&mut PoolCapabilities, String),
+) -> Result<Pubkey, String> {
+    if let Some(pubkey) = get(url).as_ref().and_then(cached).and_then(|p| Pubkey::from_str(&p).ok()) {
+        return Ok(pubkey);
+    }
+
+    let PoolPubkey(pubkey) = schema::get(client, url_prefix, url, endpoint, "").await.map_err(|e| e.to_string())?;
+    update(url, |caps| store(caps, pubkey.to_string()));
+    Ok(pubkey)
+}
+
+/// Pool authority pubkey, from the cache or `/pool/authority/pubkey`.
+pub async fn authority_pubkey(client: &reqwest::Client, url_prefix: &str, url: &str) -> Result<Pubkey, String> {
+    cached_pubkey(
+        client,
+        url_prefix,
+        url,
+        "pool/authority/pubkey",
+        |caps| caps.authority_pubkey.clone(),
+        |caps, pubkey| caps.authority_pubkey = Some(pubkey),
+    )
+    .await
+}
+
+/// Pool fee payer pubkey, from the cache or `/pool/fee_payer/pubkey`.
+pub async fn fee_payer_pubkey(client: &reqwest::Client, url_prefix: &str, url: &str) -> Result<Pubkey, String> {
+    cached_pubkey(
+        client,
+        url_prefix,
+        url,
+        "pool/fee_payer/pubkey",
+        |caps| caps.fee_payer_pubkey.clone(),
+        |caps, pubkey| caps.fee_payer_pubkey = Some(pubkey),
+    )
+    .await
+}
+
+fn format_flag(flag: Option<bool>) -> &'static str {
+    match flag {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unknown",
+    }
+}
+
+/// Prints all cached pool profiles without contacting any server.
+pub fn print_pools() {
+    let cache = load_all();
+    if cache.is_empty() {
+        println!("  No pools cached yet.");
+        return;
+    }
+
+    for (url, caps) in cache {
+        let age = now().saturating_sub(caps.fetched_at);
+        println!("  {}{}", url, if age >= POOL_CACHE_TTL_SECS { " (expired)" } else { "" });
+        println!("    Updated:         {}s ago", age);
+        println!("    Authority:       {}", caps.authority_pubkey.as_deref().unwrap_or("unknown"));
+        println!("    Fee Payer:       {}", caps.fee_payer_pubkey.as_deref().unwrap_or("unknown"));
+        println!("    Session Keys:    {}", format_flag(caps.session_keys));
+        println!("    HTTP Submission: {}", format_flag(caps.http_submission));
+        println!("    Resumption:      {}", format_flag(caps.resumption));
+    }
+}
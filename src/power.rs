@@ -0,0 +1,140 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tokio::process::Command;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+/// How often the power source is re-checked while mining is paused.
+const POLL_INTERVAL_SECS: u64 = 10;
+/// Long term package power limit of the first CPU package.
+const RAPL_LIMIT_FILE: &str = "/sys/class/powercap/intel-rapl:0/constraint_0_power_limit_uw";
+/// Time a --power-hook script gets before it is abandoned.
+const HOOK_TIMEOUT_SECS: u64 = 30;
+
+fn read_attr(supply: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(supply.join(name)).ok().map(|value| value.trim().to_string())
+}
+
+/// Whether the machine is running on battery power. Only detected on Linux,
+/// returns false if no power supply information is available.
+pub fn on_battery() -> bool {
+    let Ok(entries) = fs::read_dir(POWER_SUPPLY_DIR) else {
+        return false;
+    };
+
+    let mut discharging = false;
+    for supply in entries.flatten().map(|entry| entry.path()) {
+        match read_attr(&supply, "type").as_deref() {
+            Some("Mains") | Some("USB") if read_attr(&supply, "online").as_deref() == Some("1") => {
+                return false;
+            }
+            Some("Battery") => {
+                discharging |= read_attr(&supply, "status").as_deref() == Some("Discharging");
+            }
+            _ => {}
+        }
+    }
+    discharging
+}
+
+/// Waits until the machine is back on AC power. Returns false if shutdown was requested first.
+pub async fn wait_for_ac(shutdown: &mut tokio::sync::watch::Receiver<bool>) -> bool {
+    while on_battery() {
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)) => {},
+            _ = shutdown.changed() => return false,
+        }
+    }
+    true
+}
+
+/// Points in a mining session where power settings are applied or restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerEvent {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+}
+
+impl PowerEvent {
+    fn name(self) -> &'static str {
+        match self {
+            PowerEvent::Start => "start",
+            PowerEvent::Pause => "pause",
+            PowerEvent::Resume => "resume",
+            PowerEvent::Stop => "stop",
+        }
+    }
+
+    fn mining(self) -> bool {
+        matches!(self, PowerEvent::Start | PowerEvent::Resume)
+    }
+}
+
+/// Package power limit set through RAPL while mining, with the limit it replaced.
+struct RaplLimit {
+    original_uw: u64,
+    limit_uw: u64,
+}
+
+/// Applies --power-limit and runs the --power-hook script when mining starts, pauses,
+/// resumes and stops. The original RAPL limit is restored when dropped.
+pub struct PowerHooks {
+    script: Option<PathBuf>,
+    rapl: Option<RaplLimit>,
+    limited: bool,
+}
+
+impl PowerHooks {
+    pub fn new(script: Option<PathBuf>, limit_watts: Option<u64>) -> Self {
+        let rapl = limit_watts.and_then(|watts| {
+            let original_uw = fs::read_to_string(RAPL_LIMIT_FILE).ok().and_then(|value| value.trim().parse().ok());
+            if original_uw.is_none() {
+                println!("RAPL power limits are not available on this machine, ignoring --power-limit.");
+            }
+            original_uw.map(|original_uw| RaplLimit { original_uw, limit_uw: watts * 1_000_000 })
+        });
+        PowerHooks { script, rapl, limited: false }
+    }
+
+    pub async fn run(&mut self, event: PowerEvent) {
+        if let Some(rapl) = &self.rapl {
+            let (limit_uw, label) = if event.mining() { (rapl.limit_uw, "Set") } else { (rapl.original_uw, "Restored") };
+            if event.mining() != self.limited {
+                match fs::write(RAPL_LIMIT_FILE, limit_uw.to_string()) {
+                    Ok(()) => {
+                        println!("{} package power limit to {}W.", label, limit_uw / 1_000_000);
+                        self.limited = event.mining();
+                    },
+                    Err(e) => println!("Failed to write {}, is the client running as root? {}", RAPL_LIMIT_FILE, e),
+                }
+            }
+        }
+
+        if let Some(script) = &self.script {
+            let status = tokio::time::timeout(
+                Duration::from_secs(HOOK_TIMEOUT_SECS),
+                Command::new(script).arg(event.name()).kill_on_drop(true).status(),
+            )
+            .await;
+            match status {
+                Ok(Ok(status)) if status.success() => {},
+                Ok(Ok(status)) => println!("Power hook {} {} exited with {}.", script.display(), event.name(), status),
+                Ok(Err(e)) => println!("Failed to run power hook {}: {}", script.display(), e),
+                Err(_) => println!("Power hook {} {} took longer than {}s, abandoned.", script.display(), event.name(), HOOK_TIMEOUT_SECS),
+            }
+        }
+    }
+}
+
+impl Drop for PowerHooks {
+    fn drop(&mut self) {
+        if let (Some(rapl), true) = (&self.rapl, self.limited) {
+            let _ = fs::write(RAPL_LIMIT_FILE, rapl.original_uw.to_string());
+        }
+    }
+}
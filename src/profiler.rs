@@ -0,0 +1,118 @@
+use std::{
+    cell::Cell,
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::format;
+
+/// How often the sampler looks at what the workers are doing.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// What a solver thread is doing. Idle threads are not sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Idle,
+    /// Allocating equix solver memories.
+    Setup,
+    /// Claiming the next chunk from the shared nonce cursor and resizing chunks.
+    Sync,
+    /// Building and solving hashes.
+    Hash,
+    /// Re-verifying candidate hashes in paranoid mode.
+    Verify,
+}
+
+const PHASES: [Phase; 4] = [Phase::Setup, Phase::Sync, Phase::Hash, Phase::Verify];
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Idle => "idle",
+            Phase::Setup => "memory_setup",
+            Phase::Sync => "sync",
+            Phase::Hash => "hashing",
+            Phase::Verify => "verify",
+        }
+    }
+
+    fn index(self) -> Option<usize> {
+        PHASES.iter().position(|phase| *phase == self)
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Number of threads currently in each of PHASES.
+static ACTIVE: [AtomicU64; 4] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+thread_local! {
+    static CURRENT: Cell<Phase> = const { Cell::new(Phase::Idle) };
+}
+
+/// Switches this thread to `phase` and returns the phase it was in.
+/// A single relaxed load when profiling is off.
+pub fn set(phase: Phase) -> Phase {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return phase;
+    }
+    let previous = CURRENT.with(|current| current.replace(phase));
+    if previous != phase {
+        if let Some(i) = previous.index() {
+            ACTIVE[i].fetch_sub(1, Ordering::Relaxed);
+        }
+        if let Some(i) = phase.index() {
+            ACTIVE[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    previous
+}
+
+/// Restores the previous phase when dropped.
+pub struct PhaseGuard(Phase);
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        set(self.0);
+    }
+}
+
+pub fn enter(phase: Phase) -> PhaseGuard {
+    PhaseGuard(set(phase))
+}
+
+/// Samples the solver threads for `secs` seconds on a background thread, then writes the
+/// samples as folded stacks (one `solver;<phase> <count>` line each) for flamegraph tools.
+pub fn start(secs: u64, output: PathBuf) {
+    ENABLED.store(true, Ordering::Relaxed);
+    println!("Profiling the solver for {}s, writing {}.", secs, output.display());
+    std::thread::spawn(move || {
+        let mut samples = [0u64; 4];
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(secs) {
+            std::thread::sleep(SAMPLE_INTERVAL);
+            for (sample, active) in samples.iter_mut().zip(ACTIVE.iter()) {
+                *sample += active.load(Ordering::Relaxed);
+            }
+        }
+        ENABLED.store(false, Ordering::Relaxed);
+
+        let total: u64 = samples.iter().sum();
+        let folded: String = PHASES
+            .iter()
+            .zip(samples.iter())
+            .filter(|(_, count)| **count > 0)
+            .map(|(phase, count)| format!("solver;{} {}\n", phase.name(), count))
+            .collect();
+        match fs::write(&output, folded) {
+            Ok(()) => println!("Solver profile written to {} ({} samples).", output.display(), format::count(total)),
+            Err(e) => println!("Failed to write solver profile to {}: {}", output.display(), e),
+        }
+        for (phase, count) in PHASES.iter().zip(samples.iter()) {
+            if total > 0 {
+                println!("  {}: {:.1}%", phase.name(), *count as f64 / total as f64 * 100.0);
+            }
+        }
+    });
+}
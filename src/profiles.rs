@@ -0,0 +1,229 @@
+use std::{collections::BTreeMap, ffi::OsString, path::PathBuf};
+
+use clap::{parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
+use toml::Value;
+
+use crate::settings;
+
+/// How a config value turns into command line arguments for `arg`, e.g. `threads = 8`
+/// into `--threads 8`, `use-http = true` into `--use-http` and arrays into repeated flags.
+fn to_flags(arg: &Arg, key: &str, value: &Value) -> Result<Vec<OsString>, String> {
+    let long = format!("--{}", arg.get_long().unwrap_or(key));
+    let takes_value = arg.get_action().takes_values();
+    let scalar = |value: &Value| match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        other => Err(format!("`{}` can't be {}", key, other.type_str())),
+    };
+    match value {
+        Value::Boolean(enabled) if !takes_value => Ok(if *enabled { vec![long.into()] } else { Vec::new() }),
+        _ if !takes_value => Err(format!("`{}` is a flag, use true or false", key)),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| Ok(vec![OsString::from(&long), scalar(value)?.into()]))
+            .collect::<Result<Vec<_>, String>>()
+            .map(|flags| flags.concat()),
+        value => Ok(vec![long.into(), scalar(value)?.into()]),
+    }
+}
+
+fn find_arg<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key) || arg.get_id().as_str() == key.replace('-', "_"))
+}
+
+fn set_on_command_line(matches: &ArgMatches, arg: &Arg) -> bool {
+    matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+}
+
+/// Options from the settings file by key, with the table each came from.
+fn file_options(matches: &ArgMatches) -> BTreeMap<String, (Value, String)> {
+    if let Some(path) = matches.get_one::<PathBuf>("config") {
+        settings::set_path(path.clone());
+    }
+    let mut config = settings::load();
+    let mut options: BTreeMap<String, (Value, String)> =
+        std::mem::take(&mut config.options).into_iter().map(|(key, value)| (key, (value, "[options]".to_string()))).collect();
+    if let Some(name) = matches.get_one::<String>("profile") {
+        let Some(profile) = config.profile.remove(name) else {
+            println!("No [profile.{}] in {}.", name, settings::path().display());
+            std::process::exit(1);
+        };
+        let origin = format!("[profile.{}]", name);
+        options.extend(profile.into_iter().map(|(key, value)| (key, (value, origin.clone()))));
+    }
+    options
+}
+
+/// Merges the settings file options into `argv`, returning the merged arguments and the
+/// table each added argument came from, by argument id. Problems with the file are only
+/// reported when `warn` is set, so they show once per run.
+fn merge(command: &Command, argv: Vec<OsString>, warn: bool) -> (Vec<OsString>, BTreeMap<String, String>) {
+    let matches = command.clone().ignore_errors(true).get_matches_from(&argv);
+    let options = file_options(&matches);
+    let mut origins = BTreeMap::new();
+    if options.is_empty() {
+        return (argv, origins);
+    }
+
+    let subcommand = matches.subcommand().and_then(|(name, sub_matches)| Some((command.find_subcommand(name)?, sub_matches)));
+    let mut global_flags = Vec::new();
+    let mut subcommand_flags = Vec::new();
+    for (key, (value, origin)) in &options {
+        let (arg, matches, flags) = if let Some(arg) = find_arg(command, key) {
+            (arg, &matches, &mut global_flags)
+        } else if let Some((arg, sub_matches)) = subcommand.and_then(|(sub, sub_matches)| Some((find_arg(sub, key)?, sub_matches))) {
+            (arg, sub_matches, &mut subcommand_flags)
+        } else {
+            // Options of other subcommands are expected, e.g. mine settings while checking a balance
+            if warn && !command.get_subcommands().any(|sub| find_arg(sub, key).is_some()) {
+                println!("Ignoring unknown option `{}` in {}.", key, settings::path().display());
+            }
+            continue;
+        };
+        if set_on_command_line(matches, arg) {
+            continue;
+        }
+        match to_flags(arg, key, value) {
+            Ok(arg_flags) => {
+                flags.extend(arg_flags);
+                origins.insert(arg.get_id().to_string(), format!("{} {}", settings::path().display(), origin));
+            },
+            Err(e) if warn => println!("Ignoring option in {}: {}", settings::path().display(), e),
+            Err(_) => {},
+        }
+    }
+
+    let mut merged = argv;
+    let tail = merged.split_off(merged.len().min(1));
+    merged.extend(global_flags);
+    merged.extend(tail);
+    merged.extend(subcommand_flags);
+    (merged, origins)
+}
+
+/// Adds the `[options]` table of the config file, overlaid with `[profile.<name>]` when
+/// --profile is given, to the command line. Options given on the command line win.
+/// Global options are inserted before the subcommand and the subcommand's own after it.
+pub fn apply(command: Command, argv: Vec<OsString>) -> Vec<OsString> {
+    merge(&command, argv, true).0
+}
+
+/// An option's value for a `mine` run and where that value came from.
+pub struct EffectiveOption {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: String,
+}
+
+fn effective_options(command: &Command, matches: &ArgMatches, origins: &BTreeMap<String, String>) -> Vec<EffectiveOption> {
+    command
+        .get_arguments()
+        .filter(|arg| arg.get_long().is_some() && arg.get_action().takes_values() || matches!(arg.get_action(), ArgAction::SetTrue))
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let value = matches
+                .get_raw(id)
+                .map(|values| values.map(|value| value.to_string_lossy()).collect::<Vec<_>>().join(","));
+            let source = match (origins.get(id), matches.value_source(id)) {
+                (Some(origin), _) => origin.clone(),
+                (None, Some(ValueSource::CommandLine)) => "command line".to_string(),
+                (None, Some(ValueSource::EnvVariable)) => "environment".to_string(),
+                (None, Some(ValueSource::DefaultValue)) => "default".to_string(),
+                _ => "unset".to_string(),
+            };
+            EffectiveOption { name: arg.get_long().unwrap_or(id).to_string(), value, source }
+        })
+        .collect()
+}
+
+/// The command line of this `config` invocation with the `config ...` subcommand swapped for
+/// `mine`, keeping the global options before it.
+fn as_mine_run(mut argv: Vec<OsString>) -> Vec<OsString> {
+    if let Some(position) = argv.iter().rposition(|token| token == "config") {
+        argv.truncate(position);
+    }
+    argv.push("mine".into());
+    argv
+}
+
+/// The global and `mine` options a `mine` run would use, with the source of each value.
+fn effective(command: &Command, argv: Vec<OsString>) -> Vec<EffectiveOption> {
+    let (merged, origins) = merge(command, argv, false);
+    let matches = command.clone().ignore_errors(true).get_matches_from(merged);
+    let mut options = effective_options(command, &matches, &origins);
+    if let (Some(mine_command), Some(mine_matches)) = (command.find_subcommand("mine"), matches.subcommand_matches("mine")) {
+        options.extend(effective_options(mine_command, mine_matches, &origins));
+    }
+    options
+}
+
+/// Values the settings file sets for the selected profile, as they would be passed on the
+/// command line, by option name.
+fn file_values(command: &Command, argv: &[OsString]) -> BTreeMap<String, String> {
+    let matches = command.clone().ignore_errors(true).get_matches_from(argv);
+    file_options(&matches)
+        .into_iter()
+        .map(|(key, (value, _))| {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Array(values) => values.iter().map(|value| value.as_str().map_or_else(|| value.to_string(), str::to_string)).collect::<Vec<_>>().join(","),
+                other => other.to_string(),
+            };
+            (key.replace('_', "-"), value)
+        })
+        .collect()
+}
+
+/// Prints every option a `mine` run with this command line would use and where its value
+/// comes from: the command line, the settings file, the environment or the default.
+pub fn show_effective(command: Command, argv: Vec<OsString>) {
+    let options = effective(&command, as_mine_run(argv));
+    let width = options.iter().map(|option| option.name.len()).max().unwrap_or(0);
+    for option in options {
+        let value = option.value.as_deref().unwrap_or("-");
+        println!("  {:width$} = {}  ({})", option.name, value, option.source, width = width);
+    }
+}
+
+/// Prints the options whose effective value differs from the settings file, i.e. values
+/// overridden or added on the command line or by the environment.
+pub fn diff(command: Command, argv: Vec<OsString>) {
+    let argv = as_mine_run(argv);
+    let file = file_values(&command, &argv);
+    let mut differences = 0;
+    // --config and --profile pick the file, they don't configure the miner
+    for option in effective(&command, argv).into_iter().filter(|option| option.name != "config" && option.name != "profile") {
+        let Some(value) = option.value.filter(|_| option.source != "default" && option.source != "unset") else {
+            continue;
+        };
+        match file.get(&option.name) {
+            Some(file_value) if *file_value == value => continue,
+            Some(file_value) => println!("  - {} = {}", option.name, file_value),
+            None => {},
+        }
+        println!("  + {} = {}  ({})", option.name, value, option.source);
+        differences += 1;
+    }
+    if differences == 0 {
+        println!("  The effective configuration matches {}.", settings::path().display());
+    }
+}
+
+/// Pool URLs set in [options] and every [profile.<name>] table of the settings file, for
+/// `mine --auto-select-pool` to choose from.
+pub fn pool_urls() -> Vec<String> {
+    let config = settings::load();
+    let mut urls = Vec::new();
+    for table in std::iter::once(&config.options).chain(config.profile.values()) {
+        match table.get("url") {
+            Some(Value::String(url)) => urls.push(url.clone()),
+            Some(Value::Array(values)) => urls.extend(values.iter().filter_map(|value| value.as_str().map(str::to_string))),
+            _ => {},
+        }
+    }
+    urls
+}
@@ -0,0 +1,139 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ore_api::{consts::{MINT_ADDRESS, TOKEN_DECIMALS}, state::{proof_pda, Proof}};
+use ore_miner_delegation::{pda::{delegated_stake_pda, managed_proof_pda}, state::{DelegatedStake, ManagedProof}, utils::AccountDeserialize as _};
+use ore_utils::AccountDeserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{hash::Hash, native_token::lamports_to_sol, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::amount_to_ui_amount;
+
+use crate::format;
+use crate::network;
+use crate::pool_cache;
+
+fn format_timestamp(ts: i64) -> String {
+    if ts <= 0 {
+        return "never".to_string();
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs() as i64;
+    format!("{} ({}s ago)", ts, now.saturating_sub(ts))
+}
+
+pub fn print_proof(label: &str, address: &Pubkey, proof: &Proof) {
+    println!("  {}: {}", label, address);
+    println!("    Authority:     {}", proof.authority);
+    println!("    Miner:         {}", proof.miner);
+    println!("    Balance:       {:.11} ORE", amount_to_ui_amount(proof.balance, TOKEN_DECIMALS));
+    println!("    Last Hash:     {}", Hash::new_from_array(proof.last_hash));
+    println!("    Last Hash At:  {}", format_timestamp(proof.last_hash_at));
+    println!("    Last Stake At: {}", format_timestamp(proof.last_stake_at));
+    println!("    Total Hashes:  {}", format::count(proof.total_hashes));
+    println!("    Total Rewards: {:.11} ORE", amount_to_ui_amount(proof.total_rewards, TOKEN_DECIMALS));
+}
+
+/// Fetches and decodes an ORE proof account, returns None if it doesn't exist.
+pub async fn fetch_proof(rpc: &RpcClient, address: &Pubkey) -> Option<Proof> {
+    let data = rpc.get_account_data(address).await.ok()?;
+    Proof::try_from_bytes(&data).ok().copied()
+}
+
+/// Displays the wallet's own proof, the pool's managed proof and the wallet's delegated stake.
+pub async fn proof(wallet: &Pubkey, url: String, unsecure: bool, rpc_url: String) {
+    let client = network::client();
+    let url_prefix = if unsecure { "http" } else { "https" };
+    let rpc = network::rpc(rpc_url);
+
+    let (wallet_proof_address, _) = proof_pda(*wallet);
+    match fetch_proof(&rpc, &wallet_proof_address).await {
+        Some(proof) => print_proof("Wallet Proof", &wallet_proof_address, &proof),
+        None => println!("  Wallet Proof: no proof account for {}", wallet),
+    }
+
+    let pool_pubkey = match pool_cache::authority_pubkey(&client, url_prefix, &url).await {
+        Ok(pubkey) => pubkey,
+        Err(e) => {
+            println!("  Error fetching pool authority: {}", e);
+            return;
+        }
+    };
+
+    let (managed_proof_address, _) = managed_proof_pda(pool_pubkey);
+    let (pool_proof_address, _) = proof_pda(managed_proof_address);
+    match fetch_proof(&rpc, &pool_proof_address).await {
+        Some(proof) => print_proof("Pool Managed Proof", &pool_proof_address, &proof),
+        None => println!("  Pool Managed Proof: not found for pool authority {}", pool_pubkey),
+    }
+
+    let (delegated_stake_address, _) = delegated_stake_pda(pool_pubkey, *wallet);
+    let delegated = rpc
+        .get_account_data(&delegated_stake_address)
+        .await
+        .ok()
+        .and_then(|data| DelegatedStake::try_from_bytes(&data).ok().copied());
+    match delegated {
+        Some(stake) => println!(
+            "  Delegated Stake: {:.11} ORE ({})",
+            amount_to_ui_amount(stake.amount, TOKEN_DECIMALS),
+            delegated_stake_address
+        ),
+        None => println!("  Delegated Stake: No staked account"),
+    }
+}
+
+/// Decodes the pool's on-chain accounts so miners can check what the pool actually holds,
+/// independent of the operator's UI.
+pub async fn pool_info(url: String, unsecure: bool, rpc_url: String) {
+    let client = network::client();
+    let url_prefix = if unsecure { "http" } else { "https" };
+    let rpc = network::rpc(rpc_url);
+
+    let pool_pubkey = match pool_cache::authority_pubkey(&client, url_prefix, &url).await {
+        Ok(pubkey) => pubkey,
+        Err(e) => {
+            println!("  Error fetching pool authority: {}", e);
+            return;
+        }
+    };
+    println!("  Pool Authority: {}", pool_pubkey);
+    match rpc.get_balance(&pool_pubkey).await {
+        Ok(lamports) => println!("    SOL Balance:   {} SOL (pays transaction fees)", lamports_to_sol(lamports)),
+        Err(e) => println!("    SOL Balance:   unavailable ({})", e),
+    }
+
+    let (managed_proof_address, _) = managed_proof_pda(pool_pubkey);
+    let managed = rpc
+        .get_account_data(&managed_proof_address)
+        .await
+        .ok()
+        .and_then(|data| ManagedProof::try_from_bytes(&data).ok().copied());
+    match managed {
+        Some(managed) => {
+            println!("  Managed Proof Account: {}", managed_proof_address);
+            println!("    Miner Authority: {}", managed.miner_authority);
+            if managed.miner_authority != pool_pubkey {
+                println!("    Warning: the managed proof belongs to a different authority than the pool reports.");
+            }
+        },
+        None => {
+            println!("  Managed Proof Account: not found for pool authority {}", pool_pubkey);
+            return;
+        }
+    }
+
+    // The proof balance holds delegated stake plus rewards not yet claimed by miners
+    let (pool_proof_address, _) = proof_pda(managed_proof_address);
+    match fetch_proof(&rpc, &pool_proof_address).await {
+        Some(proof) => {
+            print_proof("Pool Proof", &pool_proof_address, &proof);
+            println!("    (Balance is the pool stake plus rewards claimable by its miners)");
+        },
+        None => println!("  Pool Proof: not found for managed proof {}", managed_proof_address),
+    }
+
+    let token_account = get_associated_token_address(&managed_proof_address, &MINT_ADDRESS);
+    match rpc.get_token_account_balance(&token_account).await {
+        Ok(balance) => println!("  Managed Proof Token Account: {} ORE ({})", balance.ui_amount_string, token_account),
+        Err(_) => println!("  Managed Proof Token Account: not found ({})", token_account),
+    }
+}
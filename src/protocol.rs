@@ -0,0 +1,393 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+
+use crate::audit;
+
+/// Known ore-hq-server forks. Each flavor maps to a set of protocol quirks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ServerFlavor {
+    /// ore-hq-server and the forks that kept its wire format, such as the ec1ipse pool.
+    Official,
+    /// Only the quirks given with --quirk.
+    Custom,
+}
+
+/// Individual protocol deviations that can be toggled for custom servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Quirk {
+    /// Sign frames with raw 64 byte signatures instead of base58 text.
+    RawSignatures,
+    /// Reject StartMining frames carrying trailing extension bytes.
+    StrictStartMining,
+    /// Use the cutoff sent by the server as-is instead of clamping it.
+    NoCutoffClamp,
+}
+
+/// What to do with the per-round thread hint some servers append to StartMining.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ThreadHintPolicy {
+    Ignore,
+    Honor,
+    /// Only follow hints asking for fewer threads than configured.
+    LowerOnly,
+}
+
+impl ThreadHintPolicy {
+    /// Thread count to use for a round given the configured count and the server's hint.
+    /// Honored hints are capped at the core count, every solver thread has its own memory.
+    pub fn apply(&self, configured: u32, hint: Option<u32>) -> u32 {
+        match (self, hint) {
+            (ThreadHintPolicy::Honor, Some(hint)) => hint.min(std::thread::available_parallelism().map_or(1, |n| n.get() as u32)),
+            (ThreadHintPolicy::LowerOnly, Some(hint)) => hint.min(configured),
+            _ => configured,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Quirks {
+    pub raw_signatures: bool,
+    pub strict_start_mining: bool,
+    pub clamp_cutoff: bool,
+}
+
+impl Quirks {
+    pub fn new(flavor: ServerFlavor, custom: &[Quirk]) -> Self {
+        let mut quirks = Quirks {
+            raw_signatures: false,
+            strict_start_mining: false,
+            clamp_cutoff: true,
+        };
+
+        if flavor == ServerFlavor::Custom {
+            for quirk in custom {
+                match quirk {
+                    Quirk::RawSignatures => quirks.raw_signatures = true,
+                    Quirk::StrictStartMining => quirks.strict_start_mining = true,
+                    Quirk::NoCutoffClamp => quirks.clamp_cutoff = false,
+                }
+            }
+        }
+
+        quirks
+    }
+
+    fn encode_signature(&self, sig: Signature) -> Vec<u8> {
+        if self.raw_signatures {
+            sig.as_ref().to_vec()
+        } else {
+            sig.to_string().as_bytes().to_vec()
+        }
+    }
+}
+
+/// Builds the Ready message: type 0, pubkey, current timestamp and signature of the timestamp.
+/// `signer` is the main keypair, or the session key it delegated to.
+pub fn ready_message(pubkey: &Pubkey, signer: &dyn Signer, now: u64, quirks: &Quirks) -> Vec<u8> {
+    let msg = now.to_le_bytes();
+    let sig = quirks.encode_signature(audit::sign(signer, "ready", &msg));
+    let mut bin_data: Vec<u8> = Vec::new();
+    bin_data.push(0u8);
+    bin_data.extend_from_slice(&pubkey.to_bytes());
+    bin_data.extend_from_slice(&msg);
+    bin_data.extend(sig);
+    bin_data
+}
+
+/// Builds the BestSolution message: type 2, hash digest, nonce, pubkey and signature.
+pub fn best_solution_message(pubkey: &Pubkey, signer: &dyn Signer, best_hash: &drillx_2::Hash, best_nonce: u64, quirks: &Quirks) -> Vec<u8> {
+    let message_type = 2u8; // 1 u8 - BestSolution Message
+    let best_hash_bin = best_hash.d; // 16 u8
+    let best_nonce_bin = best_nonce.to_le_bytes(); // 8 u8
+
+    let mut hash_nonce_message = [0; 24];
+    hash_nonce_message[0..16].copy_from_slice(&best_hash_bin);
+    hash_nonce_message[16..24].copy_from_slice(&best_nonce_bin);
+    let signature = quirks.encode_signature(audit::sign(signer, "best-solution", &hash_nonce_message));
+
+    let mut bin_data = [0; 57];
+    bin_data[00..1].copy_from_slice(&message_type.to_le_bytes());
+    bin_data[01..17].copy_from_slice(&best_hash_bin);
+    bin_data[17..25].copy_from_slice(&best_nonce_bin);
+    bin_data[25..57].copy_from_slice(&pubkey.to_bytes());
+
+    let mut bin_vec = bin_data.to_vec();
+    bin_vec.extend(signature);
+    bin_vec
+}
+
+/// Builds the Attestation message: type 3, pubkey, interval start and end, hashes computed
+/// in the interval, client version length and bytes, then a signature over everything after the type.
+pub fn attestation_message(pubkey: &Pubkey, signer: &dyn Signer, interval: Range<u64>, hashes: u64, quirks: &Quirks) -> Vec<u8> {
+    let version = env!("CARGO_PKG_VERSION").as_bytes();
+
+    let mut body: Vec<u8> = Vec::new();
+    body.extend_from_slice(&pubkey.to_bytes());
+    body.extend_from_slice(&interval.start.to_le_bytes());
+    body.extend_from_slice(&interval.end.to_le_bytes());
+    body.extend_from_slice(&hashes.to_le_bytes());
+    body.push(version.len() as u8);
+    body.extend_from_slice(version);
+    let sig = quirks.encode_signature(audit::sign(signer, "attestation", &body));
+
+    let mut bin_data: Vec<u8> = Vec::new();
+    bin_data.push(3u8);
+    bin_data.extend(body);
+    bin_data.extend(sig);
+    bin_data
+}
+
+/// Builds the CapacityReport message: type 4, pubkey, timestamp and sustained hashes per second,
+/// then a signature over everything after the type.
+pub fn capacity_message(pubkey: &Pubkey, signer: &dyn Signer, now: u64, hashrate: u64, quirks: &Quirks) -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::new();
+    body.extend_from_slice(&pubkey.to_bytes());
+    body.extend_from_slice(&now.to_le_bytes());
+    body.extend_from_slice(&hashrate.to_le_bytes());
+    let sig = quirks.encode_signature(audit::sign(signer, "capacity", &body));
+
+    let mut bin_data: Vec<u8> = Vec::new();
+    bin_data.push(4u8);
+    bin_data.extend(body);
+    bin_data.extend(sig);
+    bin_data
+}
+
+/// Builds the timing extension appended to a BestSolution message: type 1, unix time the
+/// hashing started, hash duration and Ready to StartMining latency, all in milliseconds.
+/// It is the last 25 bytes of the frame, so servers can read it without parsing the signature.
+pub fn timing_extension(hash_started_ms: u64, hash_ms: u64, latency_ms: u64) -> Vec<u8> {
+    let mut bin_data: Vec<u8> = Vec::new();
+    bin_data.push(1u8);
+    bin_data.extend_from_slice(&hash_started_ms.to_le_bytes());
+    bin_data.extend_from_slice(&hash_ms.to_le_bytes());
+    bin_data.extend_from_slice(&latency_ms.to_le_bytes());
+    bin_data
+}
+
+/// Parses a StartMining frame into (challenge, nonce range, cutoff).
+pub fn parse_start_mining(b: &[u8], quirks: &Quirks) -> Option<([u8; 32], Range<u64>, u64)> {
+    // type (1) + challenge (32) + cutoff (8) + nonce start (8) + nonce end (8)
+    if b.len() < 57 || (quirks.strict_start_mining && b.len() != 57) {
+        return None;
+    }
+
+    let mut hash_bytes = [0u8; 32];
+    hash_bytes.copy_from_slice(&b[1..33]);
+
+    let cutoff = u64::from_le_bytes(b[33..41].try_into().ok()?);
+    let nonce_start = u64::from_le_bytes(b[41..49].try_into().ok()?);
+    let nonce_end = u64::from_le_bytes(b[49..57].try_into().ok()?);
+
+    Some((hash_bytes, nonce_start..nonce_end, cutoff))
+}
+
+/// Parses a RoundCancel frame: type 1, optionally followed by the challenge of the round to
+/// abort. Some(None) cancels whatever round is running, None means the frame is malformed.
+pub fn parse_round_cancel(b: &[u8]) -> Option<Option<[u8; 32]>> {
+    match b.len() {
+        1 => Some(None),
+        n if n >= 33 => b[1..33].try_into().ok().map(Some),
+        _ => None,
+    }
+}
+
+/// Reads the optional thread hint extension byte following a StartMining frame, 0 means no hint.
+pub fn parse_thread_hint(b: &[u8], quirks: &Quirks) -> Option<u32> {
+    if quirks.strict_start_mining {
+        return None;
+    }
+    b.get(57).copied().filter(|&hint| hint > 0).map(u32::from)
+}
+
+fn parse_announced_time(value: &str) -> Option<u64> {
+    value.parse::<u64>().ok().or_else(|| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .and_then(|dt| u64::try_from(dt.timestamp()).ok())
+    })
+}
+
+/// Extracts a maintenance window from a pool announcement, e.g.
+/// "Maintenance: 1735689600 1735693200" or "Maintenance: 2025-01-01T00:00:00Z 2025-01-01T01:00:00Z".
+/// Times are unix seconds or RFC 3339.
+pub fn parse_maintenance(text: &str) -> Option<Range<u64>> {
+    let rest = text.lines().find_map(|line| line.trim().strip_prefix("Maintenance:"))?;
+    let mut times = rest.split_whitespace().filter(|part| *part != "-" && *part != "to");
+    let start = parse_announced_time(times.next()?)?;
+    let end = parse_announced_time(times.next()?)?;
+    (end > start).then_some(start..end)
+}
+
+/// Formats a frame as offset, hex bytes and printable ASCII, 16 bytes per line.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+            format!("{:04x}  {:<47}  |{}|", i * 16, hex.join(" "), ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extracts the miner's reward from the pool's round summary text, e.g. "Miner Earned: 0.00012 ORE".
+pub fn parse_miner_earned(text: &str) -> Option<f64> {
+    text.lines()
+        .find_map(|line| line.trim().strip_prefix("Miner Earned:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|amount| amount.parse::<f64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::Keypair;
+
+    use super::*;
+
+    fn official() -> Quirks {
+        Quirks::new(ServerFlavor::Official, &[])
+    }
+
+    fn raw() -> Quirks {
+        Quirks::new(ServerFlavor::Custom, &[Quirk::RawSignatures])
+    }
+
+    /// Checks that `frame` ends in a base58 signature by `key` over `signed`.
+    fn assert_signed(frame: &[u8], signed_len: usize, signed: &[u8], key: &Keypair) {
+        let sig: Signature = std::str::from_utf8(&frame[signed_len..]).unwrap().parse().unwrap();
+        assert!(sig.verify(key.pubkey().as_ref(), signed));
+    }
+
+    #[test]
+    fn ready_message_layout() {
+        let key = Keypair::new();
+        let frame = ready_message(&key.pubkey(), &key, 1_700_000_000, &official());
+        assert_eq!(frame[0], 0);
+        assert_eq!(&frame[1..33], key.pubkey().as_ref());
+        assert_eq!(&frame[33..41], &1_700_000_000u64.to_le_bytes());
+        assert_signed(&frame, 41, &1_700_000_000u64.to_le_bytes(), &key);
+
+        let frame = ready_message(&key.pubkey(), &key, 1_700_000_000, &raw());
+        assert_eq!(frame.len(), 41 + 64);
+        assert!(Signature::try_from(&frame[41..]).unwrap().verify(key.pubkey().as_ref(), &frame[33..41]));
+    }
+
+    #[test]
+    fn best_solution_message_layout() {
+        let key = Keypair::new();
+        let hash = drillx_2::Hash { d: [7; 16], h: [0; 32] };
+        let frame = best_solution_message(&key.pubkey(), &key, &hash, 42, &official());
+        assert_eq!(frame[0], 2);
+        assert_eq!(&frame[1..17], &[7; 16]);
+        assert_eq!(&frame[17..25], &42u64.to_le_bytes());
+        assert_eq!(&frame[25..57], key.pubkey().as_ref());
+        assert_signed(&frame, 57, &frame[1..25], &key);
+    }
+
+    #[test]
+    fn attestation_and_capacity_sign_everything_after_the_type() {
+        let key = Keypair::new();
+        let version = env!("CARGO_PKG_VERSION").as_bytes();
+        let frame = attestation_message(&key.pubkey(), &key, 100..160, 5_000, &raw());
+        let body_len = 32 + 8 + 8 + 8 + 1 + version.len();
+        assert_eq!(frame[0], 3);
+        assert_eq!(&frame[33..41], &100u64.to_le_bytes());
+        assert_eq!(&frame[41..49], &160u64.to_le_bytes());
+        assert_eq!(&frame[49..57], &5_000u64.to_le_bytes());
+        assert_eq!(frame[57] as usize, version.len());
+        assert_eq!(&frame[58..1 + body_len], version);
+        assert!(Signature::try_from(&frame[1 + body_len..]).unwrap().verify(key.pubkey().as_ref(), &frame[1..1 + body_len]));
+
+        let frame = capacity_message(&key.pubkey(), &key, 1_700_000_000, 1_234, &official());
+        assert_eq!(frame[0], 4);
+        assert_eq!(&frame[41..49], &1_234u64.to_le_bytes());
+        assert_signed(&frame, 49, &frame[1..49], &key);
+    }
+
+    #[test]
+    fn timing_extension_layout() {
+        let extension = timing_extension(1, 2, 3);
+        assert_eq!(extension.len(), 25);
+        assert_eq!(extension[0], 1);
+        assert_eq!(&extension[17..25], &3u64.to_le_bytes());
+    }
+
+    fn start_mining(extension: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8];
+        frame.extend_from_slice(&[9; 32]);
+        frame.extend_from_slice(&15u64.to_le_bytes());
+        frame.extend_from_slice(&100u64.to_le_bytes());
+        frame.extend_from_slice(&200u64.to_le_bytes());
+        frame.extend_from_slice(extension);
+        frame
+    }
+
+    #[test]
+    fn parses_start_mining_and_its_thread_hint() {
+        let frame = start_mining(&[]);
+        assert_eq!(parse_start_mining(&frame, &official()), Some(([9; 32], 100..200, 15)));
+        assert_eq!(parse_start_mining(&frame[..56], &official()), None);
+        assert_eq!(parse_thread_hint(&frame, &official()), None);
+
+        let frame = start_mining(&[6]);
+        assert_eq!(parse_thread_hint(&frame, &official()), Some(6));
+        assert_eq!(parse_thread_hint(&start_mining(&[0]), &official()), None);
+
+        let strict = Quirks::new(ServerFlavor::Custom, &[Quirk::StrictStartMining]);
+        assert_eq!(parse_start_mining(&frame, &strict), None);
+        assert_eq!(parse_thread_hint(&frame, &strict), None);
+    }
+
+    #[test]
+    fn parses_round_cancel() {
+        assert_eq!(parse_round_cancel(&[1]), Some(None));
+        let mut frame = vec![1u8];
+        frame.extend_from_slice(&[5; 32]);
+        assert_eq!(parse_round_cancel(&frame), Some(Some([5; 32])));
+        assert_eq!(parse_round_cancel(&frame[..20]), None);
+    }
+
+    #[test]
+    fn parses_maintenance_windows() {
+        assert_eq!(parse_maintenance("Heads up\nMaintenance: 1735689600 1735693200"), Some(1735689600..1735693200));
+        assert_eq!(parse_maintenance("Maintenance: 2025-01-01T00:00:00Z to 2025-01-01T01:00:00Z"), Some(1735689600..1735693200));
+        assert_eq!(parse_maintenance("Maintenance: 1735693200 - 1735689600"), None);
+        assert_eq!(parse_maintenance("Maintenance: soon"), None);
+        assert_eq!(parse_maintenance("All good"), None);
+    }
+
+    #[test]
+    fn parses_miner_earned() {
+        assert_eq!(parse_miner_earned("Round done\n  Miner Earned: 0.00012 ORE\n"), Some(0.00012));
+        assert_eq!(parse_miner_earned("Pool Earned: 1 ORE"), None);
+    }
+
+    #[test]
+    fn thread_hint_policies() {
+        assert_eq!(ThreadHintPolicy::Ignore.apply(8, Some(2)), 8);
+        assert_eq!(ThreadHintPolicy::Honor.apply(8, Some(1)), 1);
+        assert_eq!(ThreadHintPolicy::LowerOnly.apply(8, Some(12)), 8);
+        assert_eq!(ThreadHintPolicy::LowerOnly.apply(8, Some(2)), 2);
+        assert_eq!(ThreadHintPolicy::Honor.apply(8, None), 8);
+    }
+
+    #[test]
+    fn honored_hints_stop_at_the_core_count() {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+        assert_eq!(ThreadHintPolicy::Honor.apply(8, Some(255)), cores.min(255));
+        assert_eq!(ThreadHintPolicy::Honor.apply(8, Some(cores + 1)), cores);
+    }
+
+    #[test]
+    fn custom_quirks_only_apply_to_custom_servers() {
+        let quirks = Quirks::new(ServerFlavor::Official, &[Quirk::RawSignatures, Quirk::NoCutoffClamp]);
+        assert!(!quirks.raw_signatures && quirks.clamp_cutoff);
+        let quirks = Quirks::new(ServerFlavor::Custom, &[Quirk::RawSignatures, Quirk::NoCutoffClamp]);
+        assert!(quirks.raw_signatures && !quirks.strict_start_mining && !quirks.clamp_cutoff);
+    }
+}
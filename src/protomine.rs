@@ -21,6 +21,12 @@ use tokio_tungstenite::{
 use std::sync::Once;
 use drillx_2::equix;
 
+use crate::audit;
+use crate::endpoint;
+use crate::format;
+use crate::network;
+use crate::schema::{self, SchemaError, ServerTime};
+
 static INIT_RAYON: Once = Once::new();
 
 // Constants for tuning performance
@@ -159,42 +165,19 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
 
     loop {
         let base_url = url.clone();
-        let mut ws_url_str = if unsecure {
-            format!("ws://{}", url)
-        } else {
-            format!("wss://{}", url)
-        };
-
-        if !ws_url_str.ends_with('/') {
-            ws_url_str.push('/');
-        }
-
-        let client = reqwest::Client::new();
+        let client = network::client();
 
         let http_prefix = if unsecure { "http" } else { "https" };
 
-        let timestamp = match client.get(format!("{}://{}/timestamp", http_prefix, base_url)).send().await {
-            Ok(response) => {
-                match response.text().await {
-                    Ok(ts) => {
-                        match ts.parse::<u64>() {
-                            Ok(timestamp) => timestamp,
-                            Err(_) => {
-                                eprintln!("Server response body for /timestamp failed to parse, contact admin.");
-                                tokio::time::sleep(Duration::from_secs(3)).await;
-                                continue;
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        eprintln!("Server response body for /timestamp is empty, contact admin.");
-                        tokio::time::sleep(Duration::from_secs(3)).await;
-                        continue;
-                    }
-                }
+        let timestamp = match schema::get::<ServerTime>(&client, http_prefix, &base_url, "timestamp", "").await {
+            Ok(ServerTime(timestamp)) => timestamp,
+            Err(e @ (SchemaError::Request { .. } | SchemaError::Status { .. })) => {
+                eprintln!("Server restarting, trying again in 3 seconds... ({})", e);
+                tokio::time::sleep(Duration::from_secs(3)).await;
+                continue;
             }
-            Err(_) => {
-                eprintln!("Server restarting, trying again in 3 seconds...");
+            Err(e) => {
+                eprintln!("Server response for /timestamp is unusable, contact admin: {}", e);
                 tokio::time::sleep(Duration::from_secs(3)).await;
                 continue;
             }
@@ -203,10 +186,9 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
         println!("Server Timestamp: {}", timestamp);
 
         let ts_msg = timestamp.to_le_bytes();
-        let sig = key.sign_message(&ts_msg);
+        let sig = audit::sign(&key, "timestamp", &ts_msg);
 
-        ws_url_str.push_str(&format!("?timestamp={}", timestamp));
-        let url = url::Url::parse(&ws_url_str).expect("Failed to parse server url");
+        let url = url::Url::parse(&endpoint::ws(unsecure, &base_url, &format!("timestamp={}", timestamp))).expect("Failed to parse server url");
         let host = url.host_str().expect("Invalid host in server url");
 
         let auth = BASE64_STANDARD.encode(format!("{}:{}", key.pubkey(), sig));
@@ -224,7 +206,7 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
             .body(())
             .unwrap();
 
-        match connect_async(request).await {
+        match network::within(network::connect_timeout() + network::read_timeout(), "connecting", connect_async(request)).await {
             Ok((ws_stream, _)) => {
                 println!("Connected to network!");
 
@@ -246,14 +228,14 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
                     .as_secs();
 
                 let msg = now.to_le_bytes();
-                let sig = key.sign_message(&msg).to_string().as_bytes().to_vec();
+                let sig = audit::sign(&key, "ready", &msg).to_string().as_bytes().to_vec();
                 let mut bin_data: Vec<u8> = Vec::with_capacity(1 + 32 + 8 + sig.len());
                 bin_data.push(0u8);
                 bin_data.extend_from_slice(&key.pubkey().to_bytes());
                 bin_data.extend_from_slice(&msg);
                 bin_data.extend(sig);
 
-                let _ = sender.send(Message::Binary(bin_data)).await;
+                let _ = network::within(network::write_timeout(), "sending", sender.send(Message::Binary(bin_data))).await;
 
                 // receive messages
                 while let Some(msg) = message_receiver.recv().await {
@@ -276,11 +258,11 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
                             let hash_time = hash_timer.elapsed();
 
                             println!("Found best diff: {}", best_difficulty);
-                            println!("Processed: {}", total_nonces_checked);
-                            println!("Hash time: {:?}", hash_time);
+                            println!("Processed: {}", format::count(total_nonces_checked));
+                            println!("Hash time: {}", format::duration(hash_time));
                             let hash_time_secs = hash_time.as_secs();
                             if hash_time_secs > 0 {
-                                println!("Hashpower: {:?} H/s", total_nonces_checked.saturating_div(hash_time_secs));
+                                println!("Hashpower: {}", format::hashrate(total_nonces_checked as f64 / hash_time.as_secs_f64()));
                             }
 
                             let message_type = 2u8; // 2 u8 - BestSolution Message
@@ -290,7 +272,7 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
                             let mut hash_nonce_message = [0; 24];
                             hash_nonce_message[0..16].copy_from_slice(&best_hash_bin);
                             hash_nonce_message[16..24].copy_from_slice(&best_nonce_bin);
-                            let signature = key.sign_message(&hash_nonce_message).to_string().as_bytes().to_vec();
+                            let signature = audit::sign(&key, "best-solution", &hash_nonce_message).to_string().as_bytes().to_vec();
 
                             let mut bin_data = Vec::with_capacity(57 + signature.len());
                             bin_data.extend_from_slice(&message_type.to_le_bytes());
@@ -299,7 +281,7 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
                             bin_data.extend_from_slice(&key.pubkey().to_bytes());
                             bin_data.extend(signature);
 
-                            let _ = sender.send(Message::Binary(bin_data)).await;
+                            let _ = network::within(network::write_timeout(), "sending", sender.send(Message::Binary(bin_data))).await;
 
                             tokio::time::sleep(Duration::from_secs(3)).await;
 
@@ -309,14 +291,14 @@ pub async fn protomine(args: MineArgs, key: Keypair, url: String, unsecure: bool
                                 .as_secs();
 
                             let msg = now.to_le_bytes();
-                            let sig = key.sign_message(&msg).to_string().as_bytes().to_vec();
+                            let sig = audit::sign(&key, "ready", &msg).to_string().as_bytes().to_vec();
                             let mut bin_data = Vec::with_capacity(1 + 32 + 8 + sig.len());
                             bin_data.push(0u8);
                             bin_data.extend_from_slice(&key.pubkey().to_bytes());
                             bin_data.extend_from_slice(&msg);
                             bin_data.extend(sig);
 
-                            let _ = sender.send(Message::Binary(bin_data)).await;
+                            let _ = network::within(network::write_timeout(), "sending", sender.send(Message::Binary(bin_data))).await;
                         }
                     }
                 }
@@ -0,0 +1,98 @@
+use std::{fs, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::history;
+
+/// Rounds started in the last hour and rounds skipped for --max-rounds-per-hour, across runs.
+pub const ROUND_QUOTA_FILE: &str = "round_quota.json";
+const HOUR_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RoundQuota {
+    /// Start times of the rounds in the last hour, oldest first.
+    rounds: Vec<u64>,
+    skipped: u64,
+}
+
+fn load() -> RoundQuota {
+    let mut quota: RoundQuota = fs::read_to_string(ROUND_QUOTA_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    retain_last_hour(&mut quota.rounds, history::now());
+    quota
+}
+
+fn retain_last_hour(rounds: &mut Vec<u64>, now: u64) {
+    rounds.retain(|at| now.saturating_sub(*at) < HOUR_SECS);
+}
+
+fn save(quota: &RoundQuota) {
+    if let Ok(contents) = serde_json::to_string(quota) {
+        let _ = fs::write(ROUND_QUOTA_FILE, contents);
+    }
+}
+
+/// Rounds started in the last hour.
+pub fn used() -> usize {
+    load().rounds.len()
+}
+
+/// Counts a round against the quota.
+pub fn record_round() {
+    let mut quota = load();
+    quota.rounds.push(history::now());
+    save(&quota);
+}
+
+/// Counts a round skipped because of the quota, returning the total skipped so far.
+pub fn record_skipped() -> u64 {
+    let mut quota = load();
+    quota.skipped += 1;
+    save(&quota);
+    quota.skipped
+}
+
+/// Time until another round fits in a quota of `max` rounds per hour, None if one fits now.
+pub fn until_free(max: u32) -> Option<Duration> {
+    wait_for_slot(&load().rounds, max, history::now())
+}
+
+/// `until_free` for the round start times of the last hour, oldest first.
+fn wait_for_slot(rounds: &[u64], max: u32, now: u64) -> Option<Duration> {
+    let over = (rounds.len() + 1).checked_sub(max as usize).filter(|over| *over > 0)?;
+    let frees_at = rounds[over - 1] + HOUR_SECS;
+    Some(Duration::from_secs(frees_at.saturating_sub(now).max(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_older_than_an_hour_expire() {
+        let mut rounds = vec![1_000, 4_000, 4_599, 4_600];
+        retain_last_hour(&mut rounds, 4_000 + HOUR_SECS - 1);
+        assert_eq!(rounds, vec![4_000, 4_599, 4_600]);
+    }
+
+    #[test]
+    fn a_round_fits_below_the_quota() {
+        assert_eq!(wait_for_slot(&[], 1, 10_000), None);
+        assert_eq!(wait_for_slot(&[9_000, 9_500], 3, 10_000), None);
+    }
+
+    #[test]
+    fn a_full_quota_waits_for_the_oldest_round_to_expire() {
+        let rounds = [9_000, 9_500, 9_900];
+        assert_eq!(wait_for_slot(&rounds, 3, 10_000), Some(Duration::from_secs(9_000 + HOUR_SECS - 10_000)));
+        // Two over a lowered quota: the second oldest has to expire as well
+        assert_eq!(wait_for_slot(&rounds, 2, 10_000), Some(Duration::from_secs(9_500 + HOUR_SECS - 10_000)));
+    }
+
+    #[test]
+    fn waits_at_least_a_second() {
+        assert_eq!(wait_for_slot(&[10_000], 1, 10_000 + HOUR_SECS), Some(Duration::from_secs(1)));
+    }
+}
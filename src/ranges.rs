@@ -0,0 +1,189 @@
+use std::{collections::VecDeque, ops::Range};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::history;
+use crate::store;
+
+/// Rounds used for the session's reference range size and for the recent window.
+const WINDOW: usize = 5;
+/// Recent ranges below this share of the reference count as a shrink.
+const SHRINK_RATIO: f64 = 0.5;
+/// Ranges below this share of what we can hash before the cutoff leave hashpower idle.
+const UNDERSIZED_RATIO: f64 = 0.8;
+/// Rounds the sustained capacity is measured over.
+const CAPACITY_ROUNDS: usize = 10;
+
+fn median(values: impl Iterator<Item = u64>) -> u64 {
+    let mut sorted: Vec<u64> = values.collect();
+    sorted.sort_unstable();
+    sorted[sorted.len() / 2]
+}
+
+/// Sustained hashrate for capacity reports: the median of recent rounds, so a single
+/// unusually fast round can't inflate it.
+#[derive(Default)]
+pub struct CapacityMeter {
+    hashrates: VecDeque<u64>,
+}
+
+impl CapacityMeter {
+    pub fn record(&mut self, hashrate: u64) {
+        if self.hashrates.len() == CAPACITY_ROUNDS {
+            self.hashrates.pop_front();
+        }
+        self.hashrates.push_back(hashrate);
+    }
+
+    /// Measured capacity limited to `cap`, None until enough rounds were seen.
+    pub fn sustained(&self, cap: Option<u64>) -> Option<u64> {
+        if self.hashrates.len() < WINDOW {
+            return None;
+        }
+        let measured = median(self.hashrates.iter().copied());
+        Some(cap.map_or(measured, |cap| measured.min(cap)))
+    }
+}
+
+/// Follows the nonce range sizes the pool assigns and flags when they shrink
+/// or no longer match our hashrate.
+#[derive(Default)]
+pub struct RangeTracker {
+    reference: Vec<u64>,
+    recent: VecDeque<u64>,
+    shrink_reported: bool,
+}
+
+impl RangeTracker {
+    /// Records a round's range and returns warnings worth showing.
+    pub fn record(&mut self, range_size: u64, hashrate: f64, cutoff: u64) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.reference.len() < WINDOW {
+            self.reference.push(range_size);
+        }
+        if self.recent.len() == WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(range_size);
+
+        if self.reference.len() == WINDOW && self.recent.len() == WINDOW {
+            let reference = median(self.reference.iter().copied());
+            let recent = median(self.recent.iter().copied());
+            let shrunk = (recent as f64) < reference as f64 * SHRINK_RATIO;
+            if shrunk && !self.shrink_reported {
+                warnings.push(format!(
+                    "Assigned nonce ranges shrank from {} to {} over the session, the pool may consider this miner slow.",
+                    reference, recent
+                ));
+            }
+            self.shrink_reported = shrunk;
+        }
+
+        let capacity = hashrate * cutoff as f64;
+        if capacity > 0.0 && (range_size as f64) < capacity * UNDERSIZED_RATIO {
+            warnings.push(format!(
+                "Assigned range of {} nonces covers only {:.0}% of what this miner can hash before the cutoff.",
+                range_size,
+                range_size as f64 * 100.0 / capacity
+            ));
+        }
+        warnings
+    }
+}
+
+/// Store collection of the nonce ranges each rig mined, to spot a pool handing two rigs
+/// sharing the store overlapping work.
+const ASSIGNMENTS_COLLECTION: &str = "assignments";
+/// Assignments older than this can't be for a challenge still being mined.
+const ASSIGNMENT_TTL_SECS: u64 = 300;
+
+/// What to do when another rig got part of our range for the same challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RangeCollision {
+    /// Only report the collision.
+    Log,
+    /// Report it and mine the largest part of the range no other rig has.
+    Offset,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Assignment {
+    at: u64,
+    pool: String,
+    wallet: String,
+    rig: String,
+    pid: u32,
+    challenge: String,
+    start: u64,
+    end: u64,
+}
+
+/// Largest part of `range` that none of `taken` covers, None if they cover all of it.
+fn largest_uncovered(range: &Range<u64>, taken: &[Range<u64>]) -> Option<Range<u64>> {
+    let mut free = vec![range.clone()];
+    for taken in taken {
+        free = free
+            .into_iter()
+            .flat_map(|part| [part.start..part.end.min(taken.start), part.start.max(taken.end)..part.end])
+            .filter(|part| !part.is_empty())
+            .collect();
+    }
+    free.into_iter().max_by_key(|part| part.end - part.start)
+}
+
+/// Checks `range` against what other rigs sharing the store were assigned for `challenge`
+/// and records the range that will be mined. Returns that range, and a description of the
+/// collision if there was one.
+pub fn check_collision(pool: &str, wallet: &str, challenge: &[u8; 32], range: Range<u64>, policy: RangeCollision) -> (Range<u64>, Option<String>) {
+    let challenge = solana_sdk::bs58::encode(challenge).into_string();
+    let rig = history::rig_name();
+    let pid = std::process::id();
+    let now = history::now();
+    let others: Vec<Assignment> = store::with(|store| store.load(ASSIGNMENTS_COLLECTION))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|line| serde_json::from_str::<Assignment>(line).ok())
+        .filter(|other| now.saturating_sub(other.at) < ASSIGNMENT_TTL_SECS && other.challenge == challenge && other.pool == pool)
+        .filter(|other| (other.rig.as_str(), other.pid) != (rig.as_str(), pid))
+        .filter(|other| other.start < range.end && range.start < other.end)
+        .collect();
+
+    let mut mined = range.clone();
+    let collision = (!others.is_empty()).then(|| {
+        let with = others
+            .iter()
+            .map(|other| format!("{} ({}..{}, wallet {})", other.rig, other.start, other.end, other.wallet))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut message = format!("Assigned range {}..{} for challenge {} overlaps the range of {}", range.start, range.end, challenge, with);
+        if policy == RangeCollision::Offset {
+            let taken: Vec<Range<u64>> = others.iter().map(|other| other.start..other.end).collect();
+            match largest_uncovered(&range, &taken) {
+                Some(free) => {
+                    message.push_str(&format!(", mining {}..{} instead.", free.start, free.end));
+                    mined = free;
+                },
+                None => message.push_str(", and no part of it is free, mining it anyway."),
+            }
+        } else {
+            message.push('.');
+        }
+        message
+    });
+
+    let assignment = Assignment {
+        at: now,
+        pool: pool.to_string(),
+        wallet: wallet.to_string(),
+        rig,
+        pid,
+        challenge,
+        start: mined.start,
+        end: mined.end,
+    };
+    if let Ok(line) = serde_json::to_string(&assignment) {
+        let _ = store::with(|store| store.append(ASSIGNMENTS_COLLECTION, &line));
+    }
+    (mined, collision)
+}
@@ -0,0 +1,128 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+use clap::ValueEnum;
+
+/// Base58 alphabet used by Solana pubkeys, hashes and signatures.
+const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// Length range of a base58 encoded 32 byte pubkey or hash.
+const PUBKEY_LEN: std::ops::RangeInclusive<usize> = 32..=44;
+/// Length range of a base58 encoded 64 byte signature.
+const SIGNATURE_LEN: std::ops::RangeInclusive<usize> = 86..=88;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogRedaction {
+    /// Shorten signatures and hide registered secrets such as resume tokens.
+    Secrets,
+    /// Also shorten pubkeys and hashes.
+    Pubkeys,
+    /// Print everything in full, for debugging.
+    Off,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(LogRedaction::Secrets as u8);
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn init(mode: LogRedaction) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+fn mode() -> LogRedaction {
+    match MODE.load(Ordering::Relaxed) {
+        m if m == LogRedaction::Off as u8 => LogRedaction::Off,
+        m if m == LogRedaction::Pubkeys as u8 => LogRedaction::Pubkeys,
+        _ => LogRedaction::Secrets,
+    }
+}
+
+/// Marks a value as secret so it never shows up in output, e.g. a session resume token.
+pub fn register_secret(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.lock().unwrap();
+    if !secrets.iter().any(|known| known == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// First and last four characters, e.g. `9xQe..Kd3P`.
+pub fn short(value: &str) -> String {
+    if value.len() <= 10 {
+        return value.to_string();
+    }
+    format!("{}..{}", &value[..4], &value[value.len() - 4..])
+}
+
+/// Applies the redaction mode to a line of output. All println! output goes through here,
+/// see the macros at the top of main.rs.
+pub fn filter(line: &str) -> Cow<'_, str> {
+    let mode = mode();
+    if mode == LogRedaction::Off {
+        return Cow::Borrowed(line);
+    }
+
+    let mut line = Cow::Borrowed(line);
+    for secret in SECRETS.lock().unwrap().iter() {
+        if line.contains(secret.as_str()) {
+            line = Cow::Owned(line.replace(secret.as_str(), "[redacted]"));
+        }
+    }
+
+    let is_base58 = |c: char| BASE58.contains(c);
+    let shorten = |word: &str| SIGNATURE_LEN.contains(&word.len()) || (mode == LogRedaction::Pubkeys && PUBKEY_LEN.contains(&word.len()));
+    if !line.split(|c| !is_base58(c)).any(shorten) {
+        return line;
+    }
+
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line.as_ref();
+    while let Some(start) = rest.find(is_base58) {
+        out.push_str(&rest[..start]);
+        let len = rest[start..].find(|c| !is_base58(c)).unwrap_or(rest.len() - start);
+        let word = &rest[start..start + len];
+        out.push_str(&if shorten(word) { short(word) } else { word.to_string() });
+        rest = &rest[start + len..];
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "DvNEd2HbW7QHvGkxtwjtHnaSq24yUAfDXZZrZSE9jm5z";
+    const SIGNATURE: &str = "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+    #[test]
+    fn short_keeps_both_ends() {
+        assert_eq!(short(PUBKEY), "DvNE..jm5z");
+        assert_eq!(short("0123456789"), "0123456789");
+    }
+
+    // One test, since the mode and the secrets are process wide
+    #[test]
+    fn filter_follows_the_mode() {
+        let line = format!("Submitted {} for {} with token s3cr3t-token.", SIGNATURE, PUBKEY);
+        register_secret("s3cr3t-token");
+        register_secret("");
+
+        init(LogRedaction::Secrets);
+        assert_eq!(filter(&line), format!("Submitted 5VER..kQUW for {} with token [redacted].", PUBKEY));
+        assert!(matches!(filter("Nothing to hide here."), Cow::Borrowed(_)));
+
+        init(LogRedaction::Pubkeys);
+        assert_eq!(filter(&line), "Submitted 5VER..kQUW for DvNE..jm5z with token [redacted].");
+
+        init(LogRedaction::Off);
+        assert_eq!(filter(&line), line);
+
+        init(LogRedaction::Secrets);
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveTime, Utc, Weekday};
+use tokio::task::JoinHandle;
+
+use crate::history;
+use crate::settings::{ReportPeriod, ReportSettings};
+use crate::tasks::TaskContext;
+
+/// Seconds until the next scheduled report, and the length of the period it covers.
+fn next_report(settings: &ReportSettings, at: NaiveTime) -> (u64, u64) {
+    let now = Utc::now();
+    let mut next = now.date_naive().and_time(at).and_utc();
+    while next <= now || (settings.period == ReportPeriod::Weekly && next.weekday() != Weekday::Mon) {
+        next += chrono::Duration::days(1);
+    }
+    let period_secs = match settings.period {
+        ReportPeriod::Daily => 24 * 60 * 60,
+        ReportPeriod::Weekly => 7 * 24 * 60 * 60,
+    };
+    ((next - now).num_seconds().max(0) as u64, period_secs)
+}
+
+/// Posts `text` to a Slack or Discord compatible webhook.
+pub async fn send(ctx: &TaskContext, webhook: &str, text: &str) -> Result<(), String> {
+    // "text" is read by Slack style webhooks, "content" by Discord
+    let payload = serde_json::json!({ "text": text, "content": text });
+    ctx.client
+        .post(webhook)
+        .header("Content-Type", "application/json")
+        .body(payload.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Posts a daily or weekly summary from the local history and the pool's rewards API
+/// to the configured webhook, for monitoring remote rigs.
+pub fn spawn(ctx: TaskContext, settings: ReportSettings) -> Option<JoinHandle<()>> {
+    let webhook = settings.webhook.clone()?;
+    let Ok(at) = NaiveTime::parse_from_str(&settings.at, "%H:%M") else {
+        println!("Invalid report time {:?}, expected HH:MM. Earnings reports are disabled.", settings.at);
+        return None;
+    };
+
+    Some(tokio::spawn(async move {
+        loop {
+            let (wait_secs, period_secs) = next_report(&settings, at);
+            tokio::time::sleep(Duration::from_secs(wait_secs)).await;
+
+            let end = history::now();
+            let title = match settings.period {
+                ReportPeriod::Daily => "Daily",
+                ReportPeriod::Weekly => "Weekly",
+            };
+            let mut lines = vec![format!("{} mining report for {} on {}", title, ctx.pubkey, history::rig_name())];
+            lines.extend(history::summary_lines(end.saturating_sub(period_secs), end, &ctx.pubkey.to_string(), None));
+            match ctx.fetch_rewards().await {
+                Some(rewards) => lines.push(format!("Unclaimed:       {:.11} ORE", rewards)),
+                None => lines.push("Unclaimed:       unavailable".to_string()),
+            }
+
+            let text = lines.join("\n");
+            if let Err(e) = send(&ctx, &webhook, &text).await {
+                println!("Failed to send earnings report: {}", e);
+            }
+        }
+    }))
+}
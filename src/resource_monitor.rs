@@ -0,0 +1,66 @@
+use std::{collections::VecDeque, fs, time::Duration};
+
+use tokio::task::JoinHandle;
+
+/// Number of consecutive samples that must grow before a leak warning is printed.
+const GROWTH_WINDOW: usize = 6;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceSample {
+    pub rss_kb: u64,
+    pub open_fds: u64,
+}
+
+/// Reads the resident set size and open file descriptor count of this process.
+/// Only supported on Linux, returns None elsewhere.
+pub fn sample() -> Option<ResourceSample> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let rss_kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())?;
+
+    let open_fds = fs::read_dir("/proc/self/fd").ok()?.count() as u64;
+
+    Some(ResourceSample { rss_kb, open_fds })
+}
+
+fn monotonic_growth(values: impl Iterator<Item = u64>) -> bool {
+    let values: Vec<u64> = values.collect();
+    values.len() >= GROWTH_WINDOW && values.windows(2).all(|w| w[1] > w[0])
+}
+
+/// Periodically samples process resources, logs them and warns when RSS or
+/// the fd count keeps growing across the whole sampling window.
+pub fn spawn(interval_secs: u64) -> Option<JoinHandle<()>> {
+    if interval_secs == 0 || sample().is_none() {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut history: VecDeque<ResourceSample> = VecDeque::with_capacity(GROWTH_WINDOW);
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+            let Some(current) = sample() else {
+                continue;
+            };
+
+            if history.len() == GROWTH_WINDOW {
+                history.pop_front();
+            }
+            history.push_back(current);
+
+            println!("Resources: RSS {} MB, open fds {}", current.rss_kb / 1024, current.open_fds);
+
+            if monotonic_growth(history.iter().map(|s| s.rss_kb)) {
+                println!("WARNING: memory usage has grown for {} consecutive samples, possible leak.", GROWTH_WINDOW);
+            }
+            if monotonic_growth(history.iter().map(|s| s.open_fds)) {
+                println!("WARNING: open file descriptors have grown for {} consecutive samples, possible leak.", GROWTH_WINDOW);
+            }
+        }
+    }))
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use reqwest::header::{HeaderMap, HeaderName, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+
+use crate::history;
+
+pub const RESPONSE_CACHE_FILE: &str = "response_cache.json";
+
+/// Set by --refresh: cached responses are revalidated with the pool instead of reused.
+static REFRESH: AtomicBool = AtomicBool::new(false);
+
+/// A pool response body, with the validators to ask the pool whether it changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CachedResponse {
+    fetched_at: u64,
+    max_age: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+pub fn init(refresh: bool) {
+    REFRESH.store(refresh, Ordering::Relaxed);
+}
+
+/// How long a response of `endpoint` is reused without asking the pool, when it doesn't send
+/// a Cache-Control max-age. None for endpoints that must always be fetched, like the clock.
+fn default_ttl(endpoint: &str) -> Option<u64> {
+    match endpoint {
+        "timestamp" => None,
+        "miner/balance" | "miner/rewards" | "miner/stake" => Some(30),
+        "maintenance" => Some(60),
+        "claim-info" => Some(60 * 60),
+        _ => Some(60),
+    }
+}
+
+fn load_all() -> BTreeMap<String, CachedResponse> {
+    fs::read_to_string(RESPONSE_CACHE_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_all(cache: &BTreeMap<String, CachedResponse>) {
+    if let Ok(contents) = serde_json::to_string_pretty(cache) {
+        let _ = fs::write(RESPONSE_CACHE_FILE, contents);
+    }
+}
+
+/// The cached body for `url`, if it is still fresh and --refresh wasn't given.
+pub fn fresh(url: &str, endpoint: &str) -> Option<String> {
+    default_ttl(endpoint)?;
+    if REFRESH.load(Ordering::Relaxed) {
+        return None;
+    }
+    load_all()
+        .remove(url)
+        .filter(|cached| history::now().saturating_sub(cached.fetched_at) < cached.max_age)
+        .map(|cached| cached.body)
+}
+
+/// Conditional request headers for a stale cached response of `url`.
+pub fn validators(url: &str, endpoint: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if default_ttl(endpoint).is_none() {
+        return headers;
+    }
+    if let Some(cached) = load_all().remove(url) {
+        if let Some(etag) = cached.etag.and_then(|etag| etag.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = cached.last_modified.and_then(|last_modified| last_modified.parse().ok()) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    headers
+}
+
+/// The pool answered 304 Not Modified: the cached body is fresh again.
+pub fn revalidated(url: &str) -> Option<String> {
+    let mut cache = load_all();
+    let cached = cache.get_mut(url)?;
+    cached.fetched_at = history::now();
+    let body = cached.body.clone();
+    save_all(&cache);
+    Some(body)
+}
+
+/// Caches a successful response, honoring Cache-Control no-store and max-age.
+pub fn store(url: &str, endpoint: &str, headers: &HeaderMap, body: &str) {
+    let Some(ttl) = default_ttl(endpoint) else {
+        return;
+    };
+    let header = |name: HeaderName| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+    let cache_control = header(CACHE_CONTROL).unwrap_or_default();
+    let directives: Vec<&str> = cache_control.split(',').map(str::trim).collect();
+    if directives.contains(&"no-store") {
+        return;
+    }
+    let max_age = if directives.contains(&"no-cache") {
+        0
+    } else {
+        directives.iter().find_map(|directive| directive.strip_prefix("max-age=")?.parse().ok()).unwrap_or(ttl)
+    };
+    let mut cache = load_all();
+    cache.insert(
+        url.to_string(),
+        CachedResponse {
+            fetched_at: history::now(),
+            max_age,
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            body: body.to_string(),
+        },
+    );
+    save_all(&cache);
+}
+
+/// Drops the cached responses of a pool, after a claim or stake changed the account.
+pub fn forget(base_url: &str) {
+    let mut cache = load_all();
+    let before = cache.len();
+    cache.retain(|url, _| !url.contains(base_url));
+    if cache.len() != before {
+        save_all(&cache);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use crate::cluster::{self, Cluster};
+use crate::endpoint;
+use crate::history;
+use crate::mine::{self, mine, MineArgs};
+use crate::settings::{self, PoolSlice};
+use crate::signer::MinerSigner;
+
+/// Alternates between the pools in the `[[schedule]]` settings, mining each for its slice,
+/// until stopped with Ctrl+C. Prints per-pool stats after each slice and for the whole rotation.
+/// Nothing is mined if any slice's pool is refused for `cluster`.
+pub async fn rotate(args: MineArgs, key: MinerSigner, unsecure: bool, rpc_url: String, cluster: Cluster) {
+    let slices = settings::load().schedule;
+    if slices.is_empty() {
+        println!("No pools to rotate between, add [[schedule]] entries with a name and url to {}.", settings::path().display());
+        return;
+    }
+    if let Some(slice) = slices.iter().find(|slice| slice.rounds.is_none() && slice.minutes.is_none()) {
+        println!("Schedule entry {} needs rounds or minutes, otherwise the rotation would never move on.", slice.name);
+        return;
+    }
+    if !slices.iter().all(|slice| cluster::check_pool(cluster, &endpoint::normalize(&slice.url).0, &key.pubkey())) {
+        return;
+    }
+
+    let wallet = key.pubkey().to_string();
+    let rotation_start = history::now();
+    let mut slices_mined: BTreeMap<&str, u64> = BTreeMap::new();
+    let servers = mine::serve_endpoints(&args).await;
+
+    'rotation: loop {
+        for slice in &slices {
+            println!("Switching to {} ({}) for {}.", slice.name, slice.url, describe(slice));
+            let mut slice_args = args.clone();
+            slice_args.max_rounds = slice.rounds;
+            slice_args.max_minutes = slice.minutes;
+            // Per-pool stats would be meaningless if a slice failed over to another pool
+            slice_args.failover_urls.clear();
+
+            let slice_start = history::now();
+            let completed = mine(slice_args, key.clone(), slice.url.clone(), unsecure, rpc_url.clone()).await;
+            *slices_mined.entry(&slice.name).or_default() += 1;
+
+            println!("{} slice summary:", slice.name);
+            for line in history::summary_lines(slice_start, history::now() + 1, &wallet, Some(&slice.url)) {
+                println!("  {}", line);
+            }
+            if !completed {
+                break 'rotation;
+            }
+        }
+    }
+    for server in servers {
+        server.abort();
+    }
+
+    println!("Rotation summary:");
+    for slice in &slices {
+        let Some(count) = slices_mined.get(slice.name.as_str()) else {
+            continue;
+        };
+        println!("  {} ({}), {} slices:", slice.name, slice.url, count);
+        for line in history::summary_lines(rotation_start, history::now() + 1, &wallet, Some(&slice.url)) {
+            println!("    {}", line);
+        }
+    }
+}
+
+fn describe(slice: &PoolSlice) -> String {
+    match (slice.rounds, slice.minutes) {
+        (Some(rounds), Some(minutes)) => format!("{} rounds or {} minutes", rounds, minutes),
+        (Some(rounds), None) => format!("{} rounds", rounds),
+        (None, Some(minutes)) => format!("{} minutes", minutes),
+        (None, None) => "until stopped".to_string(),
+    }
+}
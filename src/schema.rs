@@ -0,0 +1,283 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use serde_json::{Map, Value};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::endpoint;
+use crate::response_cache;
+
+/// Newest version of the JSON responses this client understands. Responses without a
+/// `version` field are treated as version 1.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Why a pool response could not be used, with enough detail to report to the pool operator.
+#[derive(Debug)]
+pub enum SchemaError {
+    Request { endpoint: &'static str, detail: String },
+    Status { endpoint: &'static str, status: u16 },
+    NotJson { endpoint: &'static str, detail: String },
+    UnknownVersion { endpoint: &'static str, version: u64 },
+    MissingField { endpoint: &'static str, field: &'static str },
+    WrongType { endpoint: &'static str, field: &'static str, expected: &'static str, found: &'static str },
+    Invalid { endpoint: &'static str, detail: String },
+}
+
+impl SchemaError {
+    /// True when the pool doesn't serve the endpoint at all, as opposed to serving it wrong.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, SchemaError::Status { status: 404, .. })
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Request { endpoint, detail } => write!(f, "request to /{} failed: {}", endpoint, detail),
+            SchemaError::Status { endpoint, status } => write!(f, "/{} returned HTTP {}", endpoint, status),
+            SchemaError::NotJson { endpoint, detail } => write!(f, "/{} did not return a JSON object: {}", endpoint, detail),
+            SchemaError::UnknownVersion { endpoint, version } => write!(
+                f,
+                "/{} uses response version {}, this client understands up to {}; update the client",
+                endpoint, version, SCHEMA_VERSION
+            ),
+            SchemaError::MissingField { endpoint, field } => write!(f, "/{} response is missing the `{}` field", endpoint, field),
+            SchemaError::WrongType { endpoint, field, expected, found } => {
+                write!(f, "/{} field `{}` should be {} but is {}", endpoint, field, expected, found)
+            },
+            SchemaError::Invalid { endpoint, detail } => write!(f, "/{} response is invalid: {}", endpoint, detail),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A response body from one of the pool's REST endpoints.
+pub trait PoolResponse: Sized {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError>;
+}
+
+/// GETs an endpoint, with a query string such as `?pubkey=...` or an empty one, and parses the body as `T`.
+/// Responses are cached per URL and revalidated with ETag or Last-Modified once stale.
+pub async fn get<T: PoolResponse>(client: &reqwest::Client, url_prefix: &str, base_url: &str, endpoint: &'static str, query: &str) -> Result<T, SchemaError> {
+    let url = endpoint::http(url_prefix, base_url, &format!("{}{}", endpoint, query));
+    if let Some(body) = response_cache::fresh(&url, endpoint) {
+        return T::parse(endpoint, &body);
+    }
+    let response = client
+        .get(&url)
+        .headers(response_cache::validators(&url, endpoint))
+        .send()
+        .await
+        .map_err(|e| SchemaError::Request { endpoint, detail: e.to_string() })?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(body) = response_cache::revalidated(&url) {
+            return T::parse(endpoint, &body);
+        }
+    }
+    if !response.status().is_success() {
+        return Err(SchemaError::Status { endpoint, status: response.status().as_u16() });
+    }
+    let headers = response.headers().clone();
+    let body = response.text().await.map_err(|e| SchemaError::Request { endpoint, detail: e.to_string() })?;
+    let parsed = T::parse(endpoint, &body)?;
+    response_cache::store(&url, endpoint, &headers, &body);
+    Ok(parsed)
+}
+
+fn invalid(endpoint: &'static str, expected: &str, body: &str) -> SchemaError {
+    let body = body.trim();
+    let shown: String = body.chars().take(64).collect();
+    let ellipsis = if shown.len() < body.len() { "..." } else { "" };
+    SchemaError::Invalid { endpoint, detail: format!("expected {}, got \"{}{}\"", expected, shown, ellipsis) }
+}
+
+/// `/timestamp`: the server's unix time as plain text.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerTime(pub u64);
+
+impl PoolResponse for ServerTime {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        body.trim().parse().map(ServerTime).map_err(|_| invalid(endpoint, "a unix timestamp", body))
+    }
+}
+
+/// `/miner/balance` and `/miner/rewards`: an ORE amount as plain text.
+#[derive(Debug, Clone, Copy)]
+pub struct OreAmount(pub f64);
+
+impl PoolResponse for OreAmount {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        body.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| amount.is_finite() && *amount >= 0.0)
+            .map(OreAmount)
+            .ok_or_else(|| invalid(endpoint, "an ORE amount", body))
+    }
+}
+
+/// `/miner/stake`: the delegated stake, or an error text when the wallet has no stake account.
+#[derive(Debug, Clone, Copy)]
+pub struct StakeAmount(pub Option<f64>);
+
+impl PoolResponse for StakeAmount {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        if body.contains("Failed to g") {
+            return Ok(StakeAmount(None));
+        }
+        OreAmount::parse(endpoint, body).map(|amount| StakeAmount(Some(amount.0)))
+    }
+}
+
+/// `/pool/authority/pubkey` and `/pool/fee_payer/pubkey`: a base58 pubkey as plain text.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolPubkey(pub Pubkey);
+
+impl PoolResponse for PoolPubkey {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        Pubkey::from_str(body.trim()).map(PoolPubkey).map_err(|_| invalid(endpoint, "a base58 pubkey", body))
+    }
+}
+
+/// A JSON object response whose version has been checked.
+struct JsonObject {
+    endpoint: &'static str,
+    fields: Map<String, Value>,
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+impl JsonObject {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        let value: Value = serde_json::from_str(body).map_err(|e| SchemaError::NotJson { endpoint, detail: e.to_string() })?;
+        let Value::Object(fields) = value else {
+            return Err(SchemaError::NotJson { endpoint, detail: format!("got {}", type_name(&value)) });
+        };
+        let object = JsonObject { endpoint, fields };
+        let version = object.optional_u64("version")?.unwrap_or(1);
+        if version > SCHEMA_VERSION {
+            return Err(SchemaError::UnknownVersion { endpoint, version });
+        }
+        Ok(object)
+    }
+
+    fn optional<T>(&self, field: &'static str, expected: &'static str, convert: impl Fn(&Value) -> Option<T>) -> Result<Option<T>, SchemaError> {
+        match self.fields.get(field) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => convert(value)
+                .map(Some)
+                .ok_or(SchemaError::WrongType { endpoint: self.endpoint, field, expected, found: type_name(value) }),
+        }
+    }
+
+    fn optional_u64(&self, field: &'static str) -> Result<Option<u64>, SchemaError> {
+        self.optional(field, "a non-negative integer", Value::as_u64)
+    }
+
+    fn optional_f64(&self, field: &'static str) -> Result<Option<f64>, SchemaError> {
+        self.optional(field, "a number", Value::as_f64)
+    }
+
+    fn required_u64(&self, field: &'static str) -> Result<u64, SchemaError> {
+        self.optional_u64(field)?.ok_or(SchemaError::MissingField { endpoint: self.endpoint, field })
+    }
+
+    fn optional_str(&self, field: &'static str) -> Result<Option<String>, SchemaError> {
+        self.optional(field, "a string", |value| value.as_str().map(str::to_string))
+    }
+
+    fn required_str(&self, field: &'static str) -> Result<String, SchemaError> {
+        self.optional_str(field)?.ok_or(SchemaError::MissingField { endpoint: self.endpoint, field })
+    }
+}
+
+/// `/claim-info`: the pool's claim rules in ORE. Missing fields mean the client defaults apply.
+#[derive(Debug, Clone, Copy)]
+pub struct ClaimRules {
+    pub min_claim: Option<f64>,
+    pub fee: Option<f64>,
+    pub token_account_fee: Option<f64>,
+}
+
+impl PoolResponse for ClaimRules {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        let object = JsonObject::parse(endpoint, body)?;
+        let rules = ClaimRules {
+            min_claim: object.optional_f64("min_claim")?,
+            fee: object.optional_f64("fee")?,
+            token_account_fee: object.optional_f64("token_account_fee")?,
+        };
+        if [rules.min_claim, rules.fee, rules.token_account_fee].into_iter().flatten().any(|amount| amount < 0.0) {
+            return Err(SchemaError::Invalid { endpoint, detail: "claim amounts and fees can't be negative".to_string() });
+        }
+        Ok(rules)
+    }
+}
+
+/// `/maintenance`: `{"start": <unix>, "end": <unix>}`, or an empty body, `null` or `{}`
+/// when nothing is scheduled.
+#[derive(Debug, Clone)]
+pub struct MaintenanceWindow(pub Option<Range<u64>>);
+
+impl PoolResponse for MaintenanceWindow {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        if body.trim().is_empty() || body.trim() == "null" {
+            return Ok(MaintenanceWindow(None));
+        }
+        let object = JsonObject::parse(endpoint, body)?;
+        if !object.fields.contains_key("start") && !object.fields.contains_key("end") {
+            return Ok(MaintenanceWindow(None));
+        }
+        let start = object.required_u64("start")?;
+        let end = object.required_u64("end")?;
+        if end <= start {
+            return Err(SchemaError::Invalid { endpoint, detail: format!("end {} is not after start {}", end, start) });
+        }
+        Ok(MaintenanceWindow(Some(start..end)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyAction {
+    Kick,
+    Ban,
+}
+
+/// Why the pool disconnected or refused us for policy reasons, sent as
+/// `{"type": "kick" | "ban", "reason": "...", "contact": "...", "until": <unix>}` in a close
+/// frame, a text message or the body of a rejected websocket upgrade.
+#[derive(Debug, Clone)]
+pub struct PolicyNotice {
+    pub action: PolicyAction,
+    pub reason: String,
+    /// How to reach the pool operator, e.g. an email address or Discord handle.
+    pub contact: Option<String>,
+    /// When the kick or ban ends, if it is temporary.
+    pub until: Option<u64>,
+}
+
+impl PoolResponse for PolicyNotice {
+    fn parse(endpoint: &'static str, body: &str) -> Result<Self, SchemaError> {
+        let object = JsonObject::parse(endpoint, body)?;
+        let action = match object.required_str("type")?.as_str() {
+            "kick" => PolicyAction::Kick,
+            "ban" => PolicyAction::Ban,
+            other => return Err(SchemaError::Invalid { endpoint, detail: format!("unknown policy action {}", other) }),
+        };
+        Ok(PolicyNotice {
+            action,
+            reason: object.required_str("reason")?,
+            contact: object.optional_str("contact")?,
+            until: object.optional_u64("until")?,
+        })
+    }
+}
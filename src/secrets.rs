@@ -0,0 +1,393 @@
+use std::{
+    fs,
+    io::{IsTerminal, Write},
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+use aes_gcm_siv::{
+    aead::{Aead, NewAead},
+    Aes256GcmSiv, Key, Nonce,
+};
+use base64::prelude::*;
+use hmac::Hmac;
+use rand::RngCore;
+use sha2::Sha256;
+use toml::Value;
+
+use crate::redact;
+use crate::settings;
+
+/// Marks an encrypted settings value: the prefix, then base64 of salt, nonce and ciphertext.
+const PREFIX: &str = "enc:v1:";
+/// Passphrase for encrypted settings, read before the OS keychain and the terminal.
+pub const PASSPHRASE_ENV: &str = "ORE_HQ_CONFIG_PASSPHRASE";
+/// Service and account the passphrase is stored under in the OS keychain.
+const KEYCHAIN_SERVICE: &str = "ore-hq-client";
+const KEYCHAIN_ACCOUNT: &str = "config";
+const PBKDF2_ROUNDS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Settings holding credentials, encrypted by `config encrypt` unless --field names others.
+pub const DEFAULT_FIELDS: &[&str] = &["report.webhook"];
+
+static PASSPHRASE: OnceLock<Option<String>> = OnceLock::new();
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Aes256GcmSiv {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    Aes256GcmSiv::new(&Key::from(key))
+}
+
+pub fn encrypt(passphrase: &str, plaintext: &str) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher(passphrase, &salt)
+        .encrypt(&Nonce::from(nonce), plaintext.as_bytes())
+        .expect("encrypting a settings value");
+    let mut sealed = salt.to_vec();
+    sealed.extend_from_slice(&nonce);
+    sealed.extend(ciphertext);
+    format!("{}{}", PREFIX, BASE64_STANDARD.encode(sealed))
+}
+
+pub fn decrypt(passphrase: &str, value: &str) -> Result<String, String> {
+    let sealed = value
+        .strip_prefix(PREFIX)
+        .and_then(|encoded| BASE64_STANDARD.decode(encoded).ok())
+        .filter(|sealed| sealed.len() > SALT_LEN + NONCE_LEN)
+        .ok_or("malformed encrypted value")?;
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into().expect("split at NONCE_LEN");
+    let plaintext = cipher(passphrase, salt)
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| "wrong passphrase".to_string())?;
+    String::from_utf8(plaintext).map_err(|_| "not valid text".to_string())
+}
+
+/// Reads the passphrase from the OS keychain: the Secret Service on Linux, the login
+/// keychain on macOS.
+fn keychain_get() -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a", KEYCHAIN_ACCOUNT, "-w"])
+            .stderr(Stdio::null())
+            .output()
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", KEYCHAIN_SERVICE, "account", KEYCHAIN_ACCOUNT])
+            .stderr(Stdio::null())
+            .output()
+    };
+    let output = output.ok().filter(|output| output.status.success())?;
+    let passphrase = String::from_utf8(output.stdout).ok()?.trim_end_matches('\n').to_string();
+    (!passphrase.is_empty()).then_some(passphrase)
+}
+
+/// Stores the passphrase in the OS keychain. It never goes on a command line, where other users
+/// could read it: secret-tool reads it on stdin and macOS `security` asks for it on the terminal.
+pub fn keychain_store(passphrase: &str) -> Result<(), String> {
+    let status = if cfg!(target_os = "macos") {
+        println!("  Enter the passphrase again for the login keychain.");
+        // -w last makes security prompt for the password
+        Command::new("security")
+            .args(["add-generic-password", "-U", "-s", KEYCHAIN_SERVICE, "-a", KEYCHAIN_ACCOUNT, "-w"])
+            .status()
+    } else {
+        Command::new("secret-tool")
+            .args(["store", "--label", "ore-hq-client settings", "service", KEYCHAIN_SERVICE, "account", KEYCHAIN_ACCOUNT])
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                child.stdin.take().expect("piped stdin").write_all(passphrase.as_bytes())?;
+                child.wait()
+            })
+    };
+    match status {
+        // What was typed at the macOS prompt must be the passphrase the settings use
+        Ok(status) if status.success() && keychain_get().as_deref() != Some(passphrase) => {
+            Err("the keychain now holds a different passphrase than the settings are encrypted with".to_string())
+        },
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("keychain helper exited with {}", status)),
+        Err(e) => Err(format!("no keychain helper available ({})", e)),
+    }
+}
+
+/// The passphrase from the environment, the OS keychain or, on a terminal, a prompt.
+/// Asked for at most once per run.
+pub fn passphrase() -> Option<&'static str> {
+    PASSPHRASE
+        .get_or_init(|| {
+            std::env::var(PASSPHRASE_ENV)
+                .ok()
+                .filter(|passphrase| !passphrase.is_empty())
+                .or_else(keychain_get)
+                .or_else(|| {
+                    std::io::stdin().is_terminal().then(|| rpassword::prompt_password("Passphrase for encrypted settings: ").ok()).flatten()
+                })
+        })
+        .as_deref()
+}
+
+/// Visits every string in `value`, with its dotted path such as `report.webhook` or
+/// `schedule.0.url`. Strings for which `f` returns false are removed.
+fn retain_strings(value: &mut Value, path: &str, f: &mut impl FnMut(&str, &mut String) -> bool) -> bool {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match value {
+        Value::String(text) => f(path, text),
+        Value::Table(table) => {
+            let dropped: Vec<String> = table
+                .iter_mut()
+                .filter_map(|(key, value)| (!retain_strings(value, &join(key), f)).then(|| key.clone()))
+                .collect();
+            for key in dropped {
+                table.remove(&key);
+            }
+            true
+        },
+        Value::Array(items) => {
+            let mut index = 0;
+            items.retain_mut(|value| {
+                index += 1;
+                retain_strings(value, &join(&(index - 1).to_string()), f)
+            });
+            true
+        },
+        _ => true,
+    }
+}
+
+/// Decrypts every encrypted string in a parsed settings file. Values that can't be decrypted
+/// are dropped with a warning, so their settings fall back to defaults.
+pub fn decrypt_settings(value: &mut Value) {
+    retain_strings(value, "", &mut |path, text| {
+        if !is_encrypted(text) {
+            return true;
+        }
+        let Some(passphrase) = passphrase() else {
+            println!("  Ignoring encrypted setting {}: no passphrase in {}, the keychain or a terminal.", path, PASSPHRASE_ENV);
+            return false;
+        };
+        match decrypt(passphrase, text) {
+            Ok(plaintext) => {
+                redact::register_secret(&plaintext);
+                *text = plaintext;
+                true
+            },
+            Err(e) => {
+                println!("  Ignoring encrypted setting {}: {}", path, e);
+                false
+            },
+        }
+    });
+}
+
+/// Paths of the encrypted strings in a parsed settings file.
+pub fn encrypted_paths(value: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    retain_strings(&mut value.clone(), "", &mut |path, text| {
+        if is_encrypted(text) {
+            paths.push(path.to_string());
+        }
+        true
+    });
+    paths
+}
+
+/// Encrypts the plain strings at `paths`, returning the paths that were encrypted.
+pub fn encrypt_paths(value: &mut Value, paths: &[String], passphrase: &str) -> Vec<String> {
+    let mut encrypted = Vec::new();
+    retain_strings(value, "", &mut |path, text| {
+        if paths.iter().any(|wanted| wanted == path) && !is_encrypted(text) {
+            *text = encrypt(passphrase, text);
+            encrypted.push(path.to_string());
+        }
+        true
+    });
+    encrypted
+}
+
+/// The string at a dotted path of `retain_strings` in an editable settings file.
+fn document_string<'a>(mut item: &'a mut toml_edit::Item, path: &str) -> Option<&'a mut toml_edit::Value> {
+    for segment in path.split('.') {
+        item = match segment.parse::<usize>() {
+            Ok(index) if item.is_array() || item.is_array_of_tables() => item.get_mut(index)?,
+            // Indexing a missing key inserts it, so look it up first
+            _ => {
+                item.get(segment)?;
+                item.get_mut(segment)?
+            },
+        };
+    }
+    item.as_value_mut().filter(|value| value.is_str())
+}
+
+/// The settings file `contents` with the strings at `paths` replaced by their values in
+/// `value`, keeping the comments and layout of the file.
+fn rewrite(contents: &str, value: &Value, paths: &[String]) -> Result<String, String> {
+    let mut document: toml_edit::Document = contents.parse().map_err(|e: toml_edit::TomlError| e.to_string())?;
+    for path in paths {
+        let text = path
+            .split('.')
+            .try_fold(value, |value, segment| match segment.parse::<usize>() {
+                Ok(index) if value.is_array() => value.get(index),
+                _ => value.get(segment),
+            })
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("{} is not a string", path))?;
+        let target = document_string(document.as_item_mut(), path).ok_or_else(|| format!("{} is not in the file", path))?;
+        let decor = target.decor().clone();
+        *target = text.into();
+        *target.decor_mut() = decor;
+    }
+    Ok(document.to_string())
+}
+
+/// Asks for a new passphrase twice on the terminal, unless one is already configured.
+fn new_passphrase() -> Result<String, String> {
+    if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()).or_else(keychain_get) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("New passphrase for encrypted settings: ").map_err(|e| e.to_string())?;
+    if passphrase.is_empty() {
+        return Err("the passphrase can't be empty".to_string());
+    }
+    if rpassword::prompt_password("Repeat the passphrase: ").map_err(|e| e.to_string())? != passphrase {
+        return Err("the passphrases don't match".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// `config encrypt`: encrypts `fields`, or DEFAULT_FIELDS, in the settings file in place.
+pub fn encrypt_settings_file(fields: &[String], keychain: bool) {
+    let path = settings::path();
+    let read = fs::read_to_string(path).map_err(|e| e.to_string());
+    let (contents, mut value) = match read.and_then(|contents| contents.parse::<Value>().map(|value| (contents, value)).map_err(|e| e.to_string())) {
+        Ok(read) => read,
+        Err(e) => {
+            println!("  Failed to read {}: {}", path.display(), e);
+            return;
+        },
+    };
+    let fields: Vec<String> = if fields.is_empty() { DEFAULT_FIELDS.iter().map(|field| field.to_string()).collect() } else { fields.to_vec() };
+
+    // Everything in one file is encrypted with the same passphrase
+    let passphrase = if encrypted_paths(&value).is_empty() {
+        new_passphrase()
+    } else {
+        passphrase().map(str::to_string).ok_or_else(|| format!("no passphrase for the values already encrypted in {}", path.display()))
+    };
+    let passphrase = match passphrase {
+        Ok(passphrase) => passphrase,
+        Err(e) => {
+            println!("  Not encrypting: {}", e);
+            return;
+        },
+    };
+
+    let encrypted = encrypt_paths(&mut value, &fields, &passphrase);
+    let already = encrypted_paths(&value);
+    for field in &fields {
+        if !already.contains(field) {
+            println!("  {} is not set or not a string, skipped.", field);
+        } else if !encrypted.contains(field) {
+            println!("  {} is already encrypted.", field);
+        }
+    }
+    if !encrypted.is_empty() {
+        let written = rewrite(&contents, &value, &encrypted).and_then(|contents| fs::write(path, contents).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => println!("  Encrypted {} in {}.", encrypted.join(", "), path.display()),
+            Err(e) => {
+                println!("  Failed to write {}: {}", path.display(), e);
+                return;
+            },
+        }
+    }
+
+    if keychain {
+        match keychain_store(&passphrase) {
+            Ok(()) => println!("  Stored the passphrase in the OS keychain."),
+            Err(e) => println!("  Failed to store the passphrase in the OS keychain: {}", e),
+        }
+    } else if !already.is_empty() {
+        println!("  Set {} or run with --keychain to store the passphrase, otherwise it is asked for at startup.", PASSPHRASE_ENV);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = r#"# Rig settings
+[report]
+# Alerts go to the ops channel
+webhook = "https://hooks.example.com/abc" # keep private
+
+[[schedule]]
+url = "pool-a.example.com"
+minutes = 30
+
+[[schedule]]
+url = "pool-b.example.com"
+minutes = 30
+"#;
+
+    #[test]
+    fn decrypts_what_it_encrypted() {
+        let sealed = encrypt("hunter2", "https://hooks.example.com/abc");
+        assert!(is_encrypted(&sealed));
+        assert_eq!(decrypt("hunter2", &sealed).unwrap(), "https://hooks.example.com/abc");
+        assert_eq!(decrypt("hunter3", &sealed).unwrap_err(), "wrong passphrase");
+        assert_eq!(decrypt("hunter2", "enc:v1:AAAA").unwrap_err(), "malformed encrypted value");
+    }
+
+    #[test]
+    fn walks_tables_and_arrays_of_tables() {
+        let mut value: Value = SETTINGS.parse().unwrap();
+        let mut paths = Vec::new();
+        retain_strings(&mut value, "", &mut |path, _| {
+            paths.push(path.to_string());
+            path != "schedule.0.url"
+        });
+        assert_eq!(paths, ["report.webhook", "schedule.0.url", "schedule.1.url"]);
+        assert!(value["schedule"][0].get("url").is_none());
+        assert_eq!(value["schedule"][1]["url"].as_str(), Some("pool-b.example.com"));
+    }
+
+    #[test]
+    fn encrypts_only_the_named_paths() {
+        let mut value: Value = SETTINGS.parse().unwrap();
+        let fields = ["schedule.1.url".to_string(), "report.missing".to_string()];
+        assert_eq!(encrypt_paths(&mut value, &fields, "hunter2"), ["schedule.1.url"]);
+        assert_eq!(encrypted_paths(&value), ["schedule.1.url"]);
+        assert!(encrypt_paths(&mut value, &fields, "hunter2").is_empty());
+        assert_eq!(value["report"]["webhook"].as_str(), Some("https://hooks.example.com/abc"));
+        assert_eq!(decrypt("hunter2", value["schedule"][1]["url"].as_str().unwrap()).unwrap(), "pool-b.example.com");
+    }
+
+    #[test]
+    fn rewrite_keeps_comments() {
+        let mut value: Value = SETTINGS.parse().unwrap();
+        let fields = ["report.webhook".to_string(), "schedule.0.url".to_string()];
+        let encrypted = encrypt_paths(&mut value, &fields, "hunter2");
+        let rewritten = rewrite(SETTINGS, &value, &encrypted).unwrap();
+        for comment in ["# Rig settings", "# Alerts go to the ops channel", "# keep private"] {
+            assert!(rewritten.contains(comment), "{} dropped from\n{}", comment, rewritten);
+        }
+        let reparsed: Value = rewritten.parse().unwrap();
+        assert_eq!(encrypted_paths(&reparsed), encrypted);
+        assert_eq!(reparsed["schedule"][1]["url"].as_str(), Some("pool-b.example.com"));
+        assert_eq!(decrypt("hunter2", reparsed["report"]["webhook"].as_str().unwrap()).unwrap(), "https://hooks.example.com/abc");
+        assert!(rewrite(SETTINGS, &value, &["report.missing".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::{stream::SplitSink, Sink, SinkExt};
+use solana_sdk::{pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer};
+use tokio::{net::TcpStream, sync::Mutex, task::JoinHandle};
+use tokio_tungstenite::{
+    tungstenite::{handshake::client::Response, Error as WsError, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::audit;
+use crate::clock;
+use crate::format;
+use crate::frames::{Direction, FrameDump};
+use crate::mine::{say, MineArgs};
+use crate::network;
+use crate::pool_cache;
+use crate::protocol::{self, Quirks};
+use crate::signer::MinerSigner;
+use crate::tasks::TaskContext;
+
+/// How long a session key delegation stays valid.
+const SESSION_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Attempts at sending a frame before the websocket counts as broken, and the delay before
+/// the first retry, growing with each attempt.
+const SEND_ATTEMPTS: u32 = 3;
+const SEND_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+pub type WsSender = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+/// An ephemeral keypair the master key delegates frame signing to for one connection.
+pub struct SessionKey {
+    pub keypair: Keypair,
+    pub certificate: Signature,
+    pub expires_at: u64,
+}
+
+impl SessionKey {
+    /// Generates a session keypair and signs the delegation certificate with the master key.
+    pub fn generate(master: &dyn Signer, now: u64) -> Self {
+        let keypair = Keypair::new();
+        let expires_at = now + SESSION_TTL_SECS;
+        let certificate = audit::sign(master, "session-certificate", &certificate_message(&keypair.pubkey(), expires_at));
+        SessionKey {
+            keypair,
+            certificate,
+            expires_at,
+        }
+    }
+}
+
+/// Message signed by the master key: domain tag, session pubkey and expiry timestamp.
+fn certificate_message(session_pubkey: &Pubkey, expires_at: u64) -> Vec<u8> {
+    let mut msg = b"ore-hq-session".to_vec();
+    msg.extend_from_slice(&session_pubkey.to_bytes());
+    msg.extend_from_slice(&expires_at.to_le_bytes());
+    msg
+}
+
+/// Sends a frame to the pool, retrying failed sends with a short backoff. Returns the error once
+/// the websocket is closed or every attempt failed, the connection then has to be replaced.
+async fn send_frame<S>(sender: &Mutex<S>, frame_dump: &FrameDump, message: Message) -> Result<(), WsError>
+where
+    S: Sink<Message, Error = WsError> + Unpin,
+{
+    frame_dump.record(Direction::Out, &message);
+    let mut sender = sender.lock().await;
+    let mut attempt = 1;
+    loop {
+        match network::within(network::write_timeout(), "sending", sender.send(message.clone())).await {
+            Ok(()) => return Ok(()),
+            Err(e @ (WsError::ConnectionClosed | WsError::AlreadyClosed)) => return Err(e),
+            Err(e) if attempt >= SEND_ATTEMPTS => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(SEND_RETRY_DELAY * attempt).await;
+                attempt += 1;
+            },
+        }
+    }
+}
+
+/// The best hash of a round, with what the timing extension reports about it.
+pub struct BestSolution {
+    pub hash: drillx_2::Hash,
+    pub nonce: u64,
+    pub hash_started: SystemTime,
+    pub hash_time: Duration,
+    /// How long the challenge took to arrive after Ready.
+    pub challenge_latency: Option<Duration>,
+}
+
+/// How a round's solution reached the pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Submission {
+    Websocket,
+    /// Over HTTP, after the websocket failed.
+    Http,
+    /// Not sent, the same nonce was already submitted for the challenge.
+    Duplicate,
+    Failed(String),
+}
+
+impl Submission {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Submission::Websocket => "websocket",
+            Submission::Http => "http",
+            Submission::Duplicate => "duplicate",
+            Submission::Failed(_) => "failed",
+        }
+    }
+}
+
+/// One websocket connection to the pool: signs and sends the frames of each round, with the
+/// key the pool accepted and the extensions it acknowledged in the upgrade response.
+pub struct Session {
+    key: MinerSigner,
+    /// Signs frames instead of `key` when the pool accepted the delegation.
+    session_key: Option<SessionKey>,
+    quirks: Quirks,
+    base_url: String,
+    sender: Arc<Mutex<WsSender>>,
+    /// Reads the pool's frames, finished once the websocket is closed.
+    receiver: JoinHandle<()>,
+    frame_dump: Arc<FrameDump>,
+    task_ctx: TaskContext,
+    send_timing: bool,
+    attestation_interval: Option<u64>,
+    /// Start of the hashes counted for the next attestation.
+    attestation_start: u64,
+    attested_hashes: u64,
+    capacity_interval: Option<u64>,
+    capacity_reported_at: Option<u64>,
+}
+
+impl Session {
+    pub fn new(key: MinerSigner, quirks: Quirks, base_url: String, sender: Arc<Mutex<WsSender>>, receiver: JoinHandle<()>, frame_dump: Arc<FrameDump>, task_ctx: TaskContext) -> Self {
+        Session {
+            key,
+            session_key: None,
+            quirks,
+            base_url,
+            sender,
+            receiver,
+            frame_dump,
+            task_ctx,
+            send_timing: false,
+            attestation_interval: None,
+            attestation_start: clock::now(),
+            attested_hashes: 0,
+            capacity_interval: None,
+            capacity_reported_at: None,
+        }
+    }
+
+    /// Applies what the pool answered to the extensions offered in the upgrade request: the
+    /// session key delegation, attestations, capacity reports and timing metadata.
+    pub fn negotiate(&mut self, response: &Response, offered: Option<SessionKey>, args: &MineArgs, timing: bool) {
+        let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok());
+
+        let session_accepted = header("X-Session-Key").is_some_and(|v| v.eq_ignore_ascii_case("accepted"));
+        if offered.is_some() {
+            pool_cache::update(&self.base_url, |caps| caps.session_keys = Some(session_accepted));
+        }
+        self.session_key = match offered {
+            Some(session) if session_accepted => {
+                say!("Signing frames with session key {}", session.keypair.pubkey());
+                Some(session)
+            },
+            Some(_) => {
+                say!("Server does not support session keys, signing frames with the main keypair.");
+                None
+            },
+            None => None,
+        };
+
+        // Servers that want attestations reply with the interval they expect them at
+        self.attestation_interval = header("X-Attestation-Interval").and_then(|v| v.parse::<u64>().ok()).filter(|secs| args.attestations && *secs > 0);
+        if let Some(secs) = self.attestation_interval {
+            say!("Sending hashrate attestations every {}s.", secs);
+        }
+        self.attestation_start = clock::now();
+
+        // Same for capacity reports, which the server uses to size our nonce ranges
+        self.capacity_interval = header("X-Capacity-Interval").and_then(|v| v.parse::<u64>().ok()).filter(|secs| args.report_capacity && *secs > 0);
+        if args.report_capacity && self.capacity_interval.is_none() {
+            say!("Server does not accept capacity reports.");
+        }
+
+        // Timing metadata is only appended when the server acknowledges it understands the extension
+        self.send_timing = timing && header("X-Timing-Metadata") == Some("1");
+        if timing && !self.send_timing {
+            say!("Server does not accept timing metadata, submitting without it.");
+        }
+    }
+
+    fn signer(&self) -> &dyn Signer {
+        match &self.session_key {
+            Some(session) => &session.keypair,
+            None => self.key.as_ref(),
+        }
+    }
+
+    async fn send(&self, frame: Vec<u8>) -> Result<(), WsError> {
+        send_frame(&self.sender, &self.frame_dump, Message::Binary(frame)).await
+    }
+
+    /// Asks the pool for the next round.
+    pub async fn ready(&self) -> Result<(), WsError> {
+        self.send(protocol::ready_message(&self.key.pubkey(), self.signer(), clock::now(), &self.quirks)).await
+    }
+
+    /// Submits a round's best solution over the websocket, or over HTTP once the websocket
+    /// is gone.
+    pub async fn submit(&self, best: &BestSolution) -> Submission {
+        let mut frame = protocol::best_solution_message(&self.key.pubkey(), self.signer(), &best.hash, best.nonce, &self.quirks);
+        if self.send_timing {
+            let hash_started_ms = best.hash_started.duration_since(UNIX_EPOCH).expect("Time went backwards").as_millis() as u64;
+            let latency_ms = best.challenge_latency.map_or(0, |latency| latency.as_millis() as u64);
+            frame.extend(protocol::timing_extension(hash_started_ms, best.hash_time.as_millis() as u64, latency_ms));
+        }
+
+        if self.is_connected() {
+            match self.send(frame.clone()).await {
+                Ok(()) => return Submission::Websocket,
+                Err(e) => say!("Failed to send the solution over the websocket: {}", e),
+            }
+        }
+        say!("Websocket unavailable, submitting solution over HTTP...");
+        let submitted = self.task_ctx.submit_solution(&self.base_url, &frame).await;
+        pool_cache::update(&self.base_url, |caps| caps.http_submission = Some(submitted.is_ok()));
+        match submitted {
+            Ok(()) => {
+                say!("Solution submitted over HTTP.");
+                Submission::Http
+            },
+            Err(e) => {
+                say!("HTTP submission failed: {}", e);
+                Submission::Failed(e)
+            },
+        }
+    }
+
+    /// Counts a round's hashes and sends the attestation the pool asked for once its interval passed.
+    pub async fn attest(&mut self, hashes: u64) -> Result<(), WsError> {
+        self.attested_hashes += hashes;
+        let Some(interval) = self.attestation_interval else {
+            return Ok(());
+        };
+        let now = clock::now();
+        if now.saturating_sub(self.attestation_start) < interval {
+            return Ok(());
+        }
+        self.send(protocol::attestation_message(&self.key.pubkey(), self.signer(), self.attestation_start..now, self.attested_hashes, &self.quirks))
+            .await?;
+        self.attestation_start = now;
+        self.attested_hashes = 0;
+        Ok(())
+    }
+
+    /// Reports the sustained hashrate, if the pool takes capacity reports and its interval passed.
+    pub async fn report_capacity(&mut self, hashrate: Option<u64>) -> Result<(), WsError> {
+        let (Some(interval), Some(hashrate)) = (self.capacity_interval, hashrate) else {
+            return Ok(());
+        };
+        let now = clock::now();
+        if self.capacity_reported_at.is_some_and(|at| now.saturating_sub(at) < interval) {
+            return Ok(());
+        }
+        say!("Reporting capacity of {}.", format::hashrate(hashrate as f64));
+        self.send(protocol::capacity_message(&self.key.pubkey(), self.signer(), now, hashrate, &self.quirks)).await?;
+        self.capacity_reported_at = Some(now);
+        Ok(())
+    }
+
+    pub fn is_connected(&self) -> bool {
+        !self.receiver.is_finished()
+    }
+
+    /// Stops reading the pool's frames, the connection is about to be replaced or closed.
+    pub fn disconnect(&self) {
+        self.receiver.abort();
+    }
+
+    /// Waits for the reader to stop, after `disconnect` or the pool closing the websocket.
+    pub async fn close(self) {
+        let _ = self.receiver.await;
+    }
+}
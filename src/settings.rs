@@ -0,0 +1,323 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::secrets;
+
+/// Optional settings file with sectioned options, read from the working directory
+/// unless --config points elsewhere.
+pub const SETTINGS_FILE: &str = "config.toml";
+
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub solver: SolverSettings,
+    #[serde(default)]
+    pub report: ReportSettings,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    #[serde(default)]
+    pub submission: SubmissionSettings,
+    #[serde(default)]
+    pub cutoff: CutoffSettings,
+    #[serde(default)]
+    pub network: NetworkSettings,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    #[serde(default)]
+    pub claim: ClaimSettings,
+    #[serde(default)]
+    pub sounds: SoundSettings,
+    #[serde(default)]
+    pub wallet_guard: WalletGuardSettings,
+    /// Command line options applied on every run, e.g. `threads = 8`. The command line wins.
+    #[serde(default)]
+    pub options: toml::value::Table,
+    /// Named option sets selected with --profile, applied over `options`.
+    #[serde(default)]
+    pub profile: BTreeMap<String, toml::value::Table>,
+    /// Pools to alternate between with `mine --rotate`, in order.
+    #[serde(default)]
+    pub schedule: Vec<PoolSlice>,
+}
+
+/// Advanced solver knobs, see `tune-solver`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolverSettings {
+    /// Equix solver memories allocated per worker thread.
+    pub memories: usize,
+    /// Consecutive nonces solved with one memory before moving to the next.
+    pub interleave: u64,
+}
+
+impl Default for SolverSettings {
+    // Wide out-of-order ARM cores tend to benefit from alternating memories, x86 does best with one
+    fn default() -> Self {
+        if cfg!(target_arch = "aarch64") {
+            SolverSettings { memories: 2, interleave: 4 }
+        } else {
+            SolverSettings { memories: 1, interleave: 1 }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Daily,
+    Weekly,
+}
+
+/// Scheduled earnings summaries posted to a webhook while mining.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportSettings {
+    /// Slack or Discord compatible webhook URL, reports are disabled without one.
+    pub webhook: Option<String>,
+    /// UTC time of day to send the report, as HH:MM.
+    pub at: String,
+    /// Weekly reports are sent on Mondays.
+    pub period: ReportPeriod,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        ReportSettings {
+            webhook: None,
+            at: "08:00".to_string(),
+            period: ReportPeriod::Daily,
+        }
+    }
+}
+
+/// Claim authorization for pools that need a second party to approve withdrawals.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClaimSettings {
+    /// Supervisor key that co-signs every claim, as ledger, remote:<url> or file:<path>.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cosigner: Option<String>,
+}
+
+/// Desktop notifications for key events, off unless `enabled` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// A round beat the wallet's best difficulty in the history.
+    pub personal_best: bool,
+    /// The pool connection dropped, at most once every few minutes.
+    pub disconnects: bool,
+    /// A claim went through.
+    pub claims: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings { enabled: false, personal_best: true, disconnects: true, claims: true }
+    }
+}
+
+/// Keys the wallet guard lets mine although they hold more than --max-wallet-sol or
+/// --max-wallet-ore, the settings file counterpart of --i-know-what-im-doing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletGuardSettings {
+    /// Pubkeys acknowledged as mining keys despite their balance.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub acknowledged: Vec<String>,
+}
+
+/// Audible alerts for rigs within earshot, each event off by default.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundSettings {
+    /// A round beat the wallet's best difficulty in the history.
+    pub personal_best: bool,
+    /// The pool stayed unreachable for `disconnect_after` seconds.
+    pub disconnects: bool,
+    pub disconnect_after: u64,
+    /// Program and arguments playing the sound, e.g. `["paplay", "alert.oga"]`. The terminal
+    /// bell rings without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings { personal_best: false, disconnects: false, disconnect_after: 60, command: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// One JSON line file per collection, e.g. history.jsonl.
+    #[default]
+    Jsonl,
+    /// A single database file, needs a build with the `sqlite` feature.
+    Sqlite,
+    /// Nothing is written to disk.
+    Memory,
+}
+
+/// Where history and other local state is kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,
+    /// Directory for jsonl, database file for sqlite. Defaults to the working directory
+    /// and state.db.
+    pub path: Option<PathBuf>,
+}
+
+/// Extra data sent along with solutions, only to pools that accept it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubmissionSettings {
+    /// Append hash start time, hash duration and challenge latency to each submission so
+    /// the pool operator can audit cutoff fairness.
+    pub timing: bool,
+}
+
+/// How the round cutoff sent by the pool is tightened before mining, with overrides per
+/// pool URL in `[cutoff.pools."<url>"]`, since pools enforce cutoffs with different strictness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CutoffSettings {
+    /// Cutoffs longer than this many seconds are treated as bogus and clamped, 0 disables.
+    pub clamp_above: u64,
+    /// Cutoff used instead of one above `clamp_above`.
+    pub clamp_to: u64,
+    /// Seconds to stop hashing before the cutoff, overrides --buffer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<u64>,
+    pub pools: BTreeMap<String, CutoffOverride>,
+}
+
+impl Default for CutoffSettings {
+    fn default() -> Self {
+        CutoffSettings { clamp_above: 60, clamp_to: 55, margin: None, pools: BTreeMap::new() }
+    }
+}
+
+/// Timeouts in seconds for pool, RPC and webhook traffic, so a blackholed route fails and
+/// reconnects instead of hanging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Establishing a TCP connection, including the websocket's.
+    pub connect_timeout: u64,
+    /// Waiting for a whole HTTP or RPC response, or the websocket upgrade response. An open
+    /// websocket is not bound by it, pools are quiet between rounds.
+    pub read_timeout: u64,
+    /// Handing a frame to the websocket.
+    pub write_timeout: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        NetworkSettings { connect_timeout: 10, read_timeout: 30, write_timeout: 10 }
+    }
+}
+
+/// Per pool values of `[cutoff]`, unset ones fall back to it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CutoffOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clamp_above: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clamp_to: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<u64>,
+}
+
+impl CutoffSettings {
+    /// Seconds to hash for a cutoff of `cutoff` from `pool`. `buffer` is the --buffer margin,
+    /// `clamp` is off for servers with the no-cutoff-clamp quirk.
+    pub fn apply(&self, pool: &str, cutoff: u64, buffer: u64, clamp: bool) -> u64 {
+        let pool = self.pools.get(pool).copied().unwrap_or_default();
+        let margin = pool.margin.or(self.margin).unwrap_or(buffer);
+        let clamp_above = pool.clamp_above.unwrap_or(self.clamp_above);
+        let cutoff = cutoff.saturating_sub(margin);
+        if clamp && clamp_above > 0 && cutoff > clamp_above {
+            pool.clamp_to.unwrap_or(self.clamp_to)
+        } else {
+            cutoff
+        }
+    }
+
+    /// Seconds until `pool` stops taking submissions for a cutoff of `cutoff`, as far as it can
+    /// be trusted: the pool's own cutoff, or the clamp limit when it is out of range.
+    pub fn deadline(&self, pool: &str, cutoff: u64, clamp: bool) -> u64 {
+        let clamp_above = self.pools.get(pool).and_then(|pool| pool.clamp_above).unwrap_or(self.clamp_above);
+        if clamp && clamp_above > 0 && cutoff > clamp_above {
+            clamp_above
+        } else {
+            cutoff
+        }
+    }
+}
+
+/// One entry of the `mine --rotate` schedule. A slice ends after `rounds` rounds or
+/// `minutes` minutes, whichever comes first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolSlice {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub rounds: Option<u64>,
+    #[serde(default)]
+    pub minutes: Option<u64>,
+}
+
+/// Reads settings from `path` instead of config.toml. Only the first call has an effect.
+pub fn set_path(path: PathBuf) {
+    let _ = PATH.set(path);
+}
+
+pub fn path() -> &'static Path {
+    PATH.get().map_or(Path::new(SETTINGS_FILE), PathBuf::as_path)
+}
+
+/// Loads the settings file, falling back to defaults if it is missing or invalid.
+pub fn load() -> Settings {
+    let Ok(contents) = fs::read_to_string(path()) else {
+        return Settings::default();
+    };
+    let parsed = contents.parse::<toml::Value>().and_then(|mut value| {
+        secrets::decrypt_settings(&mut value);
+        value.try_into()
+    });
+    match parsed {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("  Ignoring invalid {}: {}", path().display(), e);
+            Settings::default()
+        }
+    }
+}
+
+/// Writes the settings file. Values that were encrypted in it stay encrypted.
+pub fn save(settings: &Settings) -> Result<(), String> {
+    let mut value = toml::Value::try_from(settings).map_err(|e| e.to_string())?;
+    let encrypted = fs::read_to_string(path())
+        .ok()
+        .and_then(|contents| contents.parse::<toml::Value>().ok())
+        .map(|previous| secrets::encrypted_paths(&previous))
+        .unwrap_or_default();
+    if !encrypted.is_empty() {
+        let passphrase = secrets::passphrase().ok_or("no passphrase to keep encrypted settings encrypted")?;
+        secrets::encrypt_paths(&mut value, &encrypted, passphrase);
+    }
+    let contents = toml::to_string(&value).map_err(|e| e.to_string())?;
+    fs::write(path(), contents).map_err(|e| e.to_string())
+}
@@ -0,0 +1,217 @@
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{Signer, SignerError},
+};
+
+use crate::agent;
+use crate::metrics::MetricsAddr;
+use crate::network;
+
+/// Whatever signs for the miner: timestamp auth, Ready and solution frames and session
+/// certificates. A keypair file, or a signer that keeps the private key off the rig.
+pub type MinerSigner = Arc<dyn Signer + Send + Sync>;
+
+/// Where signatures come from, from --signer.
+#[derive(Debug, Clone)]
+pub enum SignerSource {
+    File(PathBuf),
+    Remote(String),
+    /// A `signer-agent` holding the key, so the rig only needs the pubkey.
+    Agent(MetricsAddr),
+    Ledger,
+}
+
+impl FromStr for SignerSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "ledger" {
+            Ok(SignerSource::Ledger)
+        } else if let Some(url) = s.strip_prefix("remote:") {
+            Ok(SignerSource::Remote(url.trim_end_matches('/').to_string()))
+        } else if let Some(path) = s.strip_prefix("file:") {
+            Ok(SignerSource::File(PathBuf::from(path)))
+        } else if let Some(addr) = s.strip_prefix("agent:") {
+            addr.parse().map(SignerSource::Agent)
+        } else {
+            Err(format!("expected ledger, remote:<url>, agent:<addr> or file:<path>, got {}", s))
+        }
+    }
+}
+
+/// Runs a request from inside the synchronous `Signer` methods. Mining runs on the
+/// multi-threaded runtime, where blocking a worker this way is allowed.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+/// A signing service that holds the key somewhere else. It answers `GET <url>/pubkey` with
+/// the base58 pubkey and `POST <url>/sign`, with the base64 message as the body, with the
+/// base58 signature.
+pub struct RemoteSigner {
+    url: String,
+    pubkey: Pubkey,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    pub async fn connect(url: String) -> Result<Self, String> {
+        let client = network::client();
+        let response = client
+            .get(format!("{}/pubkey", url))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("could not reach the signer at {}: {}", url, e))?;
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        let pubkey = Pubkey::from_str(text.trim()).map_err(|_| format!("{}/pubkey did not answer with a pubkey: {}", url, text.trim()))?;
+        Ok(RemoteSigner { url, pubkey, client })
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let request = self.client.post(format!("{}/sign", self.url)).body(BASE64_STANDARD.encode(message)).send();
+        let text = block_on(async { request.await?.error_for_status()?.text().await }).map_err(|e| SignerError::Connection(e.to_string()))?;
+        let signature = Signature::from_str(text.trim()).map_err(|e| SignerError::InvalidInput(e.to_string()))?;
+        // A signature by another key or over another message would only be rejected later by the pool
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Custom("the remote signer returned a signature that does not verify".to_string()));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// A `signer-agent` on this machine or through a tunnel, the ssh-agent of this client.
+pub struct AgentSigner {
+    addr: MetricsAddr,
+    pubkey: Pubkey,
+}
+
+impl AgentSigner {
+    pub async fn connect(addr: MetricsAddr) -> Result<Self, String> {
+        let answer = agent::request(&addr, "pubkey").await?;
+        let pubkey = answer["pubkey"]
+            .as_str()
+            .and_then(|pubkey| Pubkey::from_str(pubkey).ok())
+            .ok_or("the agent did not answer with a pubkey")?;
+        Ok(AgentSigner { addr, pubkey })
+    }
+}
+
+impl Signer for AgentSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let line = format!("sign {}", BASE64_STANDARD.encode(message));
+        let answer = block_on(agent::request(&self.addr, &line)).map_err(SignerError::Connection)?;
+        let signature = answer["signature"]
+            .as_str()
+            .and_then(|signature| Signature::from_str(signature).ok())
+            .ok_or_else(|| SignerError::InvalidInput("the agent did not answer with a signature".to_string()))?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Custom("the agent returned a signature that does not verify".to_string()));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// A Ledger, used from its own thread since the device handle can't be shared between
+/// threads. Every signature needs approval on the device. The Solana app only signs
+/// transactions, so this co-signs claims but can't sign mining frames.
+#[cfg(feature = "ledger")]
+pub struct LedgerSigner {
+    pubkey: Pubkey,
+    requests: std::sync::Mutex<std::sync::mpsc::Sender<(Vec<u8>, std::sync::mpsc::Sender<Result<Signature, SignerError>>)>>,
+}
+
+#[cfg(feature = "ledger")]
+impl LedgerSigner {
+    pub fn connect() -> Result<Self, String> {
+        use solana_remote_wallet::{
+            locator::Locator,
+            remote_keypair::generate_remote_keypair,
+            remote_wallet::maybe_wallet_manager,
+        };
+        use solana_sdk::derivation_path::DerivationPath;
+
+        let (requests, incoming) = std::sync::mpsc::channel::<(Vec<u8>, std::sync::mpsc::Sender<Result<Signature, SignerError>>)>();
+        let (ready, connected) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let keypair = maybe_wallet_manager()
+                .map_err(|e| e.to_string())
+                .and_then(|manager| manager.ok_or_else(|| "no Ledger found, is it connected and unlocked?".to_string()))
+                .and_then(|manager| {
+                    let locator = Locator::new_from_path("usb://ledger").map_err(|e| e.to_string())?;
+                    generate_remote_keypair(locator, DerivationPath::default(), &manager, false, "ore-hq-client").map_err(|e| e.to_string())
+                });
+            let keypair = match keypair {
+                Ok(keypair) => keypair,
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                },
+            };
+            let _ = ready.send(Ok(keypair.pubkey()));
+            for (message, answer) in incoming {
+                let _ = answer.send(keypair.try_sign_message(&message));
+            }
+        });
+        let pubkey = connected.recv().map_err(|e| e.to_string())??;
+        Ok(LedgerSigner { pubkey, requests: std::sync::Mutex::new(requests) })
+    }
+}
+
+#[cfg(feature = "ledger")]
+impl Signer for LedgerSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let (answer, signature) = std::sync::mpsc::channel();
+        self.requests
+            .lock()
+            .unwrap()
+            .send((message.to_vec(), answer))
+            .map_err(|_| SignerError::Connection("the Ledger thread stopped".to_string()))?;
+        tokio::task::block_in_place(|| signature.recv()).map_err(|_| SignerError::Connection("the Ledger thread stopped".to_string()))?
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+/// Connects to a remote signer, agent or Ledger. Keypair files are loaded like --keypair instead.
+pub async fn connect(source: &SignerSource) -> Result<MinerSigner, String> {
+    match source {
+        SignerSource::File(path) => solana_sdk::signature::read_keypair_file(path)
+            .map(|key| Arc::new(key) as MinerSigner)
+            .map_err(|e| format!("failed to load keypair from {}: {}", path.display(), e)),
+        SignerSource::Remote(url) => RemoteSigner::connect(url.clone()).await.map(|signer| Arc::new(signer) as MinerSigner),
+        SignerSource::Agent(addr) => AgentSigner::connect(addr.clone()).await.map(|signer| Arc::new(signer) as MinerSigner),
+        #[cfg(feature = "ledger")]
+        SignerSource::Ledger => LedgerSigner::connect().map(|signer| Arc::new(signer) as MinerSigner),
+        #[cfg(not(feature = "ledger"))]
+        SignerSource::Ledger => Err("Ledger support is not built in, rebuild with `--features ledger`".to_string()),
+    }
+}
@@ -1,12 +1,20 @@
-use std::str::FromStr;
-
 use base64::{prelude::BASE64_STANDARD, Engine};
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::Transaction};
+use solana_sdk::{native_token::lamports_to_sol, signature::Keypair, signer::Signer, system_instruction, transaction::Transaction};
+
+use crate::audit;
+use crate::endpoint;
+use crate::history::{self, HistoryEntry};
+use crate::network;
+use crate::pool_cache;
+
+const SIGNUP_LAMPORTS: u64 = 1_000_000;
+/// Signature fee of the single-signer signup transaction.
+const SIGNUP_TX_FEE_LAMPORTS: u64 = 5_000;
 
 pub async fn signup(url: String, key: Keypair, unsecure: bool) {
     let base_url = url;
 
-    let client = reqwest::Client::new();
+    let client = network::client();
 
     let url_prefix = if unsecure {
         "http".to_string()
@@ -14,32 +22,37 @@ pub async fn signup(url: String, key: Keypair, unsecure: bool) {
         "https".to_string()
     };
 
-    let resp = client.get(format!("{}://{}/pool/authority/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-
-    let pool_pubkey = Pubkey::from_str(&resp).unwrap();
+    let pool_pubkey = pool_cache::authority_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-    let resp = client.get(format!("{}://{}/latest-blockhash", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
+    let resp = client.get(endpoint::http(&url_prefix, &base_url, "latest-blockhash")).send().await.unwrap().text().await.unwrap();
 
     let decoded_blockhash = BASE64_STANDARD.decode(resp).unwrap();
     let deserialized_blockhash = bincode::deserialize(&decoded_blockhash).unwrap();
 
-    let ix = system_instruction::transfer(&key.pubkey(), &pool_pubkey, 1_000_000);
+    let ix = system_instruction::transfer(&key.pubkey(), &pool_pubkey, SIGNUP_LAMPORTS);
 
     let mut tx = Transaction::new_with_payer(&[ix], Some(&key.pubkey()));
 
     tx.sign(&[&key], deserialized_blockhash);
+    audit::record("signup-transaction", &key.pubkey(), &tx.message_data());
 
     let serialized_tx = bincode::serialize(&tx).unwrap();
 
     let encoded_tx = BASE64_STANDARD.encode(&serialized_tx);
 
-    let resp = client.post(format!("{}://{}/signup?pubkey={}", url_prefix, base_url, key.pubkey().to_string())).body(encoded_tx).send().await;
+    let resp = client.post(endpoint::http(&url_prefix, &base_url, &format!("signup?pubkey={}", key.pubkey().to_string()))).body(encoded_tx).send().await;
     if let Ok(res) = resp {
         if let Ok(txt) = res.text().await {
 
             match txt.as_str() {
                 "SUCCESS" => {
                     println!("  Successfully signed up!");
+                    history::record(HistoryEntry::Signup {
+                        at: history::now(),
+                        pool: base_url.clone(),
+                        wallet: key.pubkey().to_string(),
+                        sol: lamports_to_sol(SIGNUP_LAMPORTS + SIGNUP_TX_FEE_LAMPORTS),
+                    });
                 },
                 "EXISTS" => {
                     println!("  You're already signed up!");
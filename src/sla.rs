@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use crate::format;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Fetching the timestamp, connecting, or backing off after a failure.
+    Reconnecting,
+    /// Connected and waiting for the next round.
+    Idle,
+    /// Hashing and submitting a round.
+    Mining,
+}
+
+/// Tracks how a mining session's time splits between mining, idle waits and reconnects.
+#[derive(Debug, Clone)]
+pub struct ConnectionSla {
+    started: Instant,
+    state: ConnectionState,
+    since: Instant,
+    reconnecting: Duration,
+    idle: Duration,
+    mining: Duration,
+    reconnects: u32,
+}
+
+impl ConnectionSla {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        ConnectionSla {
+            started: now,
+            state: ConnectionState::Reconnecting,
+            since: now,
+            reconnecting: Duration::ZERO,
+            idle: Duration::ZERO,
+            mining: Duration::ZERO,
+            reconnects: 0,
+        }
+    }
+
+    pub fn enter(&mut self, state: ConnectionState) {
+        if state == self.state {
+            return;
+        }
+        self.close_current();
+        if state == ConnectionState::Reconnecting {
+            self.reconnects += 1;
+        }
+        tracing::info!(target: "connection", from = ?self.state, to = ?state, reconnects = self.reconnects);
+        self.state = state;
+    }
+
+    fn close_current(&mut self) {
+        let elapsed = self.since.elapsed();
+        self.since = Instant::now();
+        match self.state {
+            ConnectionState::Reconnecting => self.reconnecting += elapsed,
+            ConnectionState::Idle => self.idle += elapsed,
+            ConnectionState::Mining => self.mining += elapsed,
+        }
+    }
+
+    /// Time spent mining, idle and reconnecting so far, counting the state it is in now.
+    pub fn totals(&self) -> (Duration, Duration, Duration) {
+        let current = self.since.elapsed();
+        match self.state {
+            ConnectionState::Mining => (self.mining + current, self.idle, self.reconnecting),
+            ConnectionState::Idle => (self.mining, self.idle + current, self.reconnecting),
+            ConnectionState::Reconnecting => (self.mining, self.idle, self.reconnecting + current),
+        }
+    }
+
+    pub fn reconnects(&self) -> u32 {
+        self.reconnects
+    }
+
+    fn percent(&self, part: Duration) -> f64 {
+        let total = self.started.elapsed().as_secs_f64();
+        if total > 0.0 {
+            part.as_secs_f64() * 100.0 / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Prints the availability breakdown, connected time is mining plus idle-wait.
+    pub fn print_summary(&mut self) {
+        self.close_current();
+        println!("Session: {}", format::duration(self.started.elapsed()));
+        println!("  Connected:    {:.1}%", self.percent(self.mining + self.idle));
+        println!("  Mining:       {:.1}% ({})", self.percent(self.mining), format::duration(self.mining));
+        println!("  Idle wait:    {:.1}% ({})", self.percent(self.idle), format::duration(self.idle));
+        println!("  Reconnecting: {:.1}% ({}, {} reconnects)", self.percent(self.reconnecting), format::duration(self.reconnecting), self.reconnects);
+    }
+}
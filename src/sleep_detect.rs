@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use tokio::{sync::broadcast::{self, error::{RecvError, TryRecvError}}, task::JoinHandle};
+
+use crate::tasks::{MinerEvent, TaskContext};
+
+/// How often the wall clock is compared against the monotonic clock.
+const CHECK_INTERVAL_SECS: u64 = 5;
+
+/// Watches for system suspend: the monotonic clock stops while the machine sleeps
+/// but the wall clock keeps going, so a large gap between the two means we resumed.
+/// Publishes `MinerEvent::Resumed` with the number of seconds slept.
+pub fn spawn(ctx: TaskContext, threshold_secs: u64) -> Option<JoinHandle<()>> {
+    if threshold_secs == 0 {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut last_wall = SystemTime::now();
+        let mut last_mono = Instant::now();
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+
+            let wall = SystemTime::now();
+            let mono = Instant::now();
+            let wall_elapsed = wall.duration_since(last_wall).unwrap_or_default();
+            let mono_elapsed = mono.duration_since(last_mono);
+            last_wall = wall;
+            last_mono = mono;
+
+            let slept = wall_elapsed.saturating_sub(mono_elapsed).as_secs();
+            if slept >= threshold_secs {
+                ctx.publish(MinerEvent::Resumed(slept));
+            }
+        }
+    }))
+}
+
+/// Waits until a resume is published, returns the seconds slept.
+pub async fn wait_for_resume(events: &mut broadcast::Receiver<MinerEvent>) -> u64 {
+    loop {
+        match events.recv().await {
+            Ok(MinerEvent::Resumed(slept)) => return slept,
+            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => std::future::pending::<()>().await,
+        }
+    }
+}
+
+/// Drains pending events without waiting, returns the seconds slept if a resume happened.
+pub fn resumed_since(events: &mut broadcast::Receiver<MinerEvent>) -> Option<u64> {
+    let mut slept = None;
+    loop {
+        match events.try_recv() {
+            Ok(MinerEvent::Resumed(secs)) => slept = Some(secs),
+            Ok(_) | Err(TryRecvError::Lagged(_)) => continue,
+            Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return slept,
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc},
+    time::{Duration, Instant},
+};
+
+use ore_api::{
+    consts::{BUS_ADDRESSES, BUS_COUNT, CONFIG_ADDRESS, EPOCH_DURATION},
+    state::{proof_pda, Config},
+};
+use ore_utils::AccountDeserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    signer::Signer,
+    transaction::Transaction,
+};
+
+use crate::audit;
+use crate::endpoint;
+use crate::history::{self, HistoryEntry};
+use crate::mine::{self, RoundJob};
+use crate::network;
+use crate::proof::fetch_proof;
+use crate::settings::SolverSettings;
+use crate::tasks::TaskContext;
+
+/// Compute units requested for an auth + reset + mine transaction.
+const SOLO_COMPUTE_UNITS: u32 = 500_000;
+/// Pool used in history entries for rounds mined solo.
+const SOLO_POOL: &str = "solo";
+
+fn unix_now() -> i64 {
+    history::now() as i64
+}
+
+async fn send(rpc: &RpcClient, key: &dyn Signer, kind: &str, ixs: &[Instruction]) -> Result<(), String> {
+    let blockhash = rpc.get_latest_blockhash().await.map_err(|e| e.to_string())?;
+    let tx = Transaction::new_signed_with_payer(ixs, Some(&key.pubkey()), &[key], blockhash);
+    audit::record(kind, &key.pubkey(), &tx.message_data());
+    rpc.send_and_confirm_transaction(&tx).await.map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Mines one round against the ORE program directly: solves the wallet's proof challenge
+/// until the one minute cutoff and submits the best hash in a mine transaction.
+async fn solo_round(rpc: &RpcClient, key: &dyn Signer, threads: u32, solver: SolverSettings, running: &Arc<AtomicBool>) -> Result<(), String> {
+    let (proof_address, _) = proof_pda(key.pubkey());
+    let proof = match fetch_proof(rpc, &proof_address).await {
+        Some(proof) => proof,
+        None => {
+            println!("Opening a proof account for solo mining...");
+            send(rpc, key, "solo-open-transaction", &[ore_api::instruction::open(key.pubkey(), key.pubkey(), key.pubkey())]).await?;
+            fetch_proof(rpc, &proof_address).await.ok_or("proof account not found after opening")?
+        }
+    };
+    let config_data = rpc.get_account_data(&CONFIG_ADDRESS).await.map_err(|e| e.to_string())?;
+    let config = *Config::try_from_bytes(&config_data).map_err(|e| e.to_string())?;
+
+    let cutoff = (proof.last_hash_at + 60 - unix_now()).max(0) as u64;
+    let job = RoundJob {
+        challenge: proof.challenge,
+        nonce_range: 0..u64::MAX,
+        cutoff,
+        hash_timer: Instant::now(),
+        cursor: Arc::new(AtomicU64::new(0)),
+        paranoid: false,
+        paranoid_min_difficulty: 0,
+        cancelled: Arc::new(AtomicBool::new(false)),
+        solver,
+        control: Default::default(),
+        best_difficulty: Default::default(),
+        exit_target: None,
+    };
+    println!("Solo mining for {}s...", cutoff);
+    let results = mine::mine_round(job.clone(), threads, None, Default::default(), running).await;
+    let hash_secs = job.hash_timer.elapsed().as_secs_f64();
+
+    let mut hashes = 0;
+    let mut nonces = 0;
+    let mut best = None;
+    for result in results.into_iter().flatten() {
+        hashes += result.total_hashes;
+        nonces += result.total_nonces;
+        if best.as_ref().is_none_or(|b: &mine::WorkerResult| result.best_difficulty > b.best_difficulty) {
+            best = Some(result);
+        }
+    }
+    let best = best.ok_or("mining was interrupted")?;
+    if (best.best_difficulty as u64) < config.min_difficulty {
+        return Err(format!("best difficulty {} is below the program minimum {}", best.best_difficulty, config.min_difficulty));
+    }
+
+    let mut ixs = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(SOLO_COMPUTE_UNITS),
+        ore_api::instruction::auth(proof_address),
+    ];
+    if config.last_reset_at + EPOCH_DURATION <= unix_now() {
+        ixs.push(ore_api::instruction::reset(key.pubkey()));
+    }
+    let bus = BUS_ADDRESSES[rand::random::<usize>() % BUS_COUNT];
+    let solution = drillx::Solution::new(best.best_hash.d, best.best_nonce.to_le_bytes());
+    ixs.push(ore_api::instruction::mine(key.pubkey(), key.pubkey(), bus, solution));
+
+    send(rpc, key, "solo-mine-transaction", &ixs).await?;
+    println!("Solo solution landed with difficulty {}.", best.best_difficulty);
+    history::record(HistoryEntry::Round {
+        at: history::now(),
+        pool: SOLO_POOL.to_string(),
+        wallet: key.pubkey().to_string(),
+        difficulty: best.best_difficulty,
+        hashes,
+        hash_secs,
+        rig: history::rig_name(),
+        submit_ms: None,
+        range_size: None,
+        nonces: Some(nonces),
+    });
+    Ok(())
+}
+
+async fn pool_reachable(ctx: &TaskContext) -> bool {
+    ctx.client
+        .get(endpoint::http(&ctx.http_prefix, &ctx.base_url, "timestamp"))
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+/// Mines solo until the pool answers again or mining is stopped.
+pub async fn mine_until_pool_recovers(
+    ctx: &TaskContext,
+    key: &dyn Signer,
+    rpc_url: &str,
+    threads: u32,
+    solver: SolverSettings,
+    running: &Arc<AtomicBool>,
+) {
+    let rpc = network::rpc(rpc_url.to_string());
+    while running.load(Ordering::SeqCst) {
+        if pool_reachable(ctx).await {
+            println!("Pool is reachable again, returning to pool mining.");
+            return;
+        }
+        if let Err(e) = solo_round(&rpc, key, threads, solver, running).await {
+            println!("Solo round failed: {}", e);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::history;
+use crate::settings::SoundSettings;
+use crate::tasks::{MinerEvent, TaskContext};
+
+/// Plays the configured sound, or rings the terminal bell.
+fn play(command: Option<&[String]>) {
+    match command.and_then(|command| command.split_first()) {
+        Some((program, args)) => {
+            // Off the runtime, a slow or missing player must not hold up mining
+            let mut command = Command::new(program);
+            command.args(args).stdout(Stdio::null()).stderr(Stdio::null());
+            std::thread::spawn(move || command.status());
+        },
+        None => {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07");
+            let _ = stdout.flush();
+        },
+    }
+}
+
+/// Sounds an alert for record difficulties and for disconnects lasting longer than
+/// `disconnect_after`, as enabled in `[sounds]`.
+pub fn spawn(ctx: TaskContext, settings: SoundSettings) -> Option<JoinHandle<()>> {
+    if !settings.personal_best && !settings.disconnects {
+        return None;
+    }
+    let mut events = ctx.subscribe();
+    let mut best = history::best_difficulty(&ctx.pubkey.to_string());
+    Some(tokio::spawn(async move {
+        let mut disconnected_until: Option<tokio::time::Instant> = None;
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = tokio::time::sleep_until(disconnected_until.unwrap_or_else(tokio::time::Instant::now)), if disconnected_until.is_some() => {
+                    disconnected_until = None;
+                    play(settings.command.as_deref());
+                    continue;
+                }
+            };
+            match event {
+                Ok(MinerEvent::RoundFinished { difficulty }) if difficulty > best => {
+                    if settings.personal_best && best > 0 {
+                        play(settings.command.as_deref());
+                    }
+                    best = difficulty;
+                },
+                Ok(MinerEvent::Disconnected) if settings.disconnects && disconnected_until.is_none() => {
+                    disconnected_until = Some(tokio::time::Instant::now() + Duration::from_secs(settings.disconnect_after));
+                },
+                Ok(MinerEvent::Connected) => disconnected_until = None,
+                Ok(_) => {},
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }))
+}
@@ -0,0 +1,46 @@
+use std::sync::{atomic::AtomicBool, mpsc, Arc};
+
+use crossbeam::channel;
+
+use crate::mine::{self, RoundJob, WorkerResult};
+use crate::settings::SolverSettings;
+
+/// Pinned worker threads with preallocated solver memory that live across rounds,
+/// so a round starts as soon as its job is sent instead of after thread spawn and allocation.
+pub struct SprintPool {
+    workers: Vec<channel::Sender<(RoundJob, mpsc::Sender<Option<WorkerResult>>)>>,
+}
+
+impl SprintPool {
+    pub fn new(threads: u32, reserve_cores: u32, running: Arc<AtomicBool>, solver: SolverSettings) -> Self {
+        let (cores, _) = mine::split_cores(reserve_cores);
+        let workers = cores
+            .into_iter()
+            .cycle()
+            .take(threads.max(1) as usize)
+            .map(|core| {
+                let (jobs, job_receiver) = channel::unbounded::<(RoundJob, mpsc::Sender<Option<WorkerResult>>)>();
+                let running = running.clone();
+                std::thread::spawn(move || {
+                    let _ = core_affinity::set_for_current(core);
+                    let mut memories = mine::solver_memories(&solver);
+                    while let Ok((job, results)) = job_receiver.recv() {
+                        let _ = results.send(mine::solve(&job, &mut memories, &running));
+                    }
+                });
+                jobs
+            })
+            .collect();
+
+        SprintPool { workers }
+    }
+
+    /// Starts the round on up to `threads` warm workers. The receiver yields one result per worker.
+    pub fn dispatch(&self, job: &RoundJob, threads: u32) -> mpsc::Receiver<Option<WorkerResult>> {
+        let (results, receiver) = mpsc::channel();
+        for worker in self.workers.iter().take(threads.max(1) as usize) {
+            let _ = worker.send((job.clone(), results.clone()));
+        }
+        receiver
+    }
+}
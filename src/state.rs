@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+
+use crate::audit;
+use crate::cluster;
+use crate::config;
+use crate::history;
+use crate::pool_cache;
+use crate::settings;
+use crate::store;
+
+/// Bumped when the bundle layout changes, older clients refuse newer bundles.
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Subcommand)]
+pub enum StateCommands {
+    #[command(about = "Bundle settings, history, the pool cache and the audit log into one file.")]
+    Export {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        #[arg(long, help = "Also bundle the keypair files from the keypair list. Keep the bundle as safe as the keypairs")]
+        include_keypairs: bool,
+    },
+    #[command(about = "Restore a bundle written by state export on this machine.")]
+    Import {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        #[arg(long, help = "Replace files that already exist instead of skipping them")]
+        force: bool,
+    },
+}
+
+/// Everything a rig accumulates besides its keypairs. Files are stored by name, since
+/// settings live wherever --config points on each machine.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateBundle {
+    version: u32,
+    exported_at: u64,
+    files: BTreeMap<String, String>,
+    /// History records as stored, whatever the storage backend.
+    history: Vec<String>,
+    /// Keypair file contents by path, only with --include-keypairs.
+    #[serde(default)]
+    keypairs: BTreeMap<String, String>,
+}
+
+/// Local files in the bundle, by the name they are stored under.
+fn state_files() -> Vec<(&'static str, PathBuf)> {
+    vec![
+        (settings::SETTINGS_FILE, settings::path().to_path_buf()),
+        (config::CONFIG_FILE, PathBuf::from(config::CONFIG_FILE)),
+        (pool_cache::POOL_CACHE_FILE, PathBuf::from(pool_cache::POOL_CACHE_FILE)),
+        (cluster::DEVNET_KEYS_FILE, PathBuf::from(cluster::DEVNET_KEYS_FILE)),
+        (audit::AUDIT_FILE, PathBuf::from(audit::AUDIT_FILE)),
+    ]
+}
+
+pub fn run(command: &StateCommands) {
+    match command {
+        StateCommands::Export { file, include_keypairs } => export(file, *include_keypairs),
+        StateCommands::Import { file, force } => import(file, *force),
+    }
+}
+
+fn export(file: &Path, include_keypairs: bool) {
+    let mut bundle = StateBundle {
+        version: BUNDLE_VERSION,
+        exported_at: history::now(),
+        ..Default::default()
+    };
+    for (name, path) in state_files() {
+        if let Ok(contents) = fs::read_to_string(&path) {
+            bundle.files.insert(name.to_string(), contents);
+        }
+    }
+    bundle.history = match store::with(|store| store.load(history::HISTORY_COLLECTION)) {
+        Ok(history) => history,
+        Err(e) => {
+            println!("  Failed to read the history: {}", e);
+            return;
+        }
+    };
+    if include_keypairs {
+        for path in config::load() {
+            // Directories in the keypair list are scanned for keypairs, they are not keypairs themselves
+            if let Ok(contents) = fs::read_to_string(&path) {
+                bundle.keypairs.insert(path, contents);
+            }
+        }
+    }
+
+    let contents = match serde_json::to_string_pretty(&bundle) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("  Failed to serialize the state: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = write_private(file, &contents, include_keypairs) {
+        println!("  Failed to write {}: {}", file.display(), e);
+        return;
+    }
+    println!(
+        "  Exported {} files, {} history records and {} keypairs to {}.",
+        bundle.files.len(),
+        bundle.history.len(),
+        bundle.keypairs.len(),
+        file.display()
+    );
+    if !include_keypairs {
+        println!("  Keypairs are not included, copy them separately or export with --include-keypairs.");
+    }
+}
+
+/// Writes a file, readable by the owner only when it holds secrets.
+fn write_private(path: &Path, contents: &str, private: bool) -> std::io::Result<()> {
+    fs::write(path, contents)?;
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    Ok(())
+}
+
+fn import(file: &Path, force: bool) {
+    let bundle: StateBundle = match fs::read_to_string(file).map_err(|e| e.to_string()).and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string())) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            println!("  Failed to read {}: {}", file.display(), e);
+            return;
+        }
+    };
+    if bundle.version > BUNDLE_VERSION {
+        println!("  {} is bundle version {}, newer than this client supports ({}).", file.display(), bundle.version, BUNDLE_VERSION);
+        return;
+    }
+
+    let mut written = 0;
+    for (name, path) in state_files() {
+        let Some(contents) = bundle.files.get(name) else {
+            continue;
+        };
+        if path.exists() && !force {
+            println!("  Skipping {}, it already exists. Pass --force to replace it.", path.display());
+            continue;
+        }
+        match fs::write(&path, contents) {
+            Ok(()) => written += 1,
+            Err(e) => println!("  Failed to write {}: {}", path.display(), e),
+        }
+    }
+
+    // History is merged rather than replaced, so importing twice or onto a rig that already
+    // mined for a while keeps every round once
+    let existing: HashSet<String> = store::with(|store| store.load(history::HISTORY_COLLECTION)).unwrap_or_default().into_iter().collect();
+    let mut merged = 0;
+    for record in bundle.history.iter().filter(|record| !existing.contains(*record)) {
+        if let Err(e) = store::with(|store| store.append(history::HISTORY_COLLECTION, record)) {
+            println!("  Failed to import history: {}", e);
+            break;
+        }
+        merged += 1;
+    }
+
+    let mut keypairs = 0;
+    for (path, contents) in &bundle.keypairs {
+        let path = Path::new(path);
+        if path.exists() && !force {
+            println!("  Skipping keypair {}, it already exists. Pass --force to replace it.", path.display());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match write_private(path, contents, true) {
+            Ok(()) => keypairs += 1,
+            Err(e) => println!("  Failed to write keypair {}: {}", path.display(), e),
+        }
+    }
+
+    println!(
+        "  Imported {} files, {} new history records and {} keypairs from {}.",
+        written,
+        merged,
+        keypairs,
+        file.display()
+    );
+}
@@ -0,0 +1,35 @@
+use std::fs;
+
+/// Aggregate CPU jiffies from the first line of /proc/stat.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuTimes {
+    total: u64,
+    steal: u64,
+}
+
+/// Reads aggregate CPU times. Only supported on Linux, returns None elsewhere.
+pub fn sample() -> Option<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().find(|l| l.starts_with("cpu "))?;
+    let values: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|v| v.parse().ok())
+        .collect();
+
+    // user nice system idle iowait irq softirq steal ...
+    let steal = *values.get(7)?;
+    // guest time is already accounted for in user/nice
+    let total = values.iter().take(8).sum();
+
+    Some(CpuTimes { total, steal })
+}
+
+/// Percentage of CPU time stolen by the hypervisor between two samples.
+pub fn steal_percent(start: CpuTimes, end: CpuTimes) -> f64 {
+    let total = end.total.saturating_sub(start.total);
+    if total == 0 {
+        return 0.0;
+    }
+    end.steal.saturating_sub(start.steal) as f64 * 100.0 / total as f64
+}
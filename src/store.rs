@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::settings::{self, StorageBackend};
+
+/// Persistence for local state such as the round history. Records are serialized JSON,
+/// grouped in named collections and returned in insertion order.
+pub trait StateStore: Send {
+    fn append(&mut self, collection: &str, record: &str) -> io::Result<()>;
+    fn load(&self, collection: &str) -> io::Result<Vec<String>>;
+}
+
+/// One `<collection>.jsonl` file per collection in a directory.
+pub struct JsonlStore {
+    dir: PathBuf,
+}
+
+impl JsonlStore {
+    pub fn new(dir: PathBuf) -> Self {
+        JsonlStore { dir }
+    }
+
+    fn path(&self, collection: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", collection))
+    }
+}
+
+impl StateStore for JsonlStore {
+    fn append(&mut self, collection: &str, record: &str) -> io::Result<()> {
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(self.path(collection))?;
+        writeln!(file, "{}", record)
+    }
+
+    fn load(&self, collection: &str) -> io::Result<Vec<String>> {
+        match fs::read_to_string(self.path(collection)) {
+            Ok(contents) => Ok(contents.lines().map(str::to_string).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Keeps state for the lifetime of the process only, nothing touches the disk.
+#[derive(Default)]
+pub struct MemoryStore {
+    collections: HashMap<String, Vec<String>>,
+}
+
+impl StateStore for MemoryStore {
+    fn append(&mut self, collection: &str, record: &str) -> io::Result<()> {
+        self.collections.entry(collection.to_string()).or_default().push(record.to_string());
+        Ok(())
+    }
+
+    fn load(&self, collection: &str) -> io::Result<Vec<String>> {
+        Ok(self.collections.get(collection).cloned().unwrap_or_default())
+    }
+}
+
+/// A single SQLite database with one table of records, only built with the `sqlite` feature.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    connection: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let connection = rusqlite::Connection::open(path).map_err(io::Error::other)?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS records (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    collection TEXT NOT NULL,
+                    data TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS records_collection ON records (collection, id);",
+            )
+            .map_err(io::Error::other)?;
+        Ok(SqliteStore { connection })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl StateStore for SqliteStore {
+    fn append(&mut self, collection: &str, record: &str) -> io::Result<()> {
+        self.connection
+            .execute("INSERT INTO records (collection, data) VALUES (?1, ?2)", (collection, record))
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn load(&self, collection: &str) -> io::Result<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT data FROM records WHERE collection = ?1 ORDER BY id")
+            .map_err(io::Error::other)?;
+        let rows = statement
+            .query_map([collection], |row| row.get::<_, String>(0))
+            .map_err(io::Error::other)?;
+        rows.collect::<Result<_, _>>().map_err(io::Error::other)
+    }
+}
+
+/// Opens the backend selected by `[storage]` in the settings file.
+fn open() -> Box<dyn StateStore> {
+    let storage = settings::load().storage;
+    match storage.backend {
+        StorageBackend::Jsonl => Box::new(JsonlStore::new(storage.path.unwrap_or_else(|| PathBuf::from(".")))),
+        StorageBackend::Memory => Box::new(MemoryStore::default()),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => {
+            let path = storage.path.unwrap_or_else(|| PathBuf::from("state.db"));
+            match SqliteStore::open(path.clone()) {
+                Ok(store) => Box::new(store),
+                Err(e) => {
+                    println!("  Failed to open {}: {}, keeping state in memory.", path.display(), e);
+                    Box::new(MemoryStore::default())
+                }
+            }
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => {
+            println!("  This build has no SQLite support, using JSONL files. Rebuild with --features sqlite.");
+            Box::new(JsonlStore::new(PathBuf::from(".")))
+        }
+    }
+}
+
+/// Runs `f` against the process wide store, opened on first use.
+pub fn with<T>(f: impl FnOnce(&mut dyn StateStore) -> T) -> T {
+    static STORE: OnceLock<Mutex<Box<dyn StateStore>>> = OnceLock::new();
+    let mut store = STORE.get_or_init(|| Mutex::new(open())).lock().unwrap();
+    f(store.as_mut())
+}
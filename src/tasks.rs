@@ -0,0 +1,152 @@
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use base64::prelude::*;
+use reqwest::StatusCode;
+use solana_sdk::pubkey::Pubkey;
+use tokio::{sync::broadcast::{self, error::RecvError}, task::JoinHandle};
+
+use crate::endpoint;
+
+/// Events published by the mining loop for background tasks to react to.
+#[derive(Debug, Clone)]
+pub enum MinerEvent {
+    /// A round was submitted with this best difficulty.
+    RoundFinished { difficulty: u32 },
+    Connected,
+    Disconnected,
+    /// The system resumed from sleep after this many seconds.
+    Resumed(u64),
+    /// A client-side failure the pool operator may want to know about.
+    ClientError { kind: &'static str, detail: String },
+}
+
+/// Minimum time between two error reports of the same kind.
+const ERROR_REPORT_INTERVAL_SECS: u64 = 60;
+
+/// Shared handle for background tasks that run concurrently with mining.
+#[derive(Clone)]
+pub struct TaskContext {
+    pub client: reqwest::Client,
+    pub base_url: String,
+    pub http_prefix: String,
+    pub pubkey: Pubkey,
+    events: broadcast::Sender<MinerEvent>,
+}
+
+impl TaskContext {
+    pub fn new(client: reqwest::Client, base_url: String, unsecure: bool, pubkey: Pubkey) -> Self {
+        let (events, _) = broadcast::channel(64);
+        let http_prefix = if unsecure { "http" } else { "https" }.to_string();
+        TaskContext {
+            client,
+            base_url,
+            http_prefix,
+            pubkey,
+            events,
+        }
+    }
+
+    pub fn publish(&self, event: MinerEvent) {
+        // No subscribers is fine, the event is simply dropped.
+        let _ = self.events.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MinerEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn fetch_rewards(&self) -> Option<f64> {
+        let response = self.client
+            .get(endpoint::http(&self.http_prefix, &self.base_url, &format!("miner/rewards?pubkey={}", self.pubkey)))
+            .send()
+            .await
+            .ok()?;
+        response.text().await.ok()?.parse::<f64>().ok()
+    }
+
+    /// Submits a signed BestSolution frame over HTTP to the pool the round came from, for when
+    /// the websocket is gone.
+    pub async fn submit_solution(&self, base_url: &str, frame: &[u8]) -> Result<(), String> {
+        let response = self.client
+            .post(endpoint::http(&self.http_prefix, base_url, &format!("submit?pubkey={}", self.pubkey)))
+            .body(BASE64_STANDARD.encode(frame))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+                Err("server does not support HTTP submissions".to_string())
+            },
+            status => Err(format!("server responded with {}", status)),
+        }
+    }
+
+    /// Posts a compact client error report, servers without the endpoint simply ignore it.
+    pub async fn report_error(&self, kind: &str, detail: &str) -> Result<(), String> {
+        let report = serde_json::json!({
+            "kind": kind,
+            "detail": detail,
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        self.client
+            .post(endpoint::http(&self.http_prefix, &self.base_url, &format!("miner/error-report?pubkey={}", self.pubkey)))
+            .header("Content-Type", "application/json")
+            .body(report.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Forwards client errors to the pool, at most once per kind per ERROR_REPORT_INTERVAL_SECS.
+pub fn spawn_error_reporter(ctx: TaskContext) -> JoinHandle<()> {
+    let mut events = ctx.subscribe();
+    tokio::spawn(async move {
+        let mut last_sent: HashMap<&'static str, Instant> = HashMap::new();
+        loop {
+            match events.recv().await {
+                Ok(MinerEvent::ClientError { kind, detail }) => {
+                    let recent = last_sent
+                        .get(kind)
+                        .is_some_and(|at| at.elapsed() < Duration::from_secs(ERROR_REPORT_INTERVAL_SECS));
+                    if recent {
+                        continue;
+                    }
+                    last_sent.insert(kind, Instant::now());
+                    let _ = ctx.report_error(kind, &detail).await;
+                },
+                Ok(_) => {},
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Polls unclaimed rewards after each round without blocking the mining loop.
+pub fn spawn_rewards_poller(ctx: TaskContext) -> JoinHandle<()> {
+    let mut events = ctx.subscribe();
+    tokio::spawn(async move {
+        let mut last_rewards: Option<f64> = None;
+        loop {
+            match events.recv().await {
+                Ok(MinerEvent::RoundFinished { .. }) => {
+                    // Give the pool time to credit the round before polling.
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    if let Some(rewards) = ctx.fetch_rewards().await {
+                        if last_rewards != Some(rewards) {
+                            println!("Unclaimed Rewards: {:.11} ORE", rewards);
+                            last_rewards = Some(rewards);
+                        }
+                    }
+                },
+                Ok(_) => {},
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
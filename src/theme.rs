@@ -0,0 +1,107 @@
+use std::{env, io::IsTerminal, sync::OnceLock, time::{Duration, Instant}};
+
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::{format, logging};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Theme {
+    /// Red spinner, green on Windows.
+    Default,
+    /// Cyan and yellow, readable on dark and light backgrounds.
+    HighContrast,
+    /// No colors at all.
+    Mono,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Resolves the color choice against `NO_COLOR` and the terminal, and applies it
+/// to all colored output for the rest of the process.
+pub fn init(choice: ColorChoice, theme: Theme) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+    };
+
+    colored::control::set_override(enabled);
+    let _ = THEME.set(if enabled { theme } else { Theme::Mono });
+}
+
+fn current() -> Theme {
+    *THEME.get().unwrap_or(&Theme::Default)
+}
+
+/// Spinner style for the mining progress indicator.
+pub fn spinner_style() -> ProgressStyle {
+    let windows = env::consts::OS == "windows";
+
+    // wide_msg is truncated to the terminal width on every redraw, so narrow or resized
+    // terminals don't wrap the spinner onto new lines
+    let template = match (current(), windows) {
+        (Theme::Mono, _) => "{spinner} {wide_msg}",
+        (Theme::HighContrast, _) => "{spinner:.cyan.bold} {wide_msg:.yellow}",
+        (Theme::Default, true) => "{spinner:.green} {wide_msg}",
+        (Theme::Default, false) => "{spinner:.red} {wide_msg}",
+    };
+
+    // Detect if running on Windows and set symbols accordingly
+    let tick_strings: &[&str] = if windows {
+        &["-", "\\", "|", "/"] // Use simple ASCII symbols
+    } else {
+        &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]
+    };
+
+    ProgressStyle::default_spinner()
+        .tick_strings(tick_strings)
+        .template(template)
+        .expect("Failed to set progress bar template")
+}
+
+/// Seconds between plain-text progress lines when stdout is not a terminal.
+const PLAIN_PROGRESS_INTERVAL_SECS: u64 = 10;
+
+/// Progress indicator for a mining round: an animated spinner on a terminal, periodic
+/// plain-text lines otherwise (systemd, nohup, JSON logs) so logs don't fill with control sequences.
+pub enum RoundProgress {
+    Spinner(ProgressBar),
+    Plain(tokio::task::JoinHandle<()>),
+}
+
+impl RoundProgress {
+    pub fn start(message: &'static str) -> Self {
+        if logging::interactive() {
+            let pb = ProgressBar::new_spinner().with_style(spinner_style());
+            pb.set_message(message);
+            pb.enable_steady_tick(Duration::from_millis(120));
+            return RoundProgress::Spinner(pb);
+        }
+
+        println!("{}", message);
+        let started = Instant::now();
+        RoundProgress::Plain(tokio::spawn(async move {
+            let period = Duration::from_secs(PLAIN_PROGRESS_INTERVAL_SECS);
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                println!("{} {} elapsed", message, format::duration(started.elapsed()));
+            }
+        }))
+    }
+
+    pub fn finish(self) {
+        match self {
+            RoundProgress::Spinner(pb) => pb.finish_and_clear(),
+            RoundProgress::Plain(handle) => handle.abort(),
+        }
+    }
+}
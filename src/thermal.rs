@@ -0,0 +1,86 @@
+use std::{fs, sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+
+use crate::control::MinerControl;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Frequency below this share of the session's peak counts as throttling.
+const THROTTLED: f64 = 0.93;
+/// Frequency above this share of the peak leaves room to work harder again.
+const RECOVERED: f64 = 0.97;
+/// Idle share added per throttled sample and removed per recovered one, in permille.
+const BACK_OFF_STEP: u32 = 20;
+const RECOVER_STEP: u32 = 10;
+/// Never idle workers more than this, a CPU that still throttles needs better cooling.
+const MAX_IDLE: u32 = 300;
+
+/// Mean current frequency over all cores in kHz, from cpufreq. Only supported on Linux,
+/// returns None elsewhere or when the kernel doesn't expose it (many VMs).
+fn mean_frequency() -> Option<f64> {
+    let frequencies: Vec<f64> = fs::read_dir("/sys/devices/system/cpu")
+        .ok()?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let core = name.to_str()?.strip_prefix("cpu")?;
+            if !core.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let path = format!("/sys/devices/system/cpu/cpu{}/cpufreq/scaling_cur_freq", core);
+            fs::read_to_string(path).ok()?.trim().parse().ok()
+        })
+        .collect();
+    (!frequencies.is_empty()).then(|| frequencies.iter().sum::<f64>() / frequencies.len() as f64)
+}
+
+/// Holds the CPU at a steady clock instead of letting it boost and throttle in turns: when
+/// the mean core frequency drops below the session's peak, workers idle a little between
+/// chunks, and work harder again once the frequency recovers. Steady clocks often hash
+/// more per round than boom-bust cycling. Returns None if frequencies can't be read or
+/// another miner sharing `control` already runs the loop.
+pub fn spawn(control: Arc<MinerControl>) -> Option<JoinHandle<()>> {
+    if !control.start_regulating() {
+        return None;
+    }
+    if mean_frequency().is_none() {
+        control.stop_regulating();
+        println!("Thermal feedback needs per-core frequencies from /sys/devices/system/cpu, which this system doesn't provide.");
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut peak: f64 = 0.0;
+        let mut reported_idle = 0;
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            // Cores clock down between rounds, only samples taken while hashing say anything
+            if !control.is_working() {
+                continue;
+            }
+            let Some(frequency) = mean_frequency() else {
+                continue;
+            };
+            peak = peak.max(frequency);
+
+            let idle = control.idle_permille();
+            let idle = if frequency < peak * THROTTLED {
+                (idle + BACK_OFF_STEP).min(MAX_IDLE)
+            } else if frequency > peak * RECOVERED {
+                idle.saturating_sub(RECOVER_STEP)
+            } else {
+                idle
+            };
+            control.set_idle_permille(idle);
+
+            // Report settled changes, not every step
+            if idle.abs_diff(reported_idle) >= 50 || (idle == 0 && reported_idle != 0) {
+                println!(
+                    "Thermal feedback: cores at {:.2} GHz (peak {:.2} GHz), workers idle {:.0}% of the time.",
+                    frequency / 1e6,
+                    peak / 1e6,
+                    idle as f64 / 10.0
+                );
+                reported_idle = idle;
+            }
+        }
+    }))
+}
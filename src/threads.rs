@@ -0,0 +1,174 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use clap::Parser;
+
+use crate::arch;
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+const NODE_DIR: &str = "/sys/devices/system/node";
+
+#[derive(Debug, Parser)]
+pub struct ThreadsArgs {
+    #[arg(
+        long,
+        value_name = "threads",
+        default_value = "4",
+        help = "Number of threads the mine command would use"
+    )]
+    pub threads: u32,
+    #[arg(
+        long,
+        action,
+        help = "Show the placement used by mine --sprint instead of per-round workers"
+    )]
+    pub sprint: bool,
+}
+
+/// Socket and physical core of a logical CPU.
+#[derive(Debug, Clone, Copy)]
+struct CpuTopology {
+    socket: u32,
+    core: u32,
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|value| value.trim().to_string())
+}
+
+/// Numbered entries of a sysfs directory, e.g. `cpu3` or `node1`.
+fn numbered_entries(dir: &str, prefix: &str) -> Vec<usize> {
+    let mut ids: Vec<usize> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.strip_prefix(prefix)?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    ids.sort_unstable();
+    ids
+}
+
+fn cpu_topology() -> BTreeMap<usize, CpuTopology> {
+    numbered_entries(CPU_DIR, "cpu")
+        .into_iter()
+        .filter_map(|cpu| {
+            let topology = format!("{}/cpu{}/topology", CPU_DIR, cpu);
+            let socket = read_trimmed(format!("{}/physical_package_id", topology))?.parse().ok()?;
+            let core = read_trimmed(format!("{}/core_id", topology))?.parse().ok()?;
+            Some((cpu, CpuTopology { socket, core }))
+        })
+        .collect()
+}
+
+fn print_caches() {
+    let cache_dir = format!("{}/cpu0/cache", CPU_DIR);
+    for index in numbered_entries(&cache_dir, "index") {
+        let dir = format!("{}/index{}", cache_dir, index);
+        let (Some(level), Some(kind), Some(size)) = (
+            read_trimmed(format!("{}/level", dir)),
+            read_trimmed(format!("{}/type", dir)),
+            read_trimmed(format!("{}/size", dir)),
+        ) else {
+            continue;
+        };
+        let shared = read_trimmed(format!("{}/shared_cpu_list", dir)).unwrap_or_default();
+        println!("    L{} {:<12} {:>8}  shared by cpus {}", level, kind, size, shared);
+    }
+}
+
+fn print_numa_nodes() {
+    let nodes = numbered_entries(NODE_DIR, "node");
+    if nodes.is_empty() {
+        println!("  NUMA nodes:      unknown");
+        return;
+    }
+    println!("  NUMA nodes:      {}", nodes.len());
+    for node in nodes {
+        let cpus = read_trimmed(format!("{}/node{}/cpulist", NODE_DIR, node)).unwrap_or_default();
+        println!("    node {}: cpus {}", node, cpus);
+    }
+}
+
+/// Prints the CPU topology and where mine would pin its worker threads, with any conflicts.
+pub fn threads(args: &ThreadsArgs) {
+    let topology = cpu_topology();
+    let allowed: Vec<usize> = core_affinity::get_core_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|core| core.id)
+        .collect();
+
+    println!("  CPU topology");
+    arch::detect().print();
+    if topology.is_empty() {
+        println!("  Sockets:         unknown");
+        println!("  Physical cores:  unknown");
+    } else {
+        let sockets: BTreeSet<u32> = topology.values().map(|cpu| cpu.socket).collect();
+        let cores: BTreeSet<(u32, u32)> = topology.values().map(|cpu| (cpu.socket, cpu.core)).collect();
+        println!("  Sockets:         {}", sockets.len());
+        println!("  Physical cores:  {}", cores.len());
+    }
+    println!("  Logical cpus:    {}", topology.len().max(allowed.len()));
+    println!("  Usable cpus:     {}", allowed.len());
+    if let Some(cpuset) = read_trimmed("/proc/self/status").and_then(|status| {
+        status.lines().find_map(|line| line.strip_prefix("Cpus_allowed_list:").map(|list| list.trim().to_string()))
+    }) {
+        println!("  Allowed cpuset:  {}", cpuset);
+    }
+    println!("  Caches (cpu0):");
+    print_caches();
+    print_numa_nodes();
+
+    // Per-round workers pin to the cores whose id is below the thread count,
+    // sprint workers to the first usable cores
+    let pinned: Vec<usize> = if args.sprint {
+        allowed.iter().copied().take(args.threads as usize).collect()
+    } else {
+        allowed.iter().copied().filter(|&id| id < args.threads as usize).collect()
+    };
+
+    println!();
+    println!("  Placement for {} threads{}", args.threads, if args.sprint { " (sprint)" } else { "" });
+    for (worker, cpu) in pinned.iter().enumerate() {
+        match topology.get(cpu) {
+            Some(t) => println!("    worker {:>3} -> cpu {:>3} (socket {}, core {})", worker, cpu, t.socket, t.core),
+            None => println!("    worker {:>3} -> cpu {:>3}", worker, cpu),
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    if args.threads as usize > allowed.len() {
+        conflicts.push(format!("{} threads requested but only {} cpus are usable, extra threads are not started.", args.threads, allowed.len()));
+    }
+    if pinned.len() < (args.threads as usize).min(allowed.len()) {
+        conflicts.push(format!(
+            "The cpuset excludes some of cpus 0-{}, only {} workers will run. Use --sprint or adjust the cpuset.",
+            args.threads.saturating_sub(1),
+            pinned.len()
+        ));
+    }
+    let mut used_cores = BTreeMap::new();
+    for cpu in &pinned {
+        if let Some(t) = topology.get(cpu) {
+            *used_cores.entry((t.socket, t.core)).or_insert(0) += 1;
+        }
+    }
+    let shared = used_cores.values().filter(|&&count| count > 1).count();
+    if shared > 0 {
+        conflicts.push(format!("{} physical cores run more than one worker on SMT siblings, per-thread hashrate will be lower.", shared));
+    }
+
+    println!();
+    if conflicts.is_empty() {
+        println!("  No conflicts found.");
+    }
+    for conflict in conflicts {
+        println!("  WARNING: {}", conflict);
+    }
+}
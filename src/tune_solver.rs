@@ -0,0 +1,88 @@
+use std::{
+    sync::{atomic::{AtomicBool, AtomicU64}, Arc},
+    time::Instant,
+};
+
+use clap::Parser;
+
+use crate::arch;
+use crate::format;
+use crate::mine::{self, RoundJob};
+use crate::settings::{self, SolverSettings};
+
+#[derive(Debug, Parser)]
+pub struct TuneSolverArgs {
+    #[arg(
+        long,
+        value_name = "threads",
+        default_value = "4",
+        help = "Number of threads to use while tuning"
+    )]
+    pub threads: u32,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "10",
+        help = "How long to hash with each combination"
+    )]
+    pub duration: u64,
+    #[arg(
+        long,
+        action,
+        help = "Print the results without saving the best combination to the settings file"
+    )]
+    pub dry_run: bool,
+}
+
+/// Hashes a synthetic challenge with each solver combination and keeps the fastest.
+pub async fn tune_solver(args: &TuneSolverArgs) {
+    let running = Arc::new(AtomicBool::new(true));
+    let mut best: Option<(SolverSettings, f64)> = None;
+    let profile = arch::detect();
+    profile.print();
+
+    println!("  Sweeping solver settings, {}s per combination on {} threads", args.duration, args.threads);
+    for &memories in profile.memory_counts {
+        for &interleave in profile.interleaves {
+            let solver = SolverSettings { memories, interleave };
+            let started = Instant::now();
+            let job = RoundJob {
+                challenge: rand::random(),
+                nonce_range: 0..u64::MAX,
+                cutoff: args.duration,
+                hash_timer: started,
+                cursor: Arc::new(AtomicU64::new(0)),
+                paranoid: false,
+                paranoid_min_difficulty: 0,
+                cancelled: Arc::new(AtomicBool::new(false)),
+                solver,
+                control: Default::default(),
+                best_difficulty: Default::default(),
+                exit_target: None,
+            };
+            let results = mine::mine_round(job, args.threads, None, Default::default(), &running).await;
+            let hashes: u64 = results.into_iter().flatten().map(|r| r.total_hashes).sum();
+            let rate = hashes as f64 / started.elapsed().as_secs_f64();
+
+            println!("  memories {} interleave {}: {}", memories, interleave, format::hashrate(rate));
+            if best.is_none_or(|(_, best_rate)| rate > best_rate) {
+                best = Some((solver, rate));
+            }
+        }
+    }
+
+    let Some((solver, rate)) = best else {
+        return;
+    };
+    println!("  Best: memories {} interleave {} at {}", solver.memories, solver.interleave, format::hashrate(rate));
+
+    if args.dry_run {
+        return;
+    }
+    let mut current = settings::load();
+    current.solver = solver;
+    match settings::save(&current) {
+        Ok(()) => println!("  Saved to [solver] in {}", settings::path().display()),
+        Err(e) => println!("  Failed to save {}: {}", settings::path().display(), e),
+    }
+}
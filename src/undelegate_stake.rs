@@ -1,11 +1,15 @@
-use std::{str::FromStr};
 use base64::{prelude::BASE64_STANDARD, Engine};
 use clap::Parser;
 use colored::*;
 use inquire::{Text, InquireError};
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
+use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
 use spl_associated_token_account::get_associated_token_address;
 
+use crate::audit;
+use crate::endpoint;
+use crate::network;
+use crate::pool_cache;
+use crate::response_cache;
 use crate::stake_balance;
 
 #[derive(Debug, Parser)]
@@ -20,7 +24,7 @@ pub struct UnstakeArgs {
 
 pub async fn undelegate_stake(args: UnstakeArgs, key: &Keypair, url: String, unsecure: bool) {
     let base_url = url;
-    let client = reqwest::Client::new();
+    let client = network::client();
     let url_prefix = if unsecure { "http".to_string() } else { "https".to_string() };
 
     // Fetch the staked balance
@@ -69,13 +73,11 @@ match Text::new(
 
 
     // Continue with transaction
-    let resp = client.get(format!("{}://{}/pool/authority/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-    let pool_pubkey = Pubkey::from_str(&resp).unwrap();
+    let pool_pubkey = pool_cache::authority_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-    let resp = client.get(format!("{}://{}/pool/fee_payer/pubkey", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
-    let fee_pubkey = Pubkey::from_str(&resp).unwrap();
+    let fee_pubkey = pool_cache::fee_payer_pubkey(&client, &url_prefix, &base_url).await.unwrap();
 
-    let resp = client.get(format!("{}://{}/latest-blockhash", url_prefix, base_url)).send().await.unwrap().text().await.unwrap();
+    let resp = client.get(endpoint::http(&url_prefix, &base_url, "latest-blockhash")).send().await.unwrap().text().await.unwrap();
     let decoded_blockhash = BASE64_STANDARD.decode(resp).unwrap();
     let deserialized_blockhash = bincode::deserialize(&decoded_blockhash).unwrap();
 
@@ -86,16 +88,18 @@ match Text::new(
 
     let mut tx = Transaction::new_with_payer(&[ix], Some(&fee_pubkey));
     tx.partial_sign(&[&key], deserialized_blockhash);
+    audit::record("undelegate-stake-transaction", &key.pubkey(), &tx.message_data());
 
     let serialized_tx = bincode::serialize(&tx).unwrap();
     let encoded_tx = BASE64_STANDARD.encode(&serialized_tx);
 
-    let resp = client.post(format!("{}://{}/unstake?pubkey={}&amount={}", url_prefix, base_url, key.pubkey().to_string(), unstake_amount_u64)).body(encoded_tx).send().await;
+    let resp = client.post(endpoint::http(&url_prefix, &base_url, &format!("unstake?pubkey={}&amount={}", key.pubkey().to_string(), unstake_amount_u64))).body(encoded_tx).send().await;
     if let Ok(res) = resp {
         if let Ok(txt) = res.text().await {
             match txt.as_str() {
                 "SUCCESS" => {
                     println!("  Successfully unstaked!");
+                    response_cache::forget(&base_url);
                 },
                 other => {
                     println!("  Transaction failed: {}", other);
@@ -0,0 +1,56 @@
+use colored::*;
+use inquire::Confirm;
+use solana_sdk::{native_token::lamports_to_sol, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::mine::MineArgs;
+use crate::network;
+use crate::settings;
+
+/// Checks the on-chain SOL and ORE balances of the mining key before mining starts.
+/// Returns false if the key holds more than the configured thresholds and the user
+/// has not acknowledged mining with it, with the flag or in `[wallet_guard]` of the settings.
+pub async fn check_wallet_activity(args: &MineArgs, pubkey: &Pubkey, rpc_url: &str, interactive: bool) -> bool {
+    if args.i_know_what_im_doing || settings::load().wallet_guard.acknowledged.contains(&pubkey.to_string()) {
+        return true;
+    }
+
+    let client = network::rpc(rpc_url.to_string());
+
+    let sol_balance = match client.get_balance(pubkey).await {
+        Ok(lamports) => lamports_to_sol(lamports),
+        Err(e) => {
+            // Failing open: an RPC outage shouldn't stop mining, but say so clearly
+            println!("{}", format!("  WARNING: could not check the balance of {}, mining without the wallet guard.", pubkey).yellow());
+            println!("  RPC error: {}", e);
+            return true;
+        }
+    };
+
+    let ata = get_associated_token_address(pubkey, &ore_api::consts::MINT_ADDRESS);
+    let ore_balance = match client.get_token_account_balance(&ata).await {
+        Ok(amount) => amount.ui_amount.unwrap_or(0.0),
+        // No token account yet means no ORE in the wallet.
+        Err(_) => 0.0,
+    };
+
+    if sol_balance <= args.max_wallet_sol && ore_balance <= args.max_wallet_ore {
+        return true;
+    }
+
+    println!();
+    println!("{}", "  WARNING: This keypair holds a significant balance.".red());
+    println!("  SOL: {:.9} (threshold {})", sol_balance, args.max_wallet_sol);
+    println!("  ORE: {:.11} (threshold {})", ore_balance, args.max_wallet_ore);
+    println!("  Mining keys sign messages on every round, a dedicated low-balance keypair is recommended.");
+
+    if interactive {
+        return Confirm::new("  Mine with this keypair anyway?")
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+    }
+
+    println!("  Refusing to mine. Pass --i-know-what-im-doing, or add it to acknowledged in [wallet_guard] of {}, to mine with this keypair anyway.", settings::path().display());
+    false
+}
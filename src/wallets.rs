@@ -0,0 +1,154 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use futures_util::future::join_all;
+use solana_sdk::{
+    signature::{read_keypair_file, Keypair},
+    signer::Signer,
+};
+
+use crate::cluster::{self, Cluster};
+use crate::mine::{self, mine, MineArgs, WALLET_TAG};
+use crate::redact;
+use crate::wallet_guard;
+
+/// The thread budget, split evenly between the wallets currently connected to the pool.
+#[derive(Debug, Default)]
+pub struct SharedThreads {
+    connected: AtomicU32,
+}
+
+impl SharedThreads {
+
+    /// Counts a session as connected until the guard is dropped.
+    pub fn connect(self: &Arc<Self>) -> ConnectedSession {
+        self.connected.fetch_add(1, Ordering::SeqCst);
+        ConnectedSession(self.clone())
+    }
+
+    /// Threads a session may use for its next round out of `total`.
+    pub fn share(&self, total: u32) -> u32 {
+        (total / self.connected.load(Ordering::SeqCst).max(1)).max(1)
+    }
+}
+
+pub struct ConnectedSession(Arc<SharedThreads>);
+
+impl Drop for ConnectedSession {
+    fn drop(&mut self) {
+        self.0.connected.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Expands directories to the .json keypair files they contain.
+fn keypair_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let Ok(entries) = fs::read_dir(path) else {
+                println!("  Cannot read keypair directory {}", path.display());
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+fn short_pubkey(key: &Keypair) -> String {
+    redact::short(&key.pubkey().to_string())
+}
+
+fn load_keypair(path: &Path) -> Option<Keypair> {
+    match read_keypair_file(path) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            println!("  Skipping {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Mines for every keypair at once, one pool connection each, until stopped with Ctrl+C.
+/// Output from each session is prefixed with its shortened pubkey.
+pub async fn mine_wallets(mut args: MineArgs, url: String, unsecure: bool, rpc_url: String, cluster: Cluster) {
+    let mut seen = HashSet::new();
+    let mut keys = Vec::new();
+    for path in keypair_paths(&args.wallets) {
+        let Some(key) = load_keypair(&path) else {
+            continue;
+        };
+        if !seen.insert(key.pubkey()) {
+            continue;
+        }
+        if !cluster::check_pool(cluster, &url, &key.pubkey()) {
+            continue;
+        }
+        if !wallet_guard::check_wallet_activity(&args, &key.pubkey(), &rpc_url, false).await {
+            continue;
+        }
+        keys.push(key);
+    }
+    if keys.is_empty() {
+        println!("  No usable keypairs to mine with.");
+        return;
+    }
+
+    println!("  Mining with {} wallets, sharing {} threads:", keys.len(), args.threads);
+    for key in &keys {
+        println!("    [{}] {}", short_pubkey(key), key.pubkey());
+    }
+    args.thread_share = Some(Arc::new(SharedThreads::default()));
+
+    let servers = mine::serve_endpoints(&args).await;
+    let sessions = keys.into_iter().map(|key| {
+        let tag = short_pubkey(&key);
+        WALLET_TAG.scope(tag, mine(args.clone(), Arc::new(key), url.clone(), unsecure, rpc_url.clone()))
+    });
+    join_all(sessions).await;
+    for server in servers {
+        server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_are_split_between_connected_sessions() {
+        let threads = Arc::new(SharedThreads::default());
+        assert_eq!(threads.share(8), 8);
+        let first = threads.connect();
+        assert_eq!(threads.share(8), 8);
+        let second = threads.connect();
+        let third = threads.connect();
+        assert_eq!(threads.share(8), 2);
+        drop(second);
+        assert_eq!(threads.share(8), 4);
+        drop((first, third));
+        assert_eq!(threads.share(8), 8);
+    }
+
+    #[test]
+    fn every_session_keeps_at_least_one_thread() {
+        let threads = Arc::new(SharedThreads::default());
+        let sessions: Vec<_> = (0..5).map(|_| threads.connect()).collect();
+        assert_eq!(threads.share(3), 1);
+        drop(sessions);
+    }
+}
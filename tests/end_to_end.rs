@@ -0,0 +1,177 @@
+//! Mines two rounds against an in-process mock pool and checks what the client sent it.
+//! Run with `cargo test --features e2e --test end_to_end`.
+#![cfg(feature = "e2e")]
+
+use std::{
+    process::Stdio,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair, Signature, Signer},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{Callback, ErrorResponse, Request, Response},
+    Message,
+};
+
+/// Nonces handed out per round, few enough for a debug build to scan before the cutoff.
+const RANGE: u64 = 8;
+const CUTOFF_SECS: u64 = 60;
+
+/// What the mock pool saw from the client.
+#[derive(Debug, Default)]
+struct Seen {
+    /// Authorization header and query string of each websocket upgrade.
+    upgrades: Vec<(Option<String>, String)>,
+    readies: Vec<Vec<u8>>,
+    solutions: Vec<Vec<u8>>,
+}
+
+fn start_mining(round: u64) -> Vec<u8> {
+    let mut frame = vec![0u8];
+    frame.extend_from_slice(&[round as u8 + 1; 32]);
+    frame.extend_from_slice(&CUTOFF_SECS.to_le_bytes());
+    frame.extend_from_slice(&(round * RANGE).to_le_bytes());
+    frame.extend_from_slice(&((round + 1) * RANGE).to_le_bytes());
+    frame
+}
+
+/// Records the Authorization header and query string of a websocket upgrade.
+struct RecordUpgrade(Arc<Mutex<Seen>>);
+
+impl Callback for RecordUpgrade {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        let auth = request.headers().get("Authorization").and_then(|v| v.to_str().ok()).map(str::to_string);
+        let query = request.uri().query().unwrap_or_default().to_string();
+        self.0.lock().unwrap().upgrades.push((auth, query));
+        Ok(response)
+    }
+}
+
+/// Answers `/timestamp` over HTTP, anything else but a websocket upgrade with 404.
+async fn answer_http(mut stream: TcpStream) {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(len) => request.extend_from_slice(&buf[..len]),
+        }
+    }
+    let (status, body) = if String::from_utf8_lossy(&request).starts_with("GET /timestamp") {
+        ("200 OK", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs().to_string())
+    } else {
+        ("404 Not Found", "not found".to_string())
+    };
+    let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// Hands out a round for every Ready and records every frame, until the client disconnects.
+async fn serve(stream: TcpStream, seen: Arc<Mutex<Seen>>) {
+    let mut head = [0u8; 2048];
+    let len = stream.peek(&mut head).await.unwrap_or(0);
+    if !String::from_utf8_lossy(&head[..len]).to_ascii_lowercase().contains("upgrade: websocket") {
+        return answer_http(stream).await;
+    }
+    let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(stream, RecordUpgrade(seen.clone())).await else {
+        return;
+    };
+    let mut rounds = 0;
+    while let Some(Ok(message)) = ws.next().await {
+        let Message::Binary(frame) = message else {
+            continue;
+        };
+        match frame.first() {
+            Some(0) => {
+                seen.lock().unwrap().readies.push(frame);
+                let _ = ws.send(Message::Binary(start_mining(rounds))).await;
+                rounds += 1;
+            },
+            Some(2) => {
+                seen.lock().unwrap().solutions.push(frame);
+                let _ = ws.send(Message::Text("Pool Submitted Difficulty: 1\nMiner Earned: 0.00000000001 ORE\n".to_string())).await;
+            },
+            _ => {},
+        }
+    }
+}
+
+fn verify(pubkey: &Pubkey, signature: &[u8], message: &[u8]) -> bool {
+    let signature = Signature::from_str(std::str::from_utf8(signature).expect("base58 signature")).expect("valid signature");
+    signature.verify(pubkey.as_ref(), message)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mines_two_rounds_against_a_mock_pool() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    let pool = tokio::spawn({
+        let seen = seen.clone();
+        async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, seen.clone()));
+            }
+        }
+    });
+
+    // The client keeps its history and caches in the working directory
+    let dir = std::env::temp_dir().join(format!("ore-hq-client-e2e-{}", port));
+    std::fs::create_dir_all(&dir).unwrap();
+    let key = Keypair::new();
+    write_keypair_file(&key, dir.join("id.json")).unwrap();
+
+    let child = tokio::process::Command::new(env!("CARGO_BIN_EXE_ore-hq-client"))
+        .current_dir(&dir)
+        .args(["--url", &format!("127.0.0.1:{}", port), "--use-http", "--keypair", "id.json", "--rpc", "http://127.0.0.1:9"])
+        .args(["mine", "--threads", "1", "--max-rounds", "2"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    let output = tokio::time::timeout(Duration::from_secs(180), child.wait_with_output()).await.expect("the client did not stop after 2 rounds").unwrap();
+    pool.abort();
+    let _ = std::fs::remove_dir_all(&dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "the client exited with {}:\n{}", output.status, stdout);
+    assert!(stdout.contains("Mined 2 rounds"), "no clean stop after 2 rounds:\n{}", stdout);
+
+    let seen = seen.lock().unwrap();
+    let pubkey = key.pubkey();
+
+    // Authorization: Basic base64("<pubkey>:<signature of the timestamp>")
+    let (auth, query) = seen.upgrades.first().expect("no websocket upgrade");
+    let credentials = BASE64_STANDARD.decode(auth.as_deref().expect("no Authorization header").strip_prefix("Basic ").expect("not basic auth")).unwrap();
+    let (user, signature) = std::str::from_utf8(&credentials).unwrap().split_once(':').unwrap();
+    assert_eq!(user, pubkey.to_string());
+    let timestamp: u64 = query.strip_prefix("timestamp=").expect("no timestamp in the query").parse().unwrap();
+    assert!(verify(&pubkey, signature.as_bytes(), &timestamp.to_le_bytes()));
+
+    // Ready: type 0, pubkey, timestamp, signature of the timestamp
+    let ready = &seen.readies[0];
+    assert_eq!(&ready[1..33], pubkey.as_ref());
+    assert!(verify(&pubkey, &ready[41..], &ready[33..41]));
+
+    // BestSolution: type 2, digest (16), nonce (8), pubkey (32), then the signature of digest and nonce
+    assert_eq!(seen.solutions.len(), 2);
+    for (round, solution) in seen.solutions.iter().enumerate() {
+        assert!(solution.len() > 57);
+        assert_eq!(solution[0], 2);
+        let nonce = u64::from_le_bytes(solution[17..25].try_into().unwrap());
+        assert!((round as u64 * RANGE..(round as u64 + 1) * RANGE).contains(&nonce), "nonce {} outside round {}'s range", nonce, round);
+        assert_eq!(&solution[25..57], pubkey.as_ref());
+        assert!(verify(&pubkey, &solution[57..], &solution[1..25]));
+    }
+}